@@ -0,0 +1,285 @@
+//! Parsing and writing of settings files which assign Python literals to
+//! names.
+
+use crate::parse::{parse_comma_separated, syntax_error, ParseOptions, Parser, Rule};
+use crate::scan::{blank_statement_comments, blank_surroundings};
+use crate::{FormatError, FormatOptions, ParseError, Value};
+use pest::Parser as _;
+use std::borrow::Borrow;
+use std::io;
+
+/// Python's keywords, which can't be assigned to.
+const KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// Parses a file of assignments of Python literals to names, such as a
+/// simple settings file, using the default options.
+///
+/// See [`parse_assignments_with_options`] for details.
+///
+/// [`parse_assignments_with_options`]: fn.parse_assignments_with_options.html
+pub fn parse_assignments(s: &str) -> Result<Vec<(String, Value)>, ParseError> {
+    parse_assignments_with_options(s, &ParseOptions::default())
+}
+
+/// Parses a file of assignments of Python literals to names, such as a
+/// simple settings file, using the given options.
+///
+/// Each statement has the form `NAME = <literal>`, where the literal may be
+/// a tuple without parentheses, as at the top level of [`Value::from_str`].
+/// Statements are separated by line breaks, and a literal may span multiple
+/// lines inside brackets. Blank lines and `#` comments are allowed anywhere,
+/// so the `comments` option has no effect.
+///
+/// Each name must be a valid Python identifier which isn't a keyword, such
+/// as `class`. Returns the names and values in the order of the statements.
+/// A name which is assigned more than once appears more than once. Positions in
+/// errors are relative to the start of the input.
+///
+/// ```
+/// use py_literal::{parse_assignments, Value};
+///
+/// # fn main() -> Result<(), py_literal::ParseError> {
+/// let settings = parse_assignments(
+///     "# Settings\n\
+///      DEBUG = False\n\
+///      \n\
+///      ORIGIN = 0, 0\n\
+///      PATHS = [\n    '/usr/share',  # system\n    '/opt',\n]\n",
+/// )?;
+/// let names: Vec<_> = settings.iter().map(|(name, _)| name.as_str()).collect();
+/// assert_eq!(names, ["DEBUG", "ORIGIN", "PATHS"]);
+/// assert_eq!(settings[0].1, Value::Boolean(false));
+/// assert_eq!(settings[2].1.to_string(), "['/usr/share', '/opt']");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Value::from_str`]: enum.Value.html#impl-FromStr
+pub fn parse_assignments_with_options(
+    s: &str,
+    options: &ParseOptions,
+) -> Result<Vec<(String, Value)>, ParseError> {
+    let options = options.resolve();
+    let mut s = blank_statement_comments(s);
+    if options.trim_enabled() {
+        s = blank_surroundings(s);
+    }
+    options.check_cancelled()?;
+    options.check_depth(&s)?;
+    options.start_progress(s.len());
+    let mut parsed =
+        Parser::parse(Rule::assignments, &s).map_err(|e| ParseError::Syntax(format!("{}", e)))?;
+    options.check_cancelled()?;
+    let (assignments,) = parse_pairs_as!(parsed, (Rule::assignments,));
+    options.check_nodes(assignments.clone().into_inner())?;
+    let mut out = Vec::new();
+    for pair in assignments.into_inner() {
+        match pair.as_rule() {
+            Rule::assignment => {
+                let mut inner = pair.into_inner();
+                let name = inner.next().unwrap();
+                debug_assert_eq!(name.as_rule(), Rule::identifier);
+                if KEYWORDS.contains(&name.as_str()) {
+                    return Err(syntax_error(name.as_span(), "cannot assign to a keyword"));
+                }
+                let value = parse_comma_separated(inner, &options)?;
+                out.push((name.as_str().to_owned(), value));
+            }
+            Rule::newline | Rule::EOI => (),
+            _ => unreachable!(),
+        }
+    }
+    options.finish_progress();
+    Ok(out)
+}
+
+/// Returns whether the name is a valid Python identifier which isn't a
+/// keyword.
+fn is_assignable(name: &str) -> bool {
+    !KEYWORDS.contains(&name)
+        && Parser::parse(Rule::identifier, name)
+            .ok()
+            .and_then(|mut pairs| pairs.next())
+            .map_or(false, |pair| pair.as_str() == name)
+}
+
+/// Formats assignments of Python literals to names as Python source, such
+/// as a settings module, using the given options for the literals.
+///
+/// See [`write_assignments`] for details.
+///
+/// [`write_assignments`]: fn.write_assignments.html
+pub fn format_assignments<I, S, V>(
+    assignments: I,
+    options: &FormatOptions,
+) -> Result<String, FormatError>
+where
+    I: IntoIterator<Item = (S, V)>,
+    S: AsRef<str>,
+    V: Borrow<Value>,
+{
+    let mut out = Vec::new();
+    write_assignments(&mut out, assignments, options)?;
+    Ok(String::from_utf8(out).expect("the formatter writes valid UTF-8"))
+}
+
+/// Writes assignments of Python literals to names as Python source, such as
+/// a settings module, using the given options for the literals.
+///
+/// Each assignment is written as a `NAME = <literal>` statement on its own
+/// line (or lines, if pretty-printing), so the output can be imported by
+/// Python and parsed with [`parse_assignments`]. If a name isn't a valid
+/// Python identifier, or is a keyword, this fails with
+/// [`FormatError::InvalidName`]; the preceding statements have already been
+/// written then.
+///
+/// ```
+/// use py_literal::{format_assignments, FormatOptions, Value};
+///
+/// # fn main() -> Result<(), py_literal::FormatError> {
+/// let settings = vec![
+///     ("DEBUG", Value::Boolean(false)),
+///     ("PATHS", Value::List(vec![Value::String("/opt".into())])),
+/// ];
+/// assert_eq!(
+///     format_assignments(settings, &FormatOptions::new())?,
+///     "DEBUG = False\nPATHS = ['/opt']\n",
+/// );
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`parse_assignments`]: fn.parse_assignments.html
+/// [`FormatError::InvalidName`]: enum.FormatError.html#variant.InvalidName
+pub fn write_assignments<W, I, S, V>(
+    w: &mut W,
+    assignments: I,
+    options: &FormatOptions,
+) -> Result<(), FormatError>
+where
+    W: io::Write,
+    I: IntoIterator<Item = (S, V)>,
+    S: AsRef<str>,
+    V: Borrow<Value>,
+{
+    for (name, value) in assignments {
+        let name = name.as_ref();
+        if !is_assignable(name) {
+            return Err(FormatError::InvalidName(name.to_owned()));
+        }
+        let prefix = format!("{} = ", name);
+        w.write_all(prefix.as_bytes())?;
+        value
+            .borrow()
+            .write_at_column(w, options, prefix.chars().count())?;
+        w.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_assignments_example() {
+        let input = "\
+            # header\r\n\
+            A = 1  # one\r\n\
+            \r\n\
+            _b2 = 'x#y', (\r\n  2, # two\r\n  3,\r\n)\r\n\
+            \u{e9}t\u{e9} = {'k': None}\n\
+            A = 2,";
+        let parsed = parse_assignments(input).unwrap();
+        let formatted: Vec<_> = parsed
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        assert_eq!(
+            formatted,
+            [
+                "A=1",
+                "_b2=('x#y', (2, 3))",
+                "\u{e9}t\u{e9}={'k': None}",
+                "A=(2,)",
+            ],
+        );
+        assert!(parse_assignments("").unwrap().is_empty());
+        assert!(parse_assignments("\n# only a comment\n")
+            .unwrap()
+            .is_empty());
+        for input in &[
+            "A = 1 B = 2",
+            "A = ",
+            "A == 1",
+            "1A = 1",
+            "None = 1",
+            "class = 1",
+            "A = 1\nlambda = 2",
+            "A.b = 1",
+            "A = 1\nB",
+            "A = [1,\n",
+        ] {
+            assert!(parse_assignments(input).is_err(), "{:?}", input);
+        }
+        match parse_assignments("A = 1\nif = 2") {
+            Err(ParseError::Syntax(msg)) => assert!(msg.contains("keyword"), "{}", msg),
+            other => panic!("{:?}", other),
+        }
+        let parsed = parse_assignments("match = 1\nclass_ = 2").unwrap();
+        assert_eq!(parsed[0].0, "match");
+        match parse_assignments("A = 1\nB = 0755") {
+            Err(ParseError::LegacyOctalInteger(_, span)) => assert_eq!(span, 10..14),
+            other => panic!("{:?}", other),
+        }
+        let opts = ParseOptions::new().trim(true);
+        let parsed = parse_assignments_with_options("\u{feff}A = 1\n", &opts).unwrap();
+        assert_eq!(parsed, [("A".to_string(), Value::Integer(1.into()))]);
+    }
+
+    #[test]
+    fn format_assignments_example() {
+        let input = "A = 1\n_b2 = ('x', [2, 3])\n\u{e9}t\u{e9} = {'k': (None,)}\nA = 2\n";
+        let parsed = parse_assignments(input).unwrap();
+        let pairs = || parsed.iter().map(|(name, value)| (name, value));
+        let formatted = format_assignments(pairs(), &FormatOptions::new()).unwrap();
+        assert_eq!(formatted, input);
+        let pretty = FormatOptions::new().pretty(true).width(12);
+        let formatted = format_assignments(pairs(), &pretty).unwrap();
+        assert_eq!(
+            formatted,
+            "A = 1\n_b2 = (\n    'x',\n    [2, 3]\n)\n\u{e9}t\u{e9} = {\n    'k': (\n        None,\n    )\n}\nA = 2\n",
+        );
+        assert_eq!(parse_assignments(&formatted).unwrap(), parsed);
+        let list: Value = "[1, 2, 3, 4]".parse().unwrap();
+        let narrow = FormatOptions::new().pretty(true).width(20);
+        let formatted = format_assignments(vec![("LONG_NAME", &list)], &narrow).unwrap();
+        assert_eq!(
+            formatted,
+            "LONG_NAME = [
+    1,
+    2,
+    3,
+    4
+]
+"
+        );
+        assert!(formatted.lines().all(|line| line.chars().count() <= 20));
+        let formatted = format_assignments(vec![("N", &list)], &narrow).unwrap();
+        assert_eq!(formatted, "N = [1, 2, 3, 4]\n");
+        let value = Value::None;
+        for &name in &["", "1A", "A.b", "a b", "None", "class", "x=1"] {
+            match format_assignments(vec![("OK", &value), (name, &value)], &FormatOptions::new()) {
+                Err(FormatError::InvalidName(invalid)) => assert_eq!(invalid, name),
+                other => panic!("{:?}", other),
+            }
+        }
+        let formatted = format_assignments(vec![("match", &value)], &FormatOptions::new());
+        assert_eq!(formatted.unwrap(), "match = None\n");
+    }
+}
@@ -0,0 +1,150 @@
+//! Writing Python literals to asynchronous writers.
+
+use crate::{FormatError, FormatOptions, Value};
+use futures_io::AsyncWrite;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+impl Value {
+    /// Writes the value as ASCII to an asynchronous writer.
+    ///
+    /// This is available only when the `futures-io` feature is enabled. The
+    /// value is formatted into memory, so formatting errors are returned
+    /// before anything is written, and then written without blocking. This
+    /// works with any writer which implements [`futures_io::AsyncWrite`];
+    /// Tokio's writers can be adapted with `tokio-util`'s `compat` module.
+    ///
+    /// [`futures_io::AsyncWrite`]: https://docs.rs/futures-io/0.3/futures_io/trait.AsyncWrite.html
+    pub async fn write_ascii_async<W>(&self, w: &mut W) -> Result<(), FormatError>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        self.write_with_async(w, &FormatOptions::default()).await
+    }
+
+    /// Writes the value as UTF-8 to an asynchronous writer, using the given
+    /// options. See [`write_ascii_async`] for details.
+    ///
+    /// [`write_ascii_async`]: #method.write_ascii_async
+    pub async fn write_with_async<W>(
+        &self,
+        w: &mut W,
+        options: &FormatOptions,
+    ) -> Result<(), FormatError>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let formatted = self.format_with(options)?;
+        WriteAll {
+            writer: w,
+            buf: formatted.as_bytes(),
+        }
+        .await?;
+        Ok(())
+    }
+}
+
+/// Future which writes the entire buffer to the writer.
+struct WriteAll<'a, W: ?Sized> {
+    writer: &'a mut W,
+    buf: &'a [u8],
+}
+
+impl<W: AsyncWrite + Unpin + ?Sized> Future for WriteAll<'_, W> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        while !this.buf.is_empty() {
+            let written = match Pin::new(&mut *this.writer).poll_write(cx, this.buf) {
+                Poll::Ready(Ok(written)) => written,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            };
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            this.buf = &this.buf[written..];
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::task::{RawWaker, RawWakerVTable, Waker};
+
+    /// Writer which accepts at most two bytes per call, and is pending on
+    /// every other call.
+    struct Trickle {
+        out: Vec<u8>,
+        pending: bool,
+    }
+
+    impl AsyncWrite for Trickle {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let n = buf.len().min(2);
+            self.out.extend_from_slice(&buf[..n]);
+            Poll::Ready(Ok(n))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Polls the future to completion on the current thread.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        let waker = unsafe { Waker::from_raw(clone(std::ptr::null())) };
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn write_async() {
+        let value: Value = "{'a': [1, 2.5], b'\\xff': None}".parse().unwrap();
+        let mut w = Trickle {
+            out: Vec::new(),
+            pending: false,
+        };
+        block_on(value.write_ascii_async(&mut w)).unwrap();
+        assert_eq!(w.out, value.format_ascii().unwrap().as_bytes());
+        let mut w = Trickle {
+            out: Vec::new(),
+            pending: false,
+        };
+        let opts = FormatOptions::new().pretty(true);
+        block_on(value.write_with_async(&mut w, &opts)).unwrap();
+        assert_eq!(w.out, value.format_with(&opts).unwrap().as_bytes());
+        match block_on(Value::Set(vec![]).write_ascii_async(&mut w)) {
+            Err(FormatError::EmptySet) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,423 @@
+//! Parsing which borrows the contents of strings and bytes from the input.
+
+use crate::compare::{self, HashKind};
+use crate::parse::{
+    insert_dict_item, parse_bytes_value, parse_start, parse_string_value, parse_value,
+    push_set_element, Element, ElementIndex, ParseOptions, Rule,
+};
+use crate::{ParseError, Value};
+use pest::iterators::{Pair, Pairs};
+use std::borrow::Cow;
+use std::hash::Hasher;
+use std::ops::Range;
+
+/// Python literal whose strings and bytes may borrow from the input, created
+/// by [`Value::parse_borrowed`].
+///
+/// [`Value::parse_borrowed`]: enum.Value.html#method.parse_borrowed
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValueRef<'a> {
+    /// Python string (`str`), which is borrowed if it contains no escape
+    /// sequences.
+    String(Cow<'a, str>),
+    /// Python byte sequence (`bytes`), which is borrowed if it contains no
+    /// escape sequences.
+    Bytes(Cow<'a, [u8]>),
+    /// Tuple.
+    Tuple(Vec<ValueRef<'a>>),
+    /// List.
+    List(Vec<ValueRef<'a>>),
+    /// Dict.
+    Dict(Vec<(ValueRef<'a>, ValueRef<'a>)>),
+    /// Set.
+    Set(Vec<ValueRef<'a>>),
+    /// Any other value. This includes collections which aren't written with
+    /// brackets, such as `frozenset({'a'})` and `OrderedDict(...)`; their
+    /// elements are owned.
+    Value(Value),
+}
+
+impl<'a> ValueRef<'a> {
+    /// Converts the literal into a `Value`, copying borrowed strings and
+    /// bytes.
+    pub fn into_value(self) -> Value {
+        let into_values =
+            |seq: Vec<ValueRef<'_>>| seq.into_iter().map(ValueRef::into_value).collect();
+        match self {
+            ValueRef::String(s) => Value::String(s.into_owned()),
+            ValueRef::Bytes(b) => Value::Bytes(b.into_owned()),
+            ValueRef::Tuple(tup) => Value::Tuple(into_values(tup)),
+            ValueRef::List(list) => Value::List(into_values(list)),
+            ValueRef::Dict(dict) => Value::Dict(
+                dict.into_iter()
+                    .map(|(k, v)| (k.into_value(), v.into_value()))
+                    .collect(),
+            ),
+            ValueRef::Set(set) => Value::Set(into_values(set)),
+            ValueRef::Value(value) => value,
+        }
+    }
+}
+
+impl<'a> Element for ValueRef<'a> {
+    fn is_hashable(&self) -> bool {
+        match self {
+            ValueRef::String(_) | ValueRef::Bytes(_) => true,
+            ValueRef::Tuple(tup) => tup.iter().all(Element::is_hashable),
+            ValueRef::List(_) | ValueRef::Dict(_) | ValueRef::Set(_) => false,
+            ValueRef::Value(value) => value.is_hashable(),
+        }
+    }
+
+    fn python_eq(&self, other: &ValueRef<'a>) -> bool {
+        match (self, other) {
+            (ValueRef::String(a), ValueRef::String(b)) => a == b,
+            (ValueRef::Bytes(a), ValueRef::Bytes(b)) => a == b,
+            (ValueRef::Value(a), ValueRef::Value(b)) => a.python_eq(b),
+            _ => self
+                .clone()
+                .into_value()
+                .python_eq(&other.clone().into_value()),
+        }
+    }
+
+    fn python_hash(&self, depth: usize, state: &mut dyn Hasher) {
+        match self {
+            ValueRef::String(s) => compare::hash_str(s, state),
+            ValueRef::Bytes(b) => compare::hash_bytes(b, state),
+            ValueRef::Tuple(tup) => {
+                compare::hash_sequence(HashKind::Tuple, tup, depth, state, Element::python_hash)
+            }
+            ValueRef::List(list) => {
+                compare::hash_sequence(HashKind::List, list, depth, state, Element::python_hash)
+            }
+            ValueRef::Dict(_) => HashKind::Dict.hash(state),
+            ValueRef::Set(_) => HashKind::Set.hash(state),
+            ValueRef::Value(value) => value.python_hash(depth, state),
+        }
+    }
+
+    fn describe(&self) -> String {
+        self.clone().into_value().describe()
+    }
+}
+
+impl Value {
+    /// Parses a Python literal, borrowing strings and bytes from the input
+    /// where possible, using the default options.
+    ///
+    /// ```
+    /// use py_literal::{Value, ValueRef};
+    /// use std::borrow::Cow;
+    ///
+    /// # fn main() -> Result<(), py_literal::ParseError> {
+    /// let input = "['abc', 'd\\ne']";
+    /// let parsed = Value::parse_borrowed(input)?;
+    /// if let ValueRef::List(list) = &parsed {
+    ///     assert!(matches!(&list[0], ValueRef::String(Cow::Borrowed("abc"))));
+    ///     assert!(matches!(&list[1], ValueRef::String(Cow::Owned(_))));
+    /// }
+    /// assert_eq!(parsed.into_value(), input.parse()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_borrowed(s: &str) -> Result<ValueRef<'_>, ParseError> {
+        Value::parse_borrowed_with_options(s, &ParseOptions::default())
+    }
+
+    /// Parses a Python literal, borrowing strings and bytes from the input
+    /// where possible, using the given options.
+    pub fn parse_borrowed_with_options<'a>(
+        s: &'a str,
+        options: &ParseOptions,
+    ) -> Result<ValueRef<'a>, ParseError> {
+        parse_start(s, options, |pairs, opts| {
+            Borrower { input: s, opts }.comma_separated(pairs)
+        })
+    }
+}
+
+/// State of parsing with borrowed strings and bytes.
+struct Borrower<'a, 'o> {
+    /// The input. The pairs may be over a copy of it with comments blanked
+    /// out, which has the same byte offsets.
+    input: &'a str,
+    opts: &'o ParseOptions,
+}
+
+impl<'a, 'o> Borrower<'a, 'o> {
+    /// Returns the byte range in `input` of `part`, which is a slice of the
+    /// text of `pair`.
+    fn rebase(&self, pair: &Pair<'_, Rule>, part: &[u8]) -> Range<usize> {
+        let offset = part.as_ptr() as usize - pair.as_str().as_ptr() as usize;
+        let start = pair.as_span().start() + offset;
+        start..start + part.len()
+    }
+
+    /// Parses comma-separated values as a tuple, unless there is only a
+    /// single value without a trailing comma, in which case the value is
+    /// returned as-is.
+    fn comma_separated(&self, pairs: Pairs<'_, Rule>) -> Result<ValueRef<'a>, ParseError> {
+        let mut values = Vec::new();
+        let mut trailing_comma = false;
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::value => values.push(self.value(pair)?),
+                Rule::tuple_comma => trailing_comma = true,
+                Rule::EOI => (),
+                _ => unreachable!(),
+            }
+        }
+        if values.len() == 1 && !trailing_comma {
+            Ok(values.pop().unwrap())
+        } else {
+            Ok(ValueRef::Tuple(values))
+        }
+    }
+
+    /// Parses a value.
+    ///
+    /// Nested tuples, lists, sets, and dicts are parsed with an explicit stack
+    /// instead of recursively, so deeply nested input can't overflow the call
+    /// stack.
+    fn value(&self, value: Pair<'_, Rule>) -> Result<ValueRef<'a>, ParseError> {
+        debug_assert_eq!(value.as_rule(), Rule::value);
+        let opts = self.opts;
+        let mut stack = match Frame::new(value.clone()) {
+            Some(frame) => vec![frame],
+            None => return self.non_collection(value),
+        };
+        let mut done = None;
+        while let Some(frame) = stack.last_mut() {
+            opts.check_cancelled()?;
+            if let Some(value) = done.take() {
+                frame
+                    .collection
+                    .push(value, frame.span, &mut frame.index, opts)?;
+            }
+            match frame.next_value() {
+                Some(value) => match Frame::new(value.clone()) {
+                    Some(frame) => stack.push(frame),
+                    None => done = Some(self.non_collection(value)?),
+                },
+                None => done = Some(stack.pop().unwrap().collection.finish()),
+            }
+        }
+        Ok(done.unwrap())
+    }
+
+    /// Parses a value which isn't a tuple, list, set, or dict.
+    fn non_collection(&self, value: Pair<'_, Rule>) -> Result<ValueRef<'a>, ParseError> {
+        let opts = self.opts;
+        let (inner,) = parse_pairs_as!(value.clone().into_inner(), (_,));
+        Ok(match inner.as_rule() {
+            Rule::string | Rule::string_concat => {
+                ValueRef::String(match parse_string_value(inner.clone(), opts)? {
+                    Cow::Borrowed(part) => {
+                        Cow::Borrowed(&self.input[self.rebase(&inner, part.as_bytes())])
+                    }
+                    Cow::Owned(s) => Cow::Owned(s),
+                })
+            }
+            Rule::bytes | Rule::bytes_concat => {
+                ValueRef::Bytes(match parse_bytes_value(inner.clone(), opts)? {
+                    Cow::Borrowed(part) => {
+                        Cow::Borrowed(&self.input.as_bytes()[self.rebase(&inner, part)])
+                    }
+                    Cow::Owned(b) => Cow::Owned(b),
+                })
+            }
+            _ => ValueRef::Value(parse_value(value, opts)?),
+        })
+    }
+}
+
+/// Collection whose elements are being parsed by `Borrower::value`.
+enum Collection<'a, 'i> {
+    /// Comma-separated values in parentheses, and whether there is a trailing
+    /// comma.
+    Parenthesized(Vec<ValueRef<'a>>, bool),
+    List(Vec<ValueRef<'a>>),
+    Set(Vec<ValueRef<'a>>),
+    /// Dict, and the key (and its span) of the item whose value is being
+    /// parsed.
+    Dict(
+        Vec<(ValueRef<'a>, ValueRef<'a>)>,
+        Option<(ValueRef<'a>, pest::Span<'i>)>,
+    ),
+}
+
+impl<'a, 'i> Collection<'a, 'i> {
+    /// Adds an element (or dict key or value) with the given span.
+    fn push(
+        &mut self,
+        value: ValueRef<'a>,
+        span: pest::Span<'i>,
+        index: &mut ElementIndex,
+        opts: &ParseOptions,
+    ) -> Result<(), ParseError> {
+        match self {
+            Collection::Parenthesized(values, _) | Collection::List(values) => values.push(value),
+            Collection::Set(set) => push_set_element(set, index, value, span, opts)?,
+            Collection::Dict(dict, key) => match key.take() {
+                None => *key = Some((value, span)),
+                Some((key, key_span)) => insert_dict_item(dict, index, key, value, key_span, opts)?,
+            },
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> ValueRef<'a> {
+        match self {
+            Collection::Parenthesized(mut values, trailing_comma) => {
+                if values.len() == 1 && !trailing_comma {
+                    values.pop().unwrap()
+                } else {
+                    ValueRef::Tuple(values)
+                }
+            }
+            Collection::List(list) => ValueRef::List(list),
+            Collection::Set(set) => ValueRef::Set(set),
+            Collection::Dict(dict, _) => ValueRef::Dict(dict),
+        }
+    }
+}
+
+/// Collection on the stack of `Borrower::value`.
+struct Frame<'a, 'i> {
+    collection: Collection<'a, 'i>,
+    /// Remaining items of the collection.
+    items: Pairs<'i, Rule>,
+    /// Remaining key and value of the current dict item.
+    dict_elem: Option<Pairs<'i, Rule>>,
+    /// Index of the elements of a set or keys of a dict.
+    index: ElementIndex,
+    /// Span of the element being parsed.
+    span: pest::Span<'i>,
+}
+
+impl<'a, 'i> Frame<'a, 'i> {
+    /// Returns a frame for the value if it's a tuple (or a value in redundant
+    /// parentheses), list, set, or dict.
+    fn new(value: Pair<'i, Rule>) -> Option<Frame<'a, 'i>> {
+        let (inner,) = parse_pairs_as!(value.into_inner(), (_,));
+        let collection = match inner.as_rule() {
+            Rule::parenthesized => Collection::Parenthesized(Vec::new(), false),
+            Rule::list => Collection::List(Vec::new()),
+            Rule::set => Collection::Set(Vec::new()),
+            Rule::dict => Collection::Dict(Vec::new(), None),
+            _ => return None,
+        };
+        Some(Frame {
+            collection,
+            span: inner.as_span(),
+            items: inner.into_inner(),
+            dict_elem: None,
+            index: ElementIndex::default(),
+        })
+    }
+
+    /// Returns the next element (or dict key or value) to parse.
+    fn next_value(&mut self) -> Option<Pair<'i, Rule>> {
+        loop {
+            let item = match self.dict_elem.as_mut().and_then(Iterator::next) {
+                Some(pair) => pair,
+                None => self.items.next()?,
+            };
+            match item.as_rule() {
+                Rule::value => {
+                    self.span = item.as_span();
+                    return Some(item);
+                }
+                Rule::dict_elem => self.dict_elem = Some(item.into_inner()),
+                Rule::tuple_comma => {
+                    if let Collection::Parenthesized(_, trailing_comma) = &mut self.collection {
+                        *trailing_comma = true;
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_borrowed_example() {
+        let opts = ParseOptions::new().comments(true).frozensets(true);
+        for input in &[
+            "('a' ,) ",
+            " b'x', 'y\\x41',",
+            "[{'k': b''}, {'s'}, r'\\n']",
+            "[u'#', # one\n frozenset({'a'})]",
+        ] {
+            let parsed = Value::parse_borrowed_with_options(input, &opts).unwrap();
+            assert_eq!(
+                parsed.into_value(),
+                Value::parse_with_options(input, &opts).unwrap(),
+                "{:?}",
+                input,
+            );
+        }
+    }
+
+    #[test]
+    fn parse_borrowed_is_borrowed() {
+        let opts = ParseOptions::new().comments(true);
+        let input = String::from("# c\n['a#b', # 'x'\n b'c', r'\\d', 'e\\n']");
+        let parsed = Value::parse_borrowed_with_options(&input, &opts).unwrap();
+        let list = match parsed {
+            ValueRef::List(list) => list,
+            _ => panic!(),
+        };
+        match &list[..] {
+            [ValueRef::String(Cow::Borrowed(a)), ValueRef::Bytes(Cow::Borrowed(b)), ValueRef::String(Cow::Borrowed(c)), ValueRef::String(Cow::Owned(e))] =>
+            {
+                assert_eq!(*a, "a#b");
+                assert_eq!(*b, b"c");
+                assert_eq!(*c, "\\d");
+                assert_eq!(e, "e\n");
+            }
+            _ => panic!("{:?}", list),
+        }
+        let dedup = ParseOptions::new().deduplicate_sets(true);
+        let parsed = Value::parse_borrowed_with_options("{'a', 'a', 'b\\x00'}", &dedup).unwrap();
+        assert_eq!(parsed.into_value().as_set().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn parse_borrowed_deeply_nested() {
+        // The thread's stack just needs to be large enough for the grammar,
+        // which is matched recursively.
+        let test = || {
+            let depth = 10_000;
+            let input = format!("{}'a'{}", "[{1: (".repeat(depth), ",)}]".repeat(depth));
+            let parsed = Value::parse_borrowed(&input).unwrap();
+            let mut value = &parsed;
+            for _ in 0..depth {
+                value = match value {
+                    ValueRef::List(list) => match &list[0] {
+                        ValueRef::Dict(dict) => match &dict[0].1 {
+                            ValueRef::Tuple(tuple) => &tuple[0],
+                            _ => panic!(),
+                        },
+                        _ => panic!(),
+                    },
+                    _ => panic!(),
+                };
+            }
+            assert_eq!(value, &ValueRef::String(Cow::Borrowed("a")));
+            let input = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+            assert!(Value::parse_borrowed(&input).is_ok());
+        };
+        std::thread::Builder::new()
+            .stack_size(256 << 20)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
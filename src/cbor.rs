@@ -0,0 +1,494 @@
+//! Conversions between [`Value`] and CBOR values ([`ciborium::value::Value`]).
+//!
+//! This module is available only when the `ciborium` feature is enabled.
+//!
+//! CBOR natively supports byte strings, arbitrary map keys, and big integers,
+//! so Python literals can be converted to CBOR and back without losing
+//! information. The mapping is:
+//!
+//! | Python literal        | CBOR                                              |
+//! |-----------------------|---------------------------------------------------|
+//! | `Value::String`       | text string                                       |
+//! | `Value::Bytes`        | byte string                                       |
+//! | `Value::ByteArray`    | [`BYTEARRAY_TAG`] applied to a byte string        |
+//! | `Value::Integer`      | integer, or bignum (tags 2/3) if out of range     |
+//! | `Value::Float`        | float                                             |
+//! | `Value::Decimal`      | decimal fraction (tag 4)                          |
+//! | `Value::Rational`     | rational number (tag 30)                          |
+//! | `Value::Complex`      | [`COMPLEX_TAG`] applied to an array `[re, im]`    |
+//! | `Value::Tuple`        | [`TUPLE_TAG`] applied to an array                 |
+//! | `Value::List`         | array                                             |
+//! | `Value::Dict`         | map                                               |
+//! | `Value::Set`          | [`SET_TAG`] applied to an array                   |
+//! | `Value::FrozenSet`    | [`FROZENSET_TAG`] applied to an array             |
+//! | `Value::Date`         | [`DATE_TAG`] applied to `[year, month, day]`      |
+//! | `Value::DateTime`     | [`DATETIME_TAG`] applied to `[year, month, day, hour, minute, second, microsecond]` |
+//! | `Value::Boolean`      | boolean                                           |
+//! | `Value::None`         | null                                              |
+//! | `Value::Ellipsis`     | [`ELLIPSIS_TAG`] applied to null                  |
+//!
+//! [`Value`]: ../enum.Value.html
+//! [`ciborium::value::Value`]: https://docs.rs/ciborium/0.2/ciborium/value/enum.Value.html
+//! [`COMPLEX_TAG`]: constant.COMPLEX_TAG.html
+//! [`TUPLE_TAG`]: constant.TUPLE_TAG.html
+//! [`SET_TAG`]: constant.SET_TAG.html
+//! [`FROZENSET_TAG`]: constant.FROZENSET_TAG.html
+//! [`ELLIPSIS_TAG`]: constant.ELLIPSIS_TAG.html
+//! [`BYTEARRAY_TAG`]: constant.BYTEARRAY_TAG.html
+//! [`DATE_TAG`]: constant.DATE_TAG.html
+//! [`DATETIME_TAG`]: constant.DATETIME_TAG.html
+
+use crate::{Date, DateTime, Value};
+use ciborium::value::{Integer as CborInteger, Value as CborValue};
+use num_bigint as numb;
+use num_complex as numc;
+use num_traits::ToPrimitive;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+/// Tag for unsigned bignums (RFC 8949, section 3.4.3).
+const POSITIVE_BIGNUM_TAG: u64 = 2;
+
+/// Tag for negative bignums (RFC 8949, section 3.4.3).
+const NEGATIVE_BIGNUM_TAG: u64 = 3;
+
+/// Tag for decimal fractions (RFC 8949, section 3.4.4).
+#[cfg(feature = "bigdecimal")]
+const DECIMAL_FRACTION_TAG: u64 = 4;
+
+/// Tag for rational numbers, as registered with IANA.
+#[cfg(feature = "num-rational")]
+const RATIONAL_TAG: u64 = 30;
+
+/// Tag for sets, as registered with IANA ("mathematical finite set").
+pub const SET_TAG: u64 = 258;
+
+/// Tag used by this crate for tuples. (This tag is not registered with IANA.)
+pub const TUPLE_TAG: u64 = 0x7079_0001;
+
+/// Tag used by this crate for complex numbers, which are represented as an
+/// array containing the real and imaginary parts. (This tag is not registered
+/// with IANA.)
+pub const COMPLEX_TAG: u64 = 0x7079_0002;
+
+/// Tag used by this crate for `Ellipsis`, which is represented as this tag
+/// applied to null. (This tag is not registered with IANA.)
+pub const ELLIPSIS_TAG: u64 = 0x7079_0003;
+
+/// Tag used by this crate for frozen sets. (This tag is not registered with
+/// IANA.)
+pub const FROZENSET_TAG: u64 = 0x7079_0004;
+
+/// Tag used by this crate for byte arrays. (This tag is not registered with
+/// IANA.)
+pub const BYTEARRAY_TAG: u64 = 0x7079_0005;
+
+/// Tag used by this crate for dates, which are represented as an array
+/// containing the year, month, and day. (This tag is not registered with
+/// IANA.)
+pub const DATE_TAG: u64 = 0x7079_0006;
+
+/// Tag used by this crate for naive datetimes, which are represented as an
+/// array containing the year, month, day, hour, minute, second, and
+/// microsecond. (This tag is not registered with IANA.)
+pub const DATETIME_TAG: u64 = 0x7079_0007;
+
+/// Error converting a CBOR value into a Python literal.
+#[derive(Debug)]
+pub enum FromCborError {
+    /// The CBOR value contained a tag which has no Python literal
+    /// equivalent.
+    UnsupportedTag(u64),
+    /// The content of a tag did not have the expected structure.
+    MalformedTag(u64),
+    /// The CBOR value had a type which has no Python literal equivalent.
+    UnsupportedType(String),
+}
+
+impl Error for FromCborError {}
+
+impl fmt::Display for FromCborError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use FromCborError::*;
+        match self {
+            UnsupportedTag(tag) => write!(f, "unsupported CBOR tag: {}", tag),
+            MalformedTag(tag) => write!(f, "malformed content for CBOR tag {}", tag),
+            UnsupportedType(desc) => write!(f, "unsupported CBOR value: {}", desc),
+        }
+    }
+}
+
+fn integer_to_cbor(int: numb::BigInt) -> CborValue {
+    if let Some(int) = int.to_i128().and_then(|i| CborInteger::try_from(i).ok()) {
+        return CborValue::Integer(int);
+    }
+    if int.sign() == numb::Sign::Minus {
+        let magnitude: numb::BigInt = -int - 1u32;
+        CborValue::Tag(
+            NEGATIVE_BIGNUM_TAG,
+            Box::new(CborValue::Bytes(magnitude.magnitude().to_bytes_be())),
+        )
+    } else {
+        CborValue::Tag(
+            POSITIVE_BIGNUM_TAG,
+            Box::new(CborValue::Bytes(int.magnitude().to_bytes_be())),
+        )
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+fn decimal_to_cbor(decimal: bigdecimal::BigDecimal) -> CborValue {
+    let (mantissa, scale) = decimal.into_bigint_and_exponent();
+    CborValue::Tag(
+        DECIMAL_FRACTION_TAG,
+        Box::new(CborValue::Array(vec![
+            integer_to_cbor(numb::BigInt::from(-scale)),
+            integer_to_cbor(mantissa),
+        ])),
+    )
+}
+
+#[cfg(feature = "num-rational")]
+fn rational_to_cbor(ratio: num_rational::BigRational) -> CborValue {
+    let (numer, denom) = ratio.into();
+    CborValue::Tag(
+        RATIONAL_TAG,
+        Box::new(CborValue::Array(vec![
+            integer_to_cbor(numer),
+            integer_to_cbor(denom),
+        ])),
+    )
+}
+
+fn fields_to_cbor(tag: u64, fields: &[i64]) -> CborValue {
+    CborValue::Tag(
+        tag,
+        Box::new(CborValue::Array(
+            fields
+                .iter()
+                .map(|&field| CborValue::Integer(field.into()))
+                .collect(),
+        )),
+    )
+}
+
+fn seq_to_cbor(seq: Vec<Value>) -> CborValue {
+    CborValue::Array(seq.into_iter().map(CborValue::from).collect())
+}
+
+impl From<Value> for CborValue {
+    fn from(value: Value) -> CborValue {
+        match value {
+            Value::String(s) => CborValue::Text(s),
+            Value::Bytes(b) => CborValue::Bytes(b),
+            Value::ByteArray(b) => CborValue::Tag(BYTEARRAY_TAG, Box::new(CborValue::Bytes(b))),
+            Value::Integer(int) => integer_to_cbor(int),
+            Value::Float(float) => CborValue::Float(float),
+            #[cfg(feature = "bigdecimal")]
+            Value::Decimal(decimal) => decimal_to_cbor(decimal),
+            Value::Complex(numc::Complex { re, im }) => CborValue::Tag(
+                COMPLEX_TAG,
+                Box::new(CborValue::Array(vec![
+                    CborValue::Float(re),
+                    CborValue::Float(im),
+                ])),
+            ),
+            #[cfg(feature = "num-rational")]
+            Value::Rational(ratio) => rational_to_cbor(ratio),
+            Value::Tuple(tup) => CborValue::Tag(TUPLE_TAG, Box::new(seq_to_cbor(tup))),
+            Value::List(list) => seq_to_cbor(list),
+            Value::Dict(dict) => CborValue::Map(
+                dict.into_iter()
+                    .map(|(k, v)| (CborValue::from(k), CborValue::from(v)))
+                    .collect(),
+            ),
+            Value::Set(set) => CborValue::Tag(SET_TAG, Box::new(seq_to_cbor(set))),
+            Value::FrozenSet(set) => CborValue::Tag(FROZENSET_TAG, Box::new(seq_to_cbor(set))),
+            Value::Date(date) => fields_to_cbor(
+                DATE_TAG,
+                &[date.year().into(), date.month().into(), date.day().into()],
+            ),
+            Value::DateTime(datetime) => {
+                let date = datetime.date();
+                fields_to_cbor(
+                    DATETIME_TAG,
+                    &[
+                        date.year().into(),
+                        date.month().into(),
+                        date.day().into(),
+                        datetime.hour().into(),
+                        datetime.minute().into(),
+                        datetime.second().into(),
+                        datetime.microsecond().into(),
+                    ],
+                )
+            }
+            Value::Boolean(b) => CborValue::Bool(b),
+            Value::None => CborValue::Null,
+            Value::Ellipsis => CborValue::Tag(ELLIPSIS_TAG, Box::new(CborValue::Null)),
+        }
+    }
+}
+
+fn cbor_to_f64(value: CborValue, tag: u64) -> Result<f64, FromCborError> {
+    match value {
+        CborValue::Float(float) => Ok(float),
+        CborValue::Integer(int) => Ok(i128::from(int) as f64),
+        _ => Err(FromCborError::MalformedTag(tag)),
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+fn cbor_to_decimal(value: CborValue) -> Result<Value, FromCborError> {
+    let tag = DECIMAL_FRACTION_TAG;
+    let parts = match value {
+        CborValue::Array(parts) => parts,
+        _ => return Err(FromCborError::MalformedTag(tag)),
+    };
+    let mut parts = parts.into_iter();
+    let (exponent, mantissa) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(CborValue::Integer(exponent)), Some(mantissa), None) => (exponent, mantissa),
+        _ => return Err(FromCborError::MalformedTag(tag)),
+    };
+    let scale = i64::try_from(exponent)
+        .ok()
+        .and_then(i64::checked_neg)
+        .ok_or(FromCborError::MalformedTag(tag))?;
+    match Value::try_from(mantissa)? {
+        Value::Integer(mantissa) => {
+            Ok(Value::Decimal(bigdecimal::BigDecimal::new(mantissa, scale)))
+        }
+        _ => Err(FromCborError::MalformedTag(tag)),
+    }
+}
+
+#[cfg(feature = "num-rational")]
+fn cbor_to_rational(value: CborValue) -> Result<Value, FromCborError> {
+    let tag = RATIONAL_TAG;
+    let parts = match value {
+        CborValue::Array(parts) => parts,
+        _ => return Err(FromCborError::MalformedTag(tag)),
+    };
+    let mut parts = parts.into_iter();
+    let (numer, denom) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(numer), Some(denom), None) => (Value::try_from(numer)?, Value::try_from(denom)?),
+        _ => return Err(FromCborError::MalformedTag(tag)),
+    };
+    match (numer, denom) {
+        (Value::Integer(numer), Value::Integer(denom)) if denom.sign() == numb::Sign::Plus => Ok(
+            Value::Rational(num_rational::BigRational::new(numer, denom)),
+        ),
+        _ => Err(FromCborError::MalformedTag(tag)),
+    }
+}
+
+/// Converts an array of small integers, returning `None` if the value isn't
+/// an array of `len` integers in the range of `u32`.
+fn cbor_to_fields(value: CborValue, len: usize) -> Option<Vec<u32>> {
+    match value {
+        CborValue::Array(array) if array.len() == len => array
+            .into_iter()
+            .map(|field| match field {
+                CborValue::Integer(int) => u32::try_from(int).ok(),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+fn cbor_to_date(fields: &[u32]) -> Option<Date> {
+    let small = |n: u32| u8::try_from(n).ok();
+    Date::new(
+        i32::try_from(fields[0]).ok()?,
+        small(fields[1])?,
+        small(fields[2])?,
+    )
+}
+
+fn cbor_to_datetime(fields: &[u32]) -> Option<DateTime> {
+    let small = |n: u32| u8::try_from(n).ok();
+    DateTime::new(
+        cbor_to_date(&fields[..3])?,
+        small(fields[3])?,
+        small(fields[4])?,
+        small(fields[5])?,
+        fields[6],
+    )
+}
+
+fn cbor_to_seq(value: CborValue, tag: u64) -> Result<Vec<Value>, FromCborError> {
+    match value {
+        CborValue::Array(array) => array.into_iter().map(Value::try_from).collect(),
+        _ => Err(FromCborError::MalformedTag(tag)),
+    }
+}
+
+impl TryFrom<CborValue> for Value {
+    type Error = FromCborError;
+
+    fn try_from(value: CborValue) -> Result<Value, FromCborError> {
+        match value {
+            CborValue::Integer(int) => Ok(Value::Integer(i128::from(int).into())),
+            CborValue::Bytes(b) => Ok(Value::Bytes(b)),
+            CborValue::Float(float) => Ok(Value::Float(float)),
+            CborValue::Text(s) => Ok(Value::String(s)),
+            CborValue::Bool(b) => Ok(Value::Boolean(b)),
+            CborValue::Null => Ok(Value::None),
+            CborValue::Array(array) => Ok(Value::List(
+                array
+                    .into_iter()
+                    .map(Value::try_from)
+                    .collect::<Result<_, _>>()?,
+            )),
+            CborValue::Map(map) => Ok(Value::Dict(
+                map.into_iter()
+                    .map(|(k, v)| Ok((Value::try_from(k)?, Value::try_from(v)?)))
+                    .collect::<Result<_, FromCborError>>()?,
+            )),
+            CborValue::Tag(tag, content) => match (tag, *content) {
+                (POSITIVE_BIGNUM_TAG, CborValue::Bytes(b)) => Ok(Value::Integer(
+                    numb::BigInt::from_bytes_be(numb::Sign::Plus, &b),
+                )),
+                (NEGATIVE_BIGNUM_TAG, CborValue::Bytes(b)) => Ok(Value::Integer(
+                    -numb::BigInt::from_bytes_be(numb::Sign::Plus, &b) - 1u32,
+                )),
+                (POSITIVE_BIGNUM_TAG, _) | (NEGATIVE_BIGNUM_TAG, _) => {
+                    Err(FromCborError::MalformedTag(tag))
+                }
+                #[cfg(feature = "bigdecimal")]
+                (DECIMAL_FRACTION_TAG, content) => cbor_to_decimal(content),
+                #[cfg(feature = "num-rational")]
+                (RATIONAL_TAG, content) => cbor_to_rational(content),
+                (SET_TAG, content) => Ok(Value::Set(cbor_to_seq(content, tag)?)),
+                (TUPLE_TAG, content) => Ok(Value::Tuple(cbor_to_seq(content, tag)?)),
+                (FROZENSET_TAG, content) => Ok(Value::FrozenSet(cbor_to_seq(content, tag)?)),
+                (COMPLEX_TAG, CborValue::Array(parts)) => {
+                    let mut parts = parts.into_iter();
+                    match (parts.next(), parts.next(), parts.next()) {
+                        (Some(re), Some(im), None) => Ok(Value::Complex(numc::Complex::new(
+                            cbor_to_f64(re, tag)?,
+                            cbor_to_f64(im, tag)?,
+                        ))),
+                        _ => Err(FromCborError::MalformedTag(tag)),
+                    }
+                }
+                (COMPLEX_TAG, _) => Err(FromCborError::MalformedTag(tag)),
+                (BYTEARRAY_TAG, CborValue::Bytes(b)) => Ok(Value::ByteArray(b)),
+                (BYTEARRAY_TAG, _) => Err(FromCborError::MalformedTag(tag)),
+                (DATE_TAG, content) => cbor_to_fields(content, 3)
+                    .and_then(|fields| cbor_to_date(&fields))
+                    .map(Value::Date)
+                    .ok_or(FromCborError::MalformedTag(tag)),
+                (DATETIME_TAG, content) => cbor_to_fields(content, 7)
+                    .and_then(|fields| cbor_to_datetime(&fields))
+                    .map(Value::DateTime)
+                    .ok_or(FromCborError::MalformedTag(tag)),
+                (ELLIPSIS_TAG, CborValue::Null) => Ok(Value::Ellipsis),
+                (ELLIPSIS_TAG, _) => Err(FromCborError::MalformedTag(tag)),
+                _ => Err(FromCborError::UnsupportedTag(tag)),
+            },
+            other => Err(FromCborError::UnsupportedType(format!("{:?}", other))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let value: Value = "{'a': [1, (2.5, 3-4j)], b'b': {-5, None}, \
+                            (True,): 123456789012345678901234567890123456789012, \
+                            -98765432109876543210987654321098765432109: (), \
+                            'idx': (..., 0)}"
+            .parse()
+            .unwrap();
+        let cbor = CborValue::from(value.clone());
+        assert_eq!(Value::try_from(cbor).unwrap(), value);
+    }
+
+    #[test]
+    fn bignum_encoding() {
+        let big = numb::BigInt::from(1u8) << 64usize;
+        assert_eq!(
+            CborValue::from(Value::Integer(big)),
+            CborValue::Tag(
+                2,
+                Box::new(CborValue::Bytes(vec![1, 0, 0, 0, 0, 0, 0, 0, 0]))
+            ),
+        );
+        let small = -(numb::BigInt::from(1u8) << 64usize);
+        assert_eq!(
+            CborValue::from(Value::Integer(small)),
+            CborValue::Integer(CborInteger::try_from(-(1i128 << 64)).unwrap()),
+        );
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    #[test]
+    fn decimal_roundtrip() {
+        for &decimal in &["1.230", "-5E+3", "123456789012345678901234567890.5"] {
+            let value = Value::Decimal(decimal.parse().unwrap());
+            let cbor = CborValue::from(value.clone());
+            assert_eq!(Value::try_from(cbor).unwrap(), value);
+        }
+        let cbor = CborValue::from(Value::Decimal("-1.5".parse().unwrap()));
+        assert_eq!(
+            cbor,
+            CborValue::Tag(
+                4,
+                Box::new(CborValue::Array(vec![
+                    CborValue::Integer((-1).into()),
+                    CborValue::Integer((-15).into()),
+                ])),
+            ),
+        );
+    }
+
+    #[cfg(feature = "num-rational")]
+    #[test]
+    fn rational_roundtrip() {
+        let ratio = num_rational::BigRational::new((-1).into(), 3.into());
+        let cbor = CborValue::from(Value::Rational(ratio.clone()));
+        assert_eq!(
+            cbor,
+            CborValue::Tag(
+                30,
+                Box::new(CborValue::Array(vec![
+                    CborValue::Integer((-1).into()),
+                    CborValue::Integer(3.into()),
+                ])),
+            ),
+        );
+        assert_eq!(Value::try_from(cbor).unwrap(), Value::Rational(ratio));
+    }
+
+    #[test]
+    fn datetime_roundtrip() {
+        let date = Date::new(2024, 2, 29).unwrap();
+        for value in &[
+            Value::Date(date),
+            Value::DateTime(DateTime::new(date, 23, 59, 58, 999_999).unwrap()),
+        ] {
+            let cbor = CborValue::from(value.clone());
+            assert_eq!(Value::try_from(cbor).unwrap(), *value);
+        }
+        let invalid = fields_to_cbor(DATE_TAG, &[2023, 2, 29]);
+        match Value::try_from(invalid) {
+            Err(FromCborError::MalformedTag(DATE_TAG)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unsupported_tag() {
+        let cbor = CborValue::Tag(1, Box::new(CborValue::Integer(0.into())));
+        match Value::try_from(cbor) {
+            Err(FromCborError::UnsupportedTag(1)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
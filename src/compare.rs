@@ -0,0 +1,495 @@
+//! Comparison of values with Python semantics.
+
+use crate::Value;
+use num_bigint as numb;
+use num_traits::FromPrimitive;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+/// Real number, for comparing numeric values of different types.
+enum Real {
+    Integer(numb::BigInt),
+    Float(f64),
+    #[cfg(feature = "bigdecimal")]
+    Decimal(bigdecimal::BigDecimal),
+    #[cfg(feature = "num-rational")]
+    Rational(num_rational::BigRational),
+}
+
+/// Returns the value as a complex number (with real and imaginary parts), or
+/// `None` if it isn't a number.
+fn as_number(value: &Value) -> Option<(Real, f64)> {
+    match value {
+        Value::Boolean(b) => Some((Real::Integer(u8::from(*b).into()), 0.)),
+        Value::Integer(int) => Some((Real::Integer(int.clone()), 0.)),
+        Value::Float(float) => Some((Real::Float(*float), 0.)),
+        Value::Complex(comp) => Some((Real::Float(comp.re), comp.im)),
+        #[cfg(feature = "bigdecimal")]
+        Value::Decimal(decimal) => Some((Real::Decimal(decimal.clone()), 0.)),
+        #[cfg(feature = "num-rational")]
+        Value::Rational(ratio) => Some((Real::Rational(ratio.clone()), 0.)),
+        _ => None,
+    }
+}
+
+fn float_eq_int(float: f64, int: &numb::BigInt) -> bool {
+    float.fract() == 0. && numb::BigInt::from_f64(float).as_ref() == Some(int)
+}
+
+fn real_eq(lhs: &Real, rhs: &Real) -> bool {
+    use self::Real::*;
+    match (lhs, rhs) {
+        (Integer(a), Integer(b)) => a == b,
+        (Float(a), Float(b)) => a == b,
+        (Integer(int), Float(float)) | (Float(float), Integer(int)) => float_eq_int(*float, int),
+        #[cfg(feature = "bigdecimal")]
+        (Decimal(a), Decimal(b)) => a == b,
+        #[cfg(feature = "bigdecimal")]
+        (Decimal(decimal), Integer(int)) | (Integer(int), Decimal(decimal)) => {
+            *decimal == bigdecimal::BigDecimal::from(int.clone())
+        }
+        #[cfg(feature = "num-rational")]
+        (Rational(a), Rational(b)) => a == b,
+        #[cfg(feature = "num-rational")]
+        (Rational(ratio), Integer(int)) | (Integer(int), Rational(ratio)) => {
+            ratio.is_integer() && ratio.numer() == int
+        }
+        #[cfg(feature = "num-rational")]
+        (Rational(ratio), Float(float)) | (Float(float), Rational(ratio)) => {
+            num_rational::BigRational::from_float(*float).as_ref() == Some(ratio)
+        }
+        // Decimals are only compared with integers and other decimals.
+        #[allow(unreachable_patterns)]
+        _ => false,
+    }
+}
+
+/// Returns whether every element of `a` is equal to some element of `b`,
+/// according to `eq`.
+pub(crate) fn is_subset<A, B>(a: &[A], b: &[B], eq: impl Fn(&A, &B) -> bool) -> bool {
+    a.iter().all(|x| b.iter().any(|y| eq(x, y)))
+}
+
+/// Returns the items of the dict which aren't overridden by a later item
+/// with an equal key (according to `eq`), as in a Python dict display.
+fn last_items<T>(items: &[(T, T)], eq: impl Fn(&T, &T) -> bool) -> Vec<&(T, T)> {
+    items
+        .iter()
+        .enumerate()
+        .filter(|(i, (key, _))| !items[i + 1..].iter().any(|(later, _)| eq(key, later)))
+        .map(|(_, item)| item)
+        .collect()
+}
+
+/// Returns whether the dicts would compare equal in Python, given the
+/// equality of keys within each dict (`eq_a` and `eq_b`) and of keys and
+/// values between them (`eq`). Duplicate keys are resolved first, with the
+/// last value winning.
+pub(crate) fn dict_eq<A, B>(
+    a: &[(A, A)],
+    b: &[(B, B)],
+    eq_a: impl Fn(&A, &A) -> bool,
+    eq_b: impl Fn(&B, &B) -> bool,
+    eq: impl Fn(&A, &B) -> bool,
+) -> bool {
+    let (a, b) = (last_items(a, eq_a), last_items(b, eq_b));
+    let item_eq = |(a_key, a_value): &&(A, A), (b_key, b_value): &&(B, B)| {
+        eq(a_key, b_key) && eq(a_value, b_value)
+    };
+    a.len() == b.len() && is_subset(&a, &b, item_eq) && is_subset(&b, &a, |y, x| item_eq(x, y))
+}
+
+/// Returns whether the values would compare equal with `==` in Python.
+///
+/// Unlike `Value`'s `PartialEq` implementation, this considers numbers of
+/// different types (e.g. `1`, `1.0`, and `True`) to be equal if they have the
+/// same value, ignores the order of elements in sets and items in dicts, and
+/// considers sets and frozen sets with the same elements to be equal.
+pub(crate) fn python_eq(lhs: &Value, rhs: &Value) -> bool {
+    use crate::Value::*;
+    if let (Some((a_re, a_im)), Some((b_re, b_im))) = (as_number(lhs), as_number(rhs)) {
+        return a_im == b_im && real_eq(&a_re, &b_re);
+    }
+    match (lhs, rhs) {
+        (String(a), String(b)) => a == b,
+        (Bytes(a), Bytes(b))
+        | (Bytes(a), ByteArray(b))
+        | (ByteArray(a), Bytes(b))
+        | (ByteArray(a), ByteArray(b)) => a == b,
+        (Tuple(a), Tuple(b)) | (List(a), List(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(x, y)| python_eq(x, y))
+        }
+        (Dict(a), Dict(b)) => dict_eq(a, b, python_eq, python_eq, python_eq),
+        (Set(a), Set(b))
+        | (Set(a), FrozenSet(b))
+        | (FrozenSet(a), Set(b))
+        | (FrozenSet(a), FrozenSet(b)) => is_subset(a, b, python_eq) && is_subset(b, a, python_eq),
+        (Date(a), Date(b)) => a == b,
+        (DateTime(a), DateTime(b)) => a == b,
+        (None, None) | (Ellipsis, Ellipsis) => true,
+        _ => false,
+    }
+}
+
+/// Kind of value, for `python_hash`. Values of different kinds never compare
+/// equal.
+#[derive(Clone, Copy)]
+pub(crate) enum HashKind {
+    Number,
+    String,
+    Bytes,
+    Tuple,
+    List,
+    Dict,
+    Set,
+    Date,
+    DateTime,
+    None,
+    Ellipsis,
+}
+
+impl HashKind {
+    pub(crate) fn hash(self, state: &mut dyn Hasher) {
+        state.write_u8(self as u8);
+    }
+}
+
+/// Hashes a real number, so that equal numbers of different types hash
+/// equal.
+fn hash_real(real: &Real, mut state: &mut dyn Hasher) {
+    match real {
+        Real::Integer(int) => int.hash(&mut state),
+        Real::Float(float) => match numb::BigInt::from_f64(*float) {
+            Some(int) if float.fract() == 0. => int.hash(&mut state),
+            _ => float.to_bits().hash(&mut state),
+        },
+        #[cfg(feature = "bigdecimal")]
+        Real::Decimal(decimal) => {
+            if decimal.is_integer() {
+                decimal
+                    .with_scale(0)
+                    .as_bigint_and_exponent()
+                    .0
+                    .hash(&mut state)
+            } else {
+                decimal.hash(&mut state)
+            }
+        }
+        #[cfg(feature = "num-rational")]
+        Real::Rational(ratio) => {
+            use num_traits::ToPrimitive;
+            if ratio.is_integer() {
+                return ratio.numer().hash(&mut state);
+            }
+            match ratio.to_f64() {
+                Some(float)
+                    if num_rational::BigRational::from_float(float).as_ref() == Some(ratio) =>
+                {
+                    float.to_bits().hash(&mut state)
+                }
+                _ => ratio.hash(&mut state),
+            }
+        }
+    }
+}
+
+/// Hashes a string, like `python_hash` does for `Value::String`.
+pub(crate) fn hash_str(string: &str, mut state: &mut dyn Hasher) {
+    HashKind::String.hash(state);
+    string.hash(&mut state);
+}
+
+/// Hashes a byte string, like `python_hash` does for `Value::Bytes` and
+/// `Value::ByteArray`.
+pub(crate) fn hash_bytes(bytes: &[u8], mut state: &mut dyn Hasher) {
+    HashKind::Bytes.hash(state);
+    bytes.hash(&mut state);
+}
+
+/// Hashes a tuple or list, hashing the elements with `hash_elem` if `depth`
+/// is nonzero.
+pub(crate) fn hash_sequence<T>(
+    kind: HashKind,
+    elems: &[T],
+    depth: usize,
+    state: &mut dyn Hasher,
+    hash_elem: impl Fn(&T, usize, &mut dyn Hasher),
+) {
+    kind.hash(state);
+    state.write_usize(elems.len());
+    if depth > 0 {
+        for elem in elems {
+            hash_elem(elem, depth - 1, state);
+        }
+    }
+}
+
+/// Hashes the value, so that values which are equal according to
+/// `python_eq` hash equal. Elements of tuples and lists are hashed to
+/// `depth` levels of nesting. Only the kind of dicts and sets is hashed,
+/// since they can be equal regardless of their order and duplicates.
+pub(crate) fn python_hash(value: &Value, depth: usize, mut state: &mut dyn Hasher) {
+    if let Some((re, im)) = as_number(value) {
+        HashKind::Number.hash(state);
+        hash_real(&re, state);
+        if im != 0. {
+            im.to_bits().hash(&mut state);
+        }
+        return;
+    }
+    match value {
+        Value::String(string) => hash_str(string, state),
+        Value::Bytes(bytes) | Value::ByteArray(bytes) => hash_bytes(bytes, state),
+        Value::Tuple(tup) => hash_sequence(HashKind::Tuple, tup, depth, state, python_hash),
+        Value::List(list) => hash_sequence(HashKind::List, list, depth, state, python_hash),
+        Value::Dict(_) => HashKind::Dict.hash(state),
+        Value::Set(_) | Value::FrozenSet(_) => HashKind::Set.hash(state),
+        Value::Date(date) => {
+            HashKind::Date.hash(state);
+            date.hash(&mut state);
+        }
+        Value::DateTime(datetime) => {
+            HashKind::DateTime.hash(state);
+            datetime.hash(&mut state);
+        }
+        Value::None => HashKind::None.hash(state),
+        Value::Ellipsis => HashKind::Ellipsis.hash(state),
+        _ => unreachable!(),
+    }
+}
+
+/// Returns the position of the value's type in the order used by
+/// `total_cmp`.
+fn type_rank(value: &Value) -> u8 {
+    match value {
+        Value::None => 0,
+        Value::Boolean(_) => 1,
+        Value::Integer(_) => 2,
+        Value::Float(_) => 3,
+        Value::Complex(_) => 4,
+        #[cfg(feature = "bigdecimal")]
+        Value::Decimal(_) => 5,
+        #[cfg(feature = "num-rational")]
+        Value::Rational(_) => 6,
+        Value::String(_) => 7,
+        Value::Bytes(_) => 8,
+        Value::ByteArray(_) => 9,
+        Value::Tuple(_) => 10,
+        Value::List(_) => 11,
+        Value::Dict(_) => 12,
+        Value::Set(_) => 13,
+        Value::FrozenSet(_) => 14,
+        Value::Date(_) => 15,
+        Value::DateTime(_) => 16,
+        Value::Ellipsis => 17,
+    }
+}
+
+/// Total order of floats, which orders NaNs with a positive sign after
+/// infinity, and NaNs with a negative sign before negative infinity.
+fn float_cmp(lhs: f64, rhs: f64) -> Ordering {
+    let key = |float: f64| {
+        let bits = float.to_bits() as i64;
+        bits ^ (((bits >> 63) as u64) >> 1) as i64
+    };
+    key(lhs).cmp(&key(rhs))
+}
+
+fn seq_cmp<'a>(
+    lhs: impl IntoIterator<Item = &'a Value>,
+    rhs: impl IntoIterator<Item = &'a Value>,
+) -> Ordering {
+    let mut rhs = rhs.into_iter();
+    for a in lhs {
+        match rhs.next() {
+            Some(b) => match total_cmp(a, b) {
+                Ordering::Equal => (),
+                ord => return ord,
+            },
+            None => return Ordering::Greater,
+        }
+    }
+    if rhs.next().is_some() {
+        Ordering::Less
+    } else {
+        Ordering::Equal
+    }
+}
+
+/// Returns references to the elements in sorted order.
+pub(crate) fn sorted(elems: &[Value]) -> Vec<&Value> {
+    let mut sorted: Vec<_> = elems.iter().collect();
+    sorted.sort_by(|a, b| total_cmp(a, b));
+    sorted
+}
+
+/// Returns references to the items of a dict, sorted by key (and then by
+/// value, for duplicate keys).
+pub(crate) fn sorted_items(dict: &[(Value, Value)]) -> Vec<&(Value, Value)> {
+    let mut sorted: Vec<_> = dict.iter().collect();
+    sorted.sort_by(|(a_key, a_value), (b_key, b_value)| {
+        total_cmp(a_key, b_key).then_with(|| total_cmp(a_value, b_value))
+    });
+    sorted
+}
+
+/// Deterministic total order of values, for sorting.
+///
+/// Values of different types are ordered by type. Values of the same type
+/// are ordered naturally; strings and bytes by their code points or bytes,
+/// tuples and lists lexicographically, sets by their sorted elements, and
+/// dicts by their sorted items.
+pub(crate) fn total_cmp(lhs: &Value, rhs: &Value) -> Ordering {
+    use crate::Value::*;
+    match (lhs, rhs) {
+        (Boolean(a), Boolean(b)) => a.cmp(b),
+        (Integer(a), Integer(b)) => a.cmp(b),
+        (Float(a), Float(b)) => float_cmp(*a, *b),
+        (Complex(a), Complex(b)) => float_cmp(a.re, b.re).then_with(|| float_cmp(a.im, b.im)),
+        #[cfg(feature = "bigdecimal")]
+        (Decimal(a), Decimal(b)) => a.cmp(b),
+        #[cfg(feature = "num-rational")]
+        (Rational(a), Rational(b)) => a.cmp(b),
+        (String(a), String(b)) => a.cmp(b),
+        (Bytes(a), Bytes(b)) | (ByteArray(a), ByteArray(b)) => a.cmp(b),
+        (Tuple(a), Tuple(b)) | (List(a), List(b)) => seq_cmp(a, b),
+        (Dict(a), Dict(b)) => {
+            let (a, b) = (sorted_items(a), sorted_items(b));
+            for ((a_key, a_value), (b_key, b_value)) in a.iter().zip(&b) {
+                match total_cmp(a_key, b_key).then_with(|| total_cmp(a_value, b_value)) {
+                    Ordering::Equal => (),
+                    ord => return ord,
+                }
+            }
+            a.len().cmp(&b.len())
+        }
+        (Set(a), Set(b)) | (FrozenSet(a), FrozenSet(b)) => seq_cmp(sorted(a), sorted(b)),
+        (Date(a), Date(b)) => a.cmp(b),
+        (DateTime(a), DateTime(b)) => a.cmp(b),
+        _ => type_rank(lhs).cmp(&type_rank(rhs)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn python_eq_example() {
+        for &(a, b, eq) in &[
+            ("1", "1.0", true),
+            ("1", "True", true),
+            ("0", "False", true),
+            ("1", "1+0j", true),
+            ("1.5", "1.5+0j", true),
+            ("1", "1+1j", false),
+            ("0.5", "1", false),
+            ("'a'", "b'a'", false),
+            ("(1, 'a')", "(1.0, 'a')", true),
+            ("(1,)", "[1]", false),
+            ("{1: 'a', 2: 'b'}", "{2.0: 'b', True: 'a'}", true),
+            ("{1: 'a'}", "{1: 'b'}", false),
+            ("{1: 'a', 1: 'a'}", "{1: 'a', 2: 'b'}", false),
+            ("{1: 'a', 2: 'b'}", "{1: 'a', 1: 'a'}", false),
+            ("{1: 'a', True: 'b'}", "{1: 'b'}", true),
+            (
+                "{1: 'b', 2: 'c', 1.0: 'a'}",
+                "{2: 'c', 1: 'a', 2: 'c'}",
+                true,
+            ),
+            ("{1, 2}", "{2, 1, 1.0}", true),
+            ("{1, 2}", "{1}", false),
+            ("None", "None", true),
+            ("None", "...", false),
+        ] {
+            let (a, b) = (a.parse().unwrap(), b.parse().unwrap());
+            assert_eq!(python_eq(&a, &b), eq, "{:?} == {:?}", a, b);
+        }
+        let nan = Value::Float(std::f64::NAN);
+        assert!(!python_eq(&nan, &nan));
+        let big = Value::Integer(numb::BigInt::from(1u8) << 64usize);
+        assert!(python_eq(&big, &Value::Float(18446744073709551616.)));
+        assert!(!python_eq(
+            &Value::Float(1e300),
+            &Value::Float(std::f64::INFINITY)
+        ));
+    }
+
+    #[test]
+    fn python_hash_example() {
+        let hash = |value: &Value| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            python_hash(value, 2, &mut hasher);
+            hasher.finish()
+        };
+        for &(a, b) in &[
+            ("1", "1.0"),
+            ("1", "True"),
+            ("-0.0", "False"),
+            ("2.5", "2.5+0j"),
+            ("-1j", "-0.0-1j"),
+            ("(1, ('a', [2]))", "(True, ('a', [2.0]))"),
+            ("(((1,),),)", "(((1.0,),),)"),
+            ("{1: 'a', 1: 'b'}", "{1.0: 'b'}"),
+            ("{1, 1}", "frozenset({1})"),
+            ("b'a'", "bytearray(b'a')"),
+        ] {
+            let opts = crate::ParseOptions::new().frozensets(true).bytearrays(true);
+            let a = Value::parse_with_options(a, &opts).unwrap();
+            let b = Value::parse_with_options(b, &opts).unwrap();
+            assert!(python_eq(&a, &b), "{:?} == {:?}", a, b);
+            assert_eq!(hash(&a), hash(&b), "{:?} and {:?}", a, b);
+        }
+        for &(a, b) in &[
+            ("1", "2"),
+            ("0.5", "0.25"),
+            ("'a'", "b'a'"),
+            ("(1,)", "[1]"),
+        ] {
+            let (a, b) = (a.parse().unwrap(), b.parse().unwrap());
+            assert_ne!(hash(&a), hash(&b), "{:?} and {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn total_cmp_example() {
+        let values = [
+            "None",
+            "False",
+            "True",
+            "-2",
+            "10",
+            "-1e300",
+            "0.5",
+            "1j",
+            "''",
+            "'a'",
+            "'b'",
+            "b'a'",
+            "()",
+            "(1,)",
+            "(1, 2)",
+            "(2,)",
+            "[]",
+            "{}",
+            "{1: 'b', 0: 'z'}",
+            "{1: 'a'}",
+            "{1: 'b'}",
+            "{2, 1}",
+            "{1, 3}",
+            "...",
+        ];
+        let values: Vec<Value> = values.iter().map(|s| s.parse().unwrap()).collect();
+        for (i, a) in values.iter().enumerate() {
+            for (j, b) in values.iter().enumerate() {
+                assert_eq!(total_cmp(a, b), i.cmp(&j), "{} vs {}", a, b);
+            }
+        }
+        let nan = Value::Float(std::f64::NAN);
+        assert_eq!(total_cmp(&nan, &nan), Ordering::Equal);
+        assert_eq!(
+            total_cmp(&Value::Float(std::f64::INFINITY), &nan),
+            Ordering::Less
+        );
+    }
+}
@@ -0,0 +1,650 @@
+//! Conversions between [`Value`] and Rust types.
+//!
+//! Since `Vec<u8>` converts to `Value::Bytes`, there's no generic conversion
+//! from `Vec<T>` to `Value::List`; only `Vec<Value>` converts to a list, so
+//! the elements of other vectors need to be converted first. Likewise,
+//! `Vec<u8>` is converted from bytes rather than from a list of integers.
+//!
+//! [`Value`]: ../enum.Value.html
+
+use crate::Value;
+use num_bigint as numb;
+use num_complex as numc;
+use num_traits::ToPrimitive;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::convert::{Infallible, TryFrom};
+use std::error::Error;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+
+/// Error converting a [`Value`] to a Rust type.
+///
+/// [`Value`]: enum.Value.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TryFromValueError {
+    /// The value had the wrong type. Contains the names of the expected and
+    /// actual Python types, like `int` and `str`.
+    InvalidType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The value was an integer out of range of the Rust type. Contains the
+    /// name of the Rust type, like `u64`.
+    OutOfRange(&'static str),
+}
+
+impl Error for TryFromValueError {}
+
+impl fmt::Display for TryFromValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use TryFromValueError::*;
+        match self {
+            InvalidType { expected, found } => write!(f, "expected {}, found {}", expected, found),
+            OutOfRange(ty) => write!(f, "integer out of range for {}", ty),
+        }
+    }
+}
+
+impl From<Infallible> for TryFromValueError {
+    fn from(never: Infallible) -> TryFromValueError {
+        match never {}
+    }
+}
+
+/// Returns the name of the Python type of the value.
+pub(crate) fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::String(_) => "str",
+        Value::Bytes(_) => "bytes",
+        Value::ByteArray(_) => "bytearray",
+        Value::Integer(_) => "int",
+        Value::Float(_) => "float",
+        Value::Complex(_) => "complex",
+        #[cfg(feature = "bigdecimal")]
+        Value::Decimal(_) => "Decimal",
+        #[cfg(feature = "num-rational")]
+        Value::Rational(_) => "Fraction",
+        Value::Tuple(_) => "tuple",
+        Value::List(_) => "list",
+        Value::Dict(_) => "dict",
+        Value::Set(_) => "set",
+        Value::FrozenSet(_) => "frozenset",
+        Value::Date(_) => "date",
+        Value::DateTime(_) => "datetime",
+        Value::Boolean(_) => "bool",
+        Value::None => "NoneType",
+        Value::Ellipsis => "ellipsis",
+    }
+}
+
+fn invalid_type(expected: &'static str, value: &Value) -> TryFromValueError {
+    TryFromValueError::InvalidType {
+        expected,
+        found: type_name(value),
+    }
+}
+
+macro_rules! impl_from_integer {
+    ($($int:ty),*) => {
+        $(
+            impl From<$int> for Value {
+                fn from(int: $int) -> Value {
+                    Value::Integer(int.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl From<numb::BigInt> for Value {
+    fn from(int: numb::BigInt) -> Value {
+        Value::Integer(int)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(float: f64) -> Value {
+        Value::Float(float)
+    }
+}
+
+impl From<numc::Complex<f64>> for Value {
+    fn from(complex: numc::Complex<f64>) -> Value {
+        Value::Complex(complex)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(boolean: bool) -> Value {
+        Value::Boolean(boolean)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(string: &str) -> Value {
+        Value::String(string.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(string: String) -> Value {
+        Value::String(string)
+    }
+}
+
+/// Converts the bytes to `Value::Bytes`.
+impl From<Vec<u8>> for Value {
+    fn from(bytes: Vec<u8>) -> Value {
+        Value::Bytes(bytes)
+    }
+}
+
+/// Converts the unit value to `Value::None`.
+impl From<()> for Value {
+    fn from((): ()) -> Value {
+        Value::None
+    }
+}
+
+/// Converts the elements to `Value::List`.
+impl From<Vec<Value>> for Value {
+    fn from(list: Vec<Value>) -> Value {
+        Value::List(list)
+    }
+}
+
+/// Converts `Some(value)` to the converted value and `None` to `Value::None`.
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Value {
+        option.map_or(Value::None, Into::into)
+    }
+}
+
+macro_rules! impl_from_tuple {
+    ($(($($elem:ident),+)),*) => {
+        $(
+            /// Converts the tuple to `Value::Tuple`.
+            impl<$($elem: Into<Value>),+> From<($($elem,)+)> for Value {
+                #[allow(non_snake_case)]
+                fn from(($($elem,)+): ($($elem,)+)) -> Value {
+                    Value::Tuple(vec![$($elem.into()),+])
+                }
+            }
+        )*
+    };
+}
+
+impl_from_tuple!(
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F),
+    (A, B, C, D, E, F, G),
+    (A, B, C, D, E, F, G, H),
+    (A, B, C, D, E, F, G, H, I),
+    (A, B, C, D, E, F, G, H, I, J),
+    (A, B, C, D, E, F, G, H, I, J, K),
+    (A, B, C, D, E, F, G, H, I, J, K, L)
+);
+
+/// Converts the map to `Value::Dict`, in the map's iteration order.
+impl<K, V, S> From<HashMap<K, V, S>> for Value
+where
+    K: Into<Value>,
+    V: Into<Value>,
+    S: BuildHasher,
+{
+    fn from(map: HashMap<K, V, S>) -> Value {
+        Value::Dict(map.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+/// Converts the map to `Value::Dict`, with the keys in sorted order.
+impl<K: Into<Value>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(map: BTreeMap<K, V>) -> Value {
+        Value::Dict(map.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+/// Converts the set to `Value::Set`, in the set's iteration order.
+impl<T: Into<Value>, S: BuildHasher> From<HashSet<T, S>> for Value {
+    fn from(set: HashSet<T, S>) -> Value {
+        Value::Set(set.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Converts the set to `Value::Set`, with the elements in sorted order.
+impl<T: Into<Value>> From<BTreeSet<T>> for Value {
+    fn from(set: BTreeSet<T>) -> Value {
+        Value::Set(set.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Implements `TryFrom<&Value>` and `TryFrom<Value>` for a `Copy` type, given
+/// the name of the expected Python type, and a pattern matching a reference
+/// to the accepted variant with an expression converting its contents.
+macro_rules! impl_try_from_value {
+    ($ty:ty, $expected:expr, $($pat:pat)|+ => $convert:expr) => {
+        /// Converts the value if it has the corresponding type. Integers
+        /// out of range of the Rust type are rejected.
+        impl TryFrom<&Value> for $ty {
+            type Error = TryFromValueError;
+
+            fn try_from(value: &Value) -> Result<$ty, TryFromValueError> {
+                match value {
+                    $($pat)|+ => $convert,
+                    _ => Err(invalid_type($expected, value)),
+                }
+            }
+        }
+
+        /// Converts the value if it has the corresponding type. Integers
+        /// out of range of the Rust type are rejected.
+        impl TryFrom<Value> for $ty {
+            type Error = TryFromValueError;
+
+            fn try_from(value: Value) -> Result<$ty, TryFromValueError> {
+                <$ty>::try_from(&value)
+            }
+        }
+    };
+}
+
+impl_try_from_value!(i64, "int", Value::Integer(int) => {
+    int.to_i64().ok_or(TryFromValueError::OutOfRange("i64"))
+});
+impl_try_from_value!(u64, "int", Value::Integer(int) => {
+    int.to_u64().ok_or(TryFromValueError::OutOfRange("u64"))
+});
+impl_try_from_value!(f64, "float", Value::Float(float) => Ok(*float));
+impl_try_from_value!(numc::Complex<f64>, "complex", Value::Complex(complex) => Ok(*complex));
+impl_try_from_value!(bool, "bool", Value::Boolean(boolean) => Ok(*boolean));
+
+/// Converts `Value::Integer`.
+impl TryFrom<&Value> for numb::BigInt {
+    type Error = TryFromValueError;
+
+    fn try_from(value: &Value) -> Result<numb::BigInt, TryFromValueError> {
+        match value {
+            Value::Integer(int) => Ok(int.clone()),
+            _ => Err(invalid_type("int", value)),
+        }
+    }
+}
+
+/// Converts `Value::Integer`.
+impl TryFrom<Value> for numb::BigInt {
+    type Error = TryFromValueError;
+
+    fn try_from(value: Value) -> Result<numb::BigInt, TryFromValueError> {
+        match value {
+            Value::Integer(int) => Ok(int),
+            _ => Err(invalid_type("int", &value)),
+        }
+    }
+}
+
+/// Converts `Value::String`.
+impl TryFrom<&Value> for String {
+    type Error = TryFromValueError;
+
+    fn try_from(value: &Value) -> Result<String, TryFromValueError> {
+        match value {
+            Value::String(string) => Ok(string.clone()),
+            _ => Err(invalid_type("str", value)),
+        }
+    }
+}
+
+/// Converts `Value::String`.
+impl TryFrom<Value> for String {
+    type Error = TryFromValueError;
+
+    fn try_from(value: Value) -> Result<String, TryFromValueError> {
+        match value {
+            Value::String(string) => Ok(string),
+            _ => Err(invalid_type("str", &value)),
+        }
+    }
+}
+
+/// Converts `Value::Bytes` or `Value::ByteArray`.
+impl TryFrom<&Value> for Vec<u8> {
+    type Error = TryFromValueError;
+
+    fn try_from(value: &Value) -> Result<Vec<u8>, TryFromValueError> {
+        match value {
+            Value::Bytes(bytes) | Value::ByteArray(bytes) => Ok(bytes.clone()),
+            _ => Err(invalid_type("bytes", value)),
+        }
+    }
+}
+
+/// Converts `Value::Bytes` or `Value::ByteArray`.
+impl TryFrom<Value> for Vec<u8> {
+    type Error = TryFromValueError;
+
+    fn try_from(value: Value) -> Result<Vec<u8>, TryFromValueError> {
+        match value {
+            Value::Bytes(bytes) | Value::ByteArray(bytes) => Ok(bytes),
+            _ => Err(invalid_type("bytes", &value)),
+        }
+    }
+}
+
+/// Converts each element of the value, if `accept` returns its elements.
+fn try_from_elems<C, T>(
+    value: Value,
+    expected: &'static str,
+    accept: fn(Value) -> Result<Vec<Value>, Value>,
+) -> Result<C, TryFromValueError>
+where
+    C: std::iter::FromIterator<T>,
+    T: TryFrom<Value>,
+    T::Error: Into<TryFromValueError>,
+{
+    match accept(value) {
+        Ok(elems) => elems
+            .into_iter()
+            .map(|elem| T::try_from(elem).map_err(Into::into))
+            .collect(),
+        Err(value) => Err(invalid_type(expected, &value)),
+    }
+}
+
+/// Converts the items of a dict with string keys.
+fn try_from_items<C, T>(value: Value) -> Result<C, TryFromValueError>
+where
+    C: std::iter::FromIterator<(String, T)>,
+    T: TryFrom<Value>,
+    T::Error: Into<TryFromValueError>,
+{
+    match value {
+        Value::Dict(items) => items
+            .into_iter()
+            .map(|(key, value)| {
+                Ok((
+                    String::try_from(key)?,
+                    T::try_from(value).map_err(Into::into)?,
+                ))
+            })
+            .collect(),
+        _ => Err(invalid_type("dict", &value)),
+    }
+}
+
+fn list_or_tuple(value: Value) -> Result<Vec<Value>, Value> {
+    match value {
+        Value::List(elems) | Value::Tuple(elems) => Ok(elems),
+        _ => Err(value),
+    }
+}
+
+fn set_or_frozenset(value: Value) -> Result<Vec<Value>, Value> {
+    match value {
+        Value::Set(elems) | Value::FrozenSet(elems) => Ok(elems),
+        _ => Err(value),
+    }
+}
+
+/// Converts the elements of `Value::List` or `Value::Tuple`.
+impl<T> TryFrom<Value> for Vec<T>
+where
+    T: TryFrom<Value>,
+    T::Error: Into<TryFromValueError>,
+{
+    type Error = TryFromValueError;
+
+    fn try_from(value: Value) -> Result<Vec<T>, TryFromValueError> {
+        try_from_elems(value, "list", list_or_tuple)
+    }
+}
+
+/// Converts the elements of `Value::Set` or `Value::FrozenSet`. Elements which
+/// convert to equal values are deduplicated.
+impl<T, S> TryFrom<Value> for HashSet<T, S>
+where
+    T: TryFrom<Value> + Eq + Hash,
+    T::Error: Into<TryFromValueError>,
+    S: BuildHasher + Default,
+{
+    type Error = TryFromValueError;
+
+    fn try_from(value: Value) -> Result<HashSet<T, S>, TryFromValueError> {
+        try_from_elems(value, "set", set_or_frozenset)
+    }
+}
+
+/// Converts the elements of `Value::Set` or `Value::FrozenSet`. Elements which
+/// convert to equal values are deduplicated.
+impl<T> TryFrom<Value> for BTreeSet<T>
+where
+    T: TryFrom<Value> + Ord,
+    T::Error: Into<TryFromValueError>,
+{
+    type Error = TryFromValueError;
+
+    fn try_from(value: Value) -> Result<BTreeSet<T>, TryFromValueError> {
+        try_from_elems(value, "set", set_or_frozenset)
+    }
+}
+
+/// Converts the items of `Value::Dict`, whose keys must be strings. If a key
+/// occurs more than once, the last item with that key wins.
+impl<T, S> TryFrom<Value> for HashMap<String, T, S>
+where
+    T: TryFrom<Value>,
+    T::Error: Into<TryFromValueError>,
+    S: BuildHasher + Default,
+{
+    type Error = TryFromValueError;
+
+    fn try_from(value: Value) -> Result<HashMap<String, T, S>, TryFromValueError> {
+        try_from_items(value)
+    }
+}
+
+/// Converts the items of `Value::Dict`, whose keys must be strings. If a key
+/// occurs more than once, the last item with that key wins.
+impl<T> TryFrom<Value> for BTreeMap<String, T>
+where
+    T: TryFrom<Value>,
+    T::Error: Into<TryFromValueError>,
+{
+    type Error = TryFromValueError;
+
+    fn try_from(value: Value) -> Result<BTreeMap<String, T>, TryFromValueError> {
+        try_from_items(value)
+    }
+}
+
+// A generic impl for `Option<T>` would conflict with the blanket
+// `TryFrom<Value> for Option<Value>` impl (which converts every value to
+// `Some`), so these are implemented for each type which can be converted,
+// with the first rule for generic types. Keep the list in sync with the impls
+// above.
+macro_rules! impl_try_from_value_for_option {
+    (<$($param:ident),*> $ty:ty where $($bound:tt)*) => {
+        /// Converts `Value::None` to `None`, and other values to `Some`.
+        impl<$($param),*> TryFrom<Value> for Option<$ty>
+        where
+            $($bound)*
+        {
+            type Error = TryFromValueError;
+
+            fn try_from(value: Value) -> Result<Option<$ty>, TryFromValueError> {
+                match value {
+                    Value::None => Ok(None),
+                    value => <$ty>::try_from(value).map(Some),
+                }
+            }
+        }
+    };
+    ($($ty:ty),*) => {
+        $(
+            /// Converts `Value::None` to `None`, and other values to `Some`.
+            impl TryFrom<Value> for Option<$ty> {
+                type Error = TryFromValueError;
+
+                fn try_from(value: Value) -> Result<Option<$ty>, TryFromValueError> {
+                    match value {
+                        Value::None => Ok(None),
+                        value => <$ty>::try_from(value).map(Some),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_value_for_option!(
+    i64,
+    u64,
+    f64,
+    bool,
+    String,
+    Vec<u8>,
+    numb::BigInt,
+    numc::Complex<f64>
+);
+impl_try_from_value_for_option!(<T> Vec<T> where
+    T: TryFrom<Value>,
+    T::Error: Into<TryFromValueError>,
+);
+impl_try_from_value_for_option!(<T, S> HashSet<T, S> where
+    T: TryFrom<Value> + Eq + Hash,
+    T::Error: Into<TryFromValueError>,
+    S: BuildHasher + Default,
+);
+impl_try_from_value_for_option!(<T> BTreeSet<T> where
+    T: TryFrom<Value> + Ord,
+    T::Error: Into<TryFromValueError>,
+);
+impl_try_from_value_for_option!(<T, S> HashMap<String, T, S> where
+    T: TryFrom<Value>,
+    T::Error: Into<TryFromValueError>,
+    S: BuildHasher + Default,
+);
+impl_try_from_value_for_option!(<T> BTreeMap<String, T> where
+    T: TryFrom<Value>,
+    T::Error: Into<TryFromValueError>,
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_primitives() {
+        assert_eq!(Value::from(-5i8), Value::Integer((-5).into()));
+        assert_eq!(
+            Value::from(u128::max_value()),
+            Value::Integer(u128::max_value().into())
+        );
+        assert_eq!(Value::from(2.5), Value::Float(2.5));
+        assert_eq!(Value::from(true), Value::Boolean(true));
+        assert_eq!(Value::from("foo"), Value::String("foo".into()));
+        assert_eq!(Value::from(b"foo".to_vec()), Value::Bytes(b"foo".to_vec()));
+        assert_eq!(Value::from(()), Value::None);
+        let complex = numc::Complex::new(1., -2.);
+        assert_eq!(Value::from(complex), Value::Complex(complex));
+    }
+
+    #[test]
+    fn from_containers() {
+        let mut map = BTreeMap::new();
+        map.insert("b", (1, Some(2.5), None::<bool>));
+        map.insert("a", (3, None, Some(true)));
+        let value = Value::from(vec![Value::from(map), Value::from(("x",))]);
+        assert_eq!(
+            value.to_string(),
+            "[{'a': (3, None, True), 'b': (1, 2.5e0, None)}, ('x',)]",
+        );
+        let set: BTreeSet<_> = vec![3u8, 1, 2].into_iter().collect();
+        assert_eq!(Value::from(set).to_string(), "{1, 2, 3}");
+        let mut map = HashMap::new();
+        map.insert(1, "one");
+        assert_eq!(Value::from(map).to_string(), "{1: 'one'}");
+        let set: HashSet<_> = vec![()].into_iter().collect();
+        assert_eq!(Value::from(set).to_string(), "{None}");
+    }
+
+    #[test]
+    fn try_from_primitives() {
+        let value = Value::from(-3);
+        assert_eq!(i64::try_from(&value), Ok(-3));
+        assert_eq!(
+            u64::try_from(&value),
+            Err(TryFromValueError::OutOfRange("u64"))
+        );
+        assert_eq!(numb::BigInt::try_from(value), Ok((-3).into()));
+        assert_eq!(f64::try_from(Value::Float(1.5)), Ok(1.5));
+        assert_eq!(bool::try_from(Value::Boolean(true)), Ok(true));
+        assert_eq!(String::try_from(Value::from("a")), Ok("a".to_string()));
+        assert_eq!(
+            Vec::<u8>::try_from(Value::ByteArray(b"ab".to_vec())),
+            Ok(b"ab".to_vec())
+        );
+        let complex = numc::Complex::new(0., 1.);
+        assert_eq!(
+            numc::Complex::try_from(&Value::Complex(complex)),
+            Ok(complex)
+        );
+        let err = f64::try_from(Value::from(1)).unwrap_err();
+        assert_eq!(err.to_string(), "expected float, found int");
+        let err = String::try_from(&Value::None).unwrap_err();
+        assert_eq!(err.to_string(), "expected str, found NoneType");
+    }
+
+    #[test]
+    fn try_from_containers() {
+        let value: Value = "{'a': [1, None], 'b': (), 'a': [2]}".parse().unwrap();
+        let map = HashMap::<String, Vec<Option<i64>>>::try_from(value.clone()).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["a"], [Some(2)]);
+        assert_eq!(map["b"], []);
+        let map = BTreeMap::<String, Vec<Value>>::try_from(value.clone()).unwrap();
+        assert_eq!(map["a"], [Value::from(2)]);
+        assert_eq!(
+            BTreeMap::<String, Vec<i64>>::try_from(value),
+            Err(TryFromValueError::InvalidType {
+                expected: "int",
+                found: "NoneType",
+            }),
+        );
+        let value: Value = "{3, 1, 3}".parse().unwrap();
+        let set = HashSet::<u64>::try_from(value.clone()).unwrap();
+        assert_eq!(set.len(), 2);
+        let set = BTreeSet::<u64>::try_from(value.clone()).unwrap();
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), [1, 3]);
+        assert!(Vec::<u64>::try_from(value).is_err());
+        let value: Value = "{1: 2}".parse().unwrap();
+        let err = HashMap::<String, i64>::try_from(value).unwrap_err();
+        assert_eq!(err.to_string(), "expected str, found int");
+        assert_eq!(Option::<bool>::try_from(Value::None), Ok(None));
+        let value: Value = "[None, [1, 2], {'a': None}, {'b': {3}}]".parse().unwrap();
+        let list = Vec::<Option<Vec<i64>>>::try_from(value.clone());
+        assert_eq!(list.unwrap_err().to_string(), "expected list, found dict");
+        let list = Vec::<Value>::try_from(value).unwrap();
+        assert_eq!(Option::<Vec<i64>>::try_from(list[0].clone()), Ok(None));
+        assert_eq!(
+            Option::<Vec<i64>>::try_from(list[1].clone()),
+            Ok(Some(vec![1, 2]))
+        );
+        let map = Option::<HashMap<String, Option<bool>>>::try_from(list[2].clone()).unwrap();
+        assert_eq!(map.unwrap()["a"], None);
+        let map = Option::<BTreeMap<String, BTreeSet<u64>>>::try_from(list[3].clone()).unwrap();
+        assert_eq!(map.unwrap()["b"].len(), 1);
+        let set = Option::<HashSet<u64>>::try_from(Value::None).unwrap();
+        assert_eq!(set, None);
+        assert_eq!(Option::<BTreeSet<u64>>::try_from(Value::None), Ok(None));
+        assert_eq!(
+            Vec::<u8>::try_from(Value::from(vec![Value::from(1)])).ok(),
+            None
+        );
+    }
+}
@@ -0,0 +1,437 @@
+//! Lossless concrete syntax tree of a Python literal.
+
+use crate::parse::{parse_start, Rule};
+use crate::{FormatOptions, ParseError, ParseOptions, ReformatError, Value};
+use pest::iterators::Pair;
+use std::fmt;
+
+/// Node in the concrete syntax tree of a Python literal, created by
+/// [`Value::parse_cst`].
+///
+/// Unlike a [`Value`], the tree preserves everything in the input, including
+/// the quoting style of strings, the radix and underscores of numbers,
+/// whitespace, and comments. Every byte of the input belongs to exactly one
+/// token, so formatting the tree with `Display` reproduces the input exactly.
+///
+/// The tree can be edited, e.g. by replacing children with
+/// [`CstChild::from_value`], and then written back with `Display`. Untouched
+/// parts of the tree are written verbatim, so the changes to the text are
+/// minimal.
+///
+/// ```
+/// use py_literal::{CstChild, FormatOptions, ParseOptions, Value};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let input = "{\n  'name': \"demo\",  # keep\n  'size': 0x10,\n}\n";
+/// let mut cst = Value::parse_cst_with_options(input, &ParseOptions::new().comments(true))?;
+/// if let CstChild::Node(dict) = cst.elements_mut().remove(0) {
+///     if let CstChild::Node(item) = dict.elements_mut().remove(1) {
+///         let size = Value::Integer(32.into());
+///         *item.elements_mut().remove(1) = CstChild::from_value(&size, &FormatOptions::new())?;
+///     }
+/// }
+/// assert_eq!(cst.to_string(), "{\n  'name': \"demo\",  # keep\n  'size': 32,\n}\n");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`CstChild::from_value`]: enum.CstChild.html#method.from_value
+/// [`Value::parse_cst`]: enum.Value.html#method.parse_cst
+/// [`Value`]: enum.Value.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CstNode {
+    /// Kind of node.
+    pub kind: CstKind,
+    /// Child nodes and tokens, in order.
+    pub children: Vec<CstChild>,
+}
+
+/// Kind of [`CstNode`].
+///
+/// [`CstNode`]: struct.CstNode.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CstKind {
+    /// The whole input, including leading and trailing whitespace and
+    /// comments. This contains a single value, or the elements of a tuple
+    /// without parentheses.
+    Root,
+    /// Parenthesized value or tuple, e.g. `(1)` or `(1, 2)`.
+    Parenthesized,
+    /// List.
+    List,
+    /// Dict.
+    Dict,
+    /// Key and value in a dict, e.g. `'a': 1`.
+    DictItem,
+    /// Set.
+    Set,
+    /// Numeric expression with signs, operators, or parentheses, e.g. `-1` or
+    /// `1 + 2j`.
+    NumberExpr,
+    /// Call, such as `frozenset({1})` or `Decimal('1.5')`.
+    Call,
+    /// Implicitly concatenated strings or bytes, e.g. `'a' 'b'`.
+    Concat,
+}
+
+/// Child of a [`CstNode`].
+///
+/// [`CstNode`]: struct.CstNode.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CstChild {
+    /// Nested node.
+    Node(CstNode),
+    /// Token.
+    Token(CstToken),
+}
+
+/// Token in the concrete syntax tree of a Python literal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CstToken {
+    /// Kind of token.
+    pub kind: TokenKind,
+    /// The text of the token, exactly as it appears in the input.
+    pub text: String,
+}
+
+/// Kind of [`CstToken`].
+///
+/// [`CstToken`]: struct.CstToken.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// String, bytes, or number literal, or `True`, `False`, `None`, or
+    /// `...`.
+    Literal,
+    /// Name of a called function, e.g. `frozenset` or `datetime.date`.
+    Name,
+    /// Punctuation or operator, e.g. `[`, `,`, or `-`.
+    Punct,
+    /// Whitespace, including line breaks.
+    Whitespace,
+    /// Comment, from `#` to the end of the line.
+    Comment,
+}
+
+impl fmt::Display for CstNode {
+    /// Writes the text of all of the tokens in the node.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                CstChild::Node(node) => write!(f, "{}", node)?,
+                CstChild::Token(token) => f.write_str(&token.text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CstNode {
+    /// Returns the children which are values, i.e. nodes and literal tokens,
+    /// in order. These are e.g. the elements of a list, the key and value
+    /// of a dict item, or the arguments of a call.
+    pub fn elements(&self) -> Vec<&CstChild> {
+        self.children
+            .iter()
+            .filter(|child| child.is_value())
+            .collect()
+    }
+
+    /// Returns mutable references to the children which are values. See
+    /// [`elements`](#method.elements).
+    pub fn elements_mut(&mut self) -> Vec<&mut CstChild> {
+        self.children
+            .iter_mut()
+            .filter(|child| child.is_value())
+            .collect()
+    }
+
+    /// Returns the tokens in the node, recursively, in order.
+    pub fn tokens(&self) -> Vec<&CstToken> {
+        let mut out = Vec::new();
+        let mut stack = vec![self.children.iter()];
+        while let Some(children) = stack.last_mut() {
+            match children.next() {
+                Some(CstChild::Node(node)) => stack.push(node.children.iter()),
+                Some(CstChild::Token(token)) => out.push(token),
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        out
+    }
+}
+
+impl CstChild {
+    /// Creates a child for the value, formatted with the given options, e.g.
+    /// to replace a child of a tree which is being edited.
+    ///
+    /// The formatted value is parsed with all of the syntax extensions
+    /// enabled, so this fails only if the value can't be formatted, or if the
+    /// output can't be parsed, such as `NaN` written with the default float
+    /// style.
+    pub fn from_value(value: &Value, options: &FormatOptions) -> Result<CstChild, ReformatError> {
+        let formatted = value.format_with(options)?;
+        let extensions = ParseOptions::new()
+            .inf_nan(true)
+            .float_calls(true)
+            .frozensets(true)
+            .bytearrays(true)
+            .empty_calls(true)
+            .ordered_dicts(true)
+            .decimals(true)
+            .fractions(true)
+            .datetimes(true)
+            .complex_calls(true)
+            .comments(true);
+        let mut root = Value::parse_cst_with_options(&formatted, &extensions)?;
+        // The output has no surrounding whitespace, and tuples are in
+        // parentheses, so the root contains just the value.
+        debug_assert_eq!(root.children.len(), 1);
+        Ok(root.children.pop().unwrap())
+    }
+
+    /// Returns whether the child is a value, i.e. a node or a literal token.
+    fn is_value(&self) -> bool {
+        match self {
+            CstChild::Node(_) => true,
+            CstChild::Token(token) => token.kind == TokenKind::Literal,
+        }
+    }
+}
+
+impl Value {
+    /// Parses a Python literal into a lossless concrete syntax tree, using
+    /// the default options.
+    pub fn parse_cst(s: &str) -> Result<CstNode, ParseError> {
+        Value::parse_cst_with_options(s, &ParseOptions::default())
+    }
+
+    /// Parses a Python literal into a lossless concrete syntax tree, using
+    /// the given options.
+    ///
+    /// The input is checked in the same way as by
+    /// [`parse_with_options`](#method.parse_with_options). Comments are
+    /// accepted only if enabled in the options.
+    ///
+    /// ```
+    /// use py_literal::{ParseOptions, TokenKind, Value};
+    ///
+    /// # fn main() -> Result<(), py_literal::ParseError> {
+    /// let input = "{\n  'a': 0x_ff,  # hex\n  \"b\": (1,),\n}\n";
+    /// let options = ParseOptions::new().comments(true);
+    /// let cst = Value::parse_cst_with_options(input, &options)?;
+    /// assert_eq!(cst.to_string(), input);
+    /// let comments: Vec<_> = cst
+    ///     .tokens()
+    ///     .into_iter()
+    ///     .filter(|token| token.kind == TokenKind::Comment)
+    ///     .map(|token| token.text.as_str())
+    ///     .collect();
+    /// assert_eq!(comments, ["# hex"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_cst_with_options(s: &str, options: &ParseOptions) -> Result<CstNode, ParseError> {
+        parse_start(s, options, |pairs, opts| {
+            crate::parse::parse_comma_separated(pairs.clone(), opts)?;
+            let mut root = CstNode {
+                kind: CstKind::Root,
+                children: Vec::new(),
+            };
+            let mut pos = 0;
+            for pair in pairs {
+                build_child(&mut root.children, &mut pos, s, pair);
+            }
+            push_gap(&mut root.children, &s[pos..]);
+            Ok(root)
+        })
+    }
+}
+
+/// Adds the text between the previous child and `pair`, followed by the
+/// child for `pair` (if any), to `children`. `pos` is the end of the previous
+/// child, and `source` is the original input (which may differ from the text
+/// of the pairs if comments were blanked out).
+fn build_child(children: &mut Vec<CstChild>, pos: &mut usize, source: &str, pair: Pair<'_, Rule>) {
+    let span = pair.as_span();
+    let kind = match pair.as_rule() {
+        Rule::value => {
+            let (inner,) = parse_pairs_as!(pair.into_inner(), (_,));
+            return build_child(children, pos, source, inner);
+        }
+        Rule::string | Rule::bytes | Rule::number | Rule::boolean | Rule::none | Rule::ellipsis => {
+            push_gap(children, &source[*pos..span.start()]);
+            children.push(CstChild::Token(CstToken {
+                kind: TokenKind::Literal,
+                text: source[span.start()..span.end()].into(),
+            }));
+            *pos = span.end();
+            return;
+        }
+        // These are included in the text between children.
+        Rule::tuple_comma
+        | Rule::plus_sign
+        | Rule::minus_sign
+        | Rule::empty_call_name
+        | Rule::float_call_name
+        | Rule::datetime_type
+        | Rule::EOI => return,
+        Rule::parenthesized => CstKind::Parenthesized,
+        Rule::list => CstKind::List,
+        Rule::dict => CstKind::Dict,
+        Rule::dict_elem => CstKind::DictItem,
+        Rule::set => CstKind::Set,
+        Rule::number_expr => CstKind::NumberExpr,
+        Rule::string_concat | Rule::bytes_concat => CstKind::Concat,
+        Rule::float_call
+        | Rule::complex_call
+        | Rule::frozenset
+        | Rule::bytearray
+        | Rule::empty_call
+        | Rule::ordered_dict
+        | Rule::decimal
+        | Rule::rational
+        | Rule::datetime => CstKind::Call,
+        _ => unreachable!(),
+    };
+    push_gap(children, &source[*pos..span.start()]);
+    let mut node = CstNode {
+        kind,
+        children: Vec::new(),
+    };
+    *pos = span.start();
+    let pair_str = pair.as_str();
+    for inner in pair.into_inner() {
+        build_child(&mut node.children, pos, source, inner);
+    }
+    // The span may include trailing whitespace (or comments, which were
+    // blanked out), which belongs to the parent.
+    let end = span.start() + pair_str.trim_end_matches(&[' ', '\t', '\x0C'][..]).len();
+    push_gap(&mut node.children, &source[*pos..end]);
+    *pos = end;
+    // A number without signs or parentheses is just a literal.
+    if kind == CstKind::NumberExpr && node.children.len() == 1 {
+        children.push(node.children.pop().unwrap());
+    } else {
+        children.push(CstChild::Node(node));
+    }
+}
+
+/// Splits text between the children of a node into tokens, and adds them to
+/// `children`.
+fn push_gap(children: &mut Vec<CstChild>, mut text: &str) {
+    while let Some(c) = text.chars().next() {
+        let (kind, len) = match c {
+            ' ' | '\t' | '\x0C' | '\r' | '\n' => (
+                TokenKind::Whitespace,
+                text.find(|c| !matches!(c, ' ' | '\t' | '\x0C' | '\r' | '\n'))
+                    .unwrap_or(text.len()),
+            ),
+            '#' => (
+                TokenKind::Comment,
+                text.find(|c| matches!(c, '\r' | '\n'))
+                    .unwrap_or(text.len()),
+            ),
+            c if c.is_ascii_alphabetic() || c == '_' => (
+                TokenKind::Name,
+                text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                    .unwrap_or(text.len()),
+            ),
+            c => (TokenKind::Punct, c.len_utf8()),
+        };
+        children.push(CstChild::Token(CstToken {
+            kind,
+            text: text[..len].into(),
+        }));
+        text = &text[len..];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn kinds(node: &CstNode) -> String {
+        let mut out = format!("{:?}(", node.kind);
+        for child in &node.children {
+            match child {
+                CstChild::Node(node) => out.push_str(&kinds(node)),
+                CstChild::Token(token) => out.push_str(match token.kind {
+                    TokenKind::Literal => "L",
+                    TokenKind::Name => "N",
+                    TokenKind::Punct => "P",
+                    TokenKind::Whitespace => "W",
+                    TokenKind::Comment => "C",
+                }),
+            }
+        }
+        out.push(')');
+        out
+    }
+
+    #[test]
+    fn parse_cst_example() {
+        let opts = ParseOptions::new()
+            .comments(true)
+            .frozensets(true)
+            .complex_calls(true);
+        for &(input, correct) in &[
+            (" 1, ", "Root(WLPW)"),
+            ("(0b1_0, r'\\x')", "Root(Parenthesized(PLPWLP))"),
+            (
+                "[-1, (2), 1 + 2j]",
+                "Root(List(PNumberExpr(PL)PWNumberExpr(PLP)PWNumberExpr(LWPWL)P))",
+            ),
+            ("{'a': {1}} # c\n", "Root(Dict(PDictItem(LPWSet(PLP))P)WCW)"),
+            ("frozenset( {b''} )", "Root(Call(NPWSet(PLP)WP))"),
+            ("complex(1, -2)", "Root(Call(NPLPWNumberExpr(PL)P))"),
+            ("-1 # c", "Root(NumberExpr(PL)WC)"),
+        ] {
+            let cst = Value::parse_cst_with_options(input, &opts).unwrap();
+            assert_eq!(cst.to_string(), input);
+            assert_eq!(kinds(&cst), correct, "{:?}", input);
+        }
+        assert!(Value::parse_cst_with_options("[1, 2", &opts).is_err());
+        assert!(Value::parse_cst("frozenset()").is_err());
+    }
+
+    #[test]
+    fn edit_cst() {
+        let opts = ParseOptions::new().comments(true).frozensets(true);
+        let input = "[ 0b1,  # one\n  frozenset({'x'}),\n  -2 ]";
+        let mut cst = Value::parse_cst_with_options(input, &opts).unwrap();
+        let list = match cst.elements_mut().remove(0) {
+            CstChild::Node(node) => node,
+            _ => panic!(),
+        };
+        assert_eq!(list.kind, CstKind::List);
+        let mut elements = list.elements_mut();
+        assert_eq!(elements.len(), 3);
+        let pretty = FormatOptions::new().pretty(true);
+        let dict = Value::Dict(vec![(Value::Integer(3.into()), Value::Set(vec![]))]);
+        let empty_sets = pretty.clone().empty_sets(true);
+        *elements[1] = CstChild::from_value(&dict, &empty_sets).unwrap();
+        *elements[2] = CstChild::from_value(&Value::Float(2.5), &pretty).unwrap();
+        assert_eq!(
+            cst.to_string(),
+            "[ 0b1,  # one\n  {\n    3: set()\n},\n  2.5e0 ]",
+        );
+        match CstChild::from_value(&Value::Set(vec![]), &pretty) {
+            Err(ReformatError::Format(crate::FormatError::EmptySet)) => (),
+            other => panic!("{:?}", other),
+        }
+        match CstChild::from_value(&Value::Float(std::f64::NAN), &pretty) {
+            Err(ReformatError::Parse(ParseError::Syntax(_))) => (),
+            other => panic!("{:?}", other),
+        }
+        let call = Value::parse_cst_with_options("frozenset({1})", &opts).unwrap();
+        match call.elements()[0] {
+            CstChild::Node(node) => {
+                assert_eq!(node.kind, CstKind::Call);
+                assert_eq!(node.elements().len(), 1);
+            }
+            _ => panic!(),
+        }
+    }
+}
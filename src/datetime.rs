@@ -0,0 +1,255 @@
+//! Dates and times, corresponding to Python's `datetime.date` and naive
+//! `datetime.datetime`.
+
+use std::error::Error;
+use std::fmt;
+
+/// Smallest year allowed by Python's `datetime` module (`datetime.MINYEAR`).
+const MIN_YEAR: i32 = 1;
+
+/// Largest year allowed by Python's `datetime` module (`datetime.MAXYEAR`).
+const MAX_YEAR: i32 = 9999;
+
+/// Error converting a date or time which is out of the range supported by
+/// Python's `datetime` module.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutOfRangeError;
+
+impl Error for OutOfRangeError {}
+
+impl fmt::Display for OutOfRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "date or time out of range")
+    }
+}
+
+fn is_leap_year(year: i32) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Python date (`datetime.date`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    year: i32,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    /// Creates a date, or returns `None` if the date is invalid or outside
+    /// the range of years 1 to 9999 supported by Python.
+    pub fn new(year: i32, month: u8, day: u8) -> Option<Date> {
+        if (MIN_YEAR..=MAX_YEAR).contains(&year)
+            && (1..=12).contains(&month)
+            && day >= 1
+            && day <= days_in_month(year, month)
+        {
+            Some(Date { year, month, day })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the year (1 to 9999).
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    /// Returns the month (1 to 12).
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// Returns the day of the month (starting from 1).
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+}
+
+/// Python naive date and time (`datetime.datetime` without `tzinfo`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    date: Date,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    microsecond: u32,
+}
+
+impl DateTime {
+    /// Creates a date and time, or returns `None` if the time is invalid.
+    pub fn new(date: Date, hour: u8, minute: u8, second: u8, microsecond: u32) -> Option<DateTime> {
+        if hour < 24 && minute < 60 && second < 60 && microsecond < 1_000_000 {
+            Some(DateTime {
+                date,
+                hour,
+                minute,
+                second,
+                microsecond,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the date.
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    /// Returns the hour (0 to 23).
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    /// Returns the minute (0 to 59).
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    /// Returns the second (0 to 59).
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    /// Returns the microsecond (0 to 999999).
+    pub fn microsecond(&self) -> u32 {
+        self.microsecond
+    }
+}
+
+impl fmt::Display for Date {
+    /// Formats the date as `repr()` does, e.g. `datetime.date(2023, 5, 1)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "datetime.date({}, {}, {})",
+            self.year, self.month, self.day
+        )
+    }
+}
+
+impl fmt::Display for DateTime {
+    /// Formats the date and time as `repr()` does, e.g.
+    /// `datetime.datetime(2023, 5, 1, 12, 0)`. Like `repr()`, this omits the
+    /// second and microsecond if they're zero.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "datetime.datetime({}, {}, {}, {}, {}",
+            self.date.year, self.date.month, self.date.day, self.hour, self.minute
+        )?;
+        if self.microsecond != 0 {
+            write!(f, ", {}, {}", self.second, self.microsecond)?;
+        } else if self.second != 0 {
+            write!(f, ", {}", self.second)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_impls {
+    use super::{Date, DateTime, OutOfRangeError};
+    use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+    use std::convert::TryFrom;
+
+    impl From<Date> for NaiveDate {
+        fn from(date: Date) -> NaiveDate {
+            NaiveDate::from_ymd_opt(date.year, date.month.into(), date.day.into()).unwrap()
+        }
+    }
+
+    impl TryFrom<NaiveDate> for Date {
+        type Error = OutOfRangeError;
+
+        fn try_from(date: NaiveDate) -> Result<Date, OutOfRangeError> {
+            Date::new(date.year(), date.month() as u8, date.day() as u8).ok_or(OutOfRangeError)
+        }
+    }
+
+    impl From<DateTime> for NaiveDateTime {
+        fn from(datetime: DateTime) -> NaiveDateTime {
+            let time = NaiveTime::from_hms_micro_opt(
+                datetime.hour.into(),
+                datetime.minute.into(),
+                datetime.second.into(),
+                datetime.microsecond,
+            )
+            .unwrap();
+            NaiveDate::from(datetime.date).and_time(time)
+        }
+    }
+
+    impl TryFrom<NaiveDateTime> for DateTime {
+        type Error = OutOfRangeError;
+
+        /// Converts a chrono date and time. Sub-microsecond precision is
+        /// truncated. Leap seconds are out of range.
+        fn try_from(datetime: NaiveDateTime) -> Result<DateTime, OutOfRangeError> {
+            DateTime::new(
+                Date::try_from(datetime.date())?,
+                datetime.hour() as u8,
+                datetime.minute() as u8,
+                datetime.second() as u8,
+                datetime.nanosecond() / 1000,
+            )
+            .ok_or(OutOfRangeError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn date_validation() {
+        assert!(Date::new(2024, 2, 29).is_some());
+        assert!(Date::new(2023, 2, 29).is_none());
+        assert!(Date::new(1900, 2, 29).is_none());
+        assert!(Date::new(2000, 2, 29).is_some());
+        assert!(Date::new(2023, 4, 31).is_none());
+        assert!(Date::new(2023, 13, 1).is_none());
+        assert!(Date::new(0, 1, 1).is_none());
+        assert!(Date::new(10000, 1, 1).is_none());
+        let date = Date::new(2023, 5, 1).unwrap();
+        assert!(DateTime::new(date, 23, 59, 59, 999_999).is_some());
+        assert!(DateTime::new(date, 24, 0, 0, 0).is_none());
+        assert!(DateTime::new(date, 0, 0, 0, 1_000_000).is_none());
+    }
+
+    #[test]
+    fn format_repr() {
+        let date = Date::new(2023, 5, 1).unwrap();
+        assert_eq!(format!("{}", date), "datetime.date(2023, 5, 1)");
+        for &((s, us), correct) in &[
+            ((0, 0), "datetime.datetime(2023, 5, 1, 12, 0)"),
+            ((7, 0), "datetime.datetime(2023, 5, 1, 12, 0, 7)"),
+            ((0, 5), "datetime.datetime(2023, 5, 1, 12, 0, 0, 5)"),
+        ] {
+            let datetime = DateTime::new(date, 12, 0, s, us).unwrap();
+            assert_eq!(format!("{}", datetime), correct);
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_roundtrip() {
+        use chrono::NaiveDateTime;
+        use std::convert::TryFrom;
+        let datetime = DateTime::new(Date::new(2024, 2, 29).unwrap(), 1, 2, 3, 4).unwrap();
+        let naive = NaiveDateTime::from(datetime);
+        assert_eq!(naive.to_string(), "2024-02-29 01:02:03.000004");
+        assert_eq!(DateTime::try_from(naive), Ok(datetime));
+    }
+}
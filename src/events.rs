@@ -0,0 +1,546 @@
+//! Event-based parsing, which produces the parts of a literal one at a time
+//! instead of a `Value` tree.
+
+use crate::parse::{
+    parse_bytes_value, parse_string_value, parse_value, syntax_error, ParseOptions, Parser, Rule,
+};
+use crate::{Date, DateTime, ParseError, Value};
+use num_bigint as numb;
+use num_complex as numc;
+use pest::iterators::{Pair, Pairs};
+use pest::Parser as ParserTrait;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io;
+use std::vec;
+
+/// Part of a Python literal, produced by an [`EventParser`].
+///
+/// Collections are represented by a start event, the events of their
+/// elements, and an end event. The elements of a dict alternate between keys
+/// and values.
+///
+/// [`EventParser`]: struct.EventParser.html
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<'a> {
+    /// Start of a tuple.
+    StartTuple,
+    /// End of a tuple.
+    EndTuple,
+    /// Start of a list.
+    StartList,
+    /// End of a list.
+    EndList,
+    /// Start of a dict.
+    StartDict,
+    /// End of a dict.
+    EndDict,
+    /// Start of a set.
+    StartSet,
+    /// End of a set.
+    EndSet,
+    /// Start of a frozen set.
+    StartFrozenSet,
+    /// End of a frozen set.
+    EndFrozenSet,
+    /// String. This borrows from the input unless the literal contains escape
+    /// sequences.
+    Str(Cow<'a, str>),
+    /// Bytes. This borrows from the input unless the literal contains escape
+    /// sequences.
+    Bytes(Cow<'a, [u8]>),
+    /// Byte array.
+    ByteArray(Vec<u8>),
+    /// Integer.
+    Int(numb::BigInt),
+    /// Float.
+    Float(f64),
+    /// Decimal.
+    #[cfg(feature = "bigdecimal")]
+    Decimal(bigdecimal::BigDecimal),
+    /// Complex number.
+    Complex(numc::Complex<f64>),
+    /// Rational number.
+    #[cfg(feature = "num-rational")]
+    Rational(num_rational::BigRational),
+    /// Date.
+    Date(Date),
+    /// Date and time.
+    DateTime(DateTime),
+    /// Boolean.
+    Bool(bool),
+    /// None.
+    None,
+    /// Ellipsis.
+    Ellipsis,
+}
+
+/// Pull parser which produces the [events] of a Python literal one at a time,
+/// so that large collections can be processed without building a [`Value`]
+/// for the whole literal.
+///
+/// The input is checked for syntax errors up front (into a compact sequence
+/// of tokens), but the events are created as they're requested. Tuples,
+/// lists, dicts, and sets are produced element by element; other values,
+/// such as `OrderedDict(...)` calls, are parsed as a whole before their
+/// events are produced.
+///
+/// Since it doesn't collect the elements of collections, the event parser
+/// doesn't apply the `duplicate_keys`, `deduplicate_sets`, `max_elements`,
+/// and `max_nodes` options, and in strict mode it doesn't check that set
+/// elements and dict keys are hashable.
+/// The `comments` and `trim` options aren't supported.
+///
+/// ```
+/// use num::BigInt;
+/// use py_literal::{Event, EventParser};
+///
+/// # fn main() -> Result<(), py_literal::ParseError> {
+/// let mut total = BigInt::from(0);
+/// for event in EventParser::new("[1, 2, (3, 'a')]") {
+///     if let Event::Int(int) = event? {
+///         total += int;
+///     }
+/// }
+/// assert_eq!(total, BigInt::from(6));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [events]: enum.Event.html
+/// [`Value`]: enum.Value.html
+#[derive(Debug)]
+pub struct EventParser<'a> {
+    /// Remaining items of each collection being parsed, and the event to
+    /// produce at its end.
+    stack: Vec<(Pairs<'a, Rule>, Option<Event<'a>>)>,
+    /// Events which have already been created.
+    pending: VecDeque<Event<'a>>,
+    error: Option<ParseError>,
+    options: Cow<'a, ParseOptions>,
+}
+
+impl<'a> EventParser<'a> {
+    /// Creates an event parser for the input, using the default options.
+    pub fn new(input: &'a str) -> EventParser<'a> {
+        EventParser::with_options_cow(input, Cow::Owned(ParseOptions::default()))
+    }
+
+    /// Creates an event parser for the input, using the given options.
+    pub fn with_options(input: &'a str, options: &'a ParseOptions) -> EventParser<'a> {
+        EventParser::with_options_cow(input, options.resolve())
+    }
+
+    fn with_options_cow(input: &'a str, options: Cow<'a, ParseOptions>) -> EventParser<'a> {
+        let mut parser = EventParser {
+            stack: Vec::new(),
+            pending: VecDeque::new(),
+            error: None,
+            options,
+        };
+        let parsed = parser
+            .options
+            .check_depth(input)
+            .and_then(|()| {
+                Parser::parse(Rule::start, input).map_err(|e| ParseError::Syntax(format!("{}", e)))
+            })
+            .and_then(|mut parsed| {
+                if parser.options.comments_enabled() {
+                    return Err(syntax_error(
+                        parsed.next().unwrap().as_span(),
+                        "comments are not supported by the event parser",
+                    ));
+                }
+                if parser.options.trim_enabled() {
+                    return Err(syntax_error(
+                        parsed.next().unwrap().as_span(),
+                        "trimming is not supported by the event parser",
+                    ));
+                }
+                let (start,) = parse_pairs_as!(parsed, (Rule::start,));
+                parser.start_comma_separated(start.into_inner())
+            });
+        match parsed {
+            Ok(first) => parser.pending.push_front(first),
+            Err(err) => parser.error = Some(err),
+        }
+        parser
+    }
+
+    /// Starts parsing comma-separated values, which are a tuple unless there
+    /// is only a single value without a trailing comma. Returns the first
+    /// event.
+    fn start_comma_separated(&mut self, pairs: Pairs<'a, Rule>) -> Result<Event<'a>, ParseError> {
+        let mut values = pairs.clone().filter(|pair| pair.as_rule() == Rule::value);
+        if let (Some(value), None) = (values.next(), values.next()) {
+            if !pairs
+                .clone()
+                .any(|pair| pair.as_rule() == Rule::tuple_comma)
+            {
+                return self.start_value(value);
+            }
+        }
+        self.stack.push((pairs, Some(Event::EndTuple)));
+        Ok(Event::StartTuple)
+    }
+
+    /// Starts parsing a value. Returns the first event.
+    fn start_value(&mut self, value: Pair<'a, Rule>) -> Result<Event<'a>, ParseError> {
+        debug_assert_eq!(value.as_rule(), Rule::value);
+        let (inner,) = parse_pairs_as!(value.clone().into_inner(), (_,));
+        let (start, end) = match inner.as_rule() {
+            Rule::parenthesized => return self.start_comma_separated(inner.into_inner()),
+            Rule::string | Rule::string_concat => {
+                return Ok(Event::Str(parse_string_value(inner, &self.options)?))
+            }
+            Rule::bytes | Rule::bytes_concat => {
+                return Ok(Event::Bytes(parse_bytes_value(inner, &self.options)?))
+            }
+            Rule::list => (Event::StartList, Event::EndList),
+            Rule::dict => (Event::StartDict, Event::EndDict),
+            Rule::set => (Event::StartSet, Event::EndSet),
+            _ => {
+                let value = parse_value(value, &self.options)?;
+                let mut events = VecDeque::new();
+                push_events(value, &mut events);
+                let first = events.pop_front().unwrap();
+                events.append(&mut self.pending);
+                self.pending = events;
+                return Ok(first);
+            }
+        };
+        self.stack.push((inner.into_inner(), Some(end)));
+        Ok(start)
+    }
+}
+
+impl<'a> Iterator for EventParser<'a> {
+    type Item = Result<Event<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Result<Event<'a>, ParseError>> {
+        if let Some(event) = self.pending.pop_front() {
+            return Some(Ok(event));
+        }
+        if let Some(err) = self.error.take() {
+            return Some(Err(err));
+        }
+        if let Err(err) = self.options.check_cancelled() {
+            if self.stack.is_empty() {
+                return None;
+            }
+            self.stack.clear();
+            return Some(Err(err));
+        }
+        loop {
+            let (items, _) = self.stack.last_mut()?;
+            match items.next() {
+                Some(item) => match item.as_rule() {
+                    Rule::value => {
+                        let result = self.start_value(item);
+                        if result.is_err() {
+                            self.stack.clear();
+                        }
+                        return Some(result);
+                    }
+                    Rule::dict_elem => self.stack.push((item.into_inner(), None)),
+                    Rule::tuple_comma | Rule::EOI => (),
+                    _ => unreachable!(),
+                },
+                None => {
+                    if let (_, Some(end)) = self.stack.pop().unwrap() {
+                        return Some(Ok(end));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Compares the Python literals read from `a` and `b` structurally, using the
+/// default options.
+///
+/// The literals are compared event by event with [`EventParser`]s, so
+/// neither is built as a [`Value`]. The result is the same as comparing the
+/// parsed `Value`s with `==`, except that NaN is equal to itself, so a
+/// literal containing `nan` is equal to a copy of itself. In particular, the
+/// elements of dicts and sets must be in the same order.
+///
+/// The parser needs the complete input, so each reader is read until its end
+/// before comparing, even if the literals differ near the start, and both
+/// inputs are held in memory at once. This avoids the memory used by
+/// `Value`s, but it isn't suitable for inputs which don't fit in memory.
+///
+/// Returns an error if either input can't be read or decoded, if either
+/// contains a syntax error, or if either can't be parsed before the first
+/// difference.
+///
+/// ```
+/// use py_literal::streams_equal;
+///
+/// # fn main() -> Result<(), py_literal::ParseError> {
+/// let original: &[u8] = b"{'a': [1, 2.0], 'b': 'x'}";
+/// assert!(streams_equal(original, &b"{'a':[0x1,2.],'b':\"x\"}"[..])?);
+/// assert!(!streams_equal(original, &b"{'b': 'x', 'a': [1, 2.0]}"[..])?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`EventParser`]: struct.EventParser.html
+/// [`Value`]: enum.Value.html
+pub fn streams_equal<A: io::Read, B: io::Read>(a: A, b: B) -> Result<bool, ParseError> {
+    streams_equal_with_options(a, b, &ParseOptions::default())
+}
+
+/// Compares the Python literals read from `a` and `b` structurally, using the
+/// given options. See [`streams_equal`] for details. The inputs are decoded
+/// according to the [encoding] in the options, and the limitations of
+/// [`EventParser`] apply.
+///
+/// [`streams_equal`]: fn.streams_equal.html
+/// [encoding]: struct.ParseOptions.html#method.encoding
+/// [`EventParser`]: struct.EventParser.html
+pub fn streams_equal_with_options<A: io::Read, B: io::Read>(
+    a: A,
+    b: B,
+    options: &ParseOptions,
+) -> Result<bool, ParseError> {
+    let a = options.read_input(a)?;
+    let b = options.read_input(b)?;
+    let mut a_events = EventParser::with_options(&a, options);
+    let mut b_events = EventParser::with_options(&b, options);
+    loop {
+        match (a_events.next().transpose()?, b_events.next().transpose()?) {
+            (None, None) => return Ok(true),
+            (Some(a_event), Some(b_event)) if event_eq(&a_event, &b_event) => (),
+            _ => return Ok(false),
+        }
+    }
+}
+
+/// Returns whether the events are equal, considering NaN equal to itself.
+fn event_eq(a: &Event<'_>, b: &Event<'_>) -> bool {
+    let float_eq = |x: f64, y: f64| x == y || (x.is_nan() && y.is_nan());
+    match (a, b) {
+        (Event::Float(x), Event::Float(y)) => float_eq(*x, *y),
+        (Event::Complex(x), Event::Complex(y)) => float_eq(x.re, y.re) && float_eq(x.im, y.im),
+        _ => a == b,
+    }
+}
+
+/// Appends the events of the value.
+///
+/// Nested collections are converted with an explicit stack instead of
+/// recursively, so deeply nested values can't overflow the call stack.
+fn push_events(value: Value, events: &mut VecDeque<Event<'_>>) {
+    // Remaining elements of each collection being converted, and the event to
+    // produce at its end.
+    let mut stack: Vec<(vec::IntoIter<Value>, Event<'_>)> = Vec::new();
+    let mut value = value;
+    loop {
+        let collection = match value {
+            Value::Tuple(tup) => Some((Event::StartTuple, tup, Event::EndTuple)),
+            Value::List(list) => Some((Event::StartList, list, Event::EndList)),
+            Value::Dict(dict) => {
+                let mut elements = Vec::with_capacity(dict.len() * 2);
+                for (key, value) in dict {
+                    elements.push(key);
+                    elements.push(value);
+                }
+                Some((Event::StartDict, elements, Event::EndDict))
+            }
+            Value::Set(set) => Some((Event::StartSet, set, Event::EndSet)),
+            Value::FrozenSet(set) => Some((Event::StartFrozenSet, set, Event::EndFrozenSet)),
+            leaf => {
+                events.push_back(leaf_event(leaf));
+                None
+            }
+        };
+        if let Some((start, elements, end)) = collection {
+            events.push_back(start);
+            stack.push((elements.into_iter(), end));
+        }
+        value = loop {
+            let (elements, _) = match stack.last_mut() {
+                Some(top) => top,
+                None => return,
+            };
+            match elements.next() {
+                Some(element) => break element,
+                None => events.push_back(stack.pop().unwrap().1),
+            }
+        };
+    }
+}
+
+/// Returns the event of a value which isn't a collection.
+fn leaf_event(value: Value) -> Event<'static> {
+    match value {
+        Value::String(s) => Event::Str(Cow::Owned(s)),
+        Value::Bytes(b) => Event::Bytes(Cow::Owned(b)),
+        Value::ByteArray(b) => Event::ByteArray(b),
+        Value::Integer(int) => Event::Int(int),
+        Value::Float(float) => Event::Float(float),
+        #[cfg(feature = "bigdecimal")]
+        Value::Decimal(decimal) => Event::Decimal(decimal),
+        Value::Complex(comp) => Event::Complex(comp),
+        #[cfg(feature = "num-rational")]
+        Value::Rational(ratio) => Event::Rational(ratio),
+        Value::Date(date) => Event::Date(date),
+        Value::DateTime(datetime) => Event::DateTime(datetime),
+        Value::Boolean(b) => Event::Bool(b),
+        Value::None => Event::None,
+        Value::Ellipsis => Event::Ellipsis,
+        Value::Tuple(_) | Value::List(_) | Value::Dict(_) | Value::Set(_) | Value::FrozenSet(_) => {
+            unreachable!()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn events_example() {
+        use self::Event::*;
+        let opts = ParseOptions::new().frozensets(true).empty_calls(true);
+        let input = r"{'a': [1, (2,), ()], b'\x00': frozenset({(3)}), 'c': (set(), None)}";
+        let events: Vec<_> = EventParser::with_options(input, &opts)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            [
+                StartDict,
+                Str(Cow::Borrowed("a")),
+                StartList,
+                Int(1.into()),
+                StartTuple,
+                Int(2.into()),
+                EndTuple,
+                StartTuple,
+                EndTuple,
+                EndList,
+                Bytes(Cow::Owned(vec![0])),
+                StartFrozenSet,
+                Int(3.into()),
+                EndFrozenSet,
+                Str(Cow::Borrowed("c")),
+                StartTuple,
+                StartSet,
+                EndSet,
+                None,
+                EndTuple,
+                EndDict,
+            ]
+        );
+        let events: Vec<_> = EventParser::new("1, 'x'").map(Result::unwrap).collect();
+        assert_eq!(
+            events,
+            [StartTuple, Int(1.into()), Str("x".into()), EndTuple]
+        );
+        let events: Vec<_> = EventParser::new("((-1.5))").map(Result::unwrap).collect();
+        assert_eq!(events, [Float(-1.5)]);
+        let events: Vec<_> = EventParser::with_options("frozenset({1})", &opts)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(events, [StartFrozenSet, Int(1.into()), EndFrozenSet]);
+    }
+
+    #[test]
+    fn events_deeply_nested() {
+        // The thread's stack just needs to be large enough for the grammar,
+        // which is matched recursively.
+        let test = || {
+            let depth = 10_000;
+            let input = format!(
+                "frozenset({{{}1{}}})",
+                "[{1: (".repeat(depth),
+                ",)}]".repeat(depth)
+            );
+            let opts = ParseOptions::new().frozensets(true);
+            let events: Vec<_> = EventParser::with_options(&input, &opts)
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(events.len(), 2 + depth * 7 + 1);
+            assert_eq!(
+                events[..4],
+                [
+                    Event::StartFrozenSet,
+                    Event::StartList,
+                    Event::StartDict,
+                    Event::Int(1.into())
+                ]
+            );
+            assert_eq!(events[1 + depth * 4], Event::Int(1.into()));
+            assert_eq!(events[events.len() - 1], Event::EndFrozenSet);
+        };
+        std::thread::Builder::new()
+            .stack_size(256 << 20)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn streams_equal_example() {
+        for &(a, b, correct) in &[
+            ("1, (2,)", "(1, ((2,)))", true),
+            ("[1, [2, 3]]", "[1, [2, 3], 4]", false),
+            ("[1, [2, 3], 4]", "[1, [2, 3]]", false),
+            ("{'a': 1}", "{'a': True}", false),
+            ("{1, 2}", "{2, 1}", false),
+            ("[1.0, 1]", "[1., 0x1]", true),
+            ("nan", "nan", true),
+            ("[1, nan]", "[1, -nan]", true),
+            ("nan+1j", "nan+1j", true),
+            ("nan", "1.0", false),
+            ("nan+1j", "nan+2j", false),
+            ("'ab'", "'a' 'b'", true),
+            ("b'ab'", "'ab'", false),
+            ("1, ", "[1]", false),
+        ] {
+            let opts = ParseOptions::new().inf_nan(true);
+            let equal = streams_equal_with_options(a.as_bytes(), b.as_bytes(), &opts).unwrap();
+            assert_eq!(equal, correct, "{:?} {:?}", a, b);
+        }
+        assert!(streams_equal(&b"[1, 2"[..], &b"[1, 2]"[..]).is_err());
+        assert!(streams_equal(&b"[2]"[..], &b"[1, 2"[..]).is_err());
+        assert!(!streams_equal(&b"[2]"[..], &b"[1, frozenset()]"[..]).unwrap());
+        assert!(streams_equal(&b"[1, frozenset()]"[..], &b"[1, frozenset()]"[..]).is_err());
+        assert!(streams_equal(&br"'\xff'"[..], &br"'\xff'"[..]).unwrap());
+        assert!(streams_equal(&br"'\xff'"[..], &[b'\'', 0xff, b'\''][..]).is_err());
+    }
+
+    #[test]
+    fn events_error() {
+        let mut parser = EventParser::new("[1, 2");
+        assert!(parser.next().unwrap().is_err());
+        assert!(parser.next().is_none());
+        let mut parser = EventParser::new("[1, frozenset(), 2]");
+        assert_eq!(parser.next().unwrap().unwrap(), Event::StartList);
+        assert_eq!(parser.next().unwrap().unwrap(), Event::Int(1.into()));
+        assert!(parser.next().unwrap().is_err());
+        assert!(parser.next().is_none());
+        let opts = ParseOptions::new().comments(true);
+        assert!(EventParser::with_options("1", &opts)
+            .next()
+            .unwrap()
+            .is_err());
+        let opts = ParseOptions::new().max_depth(2);
+        let mut parser = EventParser::with_options("[[[1]]]", &opts);
+        assert!(matches!(
+            parser.next(),
+            Some(Err(ParseError::DepthLimitExceeded(2)))
+        ));
+        assert!(parser.next().is_none());
+        let token = crate::CancellationToken::new();
+        let opts = ParseOptions::new().cancellation_token(token.clone());
+        let mut parser = EventParser::with_options("[1, 2]", &opts);
+        assert_eq!(parser.next().unwrap().unwrap(), Event::StartList);
+        token.cancel();
+        assert!(matches!(parser.next(), Some(Err(ParseError::Cancelled))));
+        assert!(parser.next().is_none());
+    }
+}
@@ -0,0 +1,161 @@
+//! Finding Python literals embedded in other text.
+
+use crate::grammar::parse_value_pair;
+use crate::parse::{ParseOptions, Parser, Rule};
+use crate::{ParseError, Value};
+use pest::Parser as _;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Iterator over the Python literals in a text, created by
+/// [`find_literals`].
+///
+/// [`find_literals`]: fn.find_literals.html
+#[derive(Debug)]
+pub struct Literals<'a> {
+    text: &'a str,
+    /// Index of the next character to examine.
+    pos: usize,
+    options: Cow<'a, ParseOptions>,
+}
+
+/// Finds the Python literals in arbitrary text, such as log lines, and
+/// parses them using the default options.
+///
+/// The iterator yields the byte range of each literal in the text, together
+/// with the result of parsing it. Starting from the beginning of the text, it
+/// finds the longest literal at each position, and continues after its end.
+/// A literal doesn't start or end in the middle of a word, so e.g. `x1` and
+/// `1st` don't contain `1`. Text which matches the grammar but can't be
+/// parsed, such as `0755`, is yielded with the error; positions in the error
+/// are relative to the start of the literal. Text which is only a literal
+/// with extensions that the options don't enable, such as `nan` or `set()`,
+/// is skipped, and the search continues inside it.
+///
+/// Attempting a literal at each position can take time quadratic in the
+/// length of the text, e.g. for a long run of unclosed brackets, so this is
+/// intended for text such as individual lines.
+///
+/// ```
+/// use py_literal::find_literals;
+///
+/// let line = "INFO shape=(2, 3) dtype='<f8' took 1.5s";
+/// let found: Vec<_> = find_literals(line)
+///     .map(|(range, value)| (range, value.unwrap().to_string()))
+///     .collect();
+/// assert_eq!(
+///     found,
+///     [
+///         (11..17, "(2, 3)".to_string()),
+///         (24..29, "'<f8'".to_string()),
+///     ],
+/// );
+/// ```
+pub fn find_literals(text: &str) -> Literals<'_> {
+    Literals {
+        text,
+        pos: 0,
+        options: Cow::Owned(ParseOptions::default()),
+    }
+}
+
+/// Finds the Python literals in arbitrary text, and parses them using the
+/// given options. See [`find_literals`] for details. The `comments` option
+/// has no effect.
+///
+/// [`find_literals`]: fn.find_literals.html
+pub fn find_literals_with_options<'a>(text: &'a str, options: &'a ParseOptions) -> Literals<'a> {
+    Literals {
+        text,
+        pos: 0,
+        options: Cow::Borrowed(options),
+    }
+}
+
+/// Returns whether the character can be part of a word.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl<'a> Iterator for Literals<'a> {
+    type Item = (Range<usize>, Result<Value, ParseError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(c) = self.text[self.pos..].chars().next() {
+            let start = self.pos;
+            self.pos += c.len_utf8();
+            let after_word = self.text[..start]
+                .chars()
+                .next_back()
+                .map_or(false, is_word_char);
+            if c.is_whitespace() || after_word {
+                continue;
+            }
+            let rest = &self.text[start..];
+            let pair = match Parser::parse(Rule::value, rest) {
+                Ok(mut pairs) => pairs.next().unwrap(),
+                Err(_) => continue,
+            };
+            if self.options.resolve().has_disabled_form(&pair) {
+                continue;
+            }
+            let literal = pair.as_str().trim_end_matches(&[' ', '\t', '\x0C'][..]);
+            let end = start + literal.len();
+            let ends_word = literal.chars().next_back().map_or(false, is_word_char);
+            if ends_word && self.text[end..].chars().next().map_or(false, is_word_char) {
+                continue;
+            }
+            self.pos = end;
+            return Some((start..end, parse_value_pair(pair, &self.options)));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn find_literals_example() {
+        for &(text, correct) in &[
+            ("", &[][..]),
+            ("no literals here", &[][..]),
+            ("x1 = 2, y = [3, 'a b']", &[(5, 6), (12, 22)][..]),
+            ("got [1, x] and {'k': None}", &[(5, 6), (15, 26)][..]),
+            ("1st and 2nd, but 3 and -4", &[(17, 18), (23, 25)][..]),
+            ("it's b'\\x00' done", &[(5, 12)][..]),
+            ("é=5", &[(3, 4)][..]),
+        ] {
+            let found: Vec<_> = find_literals(text)
+                .map(|(range, value)| {
+                    assert!(value.is_ok(), "{:?}", value);
+                    (range.start, range.end)
+                })
+                .collect();
+            assert_eq!(found, correct, "{:?}", text);
+        }
+        let found: Vec<_> = find_literals("mode 0755").collect();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, 5..9);
+        assert!(found[0].1.is_err());
+        let opts = ParseOptions::new().inf_nan(true);
+        let found: Vec<_> = find_literals_with_options("loss=nan", &opts).collect();
+        assert!(found[0].1.as_ref().unwrap().as_float().unwrap().is_nan());
+        for &(text, correct) in &[
+            ("loss=nan", &[][..]),
+            ("s=set() l=list()", &[][..]),
+            ("x=float('inf')", &[(8, 13)][..]),
+            ("fs=frozenset({1})", &[(13, 16)][..]),
+            ("[1, inf]", &[(1, 2)][..]),
+        ] {
+            let found: Vec<_> = find_literals(text)
+                .map(|(range, value)| {
+                    assert!(value.is_ok(), "{:?}", value);
+                    (range.start, range.end)
+                })
+                .collect();
+            assert_eq!(found, correct, "{:?}", text);
+        }
+    }
+}
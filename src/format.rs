@@ -1,19 +1,36 @@
+use crate::compare::{sorted, sorted_items};
 use crate::Value;
 use num_complex as numc;
 use std::error::Error;
 use std::fmt;
-use std::io;
+use std::io::{self, Write};
 
 /// Error formatting a Python literal.
 #[derive(Debug)]
 pub enum FormatError {
     /// An error caused by the writer.
     Io(io::Error),
+    /// An error caused by the `fmt::Write` writer.
+    Fmt(fmt::Error),
     /// The literal contained an empty set.
     ///
     /// There is no literal representation of an empty set in Python. (`{}`
     /// represents an empty `dict`.)
     EmptySet,
+    /// The literal contained a float (or a part of a complex number) which
+    /// is infinite or NaN, and the format options specify
+    /// [`NonFiniteFloats::Error`].
+    ///
+    /// [`NonFiniteFloats::Error`]: enum.NonFiniteFloats.html#variant.Error
+    NonFiniteFloat,
+    /// The output was longer than the [maximum output length] in the format
+    /// options. Output up to the limit may have been written.
+    ///
+    /// [maximum output length]: struct.FormatOptions.html#method.max_output
+    OutputLimitExceeded,
+    /// A name to assign a literal to wasn't a valid Python identifier, or
+    /// was a keyword. Contains the name.
+    InvalidName(String),
 }
 
 impl Error for FormatError {
@@ -21,7 +38,11 @@ impl Error for FormatError {
         use FormatError::*;
         match self {
             Io(err) => Some(err),
+            Fmt(err) => Some(err),
             EmptySet => None,
+            NonFiniteFloat => None,
+            OutputLimitExceeded => None,
+            InvalidName(_) => None,
         }
     }
 }
@@ -31,7 +52,11 @@ impl fmt::Display for FormatError {
         use FormatError::*;
         match self {
             Io(err) => write!(f, "I/O error: {}", err),
+            Fmt(err) => write!(f, "formatter error: {}", err),
             EmptySet => write!(f, "unable to format empty set literal"),
+            NonFiniteFloat => write!(f, "unable to format non-finite float literal"),
+            OutputLimitExceeded => write!(f, "output length limit exceeded"),
+            InvalidName(name) => write!(f, "invalid name for assignment: {:?}", name),
         }
     }
 }
@@ -42,13 +67,741 @@ impl From<io::Error> for FormatError {
     }
 }
 
+impl From<fmt::Error> for FormatError {
+    fn from(err: fmt::Error) -> FormatError {
+        FormatError::Fmt(err)
+    }
+}
+
+/// How to escape the contents of strings and bytes when formatting.
+///
+/// Backslashes, quotes, and line breaks are always escaped, since they would
+/// otherwise end the literal or change its meaning, and so are non-ASCII
+/// bytes in bytes literals. Control characters are escaped as `\t`, `\n`,
+/// `\r`, or `\xhh`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscapePolicy {
+    /// Escape non-ASCII characters, but write control characters other than
+    /// line breaks as-is. This is the default, for compatibility with
+    /// [`Value::format_ascii`].
+    ///
+    /// [`Value::format_ascii`]: enum.Value.html#method.format_ascii
+    NonAscii,
+    /// Escape non-ASCII characters and control characters, so the output
+    /// consists of printable ASCII characters only.
+    ///
+    /// Together with [`QuoteStyle::PythonRepr`], this writes bytes exactly
+    /// like Python's `repr()`.
+    ///
+    /// [`QuoteStyle::PythonRepr`]: enum.QuoteStyle.html#variant.PythonRepr
+    AsciiOnly,
+    /// Escape control characters, but write other non-ASCII characters
+    /// as-is.
+    ControlOnly,
+    /// Escape only what's necessary to write a valid literal.
+    QuotesOnly,
+    /// Match Python's `repr()` of strings: escape control characters and
+    /// the other characters which Python doesn't consider printable, such
+    /// as format characters (e.g. U+200B) and whitespace other than space
+    /// (e.g. U+00A0), but write other non-ASCII characters as-is. Bytes are
+    /// escaped as with [`ControlOnly`].
+    ///
+    /// Python also escapes unassigned code points, which this writes as-is,
+    /// since this crate doesn't include the Unicode character database.
+    ///
+    /// [`ControlOnly`]: #variant.ControlOnly
+    Printable,
+}
+
+impl Default for EscapePolicy {
+    fn default() -> EscapePolicy {
+        EscapePolicy::NonAscii
+    }
+}
+
+impl EscapePolicy {
+    fn escapes_control(self) -> bool {
+        matches!(
+            self,
+            EscapePolicy::AsciiOnly | EscapePolicy::ControlOnly | EscapePolicy::Printable
+        )
+    }
+
+    /// Returns whether to escape the non-ASCII character.
+    fn escapes_non_ascii(self, c: char) -> bool {
+        match self {
+            EscapePolicy::NonAscii | EscapePolicy::AsciiOnly => true,
+            EscapePolicy::ControlOnly | EscapePolicy::QuotesOnly => false,
+            EscapePolicy::Printable => !is_printable(c),
+        }
+    }
+}
+
+/// Returns whether Python considers the non-control character printable,
+/// i.e. whether it isn't a format character (category Cf), private-use
+/// character (Co), noncharacter (Cn), or separator (Zs, Zl, or Zp) other
+/// than space.
+fn is_printable(c: char) -> bool {
+    !matches!(
+        c as u32,
+        0xa0 | 0xad
+            | 0x600..=0x605
+            | 0x61c
+            | 0x6dd
+            | 0x70f
+            | 0x890..=0x891
+            | 0x8e2
+            | 0x1680
+            | 0x180e
+            | 0x2000..=0x200f
+            | 0x2028..=0x202f
+            | 0x205f..=0x2064
+            | 0x2066..=0x206f
+            | 0x3000
+            | 0xe000..=0xf8ff
+            | 0xfdd0..=0xfdef
+            | 0xfeff
+            | 0xfff9..=0xfffb
+            | 0x110bd
+            | 0x110cd
+            | 0x13430..=0x1343f
+            | 0x1bca0..=0x1bca3
+            | 0x1d173..=0x1d17a
+            | 0xe0001
+            | 0xe0020..=0xe007f
+            | 0xf0000..=0x10ffff
+    ) && c as u32 & 0xfffe != 0xfffe
+}
+
+/// Which quotes to write around strings and bytes when formatting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// Always use single quotes. This is the default.
+    Single,
+    /// Always use double quotes.
+    Double,
+    /// Match Python's `repr()`: use single quotes, unless the contents
+    /// contain a single quote and no double quotes, in which case use double
+    /// quotes.
+    PythonRepr,
+}
+
+impl Default for QuoteStyle {
+    fn default() -> QuoteStyle {
+        QuoteStyle::Single
+    }
+}
+
+impl QuoteStyle {
+    /// Returns the quote to use for the given contents.
+    fn quote(self, contents: &[u8]) -> u8 {
+        match self {
+            QuoteStyle::Single => b'\'',
+            QuoteStyle::Double => b'"',
+            QuoteStyle::PythonRepr => {
+                if contents.contains(&b'\'') && !contents.contains(&b'"') {
+                    b'"'
+                } else {
+                    b'\''
+                }
+            }
+        }
+    }
+}
+
+/// Base in which to write integers when formatting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegerRadix {
+    /// Decimal, such as `255`. This is the default.
+    Decimal,
+    /// Binary with a `0b` prefix, such as `0b11111111`.
+    Binary,
+    /// Octal with a `0o` prefix, such as `0o377`.
+    Octal,
+    /// Hexadecimal with a `0x` prefix and lowercase digits, such as `0xff`.
+    Hex,
+}
+
+impl Default for IntegerRadix {
+    fn default() -> IntegerRadix {
+        IntegerRadix::Decimal
+    }
+}
+
+/// How to write floats (including the parts of complex numbers) when
+/// formatting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatStyle {
+    /// Always use scientific notation, such as `7e3` and `5e-1`, which makes
+    /// the value unambiguously a float. This is the default.
+    Scientific,
+    /// Match Python's `repr()`: write the shortest digits which round-trip,
+    /// in positional notation with at least one digit after the decimal
+    /// point if the decimal exponent is between -4 and 15 (such as `7000.0`
+    /// and `0.5`), and in scientific notation otherwise (such as `1e+16` and
+    /// `1.5e-05`).
+    ///
+    /// Complex numbers are written like `repr()` too: the parts have no
+    /// trailing `.0`, and unless the real part is positive zero, the number
+    /// is in parentheses, such as `(1+2j)` and `(-0-1.5j)`; otherwise it's
+    /// just the imaginary part, such as `2j`.
+    Repr,
+    /// Write exact hexadecimal floats, like Python's `float.hex()`, as calls
+    /// to `float.fromhex()`, such as `float.fromhex('0x1.8000000000000p+1')`.
+    /// This guarantees that the value round-trips bit for bit. Infinities
+    /// and NaN are written as `float.fromhex('inf')` etc. unless
+    /// [`non_finite_floats`] says otherwise. Complex numbers are written as
+    /// `complex(real, imag)` with hexadecimal parts. These can be parsed
+    /// with the [`hex_floats`] and [`complex_calls`] parse options.
+    ///
+    /// [`non_finite_floats`]: struct.FormatOptions.html#method.non_finite_floats
+    /// [`hex_floats`]: struct.ParseOptions.html#method.hex_floats
+    /// [`complex_calls`]: struct.ParseOptions.html#method.complex_calls
+    Hex,
+}
+
+impl Default for FloatStyle {
+    fn default() -> FloatStyle {
+        FloatStyle::Scientific
+    }
+}
+
+/// How to write floats which are infinite or NaN, which have no literal
+/// representation in Python.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonFiniteFloats {
+    /// Write them in the float style, e.g. `inf` and `NaN` with
+    /// [`FloatStyle::Scientific`], or `inf` and `nan` with
+    /// [`FloatStyle::Repr`], which can be parsed with the [`inf_nan`] parse
+    /// option. This is the default.
+    ///
+    /// [`FloatStyle::Scientific`]: enum.FloatStyle.html#variant.Scientific
+    /// [`FloatStyle::Repr`]: enum.FloatStyle.html#variant.Repr
+    /// [`inf_nan`]: struct.ParseOptions.html#method.inf_nan
+    Literal,
+    /// Fail with [`FormatError::NonFiniteFloat`].
+    ///
+    /// [`FormatError::NonFiniteFloat`]: enum.FormatError.html#variant.NonFiniteFloat
+    Error,
+    /// Write them as `float('inf')`, `float('-inf')`, and `float('nan')`,
+    /// like Python code which evaluates to them. These can be parsed with
+    /// the [`float_calls`] parse option. Complex numbers with a non-finite
+    /// part are written as `complex(real, imag)`, which can be parsed with
+    /// the [`complex_calls`] parse option too.
+    ///
+    /// [`float_calls`]: struct.ParseOptions.html#method.float_calls
+    /// [`complex_calls`]: struct.ParseOptions.html#method.complex_calls
+    FloatCalls,
+}
+
+impl Default for NonFiniteFloats {
+    fn default() -> NonFiniteFloats {
+        NonFiniteFloats::Literal
+    }
+}
+
+/// Options for formatting Python literals with [`Value::format_with`] and
+/// [`Value::write_with`].
+///
+/// The default options produce the same output as [`Value::format_ascii`].
+/// Options are set with builder-style methods:
+///
+/// ```
+/// use py_literal::{FormatOptions, Value};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let value: Value = "{'a': [1, 2], 'b': ()}".parse()?;
+/// let options = FormatOptions::new().pretty(true);
+/// assert_eq!(
+///     value.format_with(&options)?,
+///     "{\n    'a': [\n        1,\n        2\n    ],\n    'b': ()\n}",
+/// );
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Value::format_with`]: enum.Value.html#method.format_with
+/// [`Value::write_with`]: enum.Value.html#method.write_with
+/// [`Value::format_ascii`]: enum.Value.html#method.format_ascii
+#[derive(Clone, Debug, Default)]
+pub struct FormatOptions {
+    pretty: bool,
+    trailing_comma: bool,
+    width: Option<usize>,
+    wrap_strings: bool,
+    indent: Option<String>,
+    separators: Option<(String, String)>,
+    escape_policy: EscapePolicy,
+    quote_style: QuoteStyle,
+    integer_radix: IntegerRadix,
+    underscores: bool,
+    float_style: FloatStyle,
+    float_precision: Option<usize>,
+    non_finite_floats: NonFiniteFloats,
+    sort_keys: bool,
+    sort_sets: bool,
+    empty_sets: bool,
+    ordered_dicts: bool,
+    max_output: Option<usize>,
+    highlight: bool,
+    /// Maximum number of elements to write of each collection, for
+    /// `Abbreviated`.
+    max_elems: Option<usize>,
+    /// Maximum nesting level of collections to write the elements of, for
+    /// `Abbreviated`.
+    max_level: Option<usize>,
+    /// Whether to write `Value::Ellipsis` as `Ellipsis`, for
+    /// `Value::python_repr`.
+    ellipsis_name: bool,
+}
+
+impl FormatOptions {
+    /// Creates the default options.
+    pub fn new() -> FormatOptions {
+        FormatOptions::default()
+    }
+
+    /// Enables or disables pretty-printing, which writes each element of a
+    /// non-empty collection on its own line, indented by one [`indent`] (four
+    /// spaces by default) per level of nesting. Disabled by default.
+    ///
+    /// [`indent`]: #method.indent
+    pub fn pretty(mut self, enabled: bool) -> FormatOptions {
+        self.pretty = enabled;
+        self
+    }
+
+    /// Enables or disables writing a comma after the last element of each
+    /// non-empty collection when pretty-printing. Disabled by default. This
+    /// has no effect unless [`pretty`] is enabled.
+    ///
+    /// ```
+    /// use py_literal::{FormatOptions, Value};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let value: Value = "{'a': [1]}".parse()?;
+    /// let options = FormatOptions::new().pretty(true).trailing_comma(true);
+    /// assert_eq!(
+    ///     value.format_with(&options)?,
+    ///     "{\n    'a': [\n        1,\n    ],\n}",
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`pretty`]: #method.pretty
+    pub fn trailing_comma(mut self, enabled: bool) -> FormatOptions {
+        self.trailing_comma = enabled;
+        self
+    }
+
+    /// Sets the maximum line width when pretty-printing. A collection which
+    /// fits in the rest of the line (including a comma after it, if it's an
+    /// element of another collection) is written on one line, as without
+    /// [`pretty`], and other collections are broken across lines. Widths are
+    /// measured in characters. By default, every non-empty collection is
+    /// broken across lines. This has no effect unless [`pretty`] is enabled.
+    ///
+    /// ```
+    /// use py_literal::{FormatOptions, Value};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let value: Value = "{'short': [1, 2], 'long': ['aaaaaaaaaa', 'bbbbbbbbbb']}".parse()?;
+    /// let options = FormatOptions::new().pretty(true).width(30);
+    /// assert_eq!(
+    ///     value.format_with(&options)?,
+    ///     "{\n    'short': [1, 2],\n    'long': [\n        'aaaaaaaaaa',\n        'bbbbbbbbbb'\n    ]\n}",
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`pretty`]: #method.pretty
+    pub fn width(mut self, width: usize) -> FormatOptions {
+        self.width = Some(width);
+        self
+    }
+
+    /// Enables or disables breaking strings which don't fit in the rest of
+    /// the line into pieces on separate lines. Python concatenates adjacent
+    /// string literals, so the pieces form the same string. The pieces after
+    /// the first are indented one more level, and at the top level, all of
+    /// them are enclosed in parentheses. Escape sequences aren't split, and
+    /// each piece contains at least one character, so a line may still
+    /// exceed the width. Disabled by default. This has no effect unless
+    /// [`pretty`] is enabled and a [`width`] is set.
+    ///
+    /// ```
+    /// use py_literal::{FormatOptions, ParseOptions, Value};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let value = Value::List(vec![Value::String("abcdefghijklmnopqrstuvwxyz".into())]);
+    /// let options = FormatOptions::new().pretty(true).width(24).wrap_strings(true);
+    /// let formatted = value.format_with(&options)?;
+    /// assert_eq!(
+    ///     formatted,
+    ///     "[\n    'abcdefghijklmnopq'\n        'rstuvwxyz'\n]",
+    /// );
+    /// let options = ParseOptions::new().comments(true);
+    /// assert_eq!(Value::parse_with_options(&formatted, &options)?, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`pretty`]: #method.pretty
+    /// [`width`]: #method.width
+    pub fn wrap_strings(mut self, enabled: bool) -> FormatOptions {
+        self.wrap_strings = enabled;
+        self
+    }
+
+    /// Sets the string to write for each level of indentation when
+    /// pretty-printing, such as `"  "` or `"\t"`. The default is four
+    /// spaces. This has no effect unless [`pretty`] is enabled.
+    ///
+    /// [`pretty`]: #method.pretty
+    pub fn indent(mut self, indent: &str) -> FormatOptions {
+        self.indent = Some(indent.to_owned());
+        self
+    }
+
+    /// Sets the separators to write between the elements of collections and
+    /// between the keys and values of dicts. The defaults are `", "` and
+    /// `": "`. The separators should consist of a comma and a colon,
+    /// respectively, with optional spaces around them; otherwise the output
+    /// isn't a valid literal. When pretty-printing, trailing spaces of the
+    /// element separator are omitted at the end of each line.
+    ///
+    /// ```
+    /// use py_literal::{FormatOptions, Value};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let value: Value = "{'a': [1, 2], 'b': 3}".parse()?;
+    /// let compact = FormatOptions::new().separators(",", ":");
+    /// assert_eq!(value.format_with(&compact)?, "{'a':[1,2],'b':3}");
+    /// let tabs = FormatOptions::new().pretty(true).indent("\t").separators(",", ": ");
+    /// assert_eq!(
+    ///     value.format_with(&tabs)?,
+    ///     "{\n\t'a': [\n\t\t1,\n\t\t2\n\t],\n\t'b': 3\n}",
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn separators(mut self, item: &str, key: &str) -> FormatOptions {
+        self.separators = Some((item.to_owned(), key.to_owned()));
+        self
+    }
+
+    /// Returns the indentation string.
+    fn indent_str(&self) -> &str {
+        self.indent.as_deref().unwrap_or("    ")
+    }
+
+    /// Returns the element and key separators.
+    fn separator_strs(&self) -> (&str, &str) {
+        match &self.separators {
+            Some((item, key)) => (item, key),
+            None => (", ", ": "),
+        }
+    }
+
+    /// Rounds the float to the significant digits set by `float_precision`.
+    fn round_float(&self, float: f64) -> f64 {
+        match self.float_precision {
+            Some(digits) if float.is_finite() => {
+                let rounded = format!("{:.*e}", digits.max(1) - 1, float);
+                rounded.parse().unwrap()
+            }
+            _ => float,
+        }
+    }
+
+    /// Enables or disables writing non-ASCII characters in strings as-is.
+    /// Quotes, backslashes, and control characters are still escaped, so
+    /// the output is valid UTF-8 which parses back to the same value.
+    /// Disabled by default, in which case every non-ASCII character is
+    /// escaped.
+    ///
+    /// This is shorthand for setting the escape policy to
+    /// [`EscapePolicy::ControlOnly`] if enabled, or to the default
+    /// [`EscapePolicy::NonAscii`] if disabled.
+    ///
+    /// [`EscapePolicy::ControlOnly`]: enum.EscapePolicy.html#variant.ControlOnly
+    /// [`EscapePolicy::NonAscii`]: enum.EscapePolicy.html#variant.NonAscii
+    pub fn unicode(self, enabled: bool) -> FormatOptions {
+        self.escape_policy(if enabled {
+            EscapePolicy::ControlOnly
+        } else {
+            EscapePolicy::NonAscii
+        })
+    }
+
+    /// Sets how to escape the contents of strings and bytes. The default is
+    /// [`EscapePolicy::NonAscii`].
+    ///
+    /// ```
+    /// use py_literal::{EscapePolicy, FormatOptions, Value};
+    ///
+    /// let value = Value::String("\u{e9}\t".into());
+    /// let ascii = FormatOptions::new().escape_policy(EscapePolicy::AsciiOnly);
+    /// assert_eq!(value.format_with(&ascii).unwrap(), r"'\xe9\t'");
+    /// let quotes = FormatOptions::new().escape_policy(EscapePolicy::QuotesOnly);
+    /// assert_eq!(value.format_with(&quotes).unwrap(), "'\u{e9}\t'");
+    /// ```
+    ///
+    /// [`EscapePolicy::NonAscii`]: enum.EscapePolicy.html#variant.NonAscii
+    pub fn escape_policy(mut self, policy: EscapePolicy) -> FormatOptions {
+        self.escape_policy = policy;
+        self
+    }
+
+    /// Sets which quotes to write around strings and bytes. The default is
+    /// [`QuoteStyle::Single`].
+    ///
+    /// ```
+    /// use py_literal::{FormatOptions, QuoteStyle, Value};
+    ///
+    /// let value = Value::String("it's".into());
+    /// assert_eq!(value.format_ascii().unwrap(), r"'it\'s'");
+    /// let repr = FormatOptions::new().quote_style(QuoteStyle::PythonRepr);
+    /// assert_eq!(value.format_with(&repr).unwrap(), "\"it's\"");
+    /// ```
+    ///
+    /// [`QuoteStyle::Single`]: enum.QuoteStyle.html#variant.Single
+    pub fn quote_style(mut self, style: QuoteStyle) -> FormatOptions {
+        self.quote_style = style;
+        self
+    }
+
+    /// Sets the base in which to write integers. This applies to every
+    /// integer in the value, but not to booleans. The default is
+    /// [`IntegerRadix::Decimal`].
+    ///
+    /// ```
+    /// use py_literal::{FormatOptions, IntegerRadix, Value};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let value: Value = "{'mask': 255, 'offset': -16}".parse()?;
+    /// let hex = FormatOptions::new().integer_radix(IntegerRadix::Hex);
+    /// assert_eq!(value.format_with(&hex)?, "{'mask': 0xff, 'offset': -0x10}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`IntegerRadix::Decimal`]: enum.IntegerRadix.html#variant.Decimal
+    pub fn integer_radix(mut self, radix: IntegerRadix) -> FormatOptions {
+        self.integer_radix = radix;
+        self
+    }
+
+    /// Enables or disables separating groups of digits with underscores, as
+    /// permitted since Python 3.6. Decimal integers, and the integer parts of
+    /// floats written in positional notation (with [`FloatStyle::Repr`]),
+    /// are split into groups of three digits, and integers in other bases
+    /// into groups of four digits. Disabled by default.
+    ///
+    /// ```
+    /// use py_literal::{FloatStyle, FormatOptions, IntegerRadix, Value};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let value: Value = "[1000000, 1234567.125]".parse()?;
+    /// let options = FormatOptions::new()
+    ///     .underscores(true)
+    ///     .float_style(FloatStyle::Repr);
+    /// assert_eq!(value.format_with(&options)?, "[1_000_000, 1_234_567.125]");
+    /// let hex = FormatOptions::new()
+    ///     .underscores(true)
+    ///     .integer_radix(IntegerRadix::Hex);
+    /// assert_eq!(Value::Integer(0xdeadbeef_u32.into()).format_with(&hex)?, "0xdead_beef");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`FloatStyle::Repr`]: enum.FloatStyle.html#variant.Repr
+    pub fn underscores(mut self, enabled: bool) -> FormatOptions {
+        self.underscores = enabled;
+        self
+    }
+
+    /// Sets how to write floats. The default is [`FloatStyle::Scientific`].
+    ///
+    /// ```
+    /// use py_literal::{FloatStyle, FormatOptions, Value};
+    ///
+    /// let repr = FormatOptions::new().float_style(FloatStyle::Repr);
+    /// assert_eq!(Value::Float(7e3).format_with(&repr).unwrap(), "7000.0");
+    /// assert_eq!(Value::Float(0.1).format_with(&repr).unwrap(), "0.1");
+    /// assert_eq!(Value::Float(1e22).format_with(&repr).unwrap(), "1e+22");
+    /// ```
+    ///
+    /// [`FloatStyle::Scientific`]: enum.FloatStyle.html#variant.Scientific
+    pub fn float_style(mut self, style: FloatStyle) -> FormatOptions {
+        self.float_style = style;
+        self
+    }
+
+    /// Limits floats, including the parts of complex numbers, to the given
+    /// number of significant digits (at least one). The value is rounded to
+    /// that many digits and then written in the [`float_style`], so e.g.
+    /// trailing zeros are dropped. By default, floats are written with as
+    /// many digits as are needed to round-trip.
+    ///
+    /// ```
+    /// use py_literal::{FloatStyle, FormatOptions, Value};
+    ///
+    /// let value = Value::List(vec![Value::Float(3.14159), Value::Float(2.5e-7)]);
+    /// let opts = FormatOptions::new().float_precision(3);
+    /// assert_eq!(value.format_with(&opts).unwrap(), "[3.14e0, 2.5e-7]");
+    /// let repr = opts.float_style(FloatStyle::Repr);
+    /// assert_eq!(value.format_with(&repr).unwrap(), "[3.14, 2.5e-07]");
+    /// ```
+    ///
+    /// [`float_style`]: #method.float_style
+    pub fn float_precision(mut self, digits: usize) -> FormatOptions {
+        self.float_precision = Some(digits);
+        self
+    }
+
+    /// Sets how to write floats which are infinite or NaN. The default is
+    /// [`NonFiniteFloats::Literal`].
+    ///
+    /// ```
+    /// use py_literal::{FormatError, FormatOptions, NonFiniteFloats, Value};
+    ///
+    /// let value = Value::List(vec![Value::Float(1.5), Value::Float(std::f64::NAN)]);
+    /// let calls = FormatOptions::new().non_finite_floats(NonFiniteFloats::FloatCalls);
+    /// assert_eq!(value.format_with(&calls).unwrap(), "[1.5e0, float('nan')]");
+    /// let error = FormatOptions::new().non_finite_floats(NonFiniteFloats::Error);
+    /// assert!(matches!(value.format_with(&error), Err(FormatError::NonFiniteFloat)));
+    /// ```
+    ///
+    /// [`NonFiniteFloats::Literal`]: enum.NonFiniteFloats.html#variant.Literal
+    pub fn non_finite_floats(mut self, policy: NonFiniteFloats) -> FormatOptions {
+        self.non_finite_floats = policy;
+        self
+    }
+
+    /// Enables or disables writing the items of dicts sorted by key, so the
+    /// output doesn't depend on the order of insertion. Keys of the same
+    /// type are sorted by value (strings by code point), and keys of
+    /// different types are grouped by type. Disabled by default.
+    ///
+    /// ```
+    /// use py_literal::{FormatOptions, Value};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let value: Value = "{'b': 1, 'a': {2: None, 1: None}}".parse()?;
+    /// let sorted = FormatOptions::new().sort_keys(true);
+    /// assert_eq!(value.format_with(&sorted)?, "{'a': {1: None, 2: None}, 'b': 1}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sort_keys(mut self, enabled: bool) -> FormatOptions {
+        self.sort_keys = enabled;
+        self
+    }
+
+    /// Enables or disables writing the elements of sets and frozen sets in
+    /// sorted order, like the keys of dicts with [`sort_keys`]. Disabled by
+    /// default.
+    ///
+    /// ```
+    /// use py_literal::{FormatOptions, Value};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let value: Value = "{'b', 'a', ('c', 1)}".parse()?;
+    /// let sorted = FormatOptions::new().sort_sets(true);
+    /// assert_eq!(value.format_with(&sorted)?, "{'a', 'b', ('c', 1)}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`sort_keys`]: #method.sort_keys
+    pub fn sort_sets(mut self, enabled: bool) -> FormatOptions {
+        self.sort_sets = enabled;
+        self
+    }
+
+    /// Enables or disables writing empty sets as `set()`, which isn't a
+    /// Python literal but can be parsed with the [`empty_calls`] parse
+    /// option. Disabled by default, in which case formatting an empty set is
+    /// an error.
+    ///
+    /// [`empty_calls`]: struct.ParseOptions.html#method.empty_calls
+    pub fn empty_sets(mut self, enabled: bool) -> FormatOptions {
+        self.empty_sets = enabled;
+        self
+    }
+
+    /// Enables or disables writing dicts as `OrderedDict([(key, value),
+    /// ...])` (or `OrderedDict()` if empty), which can be parsed with the
+    /// [`ordered_dicts`] parse option. Disabled by default.
+    ///
+    /// [`ordered_dicts`]: struct.ParseOptions.html#method.ordered_dicts
+    pub fn ordered_dicts(mut self, enabled: bool) -> FormatOptions {
+        self.ordered_dicts = enabled;
+        self
+    }
+
+    /// Sets the maximum length of the output in bytes. Formatting a value
+    /// whose output would be longer fails with
+    /// [`FormatError::OutputLimitExceeded`] once the limit is reached, so
+    /// untrusted or huge values can be formatted into memory without
+    /// allocating without bound. There is no limit by default.
+    ///
+    /// ```
+    /// use py_literal::{FormatError, FormatOptions, Value};
+    ///
+    /// let value = Value::List(vec![Value::Integer(1.into()); 3]);
+    /// let opts = FormatOptions::new().max_output(9);
+    /// assert_eq!(value.format_with(&opts).unwrap(), "[1, 1, 1]");
+    /// let opts = FormatOptions::new().max_output(8);
+    /// assert!(matches!(value.format_with(&opts), Err(FormatError::OutputLimitExceeded)));
+    /// ```
+    ///
+    /// [`FormatError::OutputLimitExceeded`]: enum.FormatError.html#variant.OutputLimitExceeded
+    pub fn max_output(mut self, len: usize) -> FormatOptions {
+        self.max_output = Some(len);
+        self
+    }
+
+    /// Enables or disables syntax highlighting with ANSI color codes, for
+    /// debugging in terminals: dict keys are blue, strings and bytes are
+    /// green, numbers are cyan, and `None`, `True`, `False`, and `...` are
+    /// magenta. The color codes don't count towards the [`width`].
+    /// Disabled by default.
+    ///
+    /// This is available only when the `highlight` feature is enabled.
+    ///
+    /// ```
+    /// use py_literal::{FormatOptions, Value};
+    ///
+    /// # fn main() -> Result<(), py_literal::ParseError> {
+    /// let value: Value = "{'a': [1, None]}".parse()?;
+    /// let opts = FormatOptions::new().highlight(true);
+    /// assert_eq!(
+    ///     value.format_with(&opts).unwrap(),
+    ///     "{\x1b[34m'a'\x1b[0m: [\x1b[36m1\x1b[0m, \x1b[35mNone\x1b[0m]}",
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`width`]: #method.width
+    #[cfg(feature = "highlight")]
+    pub fn highlight(mut self, enabled: bool) -> FormatOptions {
+        self.highlight = enabled;
+        self
+    }
+}
+
 impl Value {
     /// Formats the value as an ASCII string.
     pub fn format_ascii(&self) -> Result<String, FormatError> {
-        let mut out = Vec::new();
-        self.write_ascii(&mut out)?;
+        let mut out = String::new();
+        self.write_ascii_fmt(&mut out)?;
         assert!(out.is_ascii());
-        Ok(unsafe { String::from_utf8_unchecked(out) })
+        Ok(out)
     }
 
     /// Writes the value as ASCII.
@@ -61,117 +814,1030 @@ impl Value {
     /// [`TcpStream`]: https://doc.rust-lang.org/std/net/struct.TcpStream.html
     /// [`BufWriter`]: https://doc.rust-lang.org/std/io/struct.BufWriter.html
     pub fn write_ascii<W: io::Write>(&self, w: &mut W) -> Result<(), FormatError> {
-        match *self {
-            Value::String(ref s) => {
-                w.write_all(b"'")?;
-                for c in s.chars() {
-                    match c {
-                        '\\' => w.write_all(br"\\")?,
-                        '\r' => w.write_all(br"\r")?,
-                        '\n' => w.write_all(br"\n")?,
-                        '\'' => w.write_all(br"\'")?,
-                        c if c.is_ascii() => w.write_all(&[c as u8])?,
-                        c => match c as u32 {
-                            n @ 0..=0xff => write!(w, r"\x{:0>2x}", n)?,
-                            n @ 0..=0xffff => write!(w, r"\u{:0>4x}", n)?,
-                            n @ 0..=0xffff_ffff => write!(w, r"\U{:0>8x}", n)?,
-                        },
-                    }
+        self.write_with(w, &FormatOptions::default())
+    }
+
+    /// Writes the value as ASCII to a [`fmt::Write`], such as a `String` or a
+    /// `fmt::Formatter`.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    /// use std::fmt::Write;
+    ///
+    /// # fn main() -> Result<(), py_literal::FormatError> {
+    /// let mut out = String::from("shape = ");
+    /// Value::Tuple(vec![Value::Integer(2.into()), Value::Integer(3.into())])
+    ///     .write_ascii_fmt(&mut out)?;
+    /// assert_eq!(out, "shape = (2, 3)");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`fmt::Write`]: https://doc.rust-lang.org/std/fmt/trait.Write.html
+    pub fn write_ascii_fmt<W: fmt::Write>(&self, w: &mut W) -> Result<(), FormatError> {
+        self.write_with_fmt(w, &FormatOptions::default())
+    }
+
+    /// Formats the value as a UTF-8 string, writing non-ASCII characters in
+    /// strings as-is. See [`FormatOptions::unicode`].
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let value = Value::String("caf\u{e9}\t\u{1f600}".into());
+    /// assert_eq!(value.format_unicode().unwrap(), "'caf\u{e9}\\t\u{1f600}'");
+    /// assert_eq!(value.format_ascii().unwrap(), "'caf\\xe9\t\\U0001f600'");
+    /// ```
+    ///
+    /// [`FormatOptions::unicode`]: struct.FormatOptions.html#method.unicode
+    pub fn format_unicode(&self) -> Result<String, FormatError> {
+        self.format_with(&FormatOptions::new().unicode(true))
+    }
+
+    /// Formats the value exactly like Python's `repr()`, e.g. for comparing
+    /// with output generated by Python.
+    ///
+    /// This is like formatting with [`EscapePolicy::Printable`],
+    /// [`QuoteStyle::PythonRepr`], [`FloatStyle::Repr`], and empty sets
+    /// written as `set()`, except that `Value::Ellipsis` is written as
+    /// `Ellipsis`. See [`EscapePolicy::Printable`] for the one case in which
+    /// the output differs from `repr()`.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// # fn main() -> Result<(), py_literal::ParseError> {
+    /// let value: Value = "{'it\\'s': [1.5e3, 2j, b'\\xff', ...], (1,): {3}}".parse()?;
+    /// assert_eq!(
+    ///     value.python_repr(),
+    ///     "{\"it's\": [1500.0, 2j, b'\\xff', Ellipsis], (1,): {3}}",
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`EscapePolicy::Printable`]: enum.EscapePolicy.html#variant.Printable
+    /// [`QuoteStyle::PythonRepr`]: enum.QuoteStyle.html#variant.PythonRepr
+    /// [`FloatStyle::Repr`]: enum.FloatStyle.html#variant.Repr
+    pub fn python_repr(&self) -> String {
+        let mut options = FormatOptions::new()
+            .escape_policy(EscapePolicy::Printable)
+            .quote_style(QuoteStyle::PythonRepr)
+            .float_style(FloatStyle::Repr)
+            .empty_sets(true);
+        options.ellipsis_name = true;
+        self.format_with(&options)
+            .expect("formatting with these options can't fail")
+    }
+
+    /// Writes the value as UTF-8, writing non-ASCII characters in strings
+    /// as-is. See [`FormatOptions::unicode`].
+    ///
+    /// [`FormatOptions::unicode`]: struct.FormatOptions.html#method.unicode
+    pub fn write_unicode<W: io::Write>(&self, w: &mut W) -> Result<(), FormatError> {
+        self.write_with(w, &FormatOptions::new().unicode(true))
+    }
+
+    /// Writes the value to a [`fmt::Write`], writing non-ASCII characters in
+    /// strings as-is. See [`FormatOptions::unicode`].
+    ///
+    /// [`fmt::Write`]: https://doc.rust-lang.org/std/fmt/trait.Write.html
+    /// [`FormatOptions::unicode`]: struct.FormatOptions.html#method.unicode
+    pub fn write_unicode_fmt<W: fmt::Write>(&self, w: &mut W) -> Result<(), FormatError> {
+        self.write_with_fmt(w, &FormatOptions::new().unicode(true))
+    }
+
+    /// Formats the value as a string, using the given options.
+    pub fn format_with(&self, options: &FormatOptions) -> Result<String, FormatError> {
+        let mut out = String::new();
+        self.write_with_fmt(&mut out, options)?;
+        Ok(out)
+    }
+
+    /// Writes the value as UTF-8, using the given options.
+    ///
+    /// Like [`write_ascii`], this performs a lot of small writes, so it may
+    /// be a good idea to wrap the writer in a [`BufWriter`].
+    ///
+    /// [`write_ascii`]: #method.write_ascii
+    /// [`BufWriter`]: https://doc.rust-lang.org/std/io/struct.BufWriter.html
+    pub fn write_with<W: io::Write>(
+        &self,
+        w: &mut W,
+        options: &FormatOptions,
+    ) -> Result<(), FormatError> {
+        self.write_at_column(w, options, 0)
+    }
+
+    /// Writes the value like [`write_with`], starting at the given column
+    /// (in characters) of the line, after text which has already been
+    /// written. The column counts towards the [width] when pretty-printing.
+    ///
+    /// [`write_with`]: #method.write_with
+    /// [width]: struct.FormatOptions.html#method.width
+    pub(crate) fn write_at_column<W: io::Write>(
+        &self,
+        w: &mut W,
+        options: &FormatOptions,
+        col: usize,
+    ) -> Result<(), FormatError> {
+        let mut bounded = Bounded {
+            inner: w,
+            remaining: options.max_output,
+            exceeded: false,
+        };
+        let result = Formatter {
+            w: Column {
+                inner: &mut bounded,
+                col,
+            },
+            opts: options,
+            depth: 0,
+            level: 0,
+            inline: false,
+            highlight: options.highlight,
+        }
+        .value(self);
+        match result {
+            Err(FormatError::Io(_)) if bounded.exceeded => Err(FormatError::OutputLimitExceeded),
+            result => result,
+        }
+    }
+
+    /// Returns an adapter which displays the value abbreviated, for
+    /// debugging and logging. Only the first `max_elems` elements of each
+    /// collection are written, followed by `...` if there are more, and
+    /// collections nested more than `max_depth` levels deep are written
+    /// with only `...` as their contents. Empty sets are written as
+    /// `set()`.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// # fn main() -> Result<(), py_literal::ParseError> {
+    /// let value: Value = "[1, 2, 3, 4, {'a': [5, 6]}]".parse()?;
+    /// assert_eq!(value.abbreviated(3, 2).to_string(), "[1, 2, 3, ...]");
+    /// assert_eq!(value.abbreviated(5, 1).to_string(), "[1, 2, 3, 4, {...}]");
+    /// assert_eq!(
+    ///     value.abbreviated(5, 2).to_string(),
+    ///     "[1, 2, 3, 4, {'a': [...]}]",
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn abbreviated(&self, max_elems: usize, max_depth: usize) -> Abbreviated<'_> {
+        Abbreviated {
+            value: self,
+            max_elems,
+            max_depth,
+        }
+    }
+
+    /// Returns the length in bytes of the value formatted with the given
+    /// options, without allocating the formatted string. This is useful
+    /// e.g. for computing padding before writing the value.
+    ///
+    /// ```
+    /// use py_literal::{FormatOptions, Value};
+    ///
+    /// let value = Value::String("\u{e9}".into());
+    /// assert_eq!(value.formatted_len(&FormatOptions::new()).unwrap(), 6);
+    /// assert_eq!(value.formatted_len(&FormatOptions::new().unicode(true)).unwrap(), 4);
+    /// ```
+    pub fn formatted_len(&self, options: &FormatOptions) -> Result<usize, FormatError> {
+        let mut count = Count(0);
+        self.write_with(&mut count, options)?;
+        Ok(count.0)
+    }
+
+    /// Writes the value to a [`fmt::Write`], using the given options.
+    ///
+    /// [`fmt::Write`]: https://doc.rust-lang.org/std/fmt/trait.Write.html
+    pub fn write_with_fmt<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        options: &FormatOptions,
+    ) -> Result<(), FormatError> {
+        let mut adapter = FmtAdapter {
+            inner: w,
+            failed: false,
+        };
+        match self.write_with(&mut adapter, options) {
+            Err(FormatError::Io(_)) if adapter.failed => Err(FormatError::Fmt(fmt::Error)),
+            result => result,
+        }
+    }
+}
+
+/// Display adapter which writes a value with long collections and deeply
+/// nested collections abbreviated, created by [`Value::abbreviated`].
+///
+/// With the alternate flag (`{:#}`), the value is pretty-printed.
+///
+/// [`Value::abbreviated`]: enum.Value.html#method.abbreviated
+#[derive(Clone, Copy, Debug)]
+pub struct Abbreviated<'a> {
+    value: &'a Value,
+    max_elems: usize,
+    max_depth: usize,
+}
+
+impl fmt::Display for Abbreviated<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut options = FormatOptions::new().pretty(f.alternate()).empty_sets(true);
+        options.max_elems = Some(self.max_elems);
+        options.max_level = Some(self.max_depth);
+        self.value
+            .write_with_fmt(f, &options)
+            .map_err(|_| fmt::Error)
+    }
+}
+
+/// Writer which discards its input, and counts the bytes written.
+struct Count(usize);
+
+impl io::Write for Count {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Adapter which writes to a `fmt::Write`, and records whether it failed.
+///
+/// The formatter writes only whole UTF-8 sequences, so each write is valid
+/// UTF-8 on its own.
+struct FmtAdapter<W> {
+    inner: W,
+    failed: bool,
+}
+
+impl<W: fmt::Write> io::Write for FmtAdapter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let s = std::str::from_utf8(buf).expect("the formatter writes valid UTF-8");
+        if self.inner.write_str(s).is_err() {
+            self.failed = true;
+            return Err(io::Error::new(io::ErrorKind::Other, "formatter error"));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer which keeps track of the column (in characters) of the next
+/// character.
+struct Column<W> {
+    inner: W,
+    col: usize,
+}
+
+impl<W: io::Write> io::Write for Column<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        for &byte in &buf[..written] {
+            if byte == b'\n' {
+                self.col = 0;
+            } else if !is_continuation_byte(byte) {
+                self.col += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writer which fails, and records that it failed, once more than the given
+/// number of bytes (if any) would be written.
+struct Bounded<W> {
+    inner: W,
+    remaining: Option<usize>,
+    exceeded: bool,
+}
+
+impl<W: io::Write> io::Write for Bounded<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(remaining) = self.remaining {
+            if buf.len() > remaining {
+                self.exceeded = true;
+                return Err(io::Error::new(io::ErrorKind::Other, "limit exceeded"));
+            }
+            let written = self.inner.write(buf)?;
+            self.remaining = Some(remaining - written);
+            Ok(written)
+        } else {
+            self.inner.write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writer which discards its input, and fails once more than the given
+/// number of characters have been written.
+struct Limit(usize);
+
+impl io::Write for Limit {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let chars = buf.iter().filter(|&&b| !is_continuation_byte(b)).count();
+        if chars > self.0 {
+            return Err(io::Error::new(io::ErrorKind::Other, "limit exceeded"));
+        }
+        self.0 -= chars;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns whether the byte is a UTF-8 continuation byte (i.e. not the first
+/// byte of a character).
+fn is_continuation_byte(byte: u8) -> bool {
+    byte & 0xc0 == 0x80
+}
+
+/// Pending step of writing a value, on the stack of `Formatter::value`.
+enum Task<'v> {
+    /// Write the value.
+    Value(&'v Value),
+    /// Write the value as a dict key.
+    Key(&'v Value),
+    /// Write the text.
+    Text(&'static str),
+    /// Write the element separator.
+    Separator,
+    /// Write the element separator without trailing whitespace.
+    Comma,
+    /// Write the key separator.
+    KeySeparator,
+    /// Start a new line, indented for the current depth.
+    Newline,
+    /// Increase the depth.
+    Enter,
+    /// Decrease the depth.
+    Leave,
+    /// Finish a collection.
+    Close,
+    /// Set whether to write collections on one line.
+    Inline(bool),
+}
+
+/// State of formatting a value.
+struct Formatter<'o, W> {
+    w: Column<W>,
+    opts: &'o FormatOptions,
+    /// Nesting depth of the collection being written.
+    depth: usize,
+    /// Number of collections containing the value being written.
+    level: usize,
+    /// Whether to write collections on one line, even when pretty-printing.
+    inline: bool,
+    /// Whether to write ANSI color codes.
+    highlight: bool,
+}
+
+impl<'o, W: io::Write> Formatter<'o, W> {
+    /// Writes the value.
+    ///
+    /// Nested collections are written with an explicit stack of tasks
+    /// instead of recursively, so deeply nested values can't overflow the
+    /// call stack.
+    fn value(&mut self, value: &Value) -> Result<(), FormatError> {
+        let inline = self.inline;
+        let mut tasks = vec![Task::Value(value)];
+        let result = self.run(&mut tasks);
+        self.inline = inline;
+        result
+    }
+
+    /// Performs the tasks on the stack until it's empty.
+    fn run<'v>(&mut self, tasks: &mut Vec<Task<'v>>) -> Result<(), FormatError> {
+        while let Some(task) = tasks.pop() {
+            match task {
+                Task::Value(value) => self.start_value(value, false, tasks)?,
+                Task::Key(key) => self.start_value(key, true, tasks)?,
+                Task::Text(text) => self.w.write_all(text.as_bytes())?,
+                Task::Separator => {
+                    let separator = self.opts.separator_strs().0;
+                    self.w.write_all(separator.as_bytes())?
                 }
-                w.write_all(b"'")?;
-            }
-            Value::Bytes(ref bytes) => {
-                w.write_all(b"b'")?;
-                for byte in bytes {
-                    match *byte {
-                        b'\\' => w.write_all(br"\\")?,
-                        b'\r' => w.write_all(br"\r")?,
-                        b'\n' => w.write_all(br"\n")?,
-                        b'\'' => w.write_all(br"\'")?,
-                        b if b.is_ascii() => w.write_all(&[b])?,
-                        b => write!(w, r"\x{:0>2x}", b)?,
-                    }
+                Task::Comma => {
+                    let separator = self.opts.separator_strs().0;
+                    self.w.write_all(separator.trim_end().as_bytes())?
+                }
+                Task::KeySeparator => {
+                    let separator = self.opts.separator_strs().1;
+                    self.w.write_all(separator.as_bytes())?
                 }
-                w.write_all(b"'")?;
+                Task::Newline => {
+                    self.w.write_all(b"\n")?;
+                    self.indent()?;
+                }
+                Task::Enter => self.depth += 1,
+                Task::Leave => self.depth -= 1,
+                Task::Close => self.level -= 1,
+                Task::Inline(inline) => self.inline = inline,
             }
-            Value::Integer(ref int) => write!(w, "{}", int)?,
-            Value::Float(float) => {
-                // Use scientific notation to make this unambiguously a float.
-                write!(w, "{:e}", float)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the value (with its color, if highlighting), or for a
+    /// collection, writes its start and pushes the tasks for the rest.
+    fn start_value<'v>(
+        &mut self,
+        value: &'v Value,
+        key: bool,
+        tasks: &mut Vec<Task<'v>>,
+    ) -> Result<(), FormatError> {
+        if let (true, false, Some(width)) = (self.opts.pretty, self.inline, self.opts.width) {
+            if is_collection(value) && self.fits(value, width) {
+                self.inline = true;
+                tasks.push(Task::Inline(false));
             }
-            Value::Complex(numc::Complex { re, im }) => {
-                write!(w, "{}{:+}j", re, im)?;
+        }
+        let color = if self.highlight {
+            highlight_color(value, key)
+        } else {
+            None
+        };
+        // The color codes bypass `Column`, since they don't take up space.
+        if let Some(color) = color {
+            self.w.inner.write_all(color.as_bytes())?;
+        }
+        match (value, self.wrap_width()) {
+            (Value::String(s), Some(width)) if !key => self.wrapped_string(s, width)?,
+            _ => self.write_value(value, tasks)?,
+        }
+        if color.is_some() {
+            self.w.inner.write_all(b"\x1b[0m")?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether the value fits in the rest of the line when written
+    /// on one line.
+    fn fits(&self, value: &Value, width: usize) -> bool {
+        let comma = if self.depth > 0 { 1 } else { 0 };
+        let available = match width.checked_sub(self.w.col + comma) {
+            Some(available) => available,
+            None => return false,
+        };
+        let mut measure = Formatter {
+            w: Column {
+                inner: Limit(available),
+                col: 0,
+            },
+            opts: self.opts,
+            depth: 0,
+            level: self.level,
+            inline: true,
+            highlight: false,
+        };
+        measure.value(value).is_ok()
+    }
+
+    /// Writes the value, or for a collection, writes its start and pushes
+    /// the tasks for the rest.
+    fn write_value<'v>(
+        &mut self,
+        value: &'v Value,
+        tasks: &mut Vec<Task<'v>>,
+    ) -> Result<(), FormatError> {
+        match *value {
+            Value::String(ref s) => self.string(s)?,
+            Value::Bytes(ref bytes) => self.bytes(bytes)?,
+            Value::ByteArray(ref bytes) => {
+                self.w.write_all(b"bytearray(")?;
+                self.bytes(bytes)?;
+                self.w.write_all(b")")?;
             }
-            Value::Tuple(ref tup) => {
-                w.write_all(b"(")?;
-                match tup.len() {
-                    0 => (),
-                    1 => {
-                        tup[0].write_ascii(w)?;
-                        w.write_all(b",")?;
-                    }
-                    _ => {
-                        tup[0].write_ascii(w)?;
-                        for value in &tup[1..] {
-                            w.write_all(b", ")?;
-                            value.write_ascii(w)?;
+            Value::Integer(ref int) => {
+                let formatted = match self.opts.integer_radix {
+                    IntegerRadix::Decimal => int.to_string(),
+                    IntegerRadix::Binary => format!("{:#b}", int),
+                    IntegerRadix::Octal => format!("{:#o}", int),
+                    IntegerRadix::Hex => format!("{:#x}", int),
+                };
+                if self.opts.underscores {
+                    let (group, prefix) = match self.opts.integer_radix {
+                        IntegerRadix::Decimal => (3, 0),
+                        _ => (4, 2),
+                    };
+                    let sign = if formatted.starts_with('-') { 1 } else { 0 };
+                    let (head, digits) = formatted.split_at(sign + prefix);
+                    write!(self.w, "{}{}", head, group_digits(digits, group))?;
+                } else {
+                    self.w.write_all(formatted.as_bytes())?;
+                }
+            }
+            Value::Float(float) => self.float(float)?,
+            Value::Complex(numc::Complex { re, im }) => {
+                if re.is_finite() && im.is_finite() {
+                    self.complex(re, im)?;
+                } else {
+                    match self.opts.non_finite_floats {
+                        NonFiniteFloats::Literal => self.complex(re, im)?,
+                        NonFiniteFloats::Error => return Err(FormatError::NonFiniteFloat),
+                        NonFiniteFloats::FloatCalls => {
+                            self.w.write_all(b"complex(")?;
+                            self.float(re)?;
+                            self.w.write_all(self.opts.separator_strs().0.as_bytes())?;
+                            self.float(im)?;
+                            self.w.write_all(b")")?;
                         }
                     }
                 }
-                w.write_all(b")")?;
-            }
-            Value::List(ref list) => {
-                w.write_all(b"[")?;
-                if !list.is_empty() {
-                    list[0].write_ascii(w)?;
-                    for value in &list[1..] {
-                        w.write_all(b", ")?;
-                        value.write_ascii(w)?;
+            }
+            #[cfg(feature = "bigdecimal")]
+            Value::Decimal(ref decimal) => write!(self.w, "Decimal('{}')", decimal)?,
+            #[cfg(feature = "num-rational")]
+            Value::Rational(ref ratio) => {
+                write!(self.w, "Fraction({}, {})", ratio.numer(), ratio.denom())?
+            }
+            Value::Tuple(ref tup) => {
+                self.seq(tasks, "(", tup, ")", tup.len() == 1, |value, seq| {
+                    seq.push(Task::Value(value))
+                })?
+            }
+            Value::List(ref list) => self.seq(tasks, "[", list, "]", false, |value, seq| {
+                seq.push(Task::Value(value))
+            })?,
+            Value::Dict(ref dict) => {
+                let items = if self.opts.sort_keys {
+                    sorted_items(dict)
+                } else {
+                    dict.iter().collect()
+                };
+                self.dict(tasks, &items)?
+            }
+            Value::Set(ref set) => {
+                if !set.is_empty() {
+                    let set = self.set_elements(set);
+                    self.seq(tasks, "{", &set, "}", false, |&value, seq| {
+                        seq.push(Task::Value(value))
+                    })?
+                } else if self.opts.empty_sets {
+                    self.w.write_all(b"set()")?;
+                } else {
+                    return Err(FormatError::EmptySet);
+                }
+            }
+            Value::FrozenSet(ref set) => {
+                if set.is_empty() {
+                    self.w.write_all(b"frozenset()")?;
+                } else {
+                    let set = self.set_elements(set);
+                    self.seq(tasks, "frozenset({", &set, "})", false, |&value, seq| {
+                        seq.push(Task::Value(value))
+                    })?
+                }
+            }
+            Value::Date(date) => write!(self.w, "{}", date)?,
+            Value::DateTime(datetime) => write!(self.w, "{}", datetime)?,
+            Value::Boolean(b) => {
+                if b {
+                    self.w.write_all(b"True")?;
+                } else {
+                    self.w.write_all(b"False")?;
+                }
+            }
+            Value::None => self.w.write_all(b"None")?,
+            Value::Ellipsis if self.opts.ellipsis_name => self.w.write_all(b"Ellipsis")?,
+            Value::Ellipsis => self.w.write_all(b"...")?,
+        }
+        Ok(())
+    }
+
+    /// Returns the elements of a set in the order to write them.
+    fn set_elements<'v>(&self, set: &'v [Value]) -> Vec<&'v Value> {
+        if self.opts.sort_sets {
+            sorted(set)
+        } else {
+            set.iter().collect()
+        }
+    }
+
+    fn float(&mut self, float: f64) -> Result<(), FormatError> {
+        if !float.is_finite() {
+            match self.opts.non_finite_floats {
+                NonFiniteFloats::Literal => (),
+                NonFiniteFloats::Error => return Err(FormatError::NonFiniteFloat),
+                NonFiniteFloats::FloatCalls => {
+                    let quote = self.opts.quote_style.quote(b"") as char;
+                    let arg = float_repr(float);
+                    write!(self.w, "float({}{}{})", quote, arg, quote)?;
+                    return Ok(());
+                }
+            }
+        }
+        let float = self.opts.round_float(float);
+        match self.opts.float_style {
+            // Use scientific notation to make this unambiguously a float.
+            FloatStyle::Scientific => write!(self.w, "{:e}", float)?,
+            FloatStyle::Repr => {
+                let repr = float_repr(float);
+                match repr.find('.') {
+                    Some(point) if self.opts.underscores && !repr.contains('e') => {
+                        let sign = if repr.starts_with('-') { 1 } else { 0 };
+                        let int = group_digits(&repr[sign..point], 3);
+                        write!(self.w, "{}{}{}", &repr[..sign], int, &repr[point..])?;
                     }
+                    _ => self.w.write_all(repr.as_bytes())?,
+                }
+            }
+            FloatStyle::Hex => {
+                let quote = self.opts.quote_style.quote(b"") as char;
+                let hex = float_hex(float);
+                write!(self.w, "float.fromhex({}{}{})", quote, hex, quote)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn complex(&mut self, re: f64, im: f64) -> Result<(), FormatError> {
+        let (re, im) = (self.opts.round_float(re), self.opts.round_float(im));
+        match self.opts.float_style {
+            FloatStyle::Scientific => write!(self.w, "{}{:+}j", re, im)?,
+            FloatStyle::Repr => {
+                let im_repr = complex_part_repr(im);
+                if re == 0. && re.is_sign_positive() {
+                    write!(self.w, "{}j", im_repr)?;
+                } else {
+                    let sign = if im_repr.starts_with('-') { "" } else { "+" };
+                    let re_repr = complex_part_repr(re);
+                    write!(self.w, "({}{}{}j)", re_repr, sign, im_repr)?;
+                }
+            }
+            FloatStyle::Hex => {
+                self.w.write_all(b"complex(")?;
+                self.float(re)?;
+                self.w.write_all(self.opts.separator_strs().0.as_bytes())?;
+                self.float(im)?;
+                self.w.write_all(b")")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn dict<'v>(
+        &mut self,
+        tasks: &mut Vec<Task<'v>>,
+        items: &[&'v (Value, Value)],
+    ) -> Result<(), FormatError> {
+        if !self.opts.ordered_dicts {
+            self.seq(tasks, "{", items, "}", false, |&(key, value), seq| {
+                seq.push(Task::Key(key));
+                seq.push(Task::KeySeparator);
+                seq.push(Task::Value(value));
+            })
+        } else if items.is_empty() {
+            self.w.write_all(b"OrderedDict()")?;
+            Ok(())
+        } else {
+            self.seq(
+                tasks,
+                "OrderedDict([",
+                items,
+                "])",
+                false,
+                |&(key, value), seq| {
+                    seq.push(Task::Text("("));
+                    seq.push(Task::Key(key));
+                    seq.push(Task::Separator);
+                    seq.push(Task::Value(value));
+                    seq.push(Task::Text(")"));
+                },
+            )
+        }
+    }
+
+    fn string(&mut self, s: &str) -> Result<(), FormatError> {
+        let policy = self.opts.escape_policy;
+        let quote = self.opts.quote_style.quote(s.as_bytes());
+        self.w.write_all(&[quote])?;
+        for c in s.chars() {
+            write_string_char(&mut self.w, c, quote, policy)?;
+        }
+        self.w.write_all(&[quote])?;
+        Ok(())
+    }
+
+    /// Returns the width to wrap strings at, if they should be wrapped.
+    fn wrap_width(&self) -> Option<usize> {
+        match (self.opts.pretty, self.opts.wrap_strings, self.inline) {
+            (true, true, false) => self.opts.width,
+            _ => None,
+        }
+    }
+
+    /// Writes the string, broken into adjacent pieces on separate lines if it
+    /// doesn't fit in the rest of the line.
+    fn wrapped_string(&mut self, s: &str, width: usize) -> Result<(), FormatError> {
+        let policy = self.opts.escape_policy;
+        let quote = self.opts.quote_style.quote(s.as_bytes());
+        // Escape each character separately, so that the pieces are split only
+        // between escape sequences.
+        let mut escaped = String::new();
+        let mut units = Vec::new();
+        for c in s.chars() {
+            let start = escaped.len();
+            let mut buf = Vec::new();
+            write_string_char(&mut buf, c, quote, policy)?;
+            escaped.push_str(std::str::from_utf8(&buf).unwrap());
+            units.push((start..escaped.len(), escaped[start..].chars().count()));
+        }
+        let comma = if self.depth > 0 { 1 } else { 0 };
+        let len: usize = units.iter().map(|&(_, chars)| chars).sum();
+        if units.len() < 2 || self.w.col + len + 2 + comma <= width {
+            return self.string(s);
+        }
+        // At the top level, the pieces need parentheses to be a single
+        // expression.
+        let top = self.depth == 0;
+        if top {
+            self.w.write_all(b"(\n")?;
+        }
+        self.depth += 1;
+        if top {
+            self.indent()?;
+        }
+        let mut units = units.into_iter().peekable();
+        while let Some((first, chars)) = units.next() {
+            let available = width.saturating_sub(self.w.col + 2 + comma);
+            let (start, mut end, mut used) = (first.start, first.end, chars);
+            while let Some((range, chars)) = units.peek() {
+                if used + chars > available {
+                    break;
+                }
+                end = range.end;
+                used += chars;
+                units.next();
+            }
+            self.w.write_all(&[quote])?;
+            self.w.write_all(&escaped.as_bytes()[start..end])?;
+            self.w.write_all(&[quote])?;
+            if units.peek().is_some() {
+                self.w.write_all(b"\n")?;
+                self.indent()?;
+            }
+        }
+        self.depth -= 1;
+        if top {
+            self.w.write_all(b"\n")?;
+            self.indent()?;
+            self.w.write_all(b")")?;
+        }
+        Ok(())
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> Result<(), FormatError> {
+        let policy = self.opts.escape_policy;
+        let quote = self.opts.quote_style.quote(bytes);
+        let w = &mut self.w;
+        w.write_all(&[b'b', quote])?;
+        for &byte in bytes {
+            match byte {
+                b'\\' => w.write_all(br"\\")?,
+                b'\r' => w.write_all(br"\r")?,
+                b'\n' => w.write_all(br"\n")?,
+                b if b == quote => w.write_all(&[b'\\', quote])?,
+                b'\t' if policy.escapes_control() => w.write_all(br"\t")?,
+                b if b.is_ascii_control() && policy.escapes_control() => {
+                    write!(w, r"\x{:0>2x}", b)?
                 }
-                w.write_all(b"]")?;
+                b if b.is_ascii() => w.write_all(&[b])?,
+                b => write!(w, r"\x{:0>2x}", b)?,
             }
-            Value::Dict(ref dict) => {
-                w.write_all(b"{")?;
-                if !dict.is_empty() {
-                    dict[0].0.write_ascii(w)?;
-                    w.write_all(b": ")?;
-                    dict[0].1.write_ascii(w)?;
-                    for elem in &dict[1..] {
-                        w.write_all(b", ")?;
-                        elem.0.write_ascii(w)?;
-                        w.write_all(b": ")?;
-                        elem.1.write_ascii(w)?;
-                    }
-                }
-                w.write_all(b"}")?;
+        }
+        w.write_all(&[quote])?;
+        Ok(())
+    }
+
+    /// Writes `open`, and pushes the tasks for the elements of a collection,
+    /// separated by commas, and `close`. Elements beyond the limits for
+    /// `Abbreviated` are written as `...`. If `trailing_comma` is true, a comma
+    /// is written after the last element too (as for a tuple with one
+    /// element). `elem_tasks` pushes the tasks for an element, in order.
+    fn seq<'v, 'e, T>(
+        &mut self,
+        tasks: &mut Vec<Task<'v>>,
+        open: &str,
+        elems: &'e [T],
+        close: &'static str,
+        trailing_comma: bool,
+        mut elem_tasks: impl FnMut(&'e T, &mut Vec<Task<'v>>),
+    ) -> Result<(), FormatError> {
+        self.w.write_all(open.as_bytes())?;
+        if !elems.is_empty() && self.opts.max_level.map_or(false, |max| self.level >= max) {
+            self.w.write_all(b"...")?;
+            self.w.write_all(close.as_bytes())?;
+            return Ok(());
+        }
+        self.level += 1;
+        let shown = self
+            .opts
+            .max_elems
+            .map_or(elems.len(), |max| max.min(elems.len()));
+        let len = if shown < elems.len() {
+            shown + 1
+        } else {
+            shown
+        };
+        let pretty = self.opts.pretty && !self.inline && len > 0;
+        let mut seq = Vec::new();
+        if pretty {
+            seq.push(Task::Enter);
+        }
+        let more = if shown < elems.len() {
+            Some(None)
+        } else {
+            None
+        };
+        for (i, elem) in elems[..shown].iter().map(Some).chain(more).enumerate() {
+            if pretty {
+                seq.push(Task::Newline);
+            } else if i > 0 {
+                seq.push(Task::Separator);
             }
-            Value::Set(ref set) => {
-                if set.is_empty() {
-                    return Err(FormatError::EmptySet);
-                } else {
-                    w.write_all(b"{")?;
-                    set[0].write_ascii(w)?;
-                    for value in &set[1..] {
-                        w.write_all(b", ")?;
-                        value.write_ascii(w)?;
-                    }
-                    w.write_all(b"}")?;
-                }
+            match elem {
+                Some(elem) => elem_tasks(elem, &mut seq),
+                None => seq.push(Task::Text("...")),
             }
-            Value::Boolean(b) => {
-                if b {
-                    w.write_all(b"True")?;
-                } else {
-                    w.write_all(b"False")?;
-                }
+            if pretty && (i + 1 < len || trailing_comma || self.opts.trailing_comma) {
+                seq.push(Task::Comma);
             }
-            Value::None => w.write_all(b"None")?,
+        }
+        if pretty {
+            seq.push(Task::Leave);
+            seq.push(Task::Newline);
+        } else if trailing_comma {
+            seq.push(Task::Comma);
+        }
+        seq.push(Task::Text(close));
+        seq.push(Task::Close);
+        tasks.extend(seq.into_iter().rev());
+        Ok(())
+    }
+
+    /// Writes the indentation for the current depth.
+    fn indent(&mut self) -> Result<(), FormatError> {
+        for _ in 0..self.depth {
+            self.w.write_all(self.opts.indent_str().as_bytes())?;
         }
         Ok(())
     }
 }
 
+/// Writes the character as it appears in a string literal enclosed in the
+/// quote.
+fn write_string_char<W: io::Write>(
+    w: &mut W,
+    c: char,
+    quote: u8,
+    policy: EscapePolicy,
+) -> io::Result<()> {
+    match c {
+        '\\' => w.write_all(br"\\"),
+        '\r' => w.write_all(br"\r"),
+        '\n' => w.write_all(br"\n"),
+        c if c == quote as char => w.write_all(&[b'\\', quote]),
+        '\t' if policy.escapes_control() => w.write_all(br"\t"),
+        c if c.is_control() && policy.escapes_control() => write!(w, r"\x{:0>2x}", c as u32),
+        c if c.is_ascii() => w.write_all(&[c as u8]),
+        c if !policy.escapes_non_ascii(c) => write!(w, "{}", c),
+        c => match c as u32 {
+            n @ 0..=0xff => write!(w, r"\x{:0>2x}", n),
+            n @ 0..=0xffff => write!(w, r"\u{:0>4x}", n),
+            n @ 0..=0xffff_ffff => write!(w, r"\U{:0>8x}", n),
+        },
+    }
+}
+
+/// Returns the ANSI color code to write the value in when highlighting, or
+/// `None` for collections and values which aren't highlighted.
+fn highlight_color(value: &Value, key: bool) -> Option<&'static str> {
+    match value {
+        _ if is_collection(value) => None,
+        _ if key => Some("\x1b[34m"),
+        Value::String(_) | Value::Bytes(_) | Value::ByteArray(_) => Some("\x1b[32m"),
+        Value::Integer(_) | Value::Float(_) | Value::Complex(_) => Some("\x1b[36m"),
+        #[cfg(feature = "bigdecimal")]
+        Value::Decimal(_) => Some("\x1b[36m"),
+        #[cfg(feature = "num-rational")]
+        Value::Rational(_) => Some("\x1b[36m"),
+        Value::Boolean(_) | Value::None | Value::Ellipsis => Some("\x1b[35m"),
+        _ => None,
+    }
+}
+
+/// Returns whether the value is written as a collection of other values.
+fn is_collection(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Tuple(_) | Value::List(_) | Value::Dict(_) | Value::Set(_) | Value::FrozenSet(_)
+    )
+}
+
+/// Separates groups of `group` digits, counting from the end, with
+/// underscores.
+fn group_digits(digits: &str, group: usize) -> String {
+    let mut out = String::with_capacity(digits.len() + digits.len() / group);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % group == 0 {
+            out.push('_');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Formats a float like Python's `repr()`.
+fn float_repr(float: f64) -> String {
+    if float.is_nan() {
+        return "nan".into();
+    } else if float.is_infinite() {
+        return if float > 0. { "inf" } else { "-inf" }.into();
+    }
+    // Rust's `{:e}` writes the shortest digits which round-trip, just like
+    // `repr()`; only the layout differs.
+    let sci = format!("{:e}", float);
+    let (mantissa, exp) = sci.split_at(sci.find('e').unwrap());
+    let exp: i32 = exp[1..].parse().unwrap();
+    let (sign, mantissa) = if mantissa.starts_with('-') {
+        ("-", &mantissa[1..])
+    } else {
+        ("", mantissa)
+    };
+    let digits: String = mantissa.chars().filter(|&c| c != '.').collect();
+    if (-4..16).contains(&exp) {
+        let (int, frac) = if exp < 0 {
+            ("0".to_string(), "0".repeat((-exp - 1) as usize) + &digits)
+        } else if digits.len() > exp as usize + 1 {
+            let (int, frac) = digits.split_at(exp as usize + 1);
+            (int.to_string(), frac.to_string())
+        } else {
+            let zeros = "0".repeat(exp as usize + 1 - digits.len());
+            (digits + &zeros, "0".to_string())
+        };
+        format!("{}{}.{}", sign, int, frac)
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let point = if rest.is_empty() { "" } else { "." };
+        let exp_sign = if exp < 0 { '-' } else { '+' };
+        format!(
+            "{}{}{}{}e{}{:02}",
+            sign,
+            first,
+            point,
+            rest,
+            exp_sign,
+            exp.abs()
+        )
+    }
+}
+
+/// Formats the float like Python's `float.hex()`.
+fn float_hex(float: f64) -> String {
+    if !float.is_finite() {
+        return float_repr(float);
+    }
+    let sign = if float.is_sign_negative() { "-" } else { "" };
+    let bits = float.to_bits();
+    let biased = ((bits >> 52) & 0x7ff) as i32;
+    let fraction = bits & ((1 << 52) - 1);
+    if biased == 0 && fraction == 0 {
+        format!("{}0x0.0p+0", sign)
+    } else if biased == 0 {
+        format!("{}0x0.{:013x}p-1022", sign, fraction)
+    } else {
+        format!("{}0x1.{:013x}p{:+}", sign, fraction, biased - 1023)
+    }
+}
+
+/// Formats a part of a complex number like Python's `repr()`, which is the
+/// same as for a float except without a trailing `.0`.
+fn complex_part_repr(float: f64) -> String {
+    let mut repr = float_repr(float);
+    if repr.ends_with(".0") {
+        repr.truncate(repr.len() - 2);
+    }
+    repr
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -186,6 +1852,117 @@ mod test {
         )
     }
 
+    #[test]
+    fn format_string_unicode() {
+        let value = Value::String("\u{e9}t\u{e9}\t\x03\x7f\u{85}'\\\n\u{4e2d}\u{1f600}".into());
+        assert_eq!(
+            value.format_unicode().unwrap(),
+            "'\u{e9}t\u{e9}\\t\\x03\\x7f\\x85\\'\\\\\\n\u{4e2d}\u{1f600}'",
+        );
+        assert_eq!(
+            value.format_unicode().unwrap().parse::<Value>().unwrap(),
+            value,
+        );
+        let mut out = Vec::new();
+        value.write_unicode(&mut out).unwrap();
+        assert_eq!(out, value.format_unicode().unwrap().as_bytes());
+    }
+
+    #[test]
+    fn format_escape_policy() {
+        let string = Value::String("a\u{e9}\t\x03\u{85}'\n\u{1f600}".into());
+        let bytes = Value::Bytes(b"a\xe9\t\x03\x7f'\n"[..].into());
+        for &(policy, correct_string, correct_bytes) in &[
+            (
+                EscapePolicy::NonAscii,
+                "'a\\xe9\t\x03\\x85\\'\\n\\U0001f600'",
+                "b'a\\xe9\t\x03\x7f\\'\\n'",
+            ),
+            (
+                EscapePolicy::AsciiOnly,
+                "'a\\xe9\\t\\x03\\x85\\'\\n\\U0001f600'",
+                "b'a\\xe9\\t\\x03\\x7f\\'\\n'",
+            ),
+            (
+                EscapePolicy::ControlOnly,
+                "'a\u{e9}\\t\\x03\\x85\\'\\n\u{1f600}'",
+                "b'a\\xe9\\t\\x03\\x7f\\'\\n'",
+            ),
+            (
+                EscapePolicy::QuotesOnly,
+                "'a\u{e9}\t\x03\u{85}\\'\\n\u{1f600}'",
+                "b'a\\xe9\t\x03\x7f\\'\\n'",
+            ),
+        ] {
+            let opts = FormatOptions::new().escape_policy(policy);
+            let formatted = string.format_with(&opts).unwrap();
+            assert_eq!(formatted, correct_string, "{:?}", policy);
+            assert_eq!(formatted.parse::<Value>().unwrap(), string);
+            let formatted = bytes.format_with(&opts).unwrap();
+            assert_eq!(formatted, correct_bytes, "{:?}", policy);
+            assert_eq!(formatted.parse::<Value>().unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn format_python_repr() {
+        use std::f64::{INFINITY, NAN};
+        // Expected outputs are from CPython's `repr()`.
+        for (value, correct) in vec![
+            (
+                Value::String("a\u{a0}b\u{200b}c\u{2028}\u{e000}\u{10fffd} \u{e9}\t\u{7f}".into()),
+                r"'a\xa0b\u200bc\u2028\ue000\U0010fffd é\t\x7f'",
+            ),
+            (
+                Value::String("\u{fffe}\u{3000}\u{1f600}".into()),
+                "'\\ufffe\\u3000\u{1f600}'",
+            ),
+            (Value::String("it's".into()), r#""it's""#),
+            (
+                Value::ByteArray(b"\x00'\xe9".to_vec()),
+                r#"bytearray(b"\x00'\xe9")"#,
+            ),
+            (Value::Float(1e16), "1e+16"),
+            (Value::Float(-NAN), "nan"),
+            (Value::Float(-INFINITY), "-inf"),
+            (Value::Complex(numc::Complex::new(1., INFINITY)), "(1+infj)"),
+            (Value::Complex(numc::Complex::new(0., -1.5)), "-1.5j"),
+            (Value::Set(vec![]), "set()"),
+            (Value::FrozenSet(vec![]), "frozenset()"),
+            (Value::Tuple(vec![Value::Ellipsis]), "(Ellipsis,)"),
+            (
+                Value::Dict(vec![(Value::Integer(1.into()), Value::None)]),
+                "{1: None}",
+            ),
+        ] {
+            assert_eq!(value.python_repr(), correct);
+        }
+    }
+
+    #[test]
+    fn format_quote_style() {
+        let repr = FormatOptions::new().quote_style(QuoteStyle::PythonRepr);
+        let double = FormatOptions::new().quote_style(QuoteStyle::Double);
+        for &(opts, s, correct) in &[
+            (&repr, "a", "'a'"),
+            (&repr, "it's", "\"it's\""),
+            (&repr, "say \"hi\"", "'say \"hi\"'"),
+            (&repr, "it's \"hi\"", "'it\\'s \"hi\"'"),
+            (&double, "a", "\"a\""),
+            (&double, "it's", "\"it's\""),
+            (&double, "it's \"hi\"", "\"it's \\\"hi\\\"\""),
+        ] {
+            let string = Value::String(s.into());
+            let formatted = string.format_with(opts).unwrap();
+            assert_eq!(formatted, correct);
+            assert_eq!(formatted.parse::<Value>().unwrap(), string);
+            let bytes = Value::Bytes(s.as_bytes().to_vec());
+            let formatted = bytes.format_with(opts).unwrap();
+            assert_eq!(formatted, format!("b{}", correct));
+            assert_eq!(formatted.parse::<Value>().unwrap(), bytes);
+        }
+    }
+
     #[test]
     fn format_bytes() {
         let value = Value::Bytes(b"hello\th\x03\xffo\x1bware\x07'you"[..].into());
@@ -193,6 +1970,265 @@ mod test {
         assert_eq!(formatted, "b'hello\th\x03\\xffo\x1bware\x07\\'you'")
     }
 
+    #[test]
+    fn format_bytes_repr() {
+        let opts = FormatOptions::new()
+            .escape_policy(EscapePolicy::AsciiOnly)
+            .quote_style(QuoteStyle::PythonRepr);
+        // Expected outputs are from CPython's `repr()`.
+        for &(bytes, correct) in &[
+            (&b""[..], "b''"),
+            (b"\x00\x01\x1f\x7f\x80\xff", r"b'\x00\x01\x1f\x7f\x80\xff'"),
+            (b"\t\n\r\x0b\x0c", r"b'\t\n\r\x0b\x0c'"),
+            (b"it's", r#"b"it's""#),
+            (b"\"q\"", r#"b'"q"'"#),
+            (b"'\"\\ ~", r#"b'\'"\\ ~'"#),
+        ] {
+            let value = Value::Bytes(bytes.to_vec());
+            assert_eq!(value.format_with(&opts).unwrap(), correct);
+            let value = Value::ByteArray(bytes.to_vec());
+            assert_eq!(
+                value.format_with(&opts).unwrap(),
+                format!("bytearray({})", correct),
+            );
+        }
+    }
+
+    #[test]
+    fn format_bytearray() {
+        use self::Value::*;
+        assert_eq!("bytearray(b'')", format!("{}", ByteArray(vec![])));
+        assert_eq!(
+            r"bytearray(b'a\'\xff')",
+            format!("{}", ByteArray(b"a'\xff".to_vec()))
+        );
+    }
+
+    #[test]
+    fn format_integer_radix() {
+        let value: Value = "[0, 1, -255, 12345678901234567890123, True]"
+            .parse()
+            .unwrap();
+        for &(radix, correct) in &[
+            (
+                IntegerRadix::Decimal,
+                "[0, 1, -255, 12345678901234567890123, True]",
+            ),
+            (
+                IntegerRadix::Binary,
+                "[0b0, 0b1, -0b11111111, 0b10100111010100001010110110010011100111011001110001010000100100010011001011, True]",
+            ),
+            (
+                IntegerRadix::Octal,
+                "[0o0, 0o1, -0o377, 0o2472412662347316120442313, True]",
+            ),
+            (
+                IntegerRadix::Hex,
+                "[0x0, 0x1, -0xff, 0x29d42b64e76714244cb, True]",
+            ),
+        ] {
+            let opts = FormatOptions::new().integer_radix(radix);
+            let formatted = value.format_with(&opts).unwrap();
+            assert_eq!(formatted, correct, "{:?}", radix);
+            assert_eq!(formatted.parse::<Value>().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn format_underscores() {
+        let value: Value = "[0, 999, -1000, 1234567, 1e15, -1234.5, 1.5e-7, 123j]"
+            .parse()
+            .unwrap();
+        let opts = FormatOptions::new().underscores(true);
+        assert_eq!(
+            value.format_with(&opts).unwrap(),
+            "[0, 999, -1_000, 1_234_567, 1e15, -1.2345e3, 1.5e-7, 0+123j]",
+        );
+        let repr = opts.clone().float_style(FloatStyle::Repr);
+        let formatted = value.format_with(&repr).unwrap();
+        assert_eq!(
+            formatted,
+            "[0, 999, -1_000, 1_234_567, 1_000_000_000_000_000.0, -1_234.5, 1.5e-07, 123j]",
+        );
+        assert_eq!(formatted.parse::<Value>().unwrap(), value);
+        for &(radix, correct) in &[
+            (IntegerRadix::Binary, "-0b1_0000_0000"),
+            (IntegerRadix::Octal, "-0o400"),
+            (IntegerRadix::Hex, "-0x100"),
+        ] {
+            let opts = opts.clone().integer_radix(radix);
+            let formatted = Value::Integer((-256).into()).format_with(&opts).unwrap();
+            assert_eq!(formatted, correct);
+        }
+    }
+
+    #[test]
+    fn format_float_repr() {
+        let opts = FormatOptions::new().float_style(FloatStyle::Repr);
+        for &(float, correct) in &[
+            (0., "0.0"),
+            (-0., "-0.0"),
+            (1., "1.0"),
+            (7e3, "7000.0"),
+            (0.5, "0.5"),
+            (-1.25, "-1.25"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (123456.789, "123456.789"),
+            (1e-4, "0.0001"),
+            (1.5e-5, "1.5e-05"),
+            (9999999999999998., "9999999999999998.0"),
+            (1e16, "1e+16"),
+            (-1.2345e100, "-1.2345e+100"),
+            (5e-324, "5e-324"),
+            (std::f64::MAX, "1.7976931348623157e+308"),
+            (std::f64::INFINITY, "inf"),
+            (std::f64::NEG_INFINITY, "-inf"),
+            (std::f64::NAN, "nan"),
+        ] {
+            assert_eq!(Value::Float(float).format_with(&opts).unwrap(), correct);
+        }
+        assert_eq!(Value::Float(7e3).format_ascii().unwrap(), "7e3");
+    }
+
+    #[test]
+    fn format_float_precision() {
+        let opts = FormatOptions::new()
+            .float_style(FloatStyle::Repr)
+            .float_precision(4);
+        for &(float, correct) in &[
+            (1.23456, "1.235"),
+            (-1234567.0, "-1235000.0"),
+            (0.000123456, "0.0001235"),
+            (9.99996, "10.0"),
+            (2.0, "2.0"),
+            (1e300, "1e+300"),
+        ] {
+            assert_eq!(Value::Float(float).format_with(&opts).unwrap(), correct);
+        }
+        let value = Value::Complex(numc::Complex::new(1.0 / 3.0, -2.0 / 3.0));
+        assert_eq!(value.format_with(&opts).unwrap(), "(0.3333-0.6667j)");
+        let one = FormatOptions::new().float_precision(0);
+        assert_eq!(Value::Float(2.7).format_with(&one).unwrap(), "3e0");
+        assert_eq!(
+            Value::Float(std::f64::NAN).format_with(&one).unwrap(),
+            "NaN"
+        );
+    }
+
+    #[test]
+    fn format_non_finite_floats() {
+        use std::f64::{INFINITY, NAN, NEG_INFINITY};
+        let value = Value::List(vec![
+            Value::Float(INFINITY),
+            Value::Float(NEG_INFINITY),
+            Value::Float(NAN),
+            Value::Complex(numc::Complex::new(1., NAN)),
+            Value::Complex(numc::Complex::new(0., 2.)),
+        ]);
+        assert_eq!(
+            value.format_ascii().unwrap(),
+            "[inf, -inf, NaN, 1NaNj, 0+2j]",
+        );
+        let error = FormatOptions::new().non_finite_floats(NonFiniteFloats::Error);
+        assert!(matches!(
+            value.format_with(&error),
+            Err(FormatError::NonFiniteFloat)
+        ));
+        assert!(Value::Complex(numc::Complex::new(INFINITY, 0.))
+            .format_with(&error)
+            .is_err());
+        assert_eq!(Value::Float(1.).format_with(&error).unwrap(), "1e0");
+        let calls = FormatOptions::new()
+            .non_finite_floats(NonFiniteFloats::FloatCalls)
+            .quote_style(QuoteStyle::Double);
+        let formatted = value.format_with(&calls).unwrap();
+        assert_eq!(
+            formatted,
+            "[float(\"inf\"), float(\"-inf\"), float(\"nan\"), complex(1e0, float(\"nan\")), 0+2j]",
+        );
+        let parse_opts = crate::ParseOptions::new()
+            .float_calls(true)
+            .complex_calls(true);
+        let parsed = Value::parse_with_options(&formatted, &parse_opts).unwrap();
+        let parsed = parsed.as_list().unwrap();
+        assert_eq!(parsed[0], Value::Float(INFINITY));
+        assert_eq!(parsed[1], Value::Float(NEG_INFINITY));
+        assert!(parsed[2].as_float().unwrap().is_nan());
+        assert!(parsed[3].as_complex().unwrap().im.is_nan());
+    }
+
+    #[test]
+    fn format_float_hex() {
+        use std::f64::{INFINITY, NAN};
+        let opts = FormatOptions::new().float_style(FloatStyle::Hex);
+        // Expected outputs are from CPython's `float.hex()`.
+        for &(float, correct) in &[
+            (0., "0x0.0p+0"),
+            (-0., "-0x0.0p+0"),
+            (1., "0x1.0000000000000p+0"),
+            (3., "0x1.8000000000000p+1"),
+            (0.1, "0x1.999999999999ap-4"),
+            (-1e300, "-0x1.7e43c8800759cp+996"),
+            (5e-324, "0x0.0000000000001p-1022"),
+            (std::f64::MAX, "0x1.fffffffffffffp+1023"),
+            (INFINITY, "inf"),
+        ] {
+            let formatted = Value::Float(float).format_with(&opts).unwrap();
+            assert_eq!(formatted, format!("float.fromhex('{}')", correct));
+        }
+        let value = Value::List(vec![
+            Value::Float(0.1 + 0.2),
+            Value::Float(-5e-324),
+            Value::Float(NAN),
+            Value::Complex(numc::Complex::new(1. / 3., -INFINITY)),
+        ]);
+        let formatted = value.format_with(&opts).unwrap();
+        let parse_opts = crate::ParseOptions::new()
+            .hex_floats(true)
+            .complex_calls(true);
+        let parsed = Value::parse_with_options(&formatted, &parse_opts).unwrap();
+        let parsed = parsed.as_list().unwrap();
+        assert_eq!(
+            parsed[0].as_float().unwrap().to_bits(),
+            (0.1 + 0.2f64).to_bits()
+        );
+        assert_eq!(
+            parsed[1].as_float().unwrap().to_bits(),
+            (-5e-324f64).to_bits()
+        );
+        assert!(parsed[2].as_float().unwrap().is_nan());
+        assert_eq!(parsed[3], value.as_list().unwrap()[3]);
+    }
+
+    #[test]
+    fn format_complex_repr() {
+        use std::f64::{INFINITY, NAN};
+        let opts = FormatOptions::new().float_style(FloatStyle::Repr);
+        // Expected outputs are from CPython's `repr()`.
+        for &(re, im, correct) in &[
+            (1., 2., "(1+2j)"),
+            (0., 2., "2j"),
+            (0., -1.5, "-1.5j"),
+            (0., 0., "0j"),
+            (0., -0., "-0j"),
+            (-0., 1., "(-0+1j)"),
+            (-0., -0., "(-0-0j)"),
+            (1.5, 0., "(1.5+0j)"),
+            (-2.5, -1e-5, "(-2.5-1e-05j)"),
+            (1e16, 1e300, "(1e+16+1e+300j)"),
+            (1., NAN, "(1+nanj)"),
+            (INFINITY, -INFINITY, "(inf-infj)"),
+            (0., INFINITY, "infj"),
+        ] {
+            let value = Value::Complex(numc::Complex::new(re, im));
+            assert_eq!(value.format_with(&opts).unwrap(), correct);
+        }
+        for input in &["(1+2j)", "2j", "(-2.5-1e-05j)"] {
+            let value: Value = input.parse().unwrap();
+            assert_eq!(value.format_with(&opts).unwrap(), *input);
+        }
+    }
+
     #[test]
     fn format_complex() {
         use self::Value::*;
@@ -205,10 +2241,31 @@ mod test {
         );
     }
 
+    #[cfg(feature = "bigdecimal")]
+    #[test]
+    fn format_decimal() {
+        use self::Value::*;
+        for &(decimal, correct) in &[("1.230", "Decimal('1.230')"), ("-5", "Decimal('-5')")] {
+            assert_eq!(correct, format!("{}", Decimal(decimal.parse().unwrap())));
+        }
+    }
+
+    #[cfg(feature = "num-rational")]
+    #[test]
+    fn format_rational() {
+        use self::Value::*;
+        let ratio = num_rational::BigRational::new((-2).into(), 6.into());
+        assert_eq!("Fraction(-1, 3)", format!("{}", Rational(ratio)));
+    }
+
     #[test]
     fn format_tuple() {
         use self::Value::*;
         assert_eq!("()", format!("{}", Tuple(vec![])));
+        assert_eq!(
+            "(..., 1)",
+            format!("{}", Tuple(vec![Ellipsis, Integer(1.into())]))
+        );
         assert_eq!("(1,)", format!("{}", Tuple(vec![Integer(1.into())])));
         assert_eq!(
             "(1, 2)",
@@ -273,7 +2330,20 @@ mod test {
     #[should_panic]
     fn format_empty_set() {
         use self::Value::*;
-        format!("{}", Set(vec![]));
+        let _ = format!("{}", Set(vec![]));
+    }
+
+    #[test]
+    fn format_frozenset() {
+        use self::Value::*;
+        assert_eq!("frozenset()", format!("{}", FrozenSet(vec![])));
+        assert_eq!(
+            "frozenset({1, 'hi'})",
+            format!(
+                "{}",
+                FrozenSet(vec![Integer(1.into()), String("hi".into())])
+            )
+        );
     }
 
     #[test]
@@ -297,6 +2367,179 @@ mod test {
         );
     }
 
+    #[test]
+    fn format_with_options() {
+        let input = "[(1,), {'a': frozenset({2}), 'b': {}}, [], 3]";
+        let value =
+            Value::parse_with_options(input, &crate::ParseOptions::new().frozensets(true)).unwrap();
+        let pretty = FormatOptions::new().pretty(true);
+        assert_eq!(
+            value.format_with(&pretty).unwrap(),
+            "[\n    (\n        1,\n    ),\n    {\n        'a': frozenset({\n            2\n        }),\n        'b': {}\n    },\n    [],\n    3\n]",
+        );
+        assert_eq!(format!("{:#}", value), value.format_with(&pretty).unwrap());
+        assert_eq!(
+            value.format_with(&pretty.clone().trailing_comma(true)).unwrap(),
+            "[\n    (\n        1,\n    ),\n    {\n        'a': frozenset({\n            2,\n        }),\n        'b': {},\n    },\n    [],\n    3,\n]",
+        );
+        assert_eq!(
+            value
+                .format_with(&FormatOptions::new().trailing_comma(true))
+                .unwrap(),
+            value.format_ascii().unwrap(),
+        );
+        assert_eq!(
+            value.format_with(&FormatOptions::new()).unwrap(),
+            value.format_ascii().unwrap(),
+        );
+        let ordered = FormatOptions::new().ordered_dicts(true);
+        assert_eq!(
+            value.format_with(&ordered).unwrap(),
+            "[(1,), OrderedDict([('a', frozenset({2})), ('b', OrderedDict())]), [], 3]",
+        );
+        assert_eq!(
+            Value::Dict(vec![(Value::None, Value::None)])
+                .format_with(&ordered.pretty(true))
+                .unwrap(),
+            "OrderedDict([\n    (None, None)\n])",
+        );
+        let empty = Value::List(vec![Value::Set(vec![])]);
+        assert!(matches!(
+            empty.format_with(&FormatOptions::new()),
+            Err(FormatError::EmptySet)
+        ));
+        let empty_sets = FormatOptions::new().empty_sets(true);
+        assert_eq!(empty.format_with(&empty_sets).unwrap(), "[set()]");
+    }
+
+    #[test]
+    fn format_width() {
+        let value: Value = "[[1, 2, 3], {'a': ('\u{e9}\u{e9}\u{e9}',)}, [], 4]"
+            .parse()
+            .unwrap();
+        let opts = FormatOptions::new().pretty(true).unicode(true);
+        for &(width, correct) in &[
+            (35, "[[1, 2, 3], {'a': ('\u{e9}\u{e9}\u{e9}',)}, [], 4]"),
+            (
+                34,
+                "[\n    [1, 2, 3],\n    {'a': ('\u{e9}\u{e9}\u{e9}',)},\n    [],\n    4\n]",
+            ),
+            (
+                15,
+                "[\n    [1, 2, 3],\n    {\n        'a': (\n            '\u{e9}\u{e9}\u{e9}',\n        )\n    },\n    [],\n    4\n]",
+            ),
+            (
+                0,
+                "[\n    [\n        1,\n        2,\n        3\n    ],\n    {\n        'a': (\n            '\u{e9}\u{e9}\u{e9}',\n        )\n    },\n    [],\n    4\n]",
+            ),
+        ] {
+            let formatted = value.format_with(&opts.clone().width(width)).unwrap();
+            assert_eq!(formatted, correct, "{}", width);
+        }
+        let compact = FormatOptions::new().width(0);
+        assert_eq!(
+            value.format_with(&compact).unwrap(),
+            value.format_ascii().unwrap()
+        );
+    }
+
+    #[test]
+    fn format_wrap_strings() {
+        let long = "It's a long string with \u{e9}scapes\n and ".repeat(4);
+        let value = Value::Dict(vec![
+            (Value::String("key".into()), Value::String(long.clone())),
+            (
+                Value::String("list".into()),
+                Value::List(vec![Value::String(long.clone()), Value::String("x".into())]),
+            ),
+        ]);
+        let comments = crate::ParseOptions::new().comments(true);
+        for &width in &[0, 10, 30, 80] {
+            let opts = FormatOptions::new()
+                .pretty(true)
+                .width(width)
+                .wrap_strings(true);
+            for value in &[value.clone(), Value::String(long.clone())] {
+                let formatted = value.format_with(&opts).unwrap();
+                let parsed = Value::parse_with_options(&formatted, &comments).unwrap();
+                assert_eq!(&parsed, value, "{}", formatted);
+                if width >= 30 {
+                    for line in formatted.lines() {
+                        assert!(line.chars().count() <= width, "{}", formatted);
+                    }
+                }
+            }
+        }
+        let opts = FormatOptions::new()
+            .pretty(true)
+            .width(16)
+            .wrap_strings(true);
+        let value = Value::String("abcdefghijklmnopqrst".into());
+        assert_eq!(
+            value.format_with(&opts).unwrap(),
+            "(\n    'abcdefghij'\n    'klmnopqrst'\n)"
+        );
+        let value = Value::List(vec![Value::String("a\nbcdefghijklmnopqrst".into())]);
+        assert_eq!(
+            value.format_with(&opts).unwrap(),
+            "[\n    'a\\nbcdefg'\n        'hijkl'\n        'mnopq'\n        'rst'\n]"
+        );
+        let unwrapped = FormatOptions::new().pretty(true).width(16);
+        assert_eq!(
+            value.format_with(&unwrapped).unwrap(),
+            "[\n    'a\\nbcdefghijklmnopqrst'\n]"
+        );
+    }
+
+    #[test]
+    fn format_indent_separators() {
+        let value: Value = "[(1,), {'a': 2, 'b': ()}]".parse().unwrap();
+        let compact = FormatOptions::new().separators(",", ":");
+        assert_eq!(
+            value.format_with(&compact).unwrap(),
+            "[(1,),{'a':2,'b':()}]"
+        );
+        let spaced = FormatOptions::new().separators(" , ", " : ");
+        assert_eq!(
+            value.format_with(&spaced).unwrap(),
+            "[(1 ,) , {'a' : 2 , 'b' : ()}]",
+        );
+        let pretty = FormatOptions::new()
+            .pretty(true)
+            .indent("  ")
+            .separators(",", ":")
+            .trailing_comma(true);
+        assert_eq!(
+            value.format_with(&pretty).unwrap(),
+            "[\n  (\n    1,\n  ),\n  {\n    'a':2,\n    'b':(),\n  },\n]",
+        );
+        let parse_opts = crate::ParseOptions::new().comments(true);
+        for opts in &[compact, spaced, pretty] {
+            let formatted = value.format_with(opts).unwrap();
+            assert_eq!(
+                Value::parse_with_options(&formatted, &parse_opts).unwrap(),
+                value,
+            );
+        }
+    }
+
+    #[test]
+    fn format_sorted() {
+        let input = "{frozenset({3, 1}): {'b', 'a'}, (2, 1): {'y': [2, 1], 'x': {}}}";
+        let parse_opts = crate::ParseOptions::new().frozensets(true);
+        let value = Value::parse_with_options(input, &parse_opts).unwrap();
+        let opts = FormatOptions::new().sort_keys(true).sort_sets(true);
+        assert_eq!(
+            value.format_with(&opts).unwrap(),
+            "{(2, 1): {'x': {}, 'y': [2, 1]}, frozenset({1, 3}): {'a', 'b'}}",
+        );
+        let opts = FormatOptions::new().sort_sets(true);
+        assert_eq!(
+            value.format_with(&opts).unwrap(),
+            "{frozenset({1, 3}): {'a', 'b'}, (2, 1): {'y': [2, 1], 'x': {}}}",
+        );
+    }
+
     #[test]
     fn format_nested() {
         use self::Value::*;
@@ -317,4 +2560,184 @@ mod test {
             ),
         );
     }
+
+    #[test]
+    fn format_deeply_nested() {
+        let depth = 100_000;
+        let mut value = Value::None;
+        let (mut open, mut close) = (String::new(), String::new());
+        for i in 0..depth {
+            value = if i % 2 == 0 {
+                close.push(']');
+                Value::List(vec![value])
+            } else {
+                close.push('}');
+                Value::Dict(vec![(Value::Integer(i.into()), value)])
+            };
+        }
+        for i in (0..depth).rev() {
+            if i % 2 == 0 {
+                open.push('[');
+            } else {
+                open.push_str(&format!("{{{}: ", i));
+            }
+        }
+        let formatted = value.format_ascii().unwrap();
+        assert!(formatted == format!("{}None{}", open, close));
+        assert_eq!(
+            formatted.len(),
+            value.formatted_len(&FormatOptions::new()).unwrap()
+        );
+        // Take the value apart, since dropping it would recurse.
+        let mut values = vec![value];
+        while let Some(value) = values.pop() {
+            match value {
+                Value::List(list) => values.extend(list),
+                Value::Dict(dict) => values.extend(dict.into_iter().map(|(_, value)| value)),
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn format_abbreviated() {
+        let input = "([1, 2, 3], (4,), {5: {6}}, set(), frozenset({7, 8}))";
+        let opts = crate::ParseOptions::new()
+            .empty_calls(true)
+            .frozensets(true);
+        let value = Value::parse_with_options(input, &opts).unwrap();
+        for &(max_elems, max_depth, correct) in &[
+            (0, 0, "(...)"),
+            (0, 5, "(...)"),
+            (2, 1, "([...], (...), ...)"),
+            (
+                5,
+                2,
+                "([1, 2, 3], (4,), {5: {...}}, set(), frozenset({7, 8}))",
+            ),
+            (2, 5, "([1, 2, ...], (4,), ...)"),
+        ] {
+            let abbreviated = value.abbreviated(max_elems, max_depth);
+            assert_eq!(abbreviated.to_string(), correct);
+        }
+        assert_eq!(
+            format!("{:#}", value.abbreviated(2, 1)),
+            "(\n    [...],\n    (...),\n    ...\n)",
+        );
+        assert_eq!(
+            format!("{}", Value::Integer(1.into()).abbreviated(0, 0)),
+            "1"
+        );
+    }
+
+    #[test]
+    fn format_max_output() {
+        let value: Value = "{'a': [1, 2], 'b': 'c\u{e9}'}".parse().unwrap();
+        for opts in &[
+            FormatOptions::new(),
+            FormatOptions::new().unicode(true),
+            FormatOptions::new().pretty(true),
+        ] {
+            let formatted = value.format_with(opts).unwrap();
+            let len = formatted.len();
+            let fits = opts.clone().max_output(len);
+            assert_eq!(value.format_with(&fits).unwrap(), formatted);
+            assert_eq!(value.formatted_len(&fits).unwrap(), len);
+            let short = opts.clone().max_output(len - 1);
+            match value.format_with(&short) {
+                Err(FormatError::OutputLimitExceeded) => (),
+                other => panic!("{:?}", other),
+            }
+            let mut out = Vec::new();
+            match value.write_with(&mut out, &short) {
+                Err(FormatError::OutputLimitExceeded) => (),
+                other => panic!("{:?}", other),
+            }
+            assert!(out.len() < len);
+            assert!(formatted.as_bytes().starts_with(&out));
+        }
+        let opts = FormatOptions::new().max_output(0);
+        match Value::None.format_with(&opts) {
+            Err(FormatError::OutputLimitExceeded) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[cfg(feature = "highlight")]
+    #[test]
+    fn format_highlight() {
+        let value: Value = "{(1, 'k'): [b'x', 2.5, True, ...], 'long key': 'v'}"
+            .parse()
+            .unwrap();
+        let opts = FormatOptions::new().highlight(true);
+        assert_eq!(
+            value.format_with(&opts).unwrap(),
+            "{(\x1b[36m1\x1b[0m, \x1b[32m'k'\x1b[0m): [\x1b[32mb'x'\x1b[0m, \
+             \x1b[36m2.5e0\x1b[0m, \x1b[35mTrue\x1b[0m, \x1b[35m...\x1b[0m], \
+             \x1b[34m'long key'\x1b[0m: \x1b[32m'v'\x1b[0m}",
+        );
+        let strip = |s: &str| {
+            let mut out = String::new();
+            let mut rest = s;
+            while let Some(start) = rest.find('\x1b') {
+                out.push_str(&rest[..start]);
+                rest = &rest[start + rest[start..].find('m').unwrap() + 1..];
+            }
+            out + rest
+        };
+        for width in 10..60 {
+            let plain = FormatOptions::new().pretty(true).width(width);
+            let highlighted = plain.clone().highlight(true);
+            assert_eq!(
+                strip(&value.format_with(&highlighted).unwrap()),
+                value.format_with(&plain).unwrap(),
+            );
+        }
+    }
+
+    #[test]
+    fn format_formatted_len() {
+        let value: Value = "{'caf\u{e9}': [1, 2.5, b'\\x00'], (): None}"
+            .parse()
+            .unwrap();
+        for opts in &[
+            FormatOptions::new(),
+            FormatOptions::new().unicode(true),
+            FormatOptions::new().pretty(true).indent("\t"),
+            FormatOptions::new().float_style(FloatStyle::Repr),
+        ] {
+            let formatted = value.format_with(opts).unwrap();
+            assert_eq!(value.formatted_len(opts).unwrap(), formatted.len());
+        }
+        match Value::Set(vec![]).formatted_len(&FormatOptions::new()) {
+            Err(FormatError::EmptySet) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn format_fmt_write() {
+        struct Failing;
+        impl fmt::Write for Failing {
+            fn write_str(&mut self, _: &str) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+        let value = Value::List(vec![Value::String("caf\u{e9}".into()), Value::Float(1.5)]);
+        let mut out = String::new();
+        value.write_ascii_fmt(&mut out).unwrap();
+        assert_eq!(out, "['caf\\xe9', 1.5e0]");
+        out.clear();
+        value.write_unicode_fmt(&mut out).unwrap();
+        assert_eq!(out, "['caf\u{e9}', 1.5e0]");
+        match value.write_ascii_fmt(&mut Failing) {
+            Err(FormatError::Fmt(_)) => (),
+            other => panic!("{:?}", other),
+        }
+        let empty = Value::Set(vec![]);
+        match empty.write_ascii_fmt(&mut out) {
+            Err(FormatError::EmptySet) => (),
+            other => panic!("{:?}", other),
+        }
+    }
 }
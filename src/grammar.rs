@@ -0,0 +1,106 @@
+//! The [pest] grammar of Python literals, for parsers built with pest which
+//! embed Python literals in a larger syntax.
+//!
+//! [`Parser`] is the pest parser generated from the grammar, and [`Rule`] is
+//! the type of its rules. Parsing with [`Rule::value`] matches a single
+//! literal at the start of the input, ignoring any text after it, so another
+//! parser can hand over the rest of its input and continue after the end of
+//! the matched pair. [`parse_value_pair`] then converts the pair into a
+//! [`Value`] without parsing the text again. [`GRAMMAR`] is the source of the
+//! grammar, for tools which interpret pest grammars at runtime.
+//!
+//! The rules are tied to the implementation of the parser, so their names
+//! and structure may change in any release; only the `value` rule is
+//! intended to be used directly.
+//!
+//! ```
+//! use pest::Parser as _;
+//! use py_literal::grammar::{self, Parser, Rule};
+//! use py_literal::{ParseOptions, Value};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let input = "[1, 2] -> rest";
+//! let pair = Parser::parse(Rule::value, input)?.next().unwrap();
+//! let end = pair.as_span().end();
+//! let value = grammar::parse_value_pair(pair, &ParseOptions::new())?;
+//! assert_eq!(value.to_string(), "[1, 2]");
+//! assert_eq!(&input[end..], " -> rest");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [pest]: https://docs.rs/pest/
+//! [`Parser`]: struct.Parser.html
+//! [`Rule`]: enum.Rule.html
+//! [`Rule::value`]: enum.Rule.html#variant.value
+//! [`parse_value_pair`]: fn.parse_value_pair.html
+//! [`Value`]: ../enum.Value.html
+//! [`GRAMMAR`]: constant.GRAMMAR.html
+
+use crate::parse::{self, syntax_error};
+use crate::{ParseError, ParseOptions, Value};
+use pest::iterators::Pair;
+use std::iter;
+
+pub use crate::parse::{Parser, Rule};
+
+/// Source of the pest grammar.
+pub const GRAMMAR: &str = include_str!("grammar.pest");
+
+/// Converts a pair matched by the `value` rule into a [`Value`], using the
+/// given options.
+///
+/// The pair has already been matched against the grammar, so the `comments`
+/// option has no effect, and the `max_depth` limit is checked only after the
+/// pair has been matched. All other options apply as for
+/// [`Value::parse_with_options`]. Returns an error if the pair wasn't matched
+/// by the `value` rule.
+///
+/// [`Value`]: ../enum.Value.html
+/// [`Value::parse_with_options`]: ../enum.Value.html#method.parse_with_options
+pub fn parse_value_pair(pair: Pair<'_, Rule>, options: &ParseOptions) -> Result<Value, ParseError> {
+    if pair.as_rule() != Rule::value {
+        return Err(syntax_error(pair.as_span(), "expected a `value` pair"));
+    }
+    let options = options.resolve();
+    options.check_cancelled()?;
+    let start = pair.as_span().start();
+    options
+        .check_depth(pair.as_str())
+        .map_err(|err| match err {
+            ParseError::DepthLimitExceeded(pos) => ParseError::DepthLimitExceeded(start + pos),
+            err => err,
+        })?;
+    options.check_nodes(iter::once(pair.clone()))?;
+    parse::parse_value(pair, &options)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pest::Parser as _;
+
+    #[test]
+    fn parse_value_pair_example() {
+        let opts = ParseOptions::new();
+        let pair = Parser::parse(Rule::value, "{'a': (1,)} tail")
+            .unwrap()
+            .next()
+            .unwrap();
+        assert_eq!(pair.as_span().end(), 11);
+        let value = parse_value_pair(pair, &opts).unwrap();
+        assert_eq!(value.to_string(), "{'a': (1,)}");
+        let pair = Parser::parse(Rule::list, "[1]").unwrap().next().unwrap();
+        assert!(parse_value_pair(pair, &opts).is_err());
+        let pair = Parser::parse(Rule::value, "[[1]]").unwrap().next().unwrap();
+        let result = parse_value_pair(pair, &ParseOptions::new().max_depth(1));
+        assert!(matches!(result, Err(ParseError::DepthLimitExceeded(1))));
+        let start = Parser::parse(Rule::start, "1, [[1]]")
+            .unwrap()
+            .next()
+            .unwrap();
+        let pair = start.into_inner().nth(1).unwrap();
+        let result = parse_value_pair(pair, &ParseOptions::new().max_depth(1));
+        assert!(matches!(result, Err(ParseError::DepthLimitExceeded(4))));
+    }
+}
@@ -0,0 +1,361 @@
+//! Iteration over the contents of values.
+
+use crate::Value;
+use std::iter::FromIterator;
+use std::slice;
+use std::vec;
+
+impl Value {
+    /// Returns an iterator over the elements if `self` is a `Value::List`,
+    /// `Value::Tuple`, `Value::Set`, or `Value::FrozenSet`. For other values,
+    /// the iterator is empty.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let value: Value = "[1, 'a', 2]".parse().unwrap();
+    /// assert_eq!(value.iter().filter(|elem| elem.is_integer()).count(), 2);
+    /// assert_eq!(Value::None.iter().count(), 0);
+    /// ```
+    pub fn iter(&self) -> slice::Iter<'_, Value> {
+        match self {
+            Value::List(elems)
+            | Value::Tuple(elems)
+            | Value::Set(elems)
+            | Value::FrozenSet(elems) => elems.iter(),
+            _ => [].iter(),
+        }
+    }
+
+    /// Returns the number of elements if `self` is a `Value::List`,
+    /// `Value::Tuple`, `Value::Set`, or `Value::FrozenSet`, the number of
+    /// items if it's a `Value::Dict`, the number of chars if it's a
+    /// `Value::String`, or the number of bytes if it's a `Value::Bytes` or
+    /// `Value::ByteArray`, like Python's `len()`. Returns `None` for other
+    /// values.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let value: Value = "{'a': [1, 2], 'b': 'π', 'c': b'\\xcf\\x80', 'd': None}".parse().unwrap();
+    /// assert_eq!(value.len(), Some(4));
+    /// let lens: Vec<_> = value.values().map(Value::len).collect();
+    /// assert_eq!(lens, [Some(2), Some(1), Some(2), None]);
+    /// ```
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            Value::String(string) => Some(string.chars().count()),
+            Value::Bytes(bytes) | Value::ByteArray(bytes) => Some(bytes.len()),
+            Value::List(elems)
+            | Value::Tuple(elems)
+            | Value::Set(elems)
+            | Value::FrozenSet(elems) => Some(elems.len()),
+            Value::Dict(items) => Some(items.len()),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if [`len`] is `Some(0)`. Returns `false` otherwise,
+    /// including for values without a length.
+    ///
+    /// [`len`]: #method.len
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    /// Returns an iterator over the items if `self` is a `Value::Dict`. For
+    /// other values, the iterator is empty.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let value: Value = "{'a': 1, 'b': 2}".parse().unwrap();
+    /// let keys: Vec<_> = value.keys().filter_map(Value::as_str).collect();
+    /// assert_eq!(keys, ["a", "b"]);
+    /// for (key, value) in value.entries() {
+    ///     assert!(key.is_string() && value.is_integer());
+    /// }
+    /// ```
+    pub fn entries(&self) -> Entries<'_> {
+        Entries(self.items().iter())
+    }
+
+    /// Returns an iterator over the items if `self` is a `Value::Dict`, with
+    /// mutable references to the values. For other values, the iterator is
+    /// empty.
+    pub fn entries_mut(&mut self) -> EntriesMut<'_> {
+        EntriesMut(self.items_mut().iter_mut())
+    }
+
+    /// Returns an iterator over the keys if `self` is a `Value::Dict`. For
+    /// other values, the iterator is empty.
+    pub fn keys(&self) -> Keys<'_> {
+        Keys(self.items().iter())
+    }
+
+    /// Returns an iterator over the values if `self` is a `Value::Dict`. For
+    /// other values, the iterator is empty.
+    pub fn values(&self) -> Values<'_> {
+        Values(self.items().iter())
+    }
+
+    /// Returns an iterator over mutable references to the values if `self` is
+    /// a `Value::Dict`. For other values, the iterator is empty.
+    pub fn values_mut(&mut self) -> ValuesMut<'_> {
+        ValuesMut(self.items_mut().iter_mut())
+    }
+
+    /// Creates a `Value::Tuple` from the elements of the iterator.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let tuple = Value::tuple_from_iter(1..3);
+    /// assert_eq!(tuple.to_string(), "(1, 2)");
+    /// let set = Value::set_from_iter(vec!["a", "b"]);
+    /// assert_eq!(set.to_string(), "{'a', 'b'}");
+    /// let list: Value = (1..3).map(|i| Value::from(i * 10)).collect();
+    /// assert_eq!(list.to_string(), "[10, 20]");
+    /// ```
+    pub fn tuple_from_iter<I>(iter: I) -> Value
+    where
+        I: IntoIterator,
+        I::Item: Into<Value>,
+    {
+        Value::Tuple(iter.into_iter().map(Into::into).collect())
+    }
+
+    /// Creates a `Value::Set` from the elements of the iterator. Duplicate
+    /// elements aren't removed.
+    pub fn set_from_iter<I>(iter: I) -> Value
+    where
+        I: IntoIterator,
+        I::Item: Into<Value>,
+    {
+        Value::Set(iter.into_iter().map(Into::into).collect())
+    }
+
+    fn items(&self) -> &[(Value, Value)] {
+        match self {
+            Value::Dict(items) => items,
+            _ => &[],
+        }
+    }
+
+    fn items_mut(&mut self) -> &mut [(Value, Value)] {
+        match self {
+            Value::Dict(items) => items,
+            _ => &mut [],
+        }
+    }
+
+    /// Returns an iterator over mutable references to the elements if `self`
+    /// is a `Value::List`, `Value::Tuple`, `Value::Set`, or
+    /// `Value::FrozenSet`. For other values, the iterator is empty.
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, Value> {
+        match self {
+            Value::List(elems)
+            | Value::Tuple(elems)
+            | Value::Set(elems)
+            | Value::FrozenSet(elems) => elems.iter_mut(),
+            _ => [].iter_mut(),
+        }
+    }
+}
+
+macro_rules! dict_iter {
+    ($(#[$attr:meta])* $name:ident, $inner:ident, $item:ty, |$pair:ident| $map:expr) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        pub struct $name<'a>(slice::$inner<'a, (Value, Value)>);
+
+        impl<'a> Iterator for $name<'a> {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<$item> {
+                self.0.next().map(|$pair| $map)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.0.size_hint()
+            }
+        }
+
+        impl<'a> DoubleEndedIterator for $name<'a> {
+            fn next_back(&mut self) -> Option<$item> {
+                self.0.next_back().map(|$pair| $map)
+            }
+        }
+
+        impl ExactSizeIterator for $name<'_> {}
+    };
+}
+
+dict_iter!(
+    /// Iterator over the items of a dict, created by [`Value::entries`].
+    ///
+    /// [`Value::entries`]: enum.Value.html#method.entries
+    Entries,
+    Iter,
+    (&'a Value, &'a Value),
+    |pair| (&pair.0, &pair.1)
+);
+dict_iter!(
+    /// Iterator over the items of a dict, with mutable references to the
+    /// values, created by [`Value::entries_mut`].
+    ///
+    /// [`Value::entries_mut`]: enum.Value.html#method.entries_mut
+    EntriesMut,
+    IterMut,
+    (&'a Value, &'a mut Value),
+    |pair| (&pair.0, &mut pair.1)
+);
+dict_iter!(
+    /// Iterator over the keys of a dict, created by [`Value::keys`].
+    ///
+    /// [`Value::keys`]: enum.Value.html#method.keys
+    Keys,
+    Iter,
+    &'a Value,
+    |pair| &pair.0
+);
+dict_iter!(
+    /// Iterator over the values of a dict, created by [`Value::values`].
+    ///
+    /// [`Value::values`]: enum.Value.html#method.values
+    Values,
+    Iter,
+    &'a Value,
+    |pair| &pair.1
+);
+dict_iter!(
+    /// Iterator over mutable references to the values of a dict, created by
+    /// [`Value::values_mut`].
+    ///
+    /// [`Value::values_mut`]: enum.Value.html#method.values_mut
+    ValuesMut,
+    IterMut,
+    &'a mut Value,
+    |pair| &mut pair.1
+);
+
+/// Collects the elements into a `Value::List`.
+impl FromIterator<Value> for Value {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Value {
+        Value::List(iter.into_iter().collect())
+    }
+}
+
+/// Collects the items into a `Value::Dict`.
+impl FromIterator<(Value, Value)> for Value {
+    fn from_iter<I: IntoIterator<Item = (Value, Value)>>(iter: I) -> Value {
+        Value::Dict(iter.into_iter().collect())
+    }
+}
+
+/// Iterates over the elements of a `Value::List`, `Value::Tuple`,
+/// `Value::Set`, or `Value::FrozenSet`. For other values, the iterator is
+/// empty.
+impl IntoIterator for Value {
+    type Item = Value;
+    type IntoIter = vec::IntoIter<Value>;
+
+    fn into_iter(self) -> vec::IntoIter<Value> {
+        match self {
+            Value::List(elems)
+            | Value::Tuple(elems)
+            | Value::Set(elems)
+            | Value::FrozenSet(elems) => elems.into_iter(),
+            _ => Vec::new().into_iter(),
+        }
+    }
+}
+
+/// Iterates over the elements like [`Value::iter`].
+///
+/// [`Value::iter`]: enum.Value.html#method.iter
+impl<'a> IntoIterator for &'a Value {
+    type Item = &'a Value;
+    type IntoIter = slice::Iter<'a, Value>;
+
+    fn into_iter(self) -> slice::Iter<'a, Value> {
+        self.iter()
+    }
+}
+
+/// Iterates over the elements like [`Value::iter_mut`].
+///
+/// [`Value::iter_mut`]: enum.Value.html#method.iter_mut
+impl<'a> IntoIterator for &'a mut Value {
+    type Item = &'a mut Value;
+    type IntoIter = slice::IterMut<'a, Value>;
+
+    fn into_iter(self) -> slice::IterMut<'a, Value> {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn iterate_elements() {
+        let mut value: Value = "[1, (2,), {3}]".parse().unwrap();
+        for elem in &mut value {
+            if let Some(tuple) = elem.as_tuple_mut() {
+                tuple.push(Value::None);
+            }
+        }
+        assert_eq!(value.to_string(), "[1, (2, None), {3}]");
+        let set = value.iter().nth(2).unwrap();
+        assert_eq!(set.iter().collect::<Vec<_>>(), [&Value::from(3)]);
+        assert_eq!(Value::from(1).iter().count(), 0);
+        assert_eq!(Value::from("ab").into_iter().count(), 0);
+        let elems: Vec<Value> = value.into_iter().collect();
+        assert_eq!(elems[0], Value::from(1));
+    }
+
+    #[test]
+    fn iterate_dict() {
+        let mut value: Value = "{'a': 1, 'b': [2]}".parse().unwrap();
+        for (key, value) in value.entries_mut() {
+            if key.as_str() == Some("a") {
+                *value = Value::None;
+            }
+        }
+        value
+            .values_mut()
+            .last()
+            .unwrap()
+            .iter_mut()
+            .for_each(|elem| {
+                *elem = Value::from(3);
+            });
+        assert_eq!(value.to_string(), "{'a': None, 'b': [3]}");
+        assert_eq!(
+            value.keys().rev().collect::<Vec<_>>(),
+            [&Value::from("b"), &Value::from("a")],
+        );
+        assert_eq!(value.values().len(), 2);
+        assert_eq!(value.entries().nth(1).unwrap().1.to_string(), "[3]");
+        assert_eq!(Value::from(vec![Value::None]).entries().count(), 0);
+    }
+
+    #[test]
+    fn collect_values() {
+        let value: Value = "{'a': 1, 'b': 2}".parse().unwrap();
+        let swapped: Value = value
+            .entries()
+            .map(|(key, value)| (value.clone(), key.clone()))
+            .collect();
+        assert_eq!(swapped.to_string(), "{1: 'a', 2: 'b'}");
+        let keys: Value = value.keys().cloned().collect();
+        assert_eq!(keys.to_string(), "['a', 'b']");
+        assert_eq!(
+            Value::set_from_iter(Vec::<Value>::new()),
+            Value::Set(vec![])
+        );
+        assert_eq!(Value::tuple_from_iter(Some(true)).to_string(), "(True,)");
+    }
+}
@@ -0,0 +1,447 @@
+//! Parsing which defers decoding of scalar values until they're accessed.
+
+use crate::parse::{parse_start, ParseOptions, Rule};
+use crate::spanned::span_of;
+use crate::{ParseError, Value};
+use pest::iterators::{Pair, Pairs};
+use std::ops::Range;
+use std::slice;
+use std::sync::Arc;
+
+/// Python literal whose scalar values haven't been decoded yet, created by
+/// [`Value::parse_lazy`].
+///
+/// [`Value::parse_lazy`]: enum.Value.html#method.parse_lazy
+#[derive(Clone, Debug)]
+pub enum LazyValue<'a> {
+    /// Tuple.
+    Tuple(Vec<LazyValue<'a>>),
+    /// List.
+    List(Vec<LazyValue<'a>>),
+    /// Dict.
+    Dict(Vec<(LazyValue<'a>, LazyValue<'a>)>),
+    /// Set.
+    Set(Vec<LazyValue<'a>>),
+    /// Any other value. This includes collections which aren't written with
+    /// brackets, such as `frozenset({1})` and `OrderedDict(...)`.
+    Leaf(LazyLeaf<'a>),
+}
+
+/// Value in a [`LazyValue`] which is decoded when it's accessed.
+///
+/// [`LazyValue`]: enum.LazyValue.html
+#[derive(Clone, Debug)]
+pub struct LazyLeaf<'a> {
+    text: &'a str,
+    span: Range<usize>,
+    options: Arc<ParseOptions>,
+}
+
+impl<'a> LazyLeaf<'a> {
+    /// Returns the text of the value in the input.
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    /// Returns the byte range of the value in the input.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Decodes the value, using the options it was parsed with.
+    ///
+    /// Positions in the error are relative to the start of the value.
+    pub fn decode(&self) -> Result<Value, ParseError> {
+        Value::parse_with_options(self.text, &self.options)
+    }
+}
+
+impl<'a> LazyValue<'a> {
+    /// Decodes every value in the literal.
+    pub fn to_value(&self) -> Result<Value, ParseError> {
+        // Nested collections are decoded with an explicit stack instead of
+        // recursively, so deeply nested values can't overflow the call stack.
+        let mut stack = match Decoding::new(self) {
+            Some(decoding) => vec![decoding],
+            None => return self.decode_leaf(),
+        };
+        let mut done = None;
+        while let Some(decoding) = stack.last_mut() {
+            if let Some(value) = done.take() {
+                decoding.push(value);
+            }
+            match decoding.next_value() {
+                Some(lazy) => match Decoding::new(lazy) {
+                    Some(decoding) => stack.push(decoding),
+                    None => done = Some(lazy.decode_leaf()?),
+                },
+                None => done = Some(stack.pop().unwrap().finish()),
+            }
+        }
+        Ok(done.unwrap())
+    }
+
+    fn decode_leaf(&self) -> Result<Value, ParseError> {
+        match self {
+            LazyValue::Leaf(leaf) => leaf.decode(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Collection whose elements are being decoded by `LazyValue::to_value`.
+enum Decoding<'l, 'a> {
+    /// Tuple, list, or set, with its remaining elements and the decoded ones.
+    Elements {
+        kind: fn(Vec<Value>) -> Value,
+        elems: slice::Iter<'l, LazyValue<'a>>,
+        decoded: Vec<Value>,
+    },
+    /// Dict, with its remaining items, the value of the current item if its
+    /// key is being decoded, and the decoded items and key.
+    Dict {
+        items: slice::Iter<'l, (LazyValue<'a>, LazyValue<'a>)>,
+        value: Option<&'l LazyValue<'a>>,
+        decoded: Vec<(Value, Value)>,
+        key: Option<Value>,
+    },
+}
+
+impl<'l, 'a> Decoding<'l, 'a> {
+    /// Returns a collection to decode, or `None` if `lazy` is a leaf.
+    fn new(lazy: &'l LazyValue<'a>) -> Option<Decoding<'l, 'a>> {
+        let elements = |kind, elems: &'l [LazyValue<'a>]| Decoding::Elements {
+            kind,
+            elems: elems.iter(),
+            decoded: Vec::with_capacity(elems.len()),
+        };
+        match lazy {
+            LazyValue::Tuple(tup) => Some(elements(Value::Tuple, tup)),
+            LazyValue::List(list) => Some(elements(Value::List, list)),
+            LazyValue::Set(set) => Some(elements(Value::Set, set)),
+            LazyValue::Dict(dict) => Some(Decoding::Dict {
+                items: dict.iter(),
+                value: None,
+                decoded: Vec::with_capacity(dict.len()),
+                key: None,
+            }),
+            LazyValue::Leaf(_) => None,
+        }
+    }
+
+    /// Returns the next element (or dict key or value) to decode.
+    fn next_value(&mut self) -> Option<&'l LazyValue<'a>> {
+        match self {
+            Decoding::Elements { elems, .. } => elems.next(),
+            Decoding::Dict { items, value, .. } => value.take().or_else(|| {
+                let (k, v) = items.next()?;
+                *value = Some(v);
+                Some(k)
+            }),
+        }
+    }
+
+    /// Adds a decoded element (or dict key or value).
+    fn push(&mut self, value: Value) {
+        match self {
+            Decoding::Elements { decoded, .. } => decoded.push(value),
+            Decoding::Dict { decoded, key, .. } => match key.take() {
+                None => *key = Some(value),
+                Some(key) => decoded.push((key, value)),
+            },
+        }
+    }
+
+    fn finish(self) -> Value {
+        match self {
+            Decoding::Elements { kind, decoded, .. } => kind(decoded),
+            Decoding::Dict { decoded, .. } => Value::Dict(decoded),
+        }
+    }
+}
+
+impl Value {
+    /// Parses the structure of a Python literal, using the default options,
+    /// without decoding its scalar values.
+    pub fn parse_lazy(s: &str) -> Result<LazyValue<'_>, ParseError> {
+        Value::parse_lazy_with_options(s, &ParseOptions::default())
+    }
+
+    /// Parses the structure of a Python literal, using the given options,
+    /// without decoding its scalar values.
+    ///
+    /// Tuples, lists, dicts, and sets written with brackets are parsed
+    /// immediately. Their other elements are only checked against the
+    /// grammar, and are decoded (which includes converting integers and
+    /// handling escape sequences) when [`LazyLeaf::decode`] is called, so
+    /// errors in them are reported then. Since the elements aren't compared,
+    /// the `duplicate_keys` and `deduplicate_sets` options don't apply, and
+    /// neither does the check for unhashable elements in strict mode.
+    ///
+    /// ```
+    /// use py_literal::{LazyValue, Value};
+    ///
+    /// # fn main() -> Result<(), py_literal::ParseError> {
+    /// let lazy = Value::parse_lazy("{'shape': (2, 3), 'data': [1, 2, 3, 4, 5, 6]}")?;
+    /// if let LazyValue::Dict(dict) = &lazy {
+    ///     for (key, value) in dict {
+    ///         if let LazyValue::Leaf(key) = key {
+    ///             if key.decode()?.as_string().map_or(false, |k| k == "shape") {
+    ///                 assert_eq!(value.to_value()?, "(2, 3)".parse()?);
+    ///             }
+    ///         }
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`LazyLeaf::decode`]: struct.LazyLeaf.html#method.decode
+    pub fn parse_lazy_with_options<'a>(
+        s: &'a str,
+        options: &ParseOptions,
+    ) -> Result<LazyValue<'a>, ParseError> {
+        parse_start(s, options, |pairs, opts| {
+            let lazy = Lazy {
+                input: s,
+                options: Arc::new(opts.without_progress()),
+            };
+            lazy.comma_separated(pairs)
+        })
+    }
+}
+
+/// State of lazy parsing.
+struct Lazy<'a> {
+    input: &'a str,
+    /// Options for decoding leaves.
+    options: Arc<ParseOptions>,
+}
+
+impl<'a> Lazy<'a> {
+    /// Parses comma-separated values as a tuple, unless there is only a
+    /// single value without a trailing comma, in which case the value is
+    /// returned as-is.
+    fn comma_separated(&self, pairs: Pairs<'_, Rule>) -> Result<LazyValue<'a>, ParseError> {
+        let mut values = Vec::new();
+        let mut trailing_comma = false;
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::value => values.push(self.value(pair)?),
+                Rule::tuple_comma => trailing_comma = true,
+                Rule::EOI => (),
+                _ => unreachable!(),
+            }
+        }
+        if values.len() == 1 && !trailing_comma {
+            Ok(values.pop().unwrap())
+        } else {
+            Ok(LazyValue::Tuple(values))
+        }
+    }
+
+    /// Parses a value.
+    ///
+    /// Nested tuples, lists, sets, and dicts are parsed with an explicit stack
+    /// instead of recursively, so deeply nested input can't overflow the call
+    /// stack.
+    fn value(&self, value: Pair<'_, Rule>) -> Result<LazyValue<'a>, ParseError> {
+        debug_assert_eq!(value.as_rule(), Rule::value);
+        let mut stack = match Frame::new(value.clone()) {
+            Some(frame) => vec![frame],
+            None => return Ok(self.leaf(&value)),
+        };
+        let mut done = None;
+        while let Some(frame) = stack.last_mut() {
+            self.options.check_cancelled()?;
+            if let Some(value) = done.take() {
+                frame.collection.push(value);
+            }
+            match frame.next_value() {
+                Some(value) => match Frame::new(value.clone()) {
+                    Some(frame) => stack.push(frame),
+                    None => done = Some(self.leaf(&value)),
+                },
+                None => done = Some(stack.pop().unwrap().collection.finish()),
+            }
+        }
+        Ok(done.unwrap())
+    }
+
+    fn leaf(&self, value: &Pair<'_, Rule>) -> LazyValue<'a> {
+        let span = span_of(value);
+        LazyValue::Leaf(LazyLeaf {
+            text: &self.input[span.clone()],
+            span,
+            options: self.options.clone(),
+        })
+    }
+}
+
+/// Collection whose elements are being parsed by `Lazy::value`.
+enum Collection<'a> {
+    /// Comma-separated values in parentheses, and whether there is a trailing
+    /// comma.
+    Parenthesized(Vec<LazyValue<'a>>, bool),
+    List(Vec<LazyValue<'a>>),
+    Set(Vec<LazyValue<'a>>),
+    /// Dict, and the key of the item whose value is being parsed.
+    Dict(Vec<(LazyValue<'a>, LazyValue<'a>)>, Option<LazyValue<'a>>),
+}
+
+impl<'a> Collection<'a> {
+    /// Adds an element (or dict key or value).
+    fn push(&mut self, value: LazyValue<'a>) {
+        match self {
+            Collection::Parenthesized(values, _)
+            | Collection::List(values)
+            | Collection::Set(values) => values.push(value),
+            Collection::Dict(dict, key) => match key.take() {
+                None => *key = Some(value),
+                Some(key) => dict.push((key, value)),
+            },
+        }
+    }
+
+    fn finish(self) -> LazyValue<'a> {
+        match self {
+            Collection::Parenthesized(mut values, trailing_comma) => {
+                if values.len() == 1 && !trailing_comma {
+                    values.pop().unwrap()
+                } else {
+                    LazyValue::Tuple(values)
+                }
+            }
+            Collection::List(list) => LazyValue::List(list),
+            Collection::Set(set) => LazyValue::Set(set),
+            Collection::Dict(dict, _) => LazyValue::Dict(dict),
+        }
+    }
+}
+
+/// Collection on the stack of `Lazy::value`.
+struct Frame<'a, 'i> {
+    collection: Collection<'a>,
+    /// Remaining items of the collection.
+    items: Pairs<'i, Rule>,
+    /// Remaining key and value of the current dict item.
+    dict_elem: Option<Pairs<'i, Rule>>,
+}
+
+impl<'a, 'i> Frame<'a, 'i> {
+    /// Returns a frame for the value if it's a tuple (or a value in redundant
+    /// parentheses), list, set, or dict.
+    fn new(value: Pair<'i, Rule>) -> Option<Frame<'a, 'i>> {
+        let (inner,) = parse_pairs_as!(value.into_inner(), (_,));
+        let collection = match inner.as_rule() {
+            Rule::parenthesized => Collection::Parenthesized(Vec::new(), false),
+            Rule::list => Collection::List(Vec::new()),
+            Rule::set => Collection::Set(Vec::new()),
+            Rule::dict => Collection::Dict(Vec::new(), None),
+            _ => return None,
+        };
+        Some(Frame {
+            collection,
+            items: inner.into_inner(),
+            dict_elem: None,
+        })
+    }
+
+    /// Returns the next element (or dict key or value) to parse.
+    fn next_value(&mut self) -> Option<Pair<'i, Rule>> {
+        loop {
+            let item = match self.dict_elem.as_mut().and_then(Iterator::next) {
+                Some(pair) => pair,
+                None => self.items.next()?,
+            };
+            match item.as_rule() {
+                Rule::value => return Some(item),
+                Rule::dict_elem => self.dict_elem = Some(item.into_inner()),
+                Rule::tuple_comma => {
+                    if let Collection::Parenthesized(_, trailing_comma) = &mut self.collection {
+                        *trailing_comma = true;
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_lazy_example() {
+        let opts = ParseOptions::new().comments(true).frozensets(true);
+        for input in &[
+            "(1 ,) ",
+            " 1, 'a\\x41',",
+            "((1), -2 + 3j)",
+            "[{1: b'\\n'}, {2}, []]",
+            "[1, # one\n frozenset({2, # two\n 3})]",
+        ] {
+            let lazy = Value::parse_lazy_with_options(input, &opts).unwrap();
+            assert_eq!(
+                lazy.to_value().unwrap(),
+                Value::parse_with_options(input, &opts).unwrap(),
+                "{:?}",
+                input,
+            );
+        }
+    }
+
+    #[test]
+    fn parse_lazy_leaves() {
+        let input = "{'a': 0755, 'b': [1, 'x\\N{NOT A NAME}']}";
+        let lazy = Value::parse_lazy(input).unwrap();
+        let dict = match &lazy {
+            LazyValue::Dict(dict) => dict,
+            _ => panic!(),
+        };
+        match &dict[0].1 {
+            LazyValue::Leaf(leaf) => {
+                assert_eq!(leaf.as_str(), "0755");
+                assert_eq!(leaf.span(), 6..10);
+                assert!(leaf.decode().is_err());
+            }
+            _ => panic!(),
+        }
+        match &dict[1].1 {
+            LazyValue::List(list) => match &list[0] {
+                LazyValue::Leaf(leaf) => {
+                    assert_eq!(leaf.decode().unwrap(), Value::Integer(1.into()))
+                }
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+        assert!(lazy.to_value().is_err());
+        assert!(Value::parse_lazy("[1, 2").is_err());
+    }
+
+    #[test]
+    fn parse_lazy_deeply_nested() {
+        // The thread's stack just needs to be large enough for the grammar,
+        // which is matched recursively.
+        let test = || {
+            let depth = 10_000;
+            let input = format!("{}1{}", "[{1: (".repeat(depth), ",)}]".repeat(depth));
+            let lazy = Value::parse_lazy(&input).unwrap();
+            let value = lazy.to_value().unwrap();
+            let mut elem = &value;
+            for _ in 0..depth {
+                let dict = &elem.as_list().unwrap()[0];
+                elem = &dict.as_dict().unwrap()[0].1.as_tuple().unwrap()[0];
+            }
+            assert_eq!(elem, &Value::Integer(1.into()));
+        };
+        std::thread::Builder::new()
+            .stack_size(256 << 20)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
@@ -42,14 +42,88 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! # Crate features
+//!
+//! * `bigdecimal`: Enables the `Value::Decimal` variant, which represents
+//!   Python's `decimal.Decimal`.
+//! * `chrono`: Enables conversions between [`Date`]/[`DateTime`] and
+//!   `chrono::NaiveDate`/`chrono::NaiveDateTime`.
+//! * `ciborium`: Enables the [`cbor`] module, which provides conversions
+//!   between [`Value`] and CBOR values.
+//! * `futures-io`: Enables [`Value::write_ascii_async`] and
+//!   [`Value::write_with_async`], which write to a
+//!   `futures_io::AsyncWrite`.
+//! * `highlight`: Enables [`FormatOptions::highlight`], which writes ANSI
+//!   color codes for syntax highlighting in terminals.
+//! * `memmap2`: Enables [`Value::from_path`], which parses a memory-mapped
+//!   file.
+//! * `num-rational`: Enables the `Value::Rational` variant, which represents
+//!   Python's `fractions.Fraction`.
+//! * `unicode_names2`: Enables support for Unicode name escapes (`\N{name}`)
+//!   in string literals.
+//!
+//! [`cbor`]: cbor/index.html
+//! [`Date`]: struct.Date.html
+//! [`DateTime`]: struct.DateTime.html
+//! [`FormatOptions::highlight`]: struct.FormatOptions.html#method.highlight
+//! [`Value::from_path`]: enum.Value.html#method.from_path
+//! [`Value::write_ascii_async`]: enum.Value.html#method.write_ascii_async
+//! [`Value::write_with_async`]: enum.Value.html#method.write_with_async
 
-mod format;
 #[macro_use]
 mod parse_macros;
+
+mod assign;
+#[cfg(feature = "futures-io")]
+mod async_write;
+mod borrowed;
+#[cfg(feature = "ciborium")]
+pub mod cbor;
+mod compare;
+mod convert;
+mod cst;
+mod datetime;
+mod events;
+mod find;
+mod format;
+pub mod grammar;
+mod iter;
+mod lazy;
+mod minify;
+mod mutate;
 mod parse;
+mod recover;
+mod reformat;
+mod scan;
+mod spanned;
+mod stream;
 
-pub use crate::format::FormatError;
-pub use crate::parse::ParseError;
+pub use crate::assign::{
+    format_assignments, parse_assignments, parse_assignments_with_options, write_assignments,
+};
+pub use crate::borrowed::ValueRef;
+pub use crate::convert::TryFromValueError;
+pub use crate::cst::{CstChild, CstKind, CstNode, CstToken, TokenKind};
+pub use crate::datetime::{Date, DateTime, OutOfRangeError};
+pub use crate::events::{streams_equal, streams_equal_with_options, Event, EventParser};
+pub use crate::find::{find_literals, find_literals_with_options, Literals};
+pub use crate::format::{
+    Abbreviated, EscapePolicy, FloatStyle, FormatError, FormatOptions, IntegerRadix,
+    NonFiniteFloats, QuoteStyle,
+};
+pub use crate::iter::{Entries, EntriesMut, Keys, Values, ValuesMut};
+pub use crate::lazy::{LazyLeaf, LazyValue};
+pub use crate::minify::{minify, minify_with_options};
+pub use crate::mutate::TypeError;
+pub use crate::parse::{
+    validate, CancellationToken, Dialect, DuplicateKeys, ParseError, ParseOptions, SourceEncoding,
+    SurrogateEscapes,
+};
+pub use crate::recover::{Diagnostic, Recovered};
+pub use crate::reformat::{reformat, reformat_with_options, ReformatError};
+pub use crate::spanned::{SpannedNode, SpannedValue};
+pub use crate::stream::{PushParser, PushStatus, ValueStream};
 
 use num_bigint as numb;
 use num_complex as numc;
@@ -74,6 +148,13 @@ pub enum Value {
     /// interpreted. When formatting, backslash escapes are used to ensure the
     /// result contains only ASCII chars.
     Bytes(Vec<u8>),
+    /// Python mutable byte sequence (`bytearray`). There is no literal syntax
+    /// for byte arrays, so this is formatted as a call, like
+    /// `bytearray(b'abc')`. The parser accepts that form only if enabled in
+    /// the [`ParseOptions`].
+    ///
+    /// [`ParseOptions`]: struct.ParseOptions.html#method.bytearrays
+    ByteArray(Vec<u8>),
     /// Python integer (`int`). Python integers have unlimited precision, so we
     /// use `BigInt`.
     Integer(numb::BigInt),
@@ -84,6 +165,22 @@ pub enum Value {
     /// Python complex number (`complex`). The Python `complex` type contains
     /// two `float` values.
     Complex(numc::Complex<f64>),
+    /// Python decimal number (`decimal.Decimal`). There is no literal syntax
+    /// for decimals, so this is formatted as a call, like `Decimal('1.23')`.
+    /// The parser accepts that form only if enabled in the
+    /// [`ParseOptions`]. Requires the `bigdecimal` feature.
+    ///
+    /// [`ParseOptions`]: struct.ParseOptions.html#method.decimals
+    #[cfg(feature = "bigdecimal")]
+    Decimal(bigdecimal::BigDecimal),
+    /// Python rational number (`fractions.Fraction`). There is no literal
+    /// syntax for fractions, so this is formatted as a call, like
+    /// `Fraction(1, 3)`. The parser accepts that form only if enabled in the
+    /// [`ParseOptions`]. Requires the `num-rational` feature.
+    ///
+    /// [`ParseOptions`]: struct.ParseOptions.html#method.fractions
+    #[cfg(feature = "num-rational")]
+    Rational(num_rational::BigRational),
     /// Python tuple (`tuple`).
     Tuple(Vec<Value>),
     /// Python list (`list`).
@@ -92,24 +189,105 @@ pub enum Value {
     Dict(Vec<(Value, Value)>),
     /// Python set (`set`).
     Set(Vec<Value>),
+    /// Python frozen set (`frozenset`). There is no literal syntax for frozen
+    /// sets, so this is formatted as a call, like `frozenset({1, 2})`. The
+    /// parser accepts that form only if enabled in the [`ParseOptions`].
+    ///
+    /// [`ParseOptions`]: struct.ParseOptions.html#method.frozensets
+    FrozenSet(Vec<Value>),
+    /// Python date (`datetime.date`). There is no literal syntax for dates,
+    /// so this is formatted as a call, like `datetime.date(2023, 5, 1)`. The
+    /// parser accepts that form only if enabled in the [`ParseOptions`].
+    ///
+    /// [`ParseOptions`]: struct.ParseOptions.html#method.datetimes
+    Date(Date),
+    /// Python naive date and time (`datetime.datetime` without `tzinfo`).
+    /// There is no literal syntax for these, so this is formatted as a call,
+    /// like `datetime.datetime(2023, 5, 1, 12, 0)`. The parser accepts that
+    /// form only if enabled in the [`ParseOptions`].
+    ///
+    /// [`ParseOptions`]: struct.ParseOptions.html#method.datetimes
+    DateTime(DateTime),
     /// Python boolean (`bool`).
     Boolean(bool),
     /// Python `None`.
     None,
+    /// Python `Ellipsis` (written as `...`).
+    Ellipsis,
 }
 
 impl fmt::Display for Value {
     /// Formats the value as a Python literal.
     ///
-    /// Currently, this just calls `self.format_ascii()`, but that may change
-    /// in the future.
+    /// Currently, this just calls `self.write_ascii_fmt(f)`, but that may change
+    /// in the future. With the alternate flag (`{:#}`), the value is
+    /// pretty-printed instead, as with [`FormatOptions::pretty`].
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// # fn main() -> Result<(), py_literal::ParseError> {
+    /// let value: Value = "{'a': [1, 2]}".parse()?;
+    /// assert_eq!(format!("{}", value), "{'a': [1, 2]}");
+    /// assert_eq!(
+    ///     format!("{:#}", value),
+    ///     "{\n    'a': [\n        1,\n        2\n    ]\n}",
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`FormatOptions::pretty`]: struct.FormatOptions.html#method.pretty
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        // TODO: is there a better way to do this?
-        write!(f, "{}", self.format_ascii().map_err(|_| fmt::Error)?)
+        let result = if f.alternate() {
+            self.write_with_fmt(f, &FormatOptions::new().pretty(true))
+        } else {
+            self.write_ascii_fmt(f)
+        };
+        result.map_err(|_| fmt::Error)
+    }
+}
+
+/// Returns `Value::None`.
+impl Default for Value {
+    fn default() -> Value {
+        Value::None
     }
 }
 
 impl Value {
+    /// Creates an empty `Value::Dict`.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let mut value = Value::empty_dict();
+    /// value.insert(Value::from("a"), Value::empty_tuple()).unwrap();
+    /// assert_eq!(value.to_string(), "{'a': ()}");
+    /// assert_eq!(Value::default(), Value::None);
+    /// ```
+    pub fn empty_dict() -> Value {
+        Value::Dict(Vec::new())
+    }
+
+    /// Creates an empty `Value::List`.
+    pub fn empty_list() -> Value {
+        Value::List(Vec::new())
+    }
+
+    /// Creates an empty `Value::Tuple`.
+    pub fn empty_tuple() -> Value {
+        Value::Tuple(Vec::new())
+    }
+
+    /// Creates an empty `Value::Set`. Note that there's no literal syntax
+    /// for an empty set; see [`FormatOptions::empty_sets`].
+    ///
+    /// [`FormatOptions::empty_sets`]: struct.FormatOptions.html#method.empty_sets
+    pub fn empty_set() -> Value {
+        Value::Set(Vec::new())
+    }
+
     /// Returns `true` if `self` is `Value::String`. Returns `false` otherwise.
     pub fn is_string(&self) -> bool {
         matches!(self, Value::String(_))
@@ -123,6 +301,22 @@ impl Value {
         }
     }
 
+    /// If `self` is `Value::String`, returns the associated string slice. Returns `None` otherwise.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// If `self` is `Value::String`, returns a mutable reference to the associated string. Returns `None` otherwise.
+    pub fn as_string_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
     /// Returns `true` if `self` is `Value::Bytes`. Returns `false` otherwise.
     pub fn is_bytes(&self) -> bool {
         matches!(self, Value::Bytes(_))
@@ -136,6 +330,43 @@ impl Value {
         }
     }
 
+    /// If `self` is `Value::Bytes`, returns the associated byte slice. Returns `None` otherwise.
+    pub fn as_byte_slice(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// If `self` is `Value::Bytes`, returns a mutable reference to the associated bytes. Returns `None` otherwise.
+    pub fn as_bytes_mut(&mut self) -> Option<&mut Vec<u8>> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `self` is `Value::ByteArray`. Returns `false` otherwise.
+    pub fn is_bytearray(&self) -> bool {
+        matches!(self, Value::ByteArray(_))
+    }
+
+    /// If `self` is `Value::ByteArray`, returns the associated bytes. Returns `None` otherwise.
+    pub fn as_bytearray(&self) -> Option<&Vec<u8>> {
+        match self {
+            Value::ByteArray(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// If `self` is `Value::ByteArray`, returns a mutable reference to the associated bytes. Returns `None` otherwise.
+    pub fn as_bytearray_mut(&mut self) -> Option<&mut Vec<u8>> {
+        match self {
+            Value::ByteArray(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
     /// Returns `true` if `self` is `Value::Integer`. Returns `false` otherwise.
     pub fn is_integer(&self) -> bool {
         matches!(self, Value::Integer(_))
@@ -149,6 +380,14 @@ impl Value {
         }
     }
 
+    /// If `self` is `Value::Integer`, returns a mutable reference to the associated integer. Returns `None` otherwise.
+    pub fn as_integer_mut(&mut self) -> Option<&mut numb::BigInt> {
+        match self {
+            Value::Integer(integer) => Some(integer),
+            _ => None,
+        }
+    }
+
     /// Returns `true` if `self` is `Value::Float`. Returns `false` otherwise.
     pub fn is_float(&self) -> bool {
         matches!(self, Value::Float(_))
@@ -162,6 +401,14 @@ impl Value {
         }
     }
 
+    /// If `self` is `Value::Float`, returns a mutable reference to the associated float. Returns `None` otherwise.
+    pub fn as_float_mut(&mut self) -> Option<&mut f64> {
+        match self {
+            Value::Float(float) => Some(float),
+            _ => None,
+        }
+    }
+
     /// Returns `true` if `self` is `Value::Complex`. Returns `false` otherwise.
     pub fn is_complex(&self) -> bool {
         matches!(self, Value::Complex(_))
@@ -175,6 +422,62 @@ impl Value {
         }
     }
 
+    /// If `self` is `Value::Complex`, returns a mutable reference to the associated complex number. Returns `None` otherwise.
+    pub fn as_complex_mut(&mut self) -> Option<&mut numc::Complex<f64>> {
+        match self {
+            Value::Complex(complex) => Some(complex),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `self` is `Value::Decimal`. Returns `false` otherwise.
+    #[cfg(feature = "bigdecimal")]
+    pub fn is_decimal(&self) -> bool {
+        matches!(self, Value::Decimal(_))
+    }
+
+    /// If `self` is `Value::Decimal`, returns the associated decimal. Returns `None` otherwise.
+    #[cfg(feature = "bigdecimal")]
+    pub fn as_decimal(&self) -> Option<&bigdecimal::BigDecimal> {
+        match self {
+            Value::Decimal(decimal) => Some(decimal),
+            _ => None,
+        }
+    }
+
+    /// If `self` is `Value::Decimal`, returns a mutable reference to the associated decimal. Returns `None` otherwise.
+    #[cfg(feature = "bigdecimal")]
+    pub fn as_decimal_mut(&mut self) -> Option<&mut bigdecimal::BigDecimal> {
+        match self {
+            Value::Decimal(decimal) => Some(decimal),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `self` is `Value::Rational`. Returns `false` otherwise.
+    #[cfg(feature = "num-rational")]
+    pub fn is_rational(&self) -> bool {
+        matches!(self, Value::Rational(_))
+    }
+
+    /// If `self` is `Value::Rational`, returns the associated fraction. Returns `None` otherwise.
+    #[cfg(feature = "num-rational")]
+    pub fn as_rational(&self) -> Option<&num_rational::BigRational> {
+        match self {
+            Value::Rational(rational) => Some(rational),
+            _ => None,
+        }
+    }
+
+    /// If `self` is `Value::Rational`, returns a mutable reference to the associated fraction. Returns `None` otherwise.
+    #[cfg(feature = "num-rational")]
+    pub fn as_rational_mut(&mut self) -> Option<&mut num_rational::BigRational> {
+        match self {
+            Value::Rational(rational) => Some(rational),
+            _ => None,
+        }
+    }
+
     /// Returns `true` if `self` is `Value::Tuple`. Returns `false` otherwise.
     pub fn is_tuple(&self) -> bool {
         matches!(self, Value::Tuple(_))
@@ -188,6 +491,14 @@ impl Value {
         }
     }
 
+    /// If `self` is `Value::Tuple`, returns a mutable reference to the associated data. Returns `None` otherwise.
+    pub fn as_tuple_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Tuple(tuple) => Some(tuple),
+            _ => None,
+        }
+    }
+
     /// Returns `true` if `self` is `Value::List`. Returns `false` otherwise.
     pub fn is_list(&self) -> bool {
         matches!(self, Value::List(_))
@@ -201,6 +512,22 @@ impl Value {
         }
     }
 
+    /// If `self` is `Value::List`, returns a mutable reference to the associated data. Returns `None` otherwise.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let mut value: Value = "[1, 2]".parse().unwrap();
+    /// value.as_list_mut().unwrap().push(Value::from(3));
+    /// assert_eq!(value.to_string(), "[1, 2, 3]");
+    /// ```
+    pub fn as_list_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
     /// Returns `true` if `self` is `Value::Dict`. Returns `false` otherwise.
     pub fn is_dict(&self) -> bool {
         matches!(self, Value::Dict(_))
@@ -214,6 +541,14 @@ impl Value {
         }
     }
 
+    /// If `self` is `Value::Dict`, returns a mutable reference to the associated data. Returns `None` otherwise.
+    pub fn as_dict_mut(&mut self) -> Option<&mut Vec<(Value, Value)>> {
+        match self {
+            Value::Dict(dict) => Some(dict),
+            _ => None,
+        }
+    }
+
     /// Returns `true` if `self` is `Value::Set`. Returns `false` otherwise.
     pub fn is_set(&self) -> bool {
         matches!(self, Value::Set(_))
@@ -227,6 +562,77 @@ impl Value {
         }
     }
 
+    /// If `self` is `Value::Set`, returns a mutable reference to the associated data. Returns `None` otherwise.
+    pub fn as_set_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Set(set) => Some(set),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `self` is `Value::FrozenSet`. Returns `false` otherwise.
+    pub fn is_frozenset(&self) -> bool {
+        matches!(self, Value::FrozenSet(_))
+    }
+
+    /// If `self` is `Value::FrozenSet`, returns the associated data. Returns `None` otherwise.
+    pub fn as_frozenset(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::FrozenSet(set) => Some(set),
+            _ => None,
+        }
+    }
+
+    /// If `self` is `Value::FrozenSet`, returns a mutable reference to the associated data. Returns `None` otherwise.
+    pub fn as_frozenset_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::FrozenSet(set) => Some(set),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `self` is `Value::Date`. Returns `false` otherwise.
+    pub fn is_date(&self) -> bool {
+        matches!(self, Value::Date(_))
+    }
+
+    /// If `self` is `Value::Date`, returns the associated date. Returns `None` otherwise.
+    pub fn as_date(&self) -> Option<Date> {
+        match self {
+            Value::Date(date) => Some(*date),
+            _ => None,
+        }
+    }
+
+    /// If `self` is `Value::Date`, returns a mutable reference to the associated date. Returns `None` otherwise.
+    pub fn as_date_mut(&mut self) -> Option<&mut Date> {
+        match self {
+            Value::Date(date) => Some(date),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `self` is `Value::DateTime`. Returns `false` otherwise.
+    pub fn is_datetime(&self) -> bool {
+        matches!(self, Value::DateTime(_))
+    }
+
+    /// If `self` is `Value::DateTime`, returns the associated date and time. Returns `None` otherwise.
+    pub fn as_datetime(&self) -> Option<DateTime> {
+        match self {
+            Value::DateTime(datetime) => Some(*datetime),
+            _ => None,
+        }
+    }
+
+    /// If `self` is `Value::DateTime`, returns a mutable reference to the associated date and time. Returns `None` otherwise.
+    pub fn as_datetime_mut(&mut self) -> Option<&mut DateTime> {
+        match self {
+            Value::DateTime(datetime) => Some(datetime),
+            _ => None,
+        }
+    }
+
     /// Returns `true` if `self` is `Value::Boolean`. Returns `false` otherwise.
     pub fn is_boolean(&self) -> bool {
         matches!(self, Value::Boolean(_))
@@ -240,8 +646,21 @@ impl Value {
         }
     }
 
+    /// If `self` is `Value::Boolean`, returns a mutable reference to the associated data. Returns `None` otherwise.
+    pub fn as_boolean_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            Value::Boolean(boolean) => Some(boolean),
+            _ => None,
+        }
+    }
+
     /// Returns `true` if `self` is `Value::None`. Returns `false` otherwise.
     pub fn is_none(&self) -> bool {
         matches!(self, Value::None)
     }
+
+    /// Returns `true` if `self` is `Value::Ellipsis`. Returns `false` otherwise.
+    pub fn is_ellipsis(&self) -> bool {
+        matches!(self, Value::Ellipsis)
+    }
 }
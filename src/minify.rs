@@ -0,0 +1,115 @@
+//! Removing insignificant whitespace from Python literals.
+
+use crate::parse::{check_leaves, parse_start, Rule};
+use crate::{ParseError, ParseOptions};
+
+/// Removes the insignificant whitespace from a Python literal, using the
+/// default options.
+///
+/// See [`minify_with_options`] for details.
+///
+/// ```
+/// assert_eq!(
+///     py_literal::minify("{ 'a b': [1, 2 + 3j],  'c': (None,) }").unwrap(),
+///     "{'a b':[1,2+3j],'c':(None,)}",
+/// );
+/// assert!(py_literal::minify("[1, 2").is_err());
+/// ```
+///
+/// [`minify_with_options`]: fn.minify_with_options.html
+pub fn minify(s: &str) -> Result<String, ParseError> {
+    minify_with_options(s, &ParseOptions::default())
+}
+
+/// Removes the insignificant whitespace, and comments if the `comments`
+/// option is enabled, from a Python literal, using the given options.
+///
+/// The literals in the input, such as strings and numbers, are copied as-is
+/// without creating a `Value`, so this is much cheaper than parsing and
+/// formatting, e.g. for huge inputs. The input is checked like with
+/// [`validate`], except that the options determine what's accepted.
+///
+/// [`validate`]: fn.validate.html
+pub fn minify_with_options(s: &str, options: &ParseOptions) -> Result<String, ParseError> {
+    parse_start(s, options, |pairs, opts| {
+        check_leaves(pairs.clone(), opts)?;
+        let mut out = String::with_capacity(s.len());
+        let mut pos = 0;
+        for pair in pairs.flatten() {
+            let span = pair.as_span();
+            match pair.as_rule() {
+                Rule::string
+                | Rule::bytes
+                | Rule::number
+                | Rule::boolean
+                | Rule::none
+                | Rule::ellipsis
+                    if span.start() >= pos =>
+                {
+                    push_significant(&mut out, &s[pos..span.start()]);
+                    out.push_str(&s[span.start()..span.end()]);
+                    pos = span.end();
+                }
+                _ => (),
+            }
+        }
+        push_significant(&mut out, &s[pos..]);
+        Ok(out)
+    })
+}
+
+/// Appends the text between literals, except for whitespace, comments, and
+/// byte order marks, to `out`.
+fn push_significant(out: &mut String, text: &str) {
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\x0C' | '\r' | '\n' | '\u{feff}' => (),
+            '#' => {
+                let rest = chars.as_str();
+                let end = rest
+                    .find(|c| matches!(c, '\r' | '\n'))
+                    .unwrap_or(rest.len());
+                chars = rest[end..].chars();
+            }
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Value;
+
+    #[test]
+    fn minify_example() {
+        let opts = ParseOptions::new()
+            .comments(true)
+            .trim(true)
+            .frozensets(true)
+            .datetimes(true);
+        for &(input, correct) in &[
+            (" 1 , ", "1,"),
+            ("( 0b1_0 , r' # x' )", "(0b1_0,r' # x')"),
+            ("[ -1 , ( 2 ) , 1 + 2j ]", "[-1,(2),1+2j]"),
+            (
+                "\u{feff}{\n  'a': frozenset( { 1 } ),  # set\n  \"b\":\tdatetime.date(2020, 1, 2),\n}\n",
+                "{'a':frozenset({1}),\"b\":datetime.date(2020,1,2),}",
+            ),
+            ("[ ... , True ]  # end", "[...,True]"),
+        ] {
+            let minified = minify_with_options(input, &opts).unwrap();
+            assert_eq!(minified, correct);
+            assert_eq!(
+                Value::parse_with_options(&minified, &opts).unwrap(),
+                Value::parse_with_options(input, &opts).unwrap(),
+            );
+        }
+        assert!(minify("1 # comment").is_err());
+        match minify("[1, 0755]") {
+            Err(ParseError::LegacyOctalInteger(_, span)) => assert_eq!(span, 4..8),
+            other => panic!("{:?}", other),
+        }
+    }
+}
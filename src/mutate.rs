@@ -0,0 +1,279 @@
+//! Editing the contents of values.
+
+use crate::compare::python_eq;
+use crate::convert::type_name;
+use crate::Value;
+use std::error::Error;
+use std::fmt;
+
+/// Error editing a [`Value`] whose type doesn't support the operation, like
+/// Python's `TypeError`.
+///
+/// [`Value`]: enum.Value.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeError {
+    expected: &'static str,
+    found: &'static str,
+}
+
+impl TypeError {
+    fn new(expected: &'static str, value: &Value) -> TypeError {
+        TypeError {
+            expected,
+            found: type_name(value),
+        }
+    }
+
+    /// Returns a description of the types which support the operation, like
+    /// `list` or `list, tuple, set, or frozenset`.
+    pub fn expected(&self) -> &'static str {
+        self.expected
+    }
+
+    /// Returns the name of the Python type of the value, like `int`.
+    pub fn found(&self) -> &'static str {
+        self.found
+    }
+}
+
+impl Error for TypeError {}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {}, found {}", self.expected, self.found)
+    }
+}
+
+/// Description of the variants which hold a `Vec<Value>`.
+const SEQUENCE: &str = "list, tuple, set, or frozenset";
+
+impl Value {
+    /// Returns the elements if `self` is a `Value::List`, `Value::Tuple`,
+    /// `Value::Set`, or `Value::FrozenSet`.
+    fn elements_mut(&mut self) -> Result<&mut Vec<Value>, TypeError> {
+        match self {
+            Value::List(elems)
+            | Value::Tuple(elems)
+            | Value::Set(elems)
+            | Value::FrozenSet(elems) => Ok(elems),
+            _ => Err(TypeError::new(SEQUENCE, self)),
+        }
+    }
+
+    /// Returns the items if `self` is a `Value::Dict`.
+    fn dict_items_mut(&mut self) -> Result<&mut Vec<(Value, Value)>, TypeError> {
+        match self {
+            Value::Dict(items) => Ok(items),
+            _ => Err(TypeError::new("dict", self)),
+        }
+    }
+
+    /// Replaces `self` with `Value::None` and returns the previous value, so
+    /// that it can be moved out of a larger value without cloning.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let mut value: Value = "{'a': [1, 2]}".parse().unwrap();
+    /// let list = value.values_mut().next().unwrap().take();
+    /// assert_eq!(list.to_string(), "[1, 2]");
+    /// assert_eq!(value.to_string(), "{'a': None}");
+    /// ```
+    pub fn take(&mut self) -> Value {
+        std::mem::replace(self, Value::None)
+    }
+
+    /// Replaces `self` with `value` and returns the previous value.
+    pub fn replace(&mut self, value: Value) -> Value {
+        std::mem::replace(self, value)
+    }
+
+    /// Appends the element if `self` is a `Value::List`, `Value::Tuple`,
+    /// `Value::Set`, or `Value::FrozenSet`. Duplicate set elements aren't
+    /// removed. Returns an error, leaving `self` unchanged, for other values.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let mut value = Value::List(vec![]);
+    /// value.push(Value::from(1)).unwrap();
+    /// assert_eq!(value.to_string(), "[1]");
+    /// assert!(Value::from("a").push(Value::from("b")).is_err());
+    /// ```
+    pub fn push(&mut self, elem: Value) -> Result<(), TypeError> {
+        self.elements_mut()?.push(elem);
+        Ok(())
+    }
+
+    /// If `self` is a `Value::Dict`, sets the value for the key and returns
+    /// the previous value, or appends the item and returns `None` if the key
+    /// isn't present. Keys are compared as Python would compare them, so
+    /// e.g. `1` and `1.0` are the same key. If the dict has more than one
+    /// item with the key, the last one is updated, since that's the one
+    /// which Python would keep. Returns an error, leaving `self` unchanged,
+    /// for other values.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let mut value: Value = "{1: 'a'}".parse().unwrap();
+    /// assert_eq!(value.insert(Value::from(1.0), Value::from("b")), Ok(Some(Value::from("a"))));
+    /// assert_eq!(value.insert(Value::from(2), Value::None), Ok(None));
+    /// assert_eq!(value.to_string(), "{1: 'b', 2: None}");
+    /// ```
+    pub fn insert(&mut self, key: Value, value: Value) -> Result<Option<Value>, TypeError> {
+        let items = self.dict_items_mut()?;
+        match items.iter_mut().rev().find(|(k, _)| python_eq(k, &key)) {
+            Some((_, old)) => Ok(Some(std::mem::replace(old, value))),
+            None => {
+                items.push((key, value));
+                Ok(None)
+            }
+        }
+    }
+
+    /// If `self` is a `Value::Dict`, removes all items with the key and
+    /// returns the value of the last one (the one which Python would keep),
+    /// or `None` if the key isn't present. Keys are compared as for
+    /// [`insert`]. Returns an error, leaving `self` unchanged, for other
+    /// values.
+    ///
+    /// [`insert`]: #method.insert
+    pub fn remove(&mut self, key: &Value) -> Result<Option<Value>, TypeError> {
+        let items = self.dict_items_mut()?;
+        let mut removed = None;
+        for (k, v) in std::mem::take(items) {
+            if python_eq(&k, key) {
+                removed = Some(v);
+            } else {
+                items.push((k, v));
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Appends the elements of the iterator if `self` is a `Value::List`,
+    /// `Value::Tuple`, `Value::Set`, or `Value::FrozenSet`. Duplicate set
+    /// elements aren't removed. Returns an error, leaving `self` unchanged,
+    /// for other values.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let mut value: Value = "[1]".parse().unwrap();
+    /// value.try_extend((2..4).map(Value::from)).unwrap();
+    /// assert_eq!(value.to_string(), "[1, 2, 3]");
+    /// let err = Value::None.try_extend(vec![Value::None]).unwrap_err();
+    /// assert_eq!(err.to_string(), "expected list, tuple, set, or frozenset, found NoneType");
+    /// ```
+    pub fn try_extend<I>(&mut self, iter: I) -> Result<(), TypeError>
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        self.elements_mut()?.extend(iter);
+        Ok(())
+    }
+
+    /// Appends the items of the iterator if `self` is a `Value::Dict`.
+    /// Existing items with the same keys are kept. Returns an error, leaving
+    /// `self` unchanged, for other values.
+    pub fn try_extend_items<I>(&mut self, iter: I) -> Result<(), TypeError>
+    where
+        I: IntoIterator<Item = (Value, Value)>,
+    {
+        self.dict_items_mut()?.extend(iter);
+        Ok(())
+    }
+}
+
+/// Appends the elements like [`Value::try_extend`].
+///
+/// # Panics
+///
+/// Panics if `self` isn't a `Value::List`, `Value::Tuple`, `Value::Set`, or
+/// `Value::FrozenSet`.
+///
+/// [`Value::try_extend`]: enum.Value.html#method.try_extend
+impl Extend<Value> for Value {
+    fn extend<I: IntoIterator<Item = Value>>(&mut self, iter: I) {
+        if let Err(err) = self.try_extend(iter) {
+            panic!("unable to extend value: {}", err);
+        }
+    }
+}
+
+/// Appends the items like [`Value::try_extend_items`].
+///
+/// # Panics
+///
+/// Panics if `self` isn't a `Value::Dict`.
+///
+/// [`Value::try_extend_items`]: enum.Value.html#method.try_extend_items
+impl Extend<(Value, Value)> for Value {
+    fn extend<I: IntoIterator<Item = (Value, Value)>>(&mut self, iter: I) {
+        if let Err(err) = self.try_extend_items(iter) {
+            panic!("unable to extend value: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn extend_values() {
+        let mut value: Value = "({1}, {'a': 1})".parse().unwrap();
+        value.extend(vec![Value::from(2)]);
+        let elems = value.as_tuple_mut().unwrap();
+        elems[0].extend(Some(Value::from(1)));
+        elems[1].extend(vec![(Value::from("a"), Value::from(2))]);
+        assert_eq!(value.to_string(), "({1, 1}, {'a': 1, 'a': 2}, 2)");
+        let mut int = Value::from(1);
+        let err = int.try_extend_items(vec![]).unwrap_err();
+        assert_eq!((err.expected(), err.found()), ("dict", "int"));
+        assert_eq!(int, Value::from(1));
+    }
+
+    #[test]
+    fn take_and_replace() {
+        let mut value: Value = "[(1,), 2]".parse().unwrap();
+        let elems = value.as_list_mut().unwrap();
+        let old = elems[1].replace(Value::from("b"));
+        assert_eq!(old, Value::from(2));
+        let tuple = elems[0].take();
+        assert_eq!(value.to_string(), "[None, 'b']");
+        assert_eq!(tuple.to_string(), "(1,)");
+    }
+
+    #[test]
+    fn edit_dict() {
+        let mut value: Value = "{'a': 1, 'b': 2, 'a': 3, True: 4}".parse().unwrap();
+        assert_eq!(
+            value.insert(Value::from("a"), Value::None),
+            Ok(Some(Value::from(3)))
+        );
+        assert_eq!(value.to_string(), "{'a': 1, 'b': 2, 'a': None, True: 4}");
+        assert_eq!(value.remove(&Value::from("a")), Ok(Some(Value::None)));
+        assert_eq!(value.remove(&Value::from(1)), Ok(Some(Value::from(4))));
+        assert_eq!(value.remove(&Value::from("c")), Ok(None));
+        assert_eq!(value.to_string(), "{'b': 2}");
+        let mut list = Value::List(vec![]);
+        assert_eq!(
+            list.remove(&Value::None),
+            Err(TypeError {
+                expected: "dict",
+                found: "list",
+            })
+        );
+        assert!(list.insert(Value::None, Value::None).is_err());
+        list.push(Value::None).unwrap();
+        assert_eq!(list.to_string(), "[None]");
+    }
+
+    #[test]
+    #[should_panic(expected = "expected dict, found list")]
+    fn extend_mismatch() {
+        Value::List(vec![]).extend(vec![(Value::None, Value::None)]);
+    }
+}
@@ -2,24 +2,32 @@
 // generated by Pest. See https://github.com/pest-parser/pest/issues/490
 #![allow(clippy::upper_case_acronyms)]
 
-use crate::Value;
+use crate::{compare, scan};
+use crate::{Date, DateTime, Value};
 use num_bigint as numb;
 use num_complex as numc;
 use num_traits::{Num, ToPrimitive};
-use pest::iterators::Pair;
+use pest::iterators::{Pair, Pairs};
 use pest::Parser as ParserTrait;
 use pest_derive::Parser;
+use std::borrow::Cow;
+use std::collections::hash_map::{DefaultHasher, HashMap};
+use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt;
+use std::hash::Hasher;
+use std::io;
+use std::iter;
 use std::num::ParseFloatError;
-use std::str::FromStr;
-
-#[cfg(debug_assertions)]
-const _GRAMMAR: &str = include_str!("grammar.pest");
+use std::ops::Range;
+use std::str::{self, FromStr, Utf8Error};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 
+/// Pest parser generated from the grammar of Python literals.
 #[derive(Parser)]
 #[grammar = "grammar.pest"]
-struct Parser;
+pub struct Parser;
 
 /// Error parsing a Python literal.
 #[derive(Debug)]
@@ -34,6 +42,62 @@ pub enum ParseError {
     /// An error in a numeric cast. For example, this might occur while adding
     /// an integer and float if the integer is too large to fit in a float.
     NumericCast(String, String),
+    /// A nonzero integer literal with leading zeros, such as `0755`. Python 2
+    /// interprets these as octal, but Python 3 rejects them. Contains the
+    /// literal and its byte range in the input.
+    LegacyOctalInteger(String, Range<usize>),
+    /// A dict contained the same key more than once, and the parse options
+    /// specify [`DuplicateKeys::Error`]. Contains the formatted key and its
+    /// byte range in the input.
+    ///
+    /// [`DuplicateKeys::Error`]: enum.DuplicateKeys.html#variant.Error
+    DuplicateKey(String, Range<usize>),
+    /// An error caused by the reader.
+    Io(io::Error),
+    /// The input bytes were not valid UTF-8, and the parse options specify
+    /// [`SourceEncoding::Utf8`].
+    ///
+    /// [`SourceEncoding::Utf8`]: enum.SourceEncoding.html#variant.Utf8
+    InvalidUtf8(Utf8Error),
+    /// The input was nested more deeply than the [maximum depth] in the parse
+    /// options. Contains the byte offset of the opening bracket which
+    /// exceeded the limit.
+    ///
+    /// [maximum depth]: struct.ParseOptions.html#method.max_depth
+    DepthLimitExceeded(usize),
+    /// A string, bytes, or byte array literal was longer than the [maximum
+    /// length] in the parse options. Contains the byte range of the literal.
+    ///
+    /// [maximum length]: struct.ParseOptions.html#method.max_length
+    LengthLimitExceeded(Range<usize>),
+    /// A collection had more elements than the [maximum number of elements]
+    /// in the parse options. Contains the byte range of the first element
+    /// (or dict key) beyond the limit.
+    ///
+    /// [maximum number of elements]: struct.ParseOptions.html#method.max_elements
+    ElementLimitExceeded(Range<usize>),
+    /// The input contained more values than the [maximum number of nodes] in
+    /// the parse options. Contains the byte range of the first value beyond
+    /// the limit.
+    ///
+    /// [maximum number of nodes]: struct.ParseOptions.html#method.max_nodes
+    NodeLimitExceeded(Range<usize>),
+    /// A decimal integer literal had more digits than the [maximum number of
+    /// digits] in the parse options. Contains the byte range of the literal.
+    ///
+    /// [maximum number of digits]: struct.ParseOptions.html#method.max_int_digits
+    DigitLimitExceeded(Range<usize>),
+    /// A float or imaginary literal was too large to represent as a finite
+    /// `f64`, and the parse options specify [`reject_float_overflow`].
+    /// Contains the byte range of the literal.
+    ///
+    /// [`reject_float_overflow`]: struct.ParseOptions.html#method.reject_float_overflow
+    FloatOverflow(Range<usize>),
+    /// Parsing was cancelled with the [cancellation token] in the parse
+    /// options.
+    ///
+    /// [cancellation token]: struct.CancellationToken.html
+    Cancelled,
 }
 
 impl Error for ParseError {
@@ -44,6 +108,17 @@ impl Error for ParseError {
             IllegalEscapeSequence(_) => None,
             ParseFloat(err) => Some(err),
             NumericCast(_, _) => None,
+            LegacyOctalInteger(_, _) => None,
+            DuplicateKey(_, _) => None,
+            Io(err) => Some(err),
+            InvalidUtf8(err) => Some(err),
+            DepthLimitExceeded(_) => None,
+            LengthLimitExceeded(_) => None,
+            ElementLimitExceeded(_) => None,
+            NodeLimitExceeded(_) => None,
+            DigitLimitExceeded(_) => None,
+            FloatOverflow(_) => None,
+            Cancelled => None,
         }
     }
 }
@@ -60,6 +135,890 @@ impl fmt::Display for ParseError {
             NumericCast(value, to_type) => {
                 write!(f, "error casting number: {} to {}", value, to_type)
             }
+            LegacyOctalInteger(literal, span) => {
+                write!(
+                    f,
+                    "leading zeros in integer literal are not permitted: {} at {}..{}",
+                    literal, span.start, span.end,
+                )?;
+                let digits = literal.trim_start_matches(&['0', '_'][..]);
+                if digits.chars().all(|c| matches!(c, '0'..='7' | '_')) {
+                    write!(f, " (use an 0o prefix for octal integers: 0o{})", digits)?;
+                }
+                Ok(())
+            }
+            DuplicateKey(key, span) => {
+                write!(
+                    f,
+                    "duplicate dict key: {} at {}..{}",
+                    key, span.start, span.end
+                )
+            }
+            Io(err) => write!(f, "I/O error: {}", err),
+            InvalidUtf8(err) => write!(f, "invalid UTF-8 in input: {}", err),
+            DepthLimitExceeded(pos) => write!(f, "nesting depth limit exceeded at {}", pos),
+            LengthLimitExceeded(span) => write!(
+                f,
+                "string or bytes length limit exceeded at {}..{}",
+                span.start, span.end
+            ),
+            ElementLimitExceeded(span) => write!(
+                f,
+                "collection element limit exceeded at {}..{}",
+                span.start, span.end
+            ),
+            NodeLimitExceeded(span) => {
+                write!(f, "node limit exceeded at {}..{}", span.start, span.end)
+            }
+            DigitLimitExceeded(span) => write!(
+                f,
+                "integer digit limit exceeded at {}..{}",
+                span.start, span.end
+            ),
+            FloatOverflow(span) => write!(
+                f,
+                "float literal out of range at {}..{}",
+                span.start, span.end
+            ),
+            Cancelled => write!(f, "parsing was cancelled"),
+        }
+    }
+}
+
+/// How to handle dicts which contain the same key more than once.
+///
+/// Keys are compared as Python would compare them; for example, `1`, `1.0`,
+/// and `True` are the same key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Keep every item, in order. This is the default.
+    KeepAll,
+    /// Keep only the last value for each key, at the position where the key
+    /// first appeared. This matches the behavior of Python dict literals.
+    KeepLast,
+    /// Fail with [`ParseError::DuplicateKey`].
+    ///
+    /// [`ParseError::DuplicateKey`]: enum.ParseError.html#variant.DuplicateKey
+    Error,
+}
+
+impl Default for DuplicateKeys {
+    fn default() -> DuplicateKeys {
+        DuplicateKeys::KeepAll
+    }
+}
+
+/// How to handle `\u` and `\U` escapes in strings which encode UTF-16
+/// surrogates (U+D800 to U+DFFF), which aren't valid in a Rust `String`.
+///
+/// Producers which escape non-BMP characters as UTF-16 write a character
+/// such as U+1F600 as a pair of escapes, `'\ud83d\ude00'`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurrogateEscapes {
+    /// Fail with [`ParseError::IllegalEscapeSequence`]. This is the default.
+    ///
+    /// [`ParseError::IllegalEscapeSequence`]: enum.ParseError.html#variant.IllegalEscapeSequence
+    Error,
+    /// Join a high surrogate escape immediately followed by a low surrogate
+    /// escape into the character they encode. Other surrogate escapes are an
+    /// error.
+    JoinPairs,
+    /// Join pairs like `JoinPairs`, and replace other surrogate escapes with
+    /// U+FFFD REPLACEMENT CHARACTER.
+    Replace,
+}
+
+impl Default for SurrogateEscapes {
+    fn default() -> SurrogateEscapes {
+        SurrogateEscapes::Error
+    }
+}
+
+/// Version of the Python literal syntax to accept.
+///
+/// The dialects differ in the following ways:
+///
+/// | Syntax                                      | `Py27` | `Py35` | `Py312` | `Latest` |
+/// |---------------------------------------------|--------|--------|---------|----------|
+/// | `L` suffix on integers (`123L`)             | yes    | no     | no      | no       |
+/// | octal with a leading zero (`0755`)          | yes    | no     | no      | no       |
+/// | `ur` string prefix                          | yes    | no     | no      | no       |
+/// | `rb` bytes prefix (`br` is always accepted) | no     | yes    | yes     | yes      |
+/// | `...` (`Ellipsis`)                          | no     | yes    | yes     | yes      |
+/// | underscores in numbers (`1_000`)            | no     | no     | yes     | yes      |
+///
+/// Python 2 literals are parsed into the same `Value` variants as the
+/// equivalent Python 3 literals; in particular, string literals without a
+/// prefix are always parsed as `Value::String`, even though they're byte
+/// strings in Python 2.
+///
+/// `Latest` is the newest Python version supported by this crate, so the
+/// syntax it accepts may grow in future releases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dialect {
+    /// Python 2.7.
+    Py27,
+    /// Python 3.5.
+    Py35,
+    /// Python 3.12.
+    Py312,
+    /// The latest version of Python. This is the default.
+    Latest,
+}
+
+impl Default for Dialect {
+    fn default() -> Dialect {
+        Dialect::Latest
+    }
+}
+
+/// Encoding of input bytes, for [`Value::from_bytes_with_options`] and
+/// [`Value::from_reader_buffered`].
+///
+/// Input which is already a `str` is always parsed as-is. Byte ranges in
+/// errors are relative to the decoded text, which differs from the input
+/// bytes if they aren't valid UTF-8.
+///
+/// [`Value::from_bytes_with_options`]: enum.Value.html#method.from_bytes_with_options
+/// [`Value::from_reader_buffered`]: enum.Value.html#method.from_reader_buffered
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SourceEncoding {
+    /// UTF-8, failing with [`ParseError::InvalidUtf8`] on invalid input.
+    /// This is the default.
+    ///
+    /// [`ParseError::InvalidUtf8`]: enum.ParseError.html#variant.InvalidUtf8
+    Utf8,
+    /// UTF-8, replacing invalid sequences with U+FFFD REPLACEMENT CHARACTER.
+    Utf8Lossy,
+    /// Latin-1 (ISO-8859-1), where each byte is the Unicode code point of the
+    /// same value. Every byte sequence is valid Latin-1.
+    Latin1,
+}
+
+impl Default for SourceEncoding {
+    fn default() -> SourceEncoding {
+        SourceEncoding::Utf8
+    }
+}
+
+impl SourceEncoding {
+    /// Decodes the bytes, borrowing them if possible.
+    fn decode(self, bytes: &[u8]) -> Result<Cow<'_, str>, ParseError> {
+        match self {
+            SourceEncoding::Utf8 => Ok(Cow::Borrowed(
+                str::from_utf8(bytes).map_err(ParseError::InvalidUtf8)?,
+            )),
+            SourceEncoding::Utf8Lossy => Ok(String::from_utf8_lossy(bytes)),
+            SourceEncoding::Latin1 if bytes.is_ascii() => {
+                Ok(Cow::Borrowed(str::from_utf8(bytes).unwrap()))
+            }
+            SourceEncoding::Latin1 => {
+                Ok(Cow::Owned(bytes.iter().map(|&b| char::from(b)).collect()))
+            }
+        }
+    }
+
+    /// Decodes the bytes, reusing their allocation if possible.
+    fn decode_owned(self, bytes: Vec<u8>) -> Result<String, ParseError> {
+        match String::from_utf8(bytes) {
+            Ok(s) => Ok(s),
+            Err(err) => self.decode(err.as_bytes()).map(Cow::into_owned),
+        }
+    }
+}
+
+/// Token for cancelling parsing from another thread.
+///
+/// Clones of a token share the same state, so a clone can be given to the
+/// [parse options] while the original is kept for cancelling. Parsing checks
+/// the token before and after the input is checked against the grammar, and
+/// periodically while the elements of collections are created; once the
+/// token is cancelled, parsing stops with [`ParseError::Cancelled`].
+///
+/// ```
+/// use py_literal::{CancellationToken, ParseError, ParseOptions, Value};
+///
+/// let token = CancellationToken::new();
+/// let options = ParseOptions::new().cancellation_token(token.clone());
+/// assert!(Value::parse_with_options("[1, 2]", &options).is_ok());
+/// token.cancel();
+/// assert!(matches!(
+///     Value::parse_with_options("[1, 2]", &options),
+///     Err(ParseError::Cancelled),
+/// ));
+/// ```
+///
+/// [parse options]: struct.ParseOptions.html#method.cancellation_token
+/// [`ParseError::Cancelled`]: enum.ParseError.html#variant.Cancelled
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token which hasn't been cancelled.
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancels parsing with this token (or any of its clones).
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Hook set with [`ParseOptions::string_hook`].
+///
+/// [`ParseOptions::string_hook`]: struct.ParseOptions.html#method.string_hook
+#[derive(Clone)]
+struct StringHook(Arc<StringHookFn>);
+
+type StringHookFn = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+impl fmt::Debug for StringHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StringHook { .. }")
+    }
+}
+
+/// Progress callback set with [`ParseOptions::progress`], and the state of
+/// the parse in progress.
+///
+/// [`ParseOptions::progress`]: struct.ParseOptions.html#method.progress
+#[derive(Clone)]
+struct Progress(Arc<ProgressState>);
+
+struct ProgressState {
+    callback: Box<dyn Fn(usize, usize) + Send + Sync>,
+    /// Length of the input.
+    total: AtomicUsize,
+    /// Position which was last reported.
+    last: AtomicUsize,
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Progress { .. }")
+    }
+}
+
+/// Minimum number of bytes between progress reports.
+const PROGRESS_INTERVAL: usize = 1 << 16;
+
+/// Options for parsing Python literals with [`Value::parse_with_options`].
+///
+/// The default options accept Python 3 literal syntax, the same as the
+/// [`FromStr` implementation] for `Value`. Options are set with
+/// builder-style methods:
+///
+/// ```
+/// use py_literal::{DuplicateKeys, ParseOptions, Value};
+///
+/// # fn main() -> Result<(), py_literal::ParseError> {
+/// let options = ParseOptions::new()
+///     .inf_nan(true)
+///     .duplicate_keys(DuplicateKeys::KeepLast);
+/// let value = Value::parse_with_options("{'a': inf, 'a': 1}", &options)?;
+/// assert_eq!(value.to_string(), "{'a': 1}");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Value::parse_with_options`]: enum.Value.html#method.parse_with_options
+/// [`FromStr` implementation]: enum.Value.html#impl-FromStr
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    dialect: Dialect,
+    inf_nan: bool,
+    float_calls: bool,
+    hex_floats: bool,
+    frozensets: bool,
+    bytearrays: bool,
+    empty_calls: bool,
+    ordered_dicts: bool,
+    decimals: bool,
+    fractions: bool,
+    datetimes: bool,
+    complex_calls: bool,
+    comments: bool,
+    trim: bool,
+    duplicate_keys: DuplicateKeys,
+    deduplicate_sets: bool,
+    surrogate_escapes: SurrogateEscapes,
+    string_hook: Option<StringHook>,
+    encoding: SourceEncoding,
+    max_depth: Option<usize>,
+    max_length: Option<usize>,
+    max_elements: Option<usize>,
+    max_nodes: Option<usize>,
+    max_int_digits: Option<usize>,
+    reject_float_overflow: bool,
+    cancellation_token: Option<CancellationToken>,
+    progress: Option<Progress>,
+    strict: bool,
+}
+
+impl ParseOptions {
+    /// Creates the default options.
+    pub fn new() -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    /// Sets the version of the Python literal syntax to accept. The default
+    /// is [`Dialect::Latest`].
+    ///
+    /// [`Dialect::Latest`]: enum.Dialect.html#variant.Latest
+    pub fn dialect(mut self, dialect: Dialect) -> ParseOptions {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Enables or disables parsing the identifiers `inf` and `nan` as
+    /// `Value::Float`, as they appear in NumPy reprs. They can be negated
+    /// like other numbers (e.g. `-inf`). Disabled by default, since they
+    /// aren't Python literals.
+    pub fn inf_nan(mut self, enabled: bool) -> ParseOptions {
+        self.inf_nan = enabled;
+        self
+    }
+
+    /// Enables or disables parsing the calls `float('inf')`, `float('-inf')`,
+    /// and `float('nan')` as `Value::Float`. Like Python's `float()`, the
+    /// argument may have a sign and surrounding whitespace, is
+    /// case-insensitive, and may be spelled `infinity`. Other arguments are
+    /// rejected. Disabled by default, since these aren't Python literals.
+    pub fn float_calls(mut self, enabled: bool) -> ParseOptions {
+        self.float_calls = enabled;
+        self
+    }
+
+    /// Enables or disables parsing the calls `float.fromhex('...')` as
+    /// `Value::Float`, as written with [`FloatStyle::Hex`]. Like Python's
+    /// `float.fromhex()`, the argument is a hexadecimal float such as
+    /// `0x1.8p+1` (the `0x` prefix and the exponent are optional), which is
+    /// rounded to the nearest `f64`, or `inf` or `nan` as for `float()`.
+    /// Arguments which are too large to represent are rejected. Disabled by
+    /// default, since these aren't Python literals.
+    ///
+    /// ```
+    /// use py_literal::{ParseOptions, Value};
+    ///
+    /// let options = ParseOptions::new().hex_floats(true);
+    /// assert_eq!(
+    ///     Value::parse_with_options("float.fromhex('0x1.8p+1')", &options).unwrap(),
+    ///     Value::Float(3.),
+    /// );
+    /// ```
+    ///
+    /// [`FloatStyle::Hex`]: enum.FloatStyle.html#variant.Hex
+    pub fn hex_floats(mut self, enabled: bool) -> ParseOptions {
+        self.hex_floats = enabled;
+        self
+    }
+
+    /// Enables or disables parsing frozen sets in the form produced by
+    /// `repr()` (`frozenset()` or `frozenset({...})`) as `Value::FrozenSet`.
+    /// Disabled by default, since these aren't Python literals.
+    pub fn frozensets(mut self, enabled: bool) -> ParseOptions {
+        self.frozensets = enabled;
+        self
+    }
+
+    /// Enables or disables parsing byte arrays in the form produced by
+    /// `repr()` (`bytearray(b'...')`, or `bytearray()` when empty) as
+    /// `Value::ByteArray`. Disabled by default, since these aren't Python
+    /// literals.
+    pub fn bytearrays(mut self, enabled: bool) -> ParseOptions {
+        self.bytearrays = enabled;
+        self
+    }
+
+    /// Enables or disables parsing the zero-argument constructor calls
+    /// `set()`, `dict()`, `list()`, and `tuple()` as the corresponding empty
+    /// collections. Notably, `set()` is the only way to write an empty set.
+    /// Disabled by default, since these aren't Python literals.
+    pub fn empty_calls(mut self, enabled: bool) -> ParseOptions {
+        self.empty_calls = enabled;
+        self
+    }
+
+    /// Enables or disables parsing ordered dictionaries in the forms produced
+    /// by `repr()` (`OrderedDict([('a', 1), ...])`, or `OrderedDict({'a': 1,
+    /// ...})` since Python 3.12) as `Value::Dict`, preserving the order of
+    /// the items. Disabled by default, since these aren't Python literals.
+    pub fn ordered_dicts(mut self, enabled: bool) -> ParseOptions {
+        self.ordered_dicts = enabled;
+        self
+    }
+
+    /// Enables or disables parsing decimals in the form produced by `repr()`
+    /// (`Decimal('1.23')`) as `Value::Decimal`. This requires the
+    /// `bigdecimal` feature; without it, decimals are rejected even if this
+    /// option is enabled. Special values (`Decimal('NaN')`,
+    /// `Decimal('Infinity')`, etc.) aren't supported. Disabled by default,
+    /// since these aren't Python literals.
+    pub fn decimals(mut self, enabled: bool) -> ParseOptions {
+        self.decimals = enabled;
+        self
+    }
+
+    /// Enables or disables parsing fractions in the form produced by `repr()`
+    /// (`Fraction(1, 3)`) as `Value::Rational`. The numerator and
+    /// denominator must be integers; the denominator may be omitted. This
+    /// requires the `num-rational` feature; without it, fractions are
+    /// rejected even if this option is enabled. Disabled by default, since
+    /// these aren't Python literals.
+    pub fn fractions(mut self, enabled: bool) -> ParseOptions {
+        self.fractions = enabled;
+        self
+    }
+
+    /// Enables or disables parsing dates and naive datetimes in the forms
+    /// produced by `repr()` (`datetime.date(2023, 5, 1)` and
+    /// `datetime.datetime(2023, 5, 1, 12, 0)`) as `Value::Date` and
+    /// `Value::DateTime`. The `datetime.` prefix is optional. Only positional
+    /// integer arguments are supported, so datetimes with `tzinfo` are
+    /// rejected. Disabled by default, since these aren't Python literals.
+    pub fn datetimes(mut self, enabled: bool) -> ParseOptions {
+        self.datetimes = enabled;
+        self
+    }
+
+    /// Enables or disables parsing the calls `complex()`, `complex(real)`,
+    /// and `complex(real, imag)` with numeric arguments as
+    /// `Value::Complex`. Like Python's `complex()`, the result is
+    /// `real + imag*1j`. Disabled by default, since these aren't Python
+    /// literals.
+    pub fn complex_calls(mut self, enabled: bool) -> ParseOptions {
+        self.complex_calls = enabled;
+        self
+    }
+
+    /// Enables or disables skipping `#` comments (which extend to the end of
+    /// the line) outside of string and bytes literals. Since comments are
+    /// useful only in multi-line input, line breaks outside of literals are
+    /// also treated as whitespace in this mode. Disabled by default.
+    pub fn comments(mut self, enabled: bool) -> ParseOptions {
+        self.comments = enabled;
+        self
+    }
+
+    /// Enables or disables ignoring a UTF-8 byte order mark at the start of
+    /// the input, and line breaks before and after the literal, as in files
+    /// saved by some editors. (Spaces and tabs there are always ignored.)
+    /// Disabled by default.
+    pub fn trim(mut self, enabled: bool) -> ParseOptions {
+        self.trim = enabled;
+        self
+    }
+
+    /// Sets how to handle dicts which contain the same key more than once.
+    /// The default is [`DuplicateKeys::KeepAll`].
+    ///
+    /// [`DuplicateKeys::KeepAll`]: enum.DuplicateKeys.html#variant.KeepAll
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> ParseOptions {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Enables or disables removing duplicate elements from sets and frozen
+    /// sets, keeping the first occurrence of each element, as Python does.
+    /// Elements are compared as Python would compare them, so e.g. `{1, 1.0,
+    /// True}` becomes `{1}`. Disabled by default.
+    pub fn deduplicate_sets(mut self, enabled: bool) -> ParseOptions {
+        self.deduplicate_sets = enabled;
+        self
+    }
+
+    /// Sets how to handle escapes of UTF-16 surrogates in strings. The
+    /// default is [`SurrogateEscapes::Error`].
+    ///
+    /// ```
+    /// use py_literal::{ParseOptions, SurrogateEscapes, Value};
+    ///
+    /// let options = ParseOptions::new().surrogate_escapes(SurrogateEscapes::Replace);
+    /// assert_eq!(
+    ///     Value::parse_with_options(r"'\ud83d\ude00 \udc00'", &options).unwrap(),
+    ///     Value::String("\u{1F600} \u{FFFD}".into()),
+    /// );
+    /// ```
+    ///
+    /// [`SurrogateEscapes::Error`]: enum.SurrogateEscapes.html#variant.Error
+    pub fn surrogate_escapes(mut self, policy: SurrogateEscapes) -> ParseOptions {
+        self.surrogate_escapes = policy;
+        self
+    }
+
+    /// Sets a hook which is called with the contents of each string literal
+    /// as it's parsed, and can replace them by returning `Some`, e.g. to
+    /// normalize or transform them. Strings in calls such as
+    /// `Decimal('1.5')` aren't passed to the hook.
+    ///
+    /// The hook can't be used to intern strings: `Value::String` owns its
+    /// contents, so each value holds its own copy of a replacement. To avoid
+    /// copying repeated strings, such as dict keys, use
+    /// [`Value::parse_borrowed`] instead, which borrows strings that the hook
+    /// doesn't replace from the input when possible.
+    ///
+    /// ```
+    /// use py_literal::{ParseOptions, Value};
+    ///
+    /// let options = ParseOptions::new().string_hook(|s| Some(s.to_uppercase()));
+    /// assert_eq!(
+    ///     Value::parse_with_options("{'a': ['b', b'c']}", &options).unwrap().to_string(),
+    ///     "{'A': ['B', b'c']}",
+    /// );
+    /// ```
+    ///
+    /// [`Value::parse_borrowed`]: enum.Value.html#method.parse_borrowed
+    pub fn string_hook<F>(mut self, hook: F) -> ParseOptions
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.string_hook = Some(StringHook(Arc::new(hook)));
+        self
+    }
+
+    /// Sets the encoding of input bytes. The default is
+    /// [`SourceEncoding::Utf8`].
+    ///
+    /// [`SourceEncoding::Utf8`]: enum.SourceEncoding.html#variant.Utf8
+    pub fn encoding(mut self, encoding: SourceEncoding) -> ParseOptions {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets the maximum nesting depth of brackets, braces, and parentheses
+    /// (including those of calls such as `frozenset({...})`). For example,
+    /// `[1, (2,)]` is nested two levels deep. Input which is nested more
+    /// deeply is rejected with [`ParseError::DepthLimitExceeded`] before it's
+    /// parsed. There is no limit by default.
+    ///
+    /// [`ParseError::DepthLimitExceeded`]: enum.ParseError.html#variant.DepthLimitExceeded
+    pub fn max_depth(mut self, depth: usize) -> ParseOptions {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Sets the maximum length of string, bytes, and byte array literals,
+    /// after escape sequences are decoded. The length of a string is its
+    /// number of characters, and the length of bytes is its number of bytes.
+    /// Longer literals are rejected with [`ParseError::LengthLimitExceeded`].
+    /// There is no limit by default.
+    ///
+    /// [`ParseError::LengthLimitExceeded`]: enum.ParseError.html#variant.LengthLimitExceeded
+    pub fn max_length(mut self, len: usize) -> ParseOptions {
+        self.max_length = Some(len);
+        self
+    }
+
+    /// Sets the maximum number of elements in each tuple, list, set, frozen
+    /// set, and dict (where each item counts as one element). Larger
+    /// collections are rejected with [`ParseError::ElementLimitExceeded`].
+    /// There is no limit by default.
+    ///
+    /// [`ParseError::ElementLimitExceeded`]: enum.ParseError.html#variant.ElementLimitExceeded
+    pub fn max_elements(mut self, count: usize) -> ParseOptions {
+        self.max_elements = Some(count);
+        self
+    }
+
+    /// Sets the maximum number of values in a literal, counting collections
+    /// and each of their elements, recursively. For example, `[1, (2, 3)]`
+    /// contains five values. Input with more values is rejected with
+    /// [`ParseError::NodeLimitExceeded`] before any of them are created.
+    /// There is no limit by default.
+    ///
+    /// [`ParseError::NodeLimitExceeded`]: enum.ParseError.html#variant.NodeLimitExceeded
+    pub fn max_nodes(mut self, count: usize) -> ParseOptions {
+        self.max_nodes = Some(count);
+        self
+    }
+
+    /// Sets the maximum number of digits in decimal integer literals. Longer
+    /// literals are rejected with [`ParseError::DigitLimitExceeded`] before
+    /// they're converted, since the conversion takes time quadratic in the
+    /// number of digits. Like Python's [`sys.set_int_max_str_digits()`],
+    /// this doesn't apply to binary, octal, or hexadecimal literals, whose
+    /// conversion takes linear time. There is no limit by default; Python's
+    /// default limit is 4300 digits.
+    ///
+    /// [`ParseError::DigitLimitExceeded`]: enum.ParseError.html#variant.DigitLimitExceeded
+    /// [`sys.set_int_max_str_digits()`]: https://docs.python.org/3/library/sys.html#sys.set_int_max_str_digits
+    pub fn max_int_digits(mut self, digits: usize) -> ParseOptions {
+        self.max_int_digits = Some(digits);
+        self
+    }
+
+    /// Enables or disables rejecting float and imaginary literals which are
+    /// too large to represent as a finite `f64`, such as `1e999`, with
+    /// [`ParseError::FloatOverflow`]. Python evaluates these literals as
+    /// infinity, so this is disabled by default.
+    ///
+    /// [`ParseError::FloatOverflow`]: enum.ParseError.html#variant.FloatOverflow
+    pub fn reject_float_overflow(mut self, enabled: bool) -> ParseOptions {
+        self.reject_float_overflow = enabled;
+        self
+    }
+
+    /// Sets a token for cancelling parsing from another thread. See
+    /// [`CancellationToken`] for details.
+    ///
+    /// [`CancellationToken`]: struct.CancellationToken.html
+    pub fn cancellation_token(mut self, token: CancellationToken) -> ParseOptions {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Sets a callback for reporting the progress of parsing large inputs.
+    /// The callback is called with the number of bytes of the input which
+    /// have been parsed and the total length of the input, when parsing
+    /// starts, periodically while values are created, and when parsing
+    /// finishes successfully. (The whole input is checked against the grammar
+    /// before any values are created, so no progress is reported during that
+    /// step.) Parsing with the same options on multiple threads at once
+    /// interleaves the reports.
+    ///
+    /// ```
+    /// use py_literal::{ParseOptions, Value};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// # fn main() -> Result<(), py_literal::ParseError> {
+    /// let reports = Arc::new(Mutex::new(Vec::new()));
+    /// let options = ParseOptions::new().progress({
+    ///     let reports = reports.clone();
+    ///     move |done, total| reports.lock().unwrap().push((done, total))
+    /// });
+    /// Value::parse_with_options("[1, 2, 3]", &options)?;
+    /// assert_eq!(*reports.lock().unwrap(), [(0, 9), (9, 9)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn progress<F>(mut self, callback: F) -> ParseOptions
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        self.progress = Some(Progress(Arc::new(ProgressState {
+            callback: Box::new(callback),
+            total: AtomicUsize::new(0),
+            last: AtomicUsize::new(0),
+        })));
+        self
+    }
+
+    /// Enables or disables strict mode, which accepts only input that
+    /// [`ast.literal_eval()`] would accept (for the selected dialect). This
+    /// overrides all of the extensions (`inf_nan`, `float_calls`, etc.) and
+    /// `comments`, and additionally rejects:
+    ///
+    /// * arithmetic other than a single sign on a number or the sum or
+    ///   difference of a (possibly signed) real number and an imaginary
+    ///   number (e.g. `1 + 2` and `True + 1` are rejected, but `-1` and
+    ///   `1 - 2j` are accepted)
+    /// * unhashable set elements and dict keys (e.g. `{[1]}`)
+    ///
+    /// Other options, such as the dialect, the limits, and `trim`, still
+    /// apply. The parser doesn't support all of the syntax that
+    /// `literal_eval()` does, so the converse doesn't hold. Disabled by
+    /// default.
+    ///
+    /// [`ast.literal_eval()`]: https://docs.python.org/3/library/ast.html#ast.literal_eval
+    pub fn strict(mut self, enabled: bool) -> ParseOptions {
+        self.strict = enabled;
+        self
+    }
+}
+
+impl ParseOptions {
+    /// Returns the options which are actually in effect, taking strict mode
+    /// into account.
+    pub(crate) fn resolve(&self) -> Cow<'_, ParseOptions> {
+        if self.strict {
+            // Only the extensions are overridden. Everything else, such as
+            // the dialect, the limits, and `trim`, still applies.
+            Cow::Owned(ParseOptions {
+                inf_nan: false,
+                float_calls: false,
+                hex_floats: false,
+                frozensets: false,
+                bytearrays: false,
+                empty_calls: false,
+                ordered_dicts: false,
+                decimals: false,
+                fractions: false,
+                datetimes: false,
+                complex_calls: false,
+                comments: false,
+                ..self.clone()
+            })
+        } else {
+            Cow::Borrowed(self)
+        }
+    }
+
+    /// Returns a copy of the options without the progress callback, for
+    /// parsing parts of the input separately.
+    pub(crate) fn without_progress(&self) -> ParseOptions {
+        ParseOptions {
+            progress: None,
+            ..self.clone()
+        }
+    }
+
+    /// Returns whether comments are enabled.
+    pub(crate) fn comments_enabled(&self) -> bool {
+        self.comments
+    }
+
+    /// Returns whether trimming is enabled.
+    pub(crate) fn trim_enabled(&self) -> bool {
+        self.trim
+    }
+
+    /// Reads the input from the reader, and decodes it according to the
+    /// encoding.
+    pub(crate) fn read_input<R: io::Read>(&self, mut reader: R) -> Result<String, ParseError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        self.encoding.decode_owned(bytes)
+    }
+
+    /// Applies the preprocessing specified by the options to the input,
+    /// without changing byte offsets.
+    pub(crate) fn preprocess<'s>(&self, s: &'s str) -> Cow<'s, str> {
+        let s = if self.comments {
+            scan::blank_comments(s)
+        } else {
+            Cow::Borrowed(s)
+        };
+        if self.trim {
+            scan::blank_surroundings(s)
+        } else {
+            s
+        }
+    }
+
+    /// Returns an error if parsing has been cancelled.
+    pub(crate) fn check_cancelled(&self) -> Result<(), ParseError> {
+        match &self.cancellation_token {
+            Some(token) if token.is_cancelled() => Err(ParseError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reports that parsing of an input with the given length has started.
+    pub(crate) fn start_progress(&self, total: usize) {
+        if let Some(Progress(state)) = &self.progress {
+            state.total.store(total, Ordering::Relaxed);
+            state.last.store(0, Ordering::Relaxed);
+            (state.callback)(0, total);
+        }
+    }
+
+    /// Reports that the input has been parsed up to `pos`, if enough of it
+    /// has been parsed since the last report.
+    pub(crate) fn report_progress(&self, pos: usize) {
+        if let Some(Progress(state)) = &self.progress {
+            if pos >= state.last.load(Ordering::Relaxed) + PROGRESS_INTERVAL {
+                state.last.store(pos, Ordering::Relaxed);
+                (state.callback)(pos, state.total.load(Ordering::Relaxed));
+            }
+        }
+    }
+
+    /// Reports that parsing has finished.
+    pub(crate) fn finish_progress(&self) {
+        if let Some(Progress(state)) = &self.progress {
+            let total = state.total.load(Ordering::Relaxed);
+            (state.callback)(total, total);
+        }
+    }
+
+    /// Checks that the input doesn't exceed the maximum nesting depth.
+    pub(crate) fn check_depth(&self, s: &str) -> Result<(), ParseError> {
+        match self
+            .max_depth
+            .and_then(|max_depth| scan::find_too_deep(s, max_depth, self.comments))
+        {
+            Some(pos) => Err(ParseError::DepthLimitExceeded(pos)),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that a literal with the given span isn't too long. `len` is
+    /// called only if there is a limit.
+    fn check_length(
+        &self,
+        span: pest::Span<'_>,
+        len: impl FnOnce() -> usize,
+    ) -> Result<(), ParseError> {
+        match self.max_length {
+            Some(max_length) if len() > max_length => {
+                Err(ParseError::LengthLimitExceeded(span.start()..span.end()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that an element with the given span can be added to a
+    /// collection which already has `len` elements.
+    pub(crate) fn check_elements(
+        &self,
+        len: usize,
+        span: pest::Span<'_>,
+    ) -> Result<(), ParseError> {
+        match self.max_elements {
+            Some(max_elements) if len >= max_elements => {
+                Err(ParseError::ElementLimitExceeded(span.start()..span.end()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns whether the pair or any of its descendants is an extension
+    /// form, like `nan` or `set()`, which the options don't enable.
+    pub(crate) fn has_disabled_form(&self, pair: &Pair<'_, Rule>) -> bool {
+        std::iter::once(pair.clone())
+            .chain(pair.clone().into_inner().flatten())
+            .any(|pair| match pair.as_rule() {
+                Rule::float_keyword => !self.inf_nan,
+                Rule::float_call_name if pair.as_str() == "float" => !self.float_calls,
+                Rule::float_call_name => !self.hex_floats,
+                Rule::complex_call => !self.complex_calls,
+                Rule::frozenset => !self.frozensets,
+                Rule::ordered_dict => !self.ordered_dicts,
+                Rule::bytearray => !self.bytearrays,
+                Rule::empty_call => !self.empty_calls,
+                Rule::decimal => !(self.decimals && cfg!(feature = "bigdecimal")),
+                Rule::rational => !(self.fractions && cfg!(feature = "num-rational")),
+                Rule::datetime => !self.datetimes,
+                _ => false,
+            })
+    }
+
+    /// Checks that the values in `pairs`, including nested values, don't
+    /// exceed the maximum number of nodes.
+    pub(crate) fn check_nodes<'i>(
+        &self,
+        pairs: impl Iterator<Item = Pair<'i, Rule>>,
+    ) -> Result<(), ParseError> {
+        let max_nodes = match self.max_nodes {
+            Some(max_nodes) => max_nodes,
+            None => return Ok(()),
+        };
+        let extra = pairs
+            .flat_map(|pair| std::iter::once(pair.clone()).chain(pair.into_inner().flatten()))
+            .filter(|pair| pair.as_rule() == Rule::value)
+            .nth(max_nodes);
+        match extra {
+            Some(pair) => {
+                let span = pair.as_span();
+                Err(ParseError::NodeLimitExceeded(span.start()..span.end()))
+            }
+            None => Ok(()),
         }
     }
 }
@@ -70,18 +1029,24 @@ impl From<ParseFloatError> for ParseError {
     }
 }
 
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> ParseError {
+        ParseError::Io(err)
+    }
+}
+
 impl FromStr for Value {
     type Err = ParseError;
 
     /// Parses a `Value` from a Python literal. The goal is for the parser to
-    /// support everything [`ast.literal_eval()`] does. A few things haven't
-    /// been implemented yet:
+    /// support everything [`ast.literal_eval()`] does. Like `literal_eval()`,
+    /// a tuple at the top level doesn't need parentheses (e.g. `1, 2, 3` or
+    /// `1,`), and adjacent string or bytes literals are concatenated (e.g.
+    /// `'a' 'b'` is `'ab'`). A few things haven't been implemented yet:
     ///
-    /// * `r`/`R` and `u`/`U` prefixes for string and bytes literals.
-    /// * [string literal concatenation]
     /// * newlines (except in string literals)
-    /// * parentheses (except as tuple delimiters)
-    /// * Unicode name escapes in strings (`\N{name}`)
+    /// * Unicode name escapes in strings (`\N{name}`), unless the
+    ///   `unicode_names2` feature is enabled
     ///
     /// Note that the parser is limited to Python *literals*, not the full
     /// Python AST, so many things are not supported, such as:
@@ -92,16 +1057,246 @@ impl FromStr for Value {
     /// * function calls
     ///
     /// [`ast.literal_eval()`]: https://docs.python.org/3/library/ast.html#ast.literal_eval
-    /// [string literal concatenation]: https://docs.python.org/3/reference/lexical_analysis.html#string-literal-concatenation
     fn from_str(s: &str) -> Result<Self, ParseError> {
-        let mut parsed =
-            Parser::parse(Rule::start, s).map_err(|e| ParseError::Syntax(format!("{}", e)))?;
-        let (start,) = parse_pairs_as!(parsed, (Rule::start,));
-        let (value, _) = parse_pairs_as!(start.into_inner(), (Rule::value, Rule::EOI));
-        parse_value(value)
+        Value::parse_with_options(s, &ParseOptions::default())
+    }
+}
+
+impl Value {
+    /// Parses a `Value` from a Python literal, using the given options.
+    ///
+    /// With the default options, this is the same as parsing with
+    /// [`FromStr`](#impl-FromStr).
+    ///
+    /// ```
+    /// use num::BigInt;
+    /// use py_literal::{Dialect, ParseOptions, Value};
+    ///
+    /// # fn main() -> Result<(), py_literal::ParseError> {
+    /// let options = ParseOptions::new().dialect(Dialect::Py27);
+    /// let value = Value::parse_with_options("0755L", &options)?;
+    /// assert_eq!(value, Value::Integer(BigInt::from(0o755)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_with_options(s: &str, options: &ParseOptions) -> Result<Value, ParseError> {
+        parse_start(s, options, |pairs, opts| parse_comma_separated(pairs, opts))
+    }
+
+    /// Parses a `Value` from a Python literal read from `reader`, using the
+    /// default options.
+    ///
+    /// The parser needs the complete input, so this reads until the end of
+    /// the input before parsing. The input must be valid UTF-8; see
+    /// [`from_reader_buffered`](#method.from_reader_buffered) for other
+    /// encodings.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// # fn main() -> Result<(), py_literal::ParseError> {
+    /// let header: &[u8] = b"{'descr': '<f8', 'shape': (3,)}";
+    /// let value = Value::from_reader(header)?;
+    /// assert_eq!(value.as_dict().map(|d| d.len()), Some(2));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_reader<R: io::Read>(reader: R) -> Result<Value, ParseError> {
+        Value::from_reader_buffered(reader, &mut String::new(), &ParseOptions::default())
+    }
+
+    /// Parses a `Value` from a Python literal read from `reader`, using the
+    /// given options and storing the input in `buf`.
+    ///
+    /// The input is decoded according to the [encoding] in the options.
+    /// `buf` is cleared before reading, and it contains the decoded input
+    /// afterwards if reading and decoding succeeded. Reusing the same buffer
+    /// across calls avoids allocating a new one for each input.
+    ///
+    /// [encoding]: struct.ParseOptions.html#method.encoding
+    pub fn from_reader_buffered<R: io::Read>(
+        mut reader: R,
+        buf: &mut String,
+        options: &ParseOptions,
+    ) -> Result<Value, ParseError> {
+        let mut bytes = std::mem::take(buf).into_bytes();
+        bytes.clear();
+        reader.read_to_end(&mut bytes)?;
+        *buf = options.encoding.decode_owned(bytes)?;
+        Value::parse_with_options(buf, options)
+    }
+
+    /// Parses a `Value` from a Python literal encoded as UTF-8 bytes, using
+    /// the default options.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Value, ParseError> {
+        Value::from_bytes_with_options(bytes, &ParseOptions::default())
+    }
+
+    /// Parses a `Value` from a Python literal in `bytes`, decoded according
+    /// to the [encoding] in the options.
+    ///
+    /// ```
+    /// use py_literal::{ParseOptions, SourceEncoding, Value};
+    ///
+    /// # fn main() -> Result<(), py_literal::ParseError> {
+    /// let options = ParseOptions::new().encoding(SourceEncoding::Latin1);
+    /// let value = Value::from_bytes_with_options(b"'caf\xe9'", &options)?;
+    /// assert_eq!(value, Value::String("café".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [encoding]: struct.ParseOptions.html#method.encoding
+    pub fn from_bytes_with_options(
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<Value, ParseError> {
+        Value::parse_with_options(&options.encoding.decode(bytes)?, options)
+    }
+
+    /// Parses a `Value` from a Python literal in the file at `path`, using the
+    /// given options.
+    ///
+    /// The file is memory-mapped rather than read into memory, so if it's
+    /// valid UTF-8 (or ASCII, for [`SourceEncoding::Latin1`]), the input is
+    /// never copied.
+    ///
+    /// The file must not be modified while it's being parsed. Like any
+    /// memory-mapped file, concurrent modification may cause the process to
+    /// crash or to observe invalid UTF-8.
+    ///
+    /// [`SourceEncoding::Latin1`]: enum.SourceEncoding.html#variant.Latin1
+    #[cfg(feature = "memmap2")]
+    pub fn from_path<P: AsRef<std::path::Path>>(
+        path: P,
+        options: &ParseOptions,
+    ) -> Result<Value, ParseError> {
+        let file = std::fs::File::open(path)?;
+        // The safety requirement (that the file isn't modified while mapped)
+        // is documented above.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        Value::from_bytes_with_options(&mmap, options)
     }
 }
 
+/// Checks that the input is a valid Python literal, with the default parse
+/// options, without creating the value.
+///
+/// This is much cheaper than parsing with the [`FromStr` implementation] for
+/// `Value`, since integers aren't converted, escape sequences are only
+/// checked rather than decoded, and no `Value`s are allocated. It accepts the
+/// same input as the `FromStr` implementation, except that numeric
+/// expressions are not evaluated, so an integer which is too large to be
+/// added to a float (which is a [`ParseError::NumericCast`] when parsing)
+/// isn't detected.
+///
+/// ```
+/// assert!(py_literal::validate("{'a': [1, 2e3, b'\\x00']}").is_ok());
+/// assert!(py_literal::validate("[1, 2").is_err());
+/// assert!(py_literal::validate("0755").is_err());
+/// ```
+///
+/// [`FromStr` implementation]: enum.Value.html#impl-FromStr
+/// [`ParseError::NumericCast`]: enum.ParseError.html#variant.NumericCast
+pub fn validate(s: &str) -> Result<(), ParseError> {
+    let mut parsed =
+        Parser::parse(Rule::start, s).map_err(|e| ParseError::Syntax(format!("{}", e)))?;
+    let (start,) = parse_pairs_as!(parsed, (Rule::start,));
+    check_leaves(start.into_inner(), &ParseOptions::default())
+}
+
+/// Checks the strings, escape sequences, numbers, and calls in the pairs
+/// which may be rejected by `validate`, without creating the value.
+pub(crate) fn check_leaves(pairs: Pairs<'_, Rule>, opts: &ParseOptions) -> Result<(), ParseError> {
+    for pair in pairs.flatten() {
+        // With the default options, the parse functions called here fail
+        // before converting anything, since the constructs they reject
+        // aren't enabled.
+        match pair.as_rule() {
+            Rule::string => {
+                let prefix = pair.clone().into_inner().next().unwrap();
+                if prefix.as_rule() == Rule::raw_string_prefix && prefix.as_str().len() == 2 {
+                    parse_string(pair, opts)?;
+                }
+            }
+            Rule::string_escape_seq => {
+                parse_string_escape_seq(pair)?;
+            }
+            Rule::bytes_escape_seq => {
+                parse_bytes_escape_seq(pair)?;
+            }
+            Rule::integer => {
+                let mut inner = pair.clone().into_inner();
+                let is_legacy = inner.next().unwrap().as_rule() == Rule::legacy_oct_integer;
+                if is_legacy || inner.next().is_some() {
+                    parse_integer(pair, opts)?;
+                }
+            }
+            Rule::number => {
+                let inner = pair.clone().into_inner().next().unwrap();
+                if inner.as_rule() == Rule::float_keyword {
+                    parse_number(pair, opts)?;
+                }
+            }
+            Rule::float_call => {
+                parse_float_call(pair, opts)?;
+            }
+            Rule::complex_call => {
+                parse_complex_call(pair, opts)?;
+            }
+            Rule::frozenset | Rule::ordered_dict => {
+                // The elements are checked separately, so this only checks
+                // that the call is enabled.
+                Frame::new(pair, opts)?;
+            }
+            Rule::bytearray
+            | Rule::empty_call
+            | Rule::decimal
+            | Rule::rational
+            | Rule::datetime => {
+                parse_non_collection(pair, opts)?;
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Parses the input with the `start` rule, after applying the preprocessing
+/// specified by the options, and then calls `f` with the contents of the
+/// `start` rule and the options in effect.
+pub(crate) fn parse_start<T>(
+    s: &str,
+    options: &ParseOptions,
+    f: impl FnOnce(Pairs<'_, Rule>, &ParseOptions) -> Result<T, ParseError>,
+) -> Result<T, ParseError> {
+    let options = options.resolve();
+    let s = options.preprocess(s);
+    options.check_cancelled()?;
+    options.check_depth(&s)?;
+    options.start_progress(s.len());
+    let mut parsed =
+        Parser::parse(Rule::start, &s).map_err(|e| ParseError::Syntax(format!("{}", e)))?;
+    options.check_cancelled()?;
+    let (start,) = parse_pairs_as!(parsed, (Rule::start,));
+    options.check_nodes(start.clone().into_inner())?;
+    let out = f(start.into_inner(), &options)?;
+    options.finish_progress();
+    Ok(out)
+}
+
+/// Creates a syntax error for a construct which is recognized by the grammar
+/// but is not permitted by the parse options.
+pub(crate) fn syntax_error(span: pest::Span<'_>, message: &str) -> ParseError {
+    let err = pest::error::Error::<Rule>::new_from_span(
+        pest::error::ErrorVariant::CustomError {
+            message: message.into(),
+        },
+        span,
+    );
+    ParseError::Syntax(format!("{}", err))
+}
+
 fn parse_string_escape_seq(escape_seq: Pair<'_, Rule>) -> Result<char, ParseError> {
     debug_assert_eq!(escape_seq.as_rule(), Rule::string_escape_seq);
     let (seq,) = parse_pairs_as!(escape_seq.into_inner(), (_,));
@@ -132,33 +1327,191 @@ fn parse_string_escape_seq(escape_seq: Pair<'_, Rule>) -> Result<char, ParseErro
         .ok_or_else(|| {
             ParseError::IllegalEscapeSequence(format!("Hex escape is invalid: \\x{}", seq.as_str()))
         }),
-        Rule::name_escape => Err(ParseError::IllegalEscapeSequence(
-            "Unicode name escapes are not supported.".into(),
-        )),
+        Rule::name_escape => parse_name_escape(seq.as_str()),
         _ => unreachable!(),
     }
 }
 
-fn parse_string(string: Pair<'_, Rule>) -> Result<String, ParseError> {
+/// Parses a string literal, or implicitly concatenated string literals,
+/// which is a value (rather than the argument of a call), applying the string
+/// hook.
+pub(crate) fn parse_string_value<'i>(
+    string: Pair<'i, Rule>,
+    opts: &ParseOptions,
+) -> Result<Cow<'i, str>, ParseError> {
+    let out = if string.as_rule() == Rule::string_concat {
+        let span = string.as_span();
+        let mut out = String::new();
+        for part in string.into_inner() {
+            out.push_str(&parse_string(part, opts)?);
+        }
+        opts.check_length(span, || out.chars().count())?;
+        Cow::Owned(out)
+    } else {
+        parse_string(string, opts)?
+    };
+    Ok(match &opts.string_hook {
+        Some(StringHook(hook)) => match hook(&out) {
+            Some(replaced) => Cow::Owned(replaced),
+            None => out,
+        },
+        None => out,
+    })
+}
+
+/// Parses a bytes literal, or implicitly concatenated bytes literals, which
+/// is a value (rather than the argument of a call).
+pub(crate) fn parse_bytes_value<'i>(
+    bytes: Pair<'i, Rule>,
+    opts: &ParseOptions,
+) -> Result<Cow<'i, [u8]>, ParseError> {
+    if bytes.as_rule() != Rule::bytes_concat {
+        return parse_bytes(bytes, opts);
+    }
+    let span = bytes.as_span();
+    let mut out = Vec::new();
+    for part in bytes.into_inner() {
+        out.extend_from_slice(&parse_bytes(part, opts)?);
+    }
+    opts.check_length(span, || out.len())?;
+    Ok(Cow::Owned(out))
+}
+
+/// Converts `\r\n` and `\r` line breaks in the text of a string or bytes
+/// literal to `\n`, as Python does when reading source code.
+fn normalize_newlines(text: &str) -> Cow<'_, str> {
+    if text.contains('\r') {
+        Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+fn cow_into_bytes(s: Cow<'_, str>) -> Cow<'_, [u8]> {
+    match s {
+        Cow::Borrowed(s) => Cow::Borrowed(s.as_bytes()),
+        Cow::Owned(s) => Cow::Owned(s.into_bytes()),
+    }
+}
+
+/// Returns the code unit of a `\u` or `\U` escape sequence which encodes a
+/// UTF-16 surrogate.
+fn surrogate_escape(escape_seq: &Pair<'_, Rule>) -> Option<u32> {
+    let (seq,) = parse_pairs_as!(escape_seq.clone().into_inner(), (_,));
+    if seq.as_rule() != Rule::unicode_hex_escape {
+        return None;
+    }
+    let code = u32::from_str_radix(&seq.as_str()[1..], 16).unwrap();
+    if (0xD800..=0xDFFF).contains(&code) {
+        Some(code)
+    } else {
+        None
+    }
+}
+
+/// Returns the character for a surrogate escape sequence, according to the
+/// `surrogate_escapes` option, consuming the low surrogate of a pair from
+/// `items`. Returns `None` if the escape sequence isn't a surrogate, or if it
+/// should be parsed as usual (which is an error).
+fn join_surrogates<'i>(
+    escape_seq: &Pair<'i, Rule>,
+    items: &mut iter::Peekable<Pairs<'i, Rule>>,
+    opts: &ParseOptions,
+) -> Option<char> {
+    if opts.surrogate_escapes == SurrogateEscapes::Error {
+        return None;
+    }
+    let high = surrogate_escape(escape_seq)?;
+    let low = match items.peek() {
+        Some(next) if high < 0xDC00 && next.as_rule() == Rule::string_escape_seq => {
+            surrogate_escape(next).filter(|&low| low >= 0xDC00)
+        }
+        _ => None,
+    };
+    match low {
+        Some(low) => {
+            items.next();
+            std::char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+        }
+        None if opts.surrogate_escapes == SurrogateEscapes::Replace => Some('\u{FFFD}'),
+        None => None,
+    }
+}
+
+/// Looks up the character for a Unicode name escape (`N{name}`).
+#[cfg(feature = "unicode_names2")]
+fn parse_name_escape(escape: &str) -> Result<char, ParseError> {
+    let name = &escape[2..escape.len() - 1];
+    unicode_names2::character(name).ok_or_else(|| {
+        ParseError::IllegalEscapeSequence(format!("Unknown Unicode character name: \\{}", escape))
+    })
+}
+
+/// Looks up the character for a Unicode name escape (`N{name}`).
+#[cfg(not(feature = "unicode_names2"))]
+fn parse_name_escape(escape: &str) -> Result<char, ParseError> {
+    Err(ParseError::IllegalEscapeSequence(format!(
+        "Unicode name escapes require the `unicode_names2` feature: \\{}",
+        escape
+    )))
+}
+
+/// Parses a string literal. The result borrows from the input if the string
+/// contains no escape sequences.
+pub(crate) fn parse_string<'i>(
+    string: Pair<'i, Rule>,
+    opts: &ParseOptions,
+) -> Result<Cow<'i, str>, ParseError> {
     debug_assert_eq!(string.as_rule(), Rule::string);
-    let (string_body,) = parse_pairs_as!(string.into_inner(), (_,));
-    match string_body.as_rule() {
+    let span = string.as_span();
+    let mut pairs = string.into_inner();
+    let mut string_body = pairs.next().unwrap();
+    if string_body.as_rule() == Rule::raw_string_prefix {
+        if string_body.as_str().len() == 2 && opts.dialect != Dialect::Py27 {
+            return Err(syntax_error(
+                string_body.as_span(),
+                "the `ur` string prefix is supported only in Python 2",
+            ));
+        }
+        string_body = pairs.next().unwrap();
+    }
+    debug_assert_match!(Option::None, pairs.next());
+    let out = match string_body.as_rule() {
         Rule::short_string_body | Rule::long_string_body => {
-            let mut out = String::new();
-            for item in string_body.into_inner() {
-                match item.as_rule() {
-                    Rule::short_string_non_escape
-                    | Rule::long_string_non_escape
-                    | Rule::string_unknown_escape => out.push_str(item.as_str()),
-                    Rule::line_continuation_seq => (),
-                    Rule::string_escape_seq => out.push(parse_string_escape_seq(item)?),
-                    _ => unreachable!(),
+            if string_body.clone().into_inner().all(|item| {
+                matches!(
+                    item.as_rule(),
+                    Rule::short_string_non_escape | Rule::long_string_non_escape
+                )
+            }) {
+                normalize_newlines(string_body.as_str())
+            } else {
+                let mut out = String::new();
+                let mut items = string_body.into_inner().peekable();
+                while let Some(item) = items.next() {
+                    match item.as_rule() {
+                        Rule::short_string_non_escape | Rule::long_string_non_escape => {
+                            out.push_str(&normalize_newlines(item.as_str()))
+                        }
+                        Rule::string_unknown_escape => out.push_str(item.as_str()),
+                        Rule::line_continuation_seq => (),
+                        Rule::string_escape_seq => match join_surrogates(&item, &mut items, opts) {
+                            Some(c) => out.push(c),
+                            None => out.push(parse_string_escape_seq(item)?),
+                        },
+                        _ => unreachable!(),
+                    }
                 }
+                Cow::Owned(out)
             }
-            Ok(out)
+        }
+        Rule::short_raw_string_body | Rule::long_raw_string_body => {
+            normalize_newlines(string_body.as_str())
         }
         _ => unreachable!(),
-    }
+    };
+    opts.check_length(span, || out.chars().count())?;
+    Ok(out)
 }
 
 fn parse_bytes_escape_seq(escape_seq: Pair<'_, Rule>) -> Result<u8, ParseError> {
@@ -190,137 +1543,1106 @@ fn parse_bytes_escape_seq(escape_seq: Pair<'_, Rule>) -> Result<u8, ParseError>
     }
 }
 
-fn parse_bytes(bytes: Pair<'_, Rule>) -> Result<Vec<u8>, ParseError> {
+/// Parses a bytes literal. The result borrows from the input if the literal
+/// contains no escape sequences.
+pub(crate) fn parse_bytes<'i>(
+    bytes: Pair<'i, Rule>,
+    opts: &ParseOptions,
+) -> Result<Cow<'i, [u8]>, ParseError> {
     debug_assert_eq!(bytes.as_rule(), Rule::bytes);
+    if opts.dialect == Dialect::Py27 && bytes.as_str().starts_with(&['r', 'R'][..]) {
+        return Err(syntax_error(
+            bytes.as_span(),
+            "the `rb` bytes prefix requires Python 3.3 or later",
+        ));
+    }
+    let span = bytes.as_span();
     let (bytes_body,) = parse_pairs_as!(bytes.into_inner(), (_,));
-    match bytes_body.as_rule() {
+    let out = match bytes_body.as_rule() {
         Rule::short_bytes_body | Rule::long_bytes_body => {
-            let mut out = Vec::new();
-            for item in bytes_body.into_inner() {
-                match item.as_rule() {
-                    Rule::short_bytes_non_escape
-                    | Rule::long_bytes_non_escape
-                    | Rule::bytes_unknown_escape => out.extend_from_slice(item.as_str().as_bytes()),
-                    Rule::line_continuation_seq => (),
-                    Rule::bytes_escape_seq => out.push(parse_bytes_escape_seq(item)?),
+            if bytes_body.clone().into_inner().all(|item| {
+                matches!(
+                    item.as_rule(),
+                    Rule::short_bytes_non_escape | Rule::long_bytes_non_escape
+                )
+            }) {
+                cow_into_bytes(normalize_newlines(bytes_body.as_str()))
+            } else {
+                let mut out = Vec::new();
+                for item in bytes_body.into_inner() {
+                    match item.as_rule() {
+                        Rule::short_bytes_non_escape | Rule::long_bytes_non_escape => {
+                            out.extend_from_slice(normalize_newlines(item.as_str()).as_bytes())
+                        }
+                        Rule::bytes_unknown_escape => {
+                            out.extend_from_slice(item.as_str().as_bytes())
+                        }
+                        Rule::line_continuation_seq => (),
+                        Rule::bytes_escape_seq => out.push(parse_bytes_escape_seq(item)?),
+                        _ => unreachable!(),
+                    }
+                }
+                Cow::Owned(out)
+            }
+        }
+        Rule::short_raw_bytes_body | Rule::long_raw_bytes_body => {
+            cow_into_bytes(normalize_newlines(bytes_body.as_str()))
+        }
+        _ => unreachable!(),
+    };
+    opts.check_length(span, || out.len())?;
+    Ok(out)
+}
+
+/// Kind of operand in a numeric expression, for checking expressions in
+/// strict mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OperandKind {
+    Real,
+    Imag,
+    Boolean,
+    Other,
+}
+
+/// Shape of a numeric expression, as far as `ast.literal_eval()` cares about
+/// it, matching the structure of Python's AST (in which parentheses don't
+/// appear).
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum NumberShape {
+    /// Operand with the given number of unary signs.
+    Operand(OperandKind, usize),
+    /// Binary operation, with its operands if they're (possibly signed)
+    /// operands rather than operations.
+    Binary(Option<(OperandKind, usize)>, Option<(OperandKind, usize)>),
+    /// Unary operation on a binary operation.
+    Other,
+}
+
+impl NumberShape {
+    /// Returns the shape of the expression.
+    ///
+    /// Parenthesized subexpressions are handled with an explicit stack
+    /// instead of recursively, so deeply nested input can't overflow the call
+    /// stack.
+    fn of(expr: &Pair<'_, Rule>) -> NumberShape {
+        debug_assert_eq!(expr.as_rule(), Rule::number_expr);
+        // Remaining operands, the shape so far, and the number of signs
+        // before the next operand, for each expression being examined.
+        let mut stack = vec![(expr.clone().into_inner(), None, 0)];
+        let mut done = None;
+        loop {
+            let (pairs, lhs, signs) = stack.last_mut().unwrap();
+            let operand = match done.take() {
+                Some(shape) => shape,
+                None => match pairs.next() {
+                    Some(pair) => match pair.as_rule() {
+                        Rule::plus_sign | Rule::minus_sign => {
+                            *signs += 1;
+                            continue;
+                        }
+                        Rule::number => {
+                            let (inner,) = parse_pairs_as!(pair.into_inner(), (_,));
+                            let kind = match inner.as_rule() {
+                                Rule::imag => OperandKind::Imag,
+                                Rule::float | Rule::integer => OperandKind::Real,
+                                _ => OperandKind::Other,
+                            };
+                            NumberShape::Operand(kind, 0)
+                        }
+                        Rule::boolean => NumberShape::Operand(OperandKind::Boolean, 0),
+                        Rule::number_expr => {
+                            stack.push((pair.into_inner(), None, 0));
+                            continue;
+                        }
+                        _ => NumberShape::Operand(OperandKind::Other, 0),
+                    },
+                    None => {
+                        let shape = lhs.unwrap();
+                        stack.pop();
+                        if stack.is_empty() {
+                            return shape;
+                        }
+                        done = Some(shape);
+                        continue;
+                    }
+                },
+            };
+            // The first sign between two operands is the binary operator, and
+            // the rest are unary operators on the right operand.
+            let unary_signs = if lhs.is_some() { *signs - 1 } else { *signs };
+            let operand = match operand {
+                NumberShape::Operand(kind, n) => NumberShape::Operand(kind, n + unary_signs),
+                _ if unary_signs > 0 => NumberShape::Other,
+                shape => shape,
+            };
+            *lhs = Some(match *lhs {
+                None => operand,
+                Some(lhs) => NumberShape::Binary(lhs.as_operand(), operand.as_operand()),
+            });
+            *signs = 0;
+        }
+    }
+
+    fn as_operand(self) -> Option<(OperandKind, usize)> {
+        match self {
+            NumberShape::Operand(kind, signs) => Some((kind, signs)),
+            _ => None,
+        }
+    }
+
+    /// Returns whether `ast.literal_eval()` would accept the expression.
+    fn is_literal_eval_compatible(self) -> bool {
+        let is_number = |kind| kind == OperandKind::Real || kind == OperandKind::Imag;
+        match self {
+            NumberShape::Operand(OperandKind::Boolean, 0) => true,
+            NumberShape::Operand(kind, signs) => is_number(kind) && signs <= 1,
+            NumberShape::Binary(Some((OperandKind::Real, signs)), Some((OperandKind::Imag, 0))) => {
+                signs <= 1
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Numeric expression or `complex()` call whose operands are being evaluated
+/// by `eval_number`.
+enum NumberFrame<'i> {
+    /// Expression, with its remaining operands, the result so far, and the
+    /// signs before the next operand.
+    Expr {
+        pairs: Pairs<'i, Rule>,
+        result: Option<Value>,
+        signed: bool,
+        neg: bool,
+    },
+    /// `complex()` call, with its remaining arguments and the arguments
+    /// evaluated so far.
+    ComplexCall {
+        args: Pairs<'i, Rule>,
+        parts: Vec<numc::Complex<f64>>,
+    },
+}
+
+impl<'i> NumberFrame<'i> {
+    fn new(pair: Pair<'i, Rule>, opts: &ParseOptions) -> Result<NumberFrame<'i>, ParseError> {
+        match pair.as_rule() {
+            Rule::number_expr => Ok(NumberFrame::Expr {
+                pairs: pair.into_inner(),
+                result: None,
+                signed: false,
+                neg: false,
+            }),
+            Rule::complex_call => {
+                if !opts.complex_calls {
+                    return Err(syntax_error(
+                        pair.as_span(),
+                        "`complex()` calls are supported only if enabled in the parse options",
+                    ));
+                }
+                Ok(NumberFrame::ComplexCall {
+                    args: pair.into_inner(),
+                    parts: Vec::with_capacity(2),
+                })
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Adds an evaluated operand or argument.
+    fn push(&mut self, num: Value) -> Result<(), ParseError> {
+        match self {
+            NumberFrame::Expr {
+                result,
+                signed,
+                neg,
+                ..
+            } => {
+                *result = Some(match result.take() {
+                    // Like Python, a lone unsigned operand keeps its type, but
+                    // a sign converts a boolean to an integer.
+                    None if !*signed => num,
+                    None if *neg => neg_number(num),
+                    None => bool_to_int(num),
+                    Some(lhs) if *neg => sub_numbers(lhs, num)?,
+                    Some(lhs) => add_numbers(lhs, num)?,
+                });
+                *signed = false;
+                *neg = false;
+            }
+            NumberFrame::ComplexCall { parts, .. } => {
+                parts.push(match bool_to_int(num) {
+                    Value::Integer(int) => numc::Complex::new(int_to_f64(int)?, 0.),
+                    Value::Float(float) => numc::Complex::new(float, 0.),
+                    Value::Complex(comp) => comp,
                     _ => unreachable!(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the next operand or argument to evaluate, after handling any
+    /// signs before it.
+    fn next_operand(&mut self) -> Option<Pair<'i, Rule>> {
+        match self {
+            NumberFrame::Expr {
+                pairs, signed, neg, ..
+            } => loop {
+                let pair = pairs.next()?;
+                match pair.as_rule() {
+                    Rule::plus_sign => *signed = true,
+                    Rule::minus_sign => {
+                        *signed = true;
+                        *neg = !*neg;
+                    }
+                    _ => return Some(pair),
                 }
+            },
+            NumberFrame::ComplexCall { args, .. } => args.next(),
+        }
+    }
+
+    fn finish(self) -> Value {
+        match self {
+            NumberFrame::Expr { result, .. } => result.unwrap(),
+            NumberFrame::ComplexCall { parts, .. } => {
+                let zero = numc::Complex::new(0., 0.);
+                let real = parts.first().copied().unwrap_or(zero);
+                let imag = parts.get(1).copied().unwrap_or(zero);
+                Value::Complex(numc::Complex::new(real.re - imag.im, real.im + imag.re))
             }
-            Ok(out)
         }
-        _ => unreachable!(),
     }
-}
-
-fn parse_number_expr(expr: Pair<'_, Rule>) -> Result<Value, ParseError> {
-    debug_assert_eq!(expr.as_rule(), Rule::number_expr);
-    let mut result = Value::Integer(0.into());
-    let mut neg = false;
-    for pair in expr.into_inner() {
-        match pair.as_rule() {
-            Rule::minus_sign => neg = !neg,
-            Rule::number => {
-                let num = parse_number(pair)?;
-                if neg {
-                    result = sub_numbers(result, num).unwrap();
-                } else {
-                    result = add_numbers(result, num).unwrap();
-                }
-                neg = false;
-            }
-            _ => unreachable!(),
+}
+
+/// Evaluates a numeric expression or `complex()` call.
+///
+/// Parenthesized subexpressions and `complex()` arguments are evaluated with
+/// an explicit stack instead of recursively, so deeply nested input can't
+/// overflow the call stack.
+fn eval_number(pair: Pair<'_, Rule>, opts: &ParseOptions) -> Result<Value, ParseError> {
+    let mut stack = vec![NumberFrame::new(pair, opts)?];
+    let mut done = None;
+    while let Some(frame) = stack.last_mut() {
+        if let Some(num) = done.take() {
+            frame.push(num)?;
+        }
+        match frame.next_operand() {
+            Some(pair) => match pair.as_rule() {
+                Rule::number => done = Some(parse_number(pair, opts)?),
+                Rule::boolean => done = Some(Value::Boolean(parse_boolean(pair))),
+                Rule::float_call => done = Some(Value::Float(parse_float_call(pair, opts)?)),
+                Rule::number_expr | Rule::complex_call => stack.push(NumberFrame::new(pair, opts)?),
+                _ => unreachable!(),
+            },
+            None => done = Some(stack.pop().unwrap().finish()),
+        }
+    }
+    Ok(done.unwrap())
+}
+
+fn parse_number_expr(expr: Pair<'_, Rule>, opts: &ParseOptions) -> Result<Value, ParseError> {
+    debug_assert_eq!(expr.as_rule(), Rule::number_expr);
+    eval_number(expr, opts)
+}
+
+fn parse_number(number: Pair<'_, Rule>, opts: &ParseOptions) -> Result<Value, ParseError> {
+    debug_assert_eq!(number.as_rule(), Rule::number);
+    let (inner,) = parse_pairs_as!(number.into_inner(), (_,));
+    if opts.dialect <= Dialect::Py35 && inner.as_str().contains('_') {
+        return Err(syntax_error(
+            inner.as_span(),
+            "underscores in numeric literals require Python 3.6 or later",
+        ));
+    }
+    match inner.as_rule() {
+        Rule::imag => parse_imag(inner, opts),
+        Rule::float => Ok(Value::Float(parse_float(inner, opts)?)),
+        Rule::integer => Ok(Value::Integer(parse_integer(inner, opts)?)),
+        Rule::float_keyword => {
+            if !opts.inf_nan {
+                return Err(syntax_error(
+                    inner.as_span(),
+                    "`inf` and `nan` are supported only if enabled in the parse options",
+                ));
+            }
+            match inner.as_str() {
+                "inf" => Ok(Value::Float(std::f64::INFINITY)),
+                "nan" => Ok(Value::Float(std::f64::NAN)),
+                _ => unreachable!(),
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn parse_float_call(call: Pair<'_, Rule>, opts: &ParseOptions) -> Result<f64, ParseError> {
+    debug_assert_eq!(call.as_rule(), Rule::float_call);
+    let span = call.as_span();
+    let (name, arg) = parse_pairs_as!(call.into_inner(), (Rule::float_call_name, Rule::string));
+    let fromhex = name.as_str() != "float";
+    if fromhex && !opts.hex_floats {
+        return Err(syntax_error(
+            span,
+            "`float.fromhex()` calls are supported only if enabled in the parse options",
+        ));
+    } else if !fromhex && !opts.float_calls {
+        return Err(syntax_error(
+            span,
+            "`float()` calls are supported only if enabled in the parse options",
+        ));
+    }
+    let arg = parse_string(arg, opts)?.trim().to_ascii_lowercase();
+    let (neg, magnitude) = match arg.as_bytes().first() {
+        Some(b'-') => (true, &arg[1..]),
+        Some(b'+') => (false, &arg[1..]),
+        _ => (false, &arg[..]),
+    };
+    let value = match magnitude {
+        "inf" | "infinity" => std::f64::INFINITY,
+        "nan" => std::f64::NAN,
+        _ if fromhex => parse_hex_float(magnitude).map_err(|msg| syntax_error(span, msg))?,
+        _ => {
+            return Err(syntax_error(
+                span,
+                "only `inf`, `infinity`, and `nan` are supported as `float()` arguments",
+            ))
+        }
+    };
+    Ok(if neg { -value } else { value })
+}
+
+/// Parses the magnitude of a hexadecimal float, as accepted by Python's
+/// `float.fromhex()`, rounding to the nearest `f64` (ties to even). The input
+/// must already be lowercase and have no sign.
+fn parse_hex_float(hex: &str) -> Result<f64, &'static str> {
+    const INVALID: &str = "invalid hexadecimal float in `float.fromhex()` call";
+    let hex = if hex.starts_with("0x") {
+        &hex[2..]
+    } else {
+        hex
+    };
+    let (digits, exp_str) = match hex.find('p') {
+        Some(p) => (&hex[..p], &hex[p + 1..]),
+        None => (hex, "0"),
+    };
+    let (int, frac) = match digits.find('.') {
+        Some(point) => (&digits[..point], &digits[point + 1..]),
+        None => (digits, ""),
+    };
+    let exp_digits = if exp_str.starts_with(|c| c == '+' || c == '-') {
+        &exp_str[1..]
+    } else {
+        exp_str
+    };
+    if int.len() + frac.len() == 0
+        || exp_digits.is_empty()
+        || !exp_digits.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(INVALID);
+    }
+    // Saturate huge exponents; they overflow or underflow regardless.
+    let mut exp = exp_digits
+        .bytes()
+        .fold(0i64, |acc, b| (acc * 10 + i64::from(b - b'0')).min(1 << 40));
+    if exp_str.starts_with('-') {
+        exp = -exp;
+    }
+    // Collect up to 15 significant hex digits (60 bits) into the mantissa.
+    // Any nonzero digits beyond that only matter for rounding.
+    let mut mantissa = 0u64;
+    let mut sig_digits = 0;
+    let mut sticky = false;
+    for (i, c) in int.chars().chain(frac.chars()).enumerate() {
+        let digit = u64::from(c.to_digit(16).ok_or(INVALID)?);
+        let is_frac = i >= int.len();
+        if sig_digits < 15 && (mantissa != 0 || digit != 0) {
+            mantissa = mantissa << 4 | digit;
+            sig_digits += 1;
+            if is_frac {
+                exp -= 4;
+            }
+        } else if sig_digits >= 15 {
+            sticky |= digit != 0;
+            if !is_frac {
+                exp += 4;
+            }
+        } else if is_frac {
+            exp -= 4;
+        }
+    }
+    if mantissa == 0 {
+        return Ok(0.);
+    }
+    // The value is now `mantissa * 2^exp` (plus a bit more if `sticky`).
+    // Determine the exponent of the last bit which fits in an `f64`.
+    let bits = i64::from(64 - mantissa.leading_zeros());
+    let top = exp + bits - 1;
+    let mut lsb = (top - 52).max(-1074);
+    let shift = lsb - exp;
+    let mut kept = if shift <= 0 {
+        mantissa << -shift
+    } else if shift > bits {
+        0
+    } else {
+        let rem = mantissa & ((1 << shift) - 1);
+        let half = 1 << (shift - 1);
+        let kept = mantissa >> shift;
+        if rem > half || (rem == half && (sticky || kept & 1 == 1)) {
+            kept + 1
+        } else {
+            kept
+        }
+    };
+    if kept == 1 << 53 {
+        kept >>= 1;
+        lsb += 1;
+    }
+    if kept < 1 << 52 {
+        // Subnormal (or zero), so `lsb` is -1074.
+        Ok(f64::from_bits(kept))
+    } else {
+        let biased = lsb + 52 + 1023;
+        if biased > 2046 {
+            return Err("hexadecimal value too large to represent as a float");
         }
+        Ok(f64::from_bits(
+            (biased as u64) << 52 | (kept & ((1 << 52) - 1)),
+        ))
     }
-    Ok(result)
 }
 
-fn parse_number(number: Pair<'_, Rule>) -> Result<Value, ParseError> {
-    debug_assert_eq!(number.as_rule(), Rule::number);
-    let (inner,) = parse_pairs_as!(number.into_inner(), (_,));
-    match inner.as_rule() {
-        Rule::imag => parse_imag(inner),
-        Rule::float => Ok(Value::Float(parse_float(inner)?)),
-        Rule::integer => Ok(Value::Integer(parse_integer(inner))),
+fn parse_complex_call(
+    call: Pair<'_, Rule>,
+    opts: &ParseOptions,
+) -> Result<numc::Complex<f64>, ParseError> {
+    debug_assert_eq!(call.as_rule(), Rule::complex_call);
+    match eval_number(call, opts)? {
+        Value::Complex(comp) => Ok(comp),
         _ => unreachable!(),
     }
 }
 
-fn parse_integer(int: Pair<'_, Rule>) -> numb::BigInt {
+fn parse_integer(int: Pair<'_, Rule>, opts: &ParseOptions) -> Result<numb::BigInt, ParseError> {
     debug_assert_eq!(int.as_rule(), Rule::integer);
-    let (inner,) = parse_pairs_as!(int.into_inner(), (_,));
-    match inner.as_rule() {
+    let mut pairs = int.into_inner();
+    let inner = pairs.next().unwrap();
+    if let Some(suffix) = pairs.next() {
+        debug_assert_eq!(suffix.as_rule(), Rule::long_suffix);
+        if opts.dialect != Dialect::Py27 {
+            return Err(syntax_error(
+                suffix.as_span(),
+                "the `L` suffix for integers is supported only in Python 2",
+            ));
+        }
+    }
+    Ok(match inner.as_rule() {
         Rule::bin_integer => {
-            let digits: String = inner.into_inner().map(|digit| digit.as_str()).collect();
+            let digits = strip_underscores(&inner.as_str()[2..]);
             numb::BigInt::from_str_radix(&digits, 2).unwrap_or_else(|_| {
                 unreachable!("failure parsing binary integer with digits {}", digits)
             })
         }
         Rule::oct_integer => {
-            let digits: String = inner.into_inner().map(|digit| digit.as_str()).collect();
+            let digits = strip_underscores(&inner.as_str()[2..]);
             numb::BigInt::from_str_radix(&digits, 8).unwrap_or_else(|_| {
                 unreachable!("failure parsing octal integer with digits {}", digits)
             })
         }
         Rule::hex_integer => {
-            let digits: String = inner.into_inner().map(|digit| digit.as_str()).collect();
+            let digits = strip_underscores(&inner.as_str()[2..]);
             numb::BigInt::from_str_radix(&digits, 16).unwrap_or_else(|_| {
                 unreachable!("failure parsing hexadecimal integer with digits {}", digits)
             })
         }
         Rule::dec_integer => {
-            let digits: String = inner.into_inner().map(|digit| digit.as_str()).collect();
+            if let Some(max_digits) = opts.max_int_digits {
+                if inner.clone().into_inner().nth(max_digits).is_some() {
+                    let span = inner.as_span();
+                    return Err(ParseError::DigitLimitExceeded(span.start()..span.end()));
+                }
+            }
+            let digits = strip_underscores(inner.as_str());
             digits
                 .parse()
                 .unwrap_or_else(|_| unreachable!("failure parsing integer with digits {}", digits))
         }
+        Rule::legacy_oct_integer => {
+            let span = inner.as_span();
+            if opts.dialect == Dialect::Py27 {
+                let digits = strip_underscores(inner.as_str());
+                // Digits 8 and 9 are invalid in Python 2 octal literals too.
+                if let Ok(int) = numb::BigInt::from_str_radix(&digits, 8) {
+                    return Ok(int);
+                }
+            }
+            return Err(ParseError::LegacyOctalInteger(
+                span.as_str().into(),
+                span.start()..span.end(),
+            ));
+        }
         _ => unreachable!(),
-    }
+    })
 }
 
-fn parse_float(float: Pair<'_, Rule>) -> Result<f64, ParseError> {
+fn parse_float(float: Pair<'_, Rule>, opts: &ParseOptions) -> Result<f64, ParseError> {
     debug_assert_eq!(float.as_rule(), Rule::float);
-    let (inner,) = parse_pairs_as!(float.into_inner(), (_,));
-    let mut parsable = String::new();
-    for pair in inner.into_inner().flatten() {
-        match pair.as_rule() {
-            Rule::digit => parsable.push_str(pair.as_str()),
-            Rule::fraction => parsable.push('.'),
-            Rule::pos_exponent => parsable.push('e'),
-            Rule::neg_exponent => parsable.push_str("e-"),
-            _ => (),
-        }
-    }
-    Ok(parsable.parse()?)
+    // Apart from underscores, the syntax of Python floats is accepted by
+    // `f64::from_str`.
+    let span = float.as_span();
+    check_float_overflow(strip_underscores(float.as_str()).parse()?, span, opts)
 }
 
-fn parse_imag(imag: Pair<'_, Rule>) -> Result<Value, ParseError> {
+fn parse_imag(imag: Pair<'_, Rule>, opts: &ParseOptions) -> Result<Value, ParseError> {
     debug_assert_eq!(imag.as_rule(), Rule::imag);
     let (inner,) = parse_pairs_as!(imag.into_inner(), (_,));
     let imag: f64 = match inner.as_rule() {
-        Rule::float => parse_float(inner)?,
+        Rule::float => parse_float(inner, opts)?,
         Rule::digit_part => {
-            let digits: String = inner.into_inner().map(|digit| digit.as_str()).collect();
-            digits.parse()?
+            let span = inner.as_span();
+            check_float_overflow(strip_underscores(inner.as_str()).parse()?, span, opts)?
         }
         _ => unreachable!(),
     };
     Ok(Value::Complex(numc::Complex::new(0., imag)))
 }
 
-/// Parses a tuple, list, or set.
-fn parse_seq(seq: Pair<'_, Rule>) -> Result<Vec<Value>, ParseError> {
-    debug_assert!([Rule::tuple, Rule::list, Rule::set].contains(&seq.as_rule()));
-    seq.into_inner().map(parse_value).collect()
+/// Returns the digits of a numeric literal without underscores, borrowing
+/// from the literal if it has none.
+fn strip_underscores(digits: &str) -> Cow<'_, str> {
+    if digits.contains('_') {
+        Cow::Owned(digits.replace('_', ""))
+    } else {
+        Cow::Borrowed(digits)
+    }
 }
 
-fn parse_dict(dict: Pair<'_, Rule>) -> Result<Vec<(Value, Value)>, ParseError> {
-    debug_assert_eq!(dict.as_rule(), Rule::dict);
-    let mut out = Vec::new();
-    for elem in dict.into_inner() {
-        let (key, value) = parse_pairs_as!(elem.into_inner(), (Rule::value, Rule::value));
-        out.push((parse_value(key)?, parse_value(value)?));
+/// Checks that a float parsed from the literal with the given span is
+/// finite, if the parse options require it.
+fn check_float_overflow(
+    float: f64,
+    span: pest::Span<'_>,
+    opts: &ParseOptions,
+) -> Result<f64, ParseError> {
+    if opts.reject_float_overflow && !float.is_finite() {
+        return Err(ParseError::FloatOverflow(span.start()..span.end()));
     }
-    Ok(out)
+    Ok(float)
+}
+
+/// Collection whose elements are being parsed by `parse_collection`.
+enum Collection<'i> {
+    /// Comma-separated values in parentheses, and whether there is a trailing
+    /// comma.
+    Parenthesized(Vec<Value>, bool),
+    List(Vec<Value>),
+    Set(Vec<Value>),
+    /// `frozenset()` call.
+    FrozenSet(Vec<Value>),
+    /// Dict, and the key (and its span) of the item whose value is being
+    /// parsed.
+    Dict(Vec<(Value, Value)>, Option<(Value, pest::Span<'i>)>),
+    /// `OrderedDict()` call with a list of `(key, value)` pairs.
+    OrderedDictItems(Vec<(Value, Value)>),
+}
+
+impl<'i> Collection<'i> {
+    /// Adds an element (or dict key or value) with the given span.
+    fn push(
+        &mut self,
+        value: Value,
+        span: pest::Span<'i>,
+        index: &mut ElementIndex,
+        opts: &ParseOptions,
+    ) -> Result<(), ParseError> {
+        match self {
+            Collection::Parenthesized(values, _)
+            | Collection::List(values)
+            | Collection::Set(values)
+            | Collection::FrozenSet(values) => opts.check_elements(values.len(), span)?,
+            Collection::Dict(dict, None) | Collection::OrderedDictItems(dict) => {
+                opts.check_elements(dict.len(), span)?
+            }
+            Collection::Dict(_, Some(_)) => (),
+        }
+        match self {
+            Collection::Parenthesized(values, _) | Collection::List(values) => values.push(value),
+            Collection::Set(set) | Collection::FrozenSet(set) => {
+                push_set_element(set, index, value, span, opts)?
+            }
+            Collection::Dict(dict, key) => match key.take() {
+                None => *key = Some((value, span)),
+                Some((key, key_span)) => insert_dict_item(dict, index, key, value, key_span, opts)?,
+            },
+            Collection::OrderedDictItems(dict) => match value {
+                Value::Tuple(mut pair) | Value::List(mut pair) if pair.len() == 2 => {
+                    let value = pair.pop().unwrap();
+                    let key = pair.pop().unwrap();
+                    insert_dict_item(dict, index, key, value, span, opts)?;
+                }
+                _ => {
+                    return Err(syntax_error(
+                        span,
+                        "`OrderedDict()` items must be (key, value) pairs",
+                    ))
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Value {
+        match self {
+            Collection::Parenthesized(mut values, trailing_comma) => {
+                if values.len() == 1 && !trailing_comma {
+                    values.pop().unwrap()
+                } else {
+                    Value::Tuple(values)
+                }
+            }
+            Collection::List(list) => Value::List(list),
+            Collection::Set(set) => Value::Set(set),
+            Collection::FrozenSet(set) => Value::FrozenSet(set),
+            Collection::Dict(dict, _) | Collection::OrderedDictItems(dict) => Value::Dict(dict),
+        }
+    }
+}
+
+/// Collection on the stack of `parse_collection`.
+struct Frame<'i> {
+    collection: Collection<'i>,
+    /// Remaining items of the collection.
+    items: Pairs<'i, Rule>,
+    /// Remaining key and value of the current dict item.
+    dict_elem: Option<Pairs<'i, Rule>>,
+    /// Index of the elements of a set or keys of a dict.
+    index: ElementIndex,
+    /// Span of the element being parsed.
+    span: pest::Span<'i>,
+}
+
+impl<'i> Frame<'i> {
+    fn new(pair: Pair<'i, Rule>, opts: &ParseOptions) -> Result<Frame<'i>, ParseError> {
+        let span = pair.as_span();
+        let (collection, items) = match pair.as_rule() {
+            Rule::parenthesized => (
+                Collection::Parenthesized(Vec::new(), false),
+                pair.into_inner(),
+            ),
+            Rule::list => (Collection::List(Vec::new()), pair.into_inner()),
+            Rule::set => (Collection::Set(Vec::new()), pair.into_inner()),
+            Rule::dict => (Collection::Dict(Vec::new(), None), pair.into_inner()),
+            Rule::frozenset => {
+                if !opts.frozensets {
+                    return Err(syntax_error(
+                        span,
+                        "`frozenset()` calls are supported only if enabled in the parse options",
+                    ));
+                }
+                (Collection::FrozenSet(Vec::new()), call_arg_items(pair))
+            }
+            Rule::ordered_dict => {
+                if !opts.ordered_dicts {
+                    return Err(syntax_error(
+                        span,
+                        "`OrderedDict()` calls are supported only if enabled in the parse options",
+                    ));
+                }
+                let is_list = pair
+                    .clone()
+                    .into_inner()
+                    .next()
+                    .map_or(false, |arg| arg.as_rule() == Rule::list);
+                let collection = if is_list {
+                    Collection::OrderedDictItems(Vec::new())
+                } else {
+                    Collection::Dict(Vec::new(), None)
+                };
+                (collection, call_arg_items(pair))
+            }
+            _ => unreachable!(),
+        };
+        Ok(Frame {
+            collection,
+            items,
+            dict_elem: None,
+            index: ElementIndex::default(),
+            span,
+        })
+    }
+
+    /// Returns the next element (or dict key or value) to parse.
+    fn next_value(&mut self) -> Option<Pair<'i, Rule>> {
+        loop {
+            let item = match self.dict_elem.as_mut().and_then(Iterator::next) {
+                Some(pair) => pair,
+                None => self.items.next()?,
+            };
+            match item.as_rule() {
+                Rule::value => {
+                    self.span = item.as_span();
+                    return Some(item);
+                }
+                Rule::dict_elem => self.dict_elem = Some(item.into_inner()),
+                Rule::tuple_comma => {
+                    if let Collection::Parenthesized(_, trailing_comma) = &mut self.collection {
+                        *trailing_comma = true;
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+/// Returns the items of the collection passed to a `frozenset()` or
+/// `OrderedDict()` call, or no items if the call has no argument.
+fn call_arg_items(call: Pair<'_, Rule>) -> Pairs<'_, Rule> {
+    let mut args = call.into_inner();
+    match args.next() {
+        Some(arg) => arg.into_inner(),
+        None => args,
+    }
+}
+
+/// Returns whether the rule is for a collection parsed by `parse_collection`.
+fn is_collection(rule: Rule) -> bool {
+    matches!(
+        rule,
+        Rule::parenthesized
+            | Rule::list
+            | Rule::set
+            | Rule::dict
+            | Rule::frozenset
+            | Rule::ordered_dict
+    )
+}
+
+/// Parses a tuple (or a value in redundant parentheses), list, set, dict,
+/// `frozenset()` call, or `OrderedDict()` call.
+///
+/// Nested collections are parsed with an explicit stack instead of
+/// recursively, so deeply nested input can't overflow the call stack.
+fn parse_collection(pair: Pair<'_, Rule>, opts: &ParseOptions) -> Result<Value, ParseError> {
+    let mut stack = vec![Frame::new(pair, opts)?];
+    let mut done = None;
+    while let Some(frame) = stack.last_mut() {
+        opts.check_cancelled()?;
+        if let Some(value) = done.take() {
+            frame
+                .collection
+                .push(value, frame.span, &mut frame.index, opts)?;
+            opts.report_progress(frame.span.end());
+        }
+        match frame.next_value() {
+            Some(value) => {
+                let (inner,) = parse_pairs_as!(value.into_inner(), (_,));
+                if is_collection(inner.as_rule()) {
+                    stack.push(Frame::new(inner, opts)?);
+                } else {
+                    done = Some(parse_non_collection(inner, opts)?);
+                }
+            }
+            None => done = Some(stack.pop().unwrap().collection.finish()),
+        }
+    }
+    Ok(done.unwrap())
+}
+
+/// Element of a set or key of a dict, for the parse options which depend on
+/// comparing and hashing them.
+pub(crate) trait Element {
+    /// Returns whether the element would be hashable in Python.
+    fn is_hashable(&self) -> bool;
+
+    /// Returns whether the elements would compare equal in Python.
+    fn python_eq(&self, other: &Self) -> bool;
+
+    /// Hashes the element like `compare::python_hash`, so that elements which
+    /// would compare equal in Python hash equal.
+    fn python_hash(&self, depth: usize, state: &mut dyn Hasher);
+
+    /// Formats the element for error messages.
+    fn describe(&self) -> String;
+}
+
+impl Element for Value {
+    fn is_hashable(&self) -> bool {
+        match self {
+            Value::List(_) | Value::Dict(_) | Value::Set(_) | Value::ByteArray(_) => false,
+            Value::Tuple(tup) => tup.iter().all(Element::is_hashable),
+            _ => true,
+        }
+    }
+
+    fn python_eq(&self, other: &Value) -> bool {
+        compare::python_eq(self, other)
+    }
+
+    fn python_hash(&self, depth: usize, state: &mut dyn Hasher) {
+        compare::python_hash(self, depth, state)
+    }
+
+    fn describe(&self) -> String {
+        self.format_ascii()
+            .unwrap_or_else(|_| format!("{:?}", self))
+    }
+}
+
+/// Positions of the elements of a set (or keys of a dict) by their hash, so
+/// that an equal element can be found by comparing only those with the same
+/// hash.
+#[derive(Debug, Default)]
+pub(crate) struct ElementIndex(HashMap<u64, Vec<usize>>);
+
+impl ElementIndex {
+    /// Levels of nested tuples and lists whose elements are hashed.
+    const HASH_DEPTH: usize = 4;
+
+    /// Returns the positions of the elements with the same hash as `elem`,
+    /// to which the position of `elem` should be added if it's inserted.
+    fn bucket<T: Element>(&mut self, elem: &T) -> &mut Vec<usize> {
+        let mut hasher = DefaultHasher::new();
+        elem.python_hash(Self::HASH_DEPTH, &mut hasher);
+        self.0.entry(hasher.finish()).or_default()
+    }
+}
+
+/// Adds an element to a set, checking and deduplicating it according to the
+/// parse options. `index` must contain the elements of `set`, if
+/// deduplicating.
+pub(crate) fn push_set_element<T: Element>(
+    set: &mut Vec<T>,
+    index: &mut ElementIndex,
+    elem: T,
+    span: pest::Span<'_>,
+    opts: &ParseOptions,
+) -> Result<(), ParseError> {
+    if opts.strict && !elem.is_hashable() {
+        return Err(syntax_error(span, "unhashable set element"));
+    }
+    if opts.deduplicate_sets {
+        let bucket = index.bucket(&elem);
+        if bucket.iter().any(|&i| set[i].python_eq(&elem)) {
+            return Ok(());
+        }
+        bucket.push(set.len());
+    }
+    set.push(elem);
+    Ok(())
+}
+
+fn parse_bytearray(call: Pair<'_, Rule>, opts: &ParseOptions) -> Result<Vec<u8>, ParseError> {
+    debug_assert_eq!(call.as_rule(), Rule::bytearray);
+    if !opts.bytearrays {
+        return Err(syntax_error(
+            call.as_span(),
+            "`bytearray()` calls are supported only if enabled in the parse options",
+        ));
+    }
+    match call.into_inner().next() {
+        Some(bytes) => parse_bytes(bytes, opts).map(Cow::into_owned),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn parse_empty_call(call: Pair<'_, Rule>, opts: &ParseOptions) -> Result<Value, ParseError> {
+    debug_assert_eq!(call.as_rule(), Rule::empty_call);
+    if !opts.empty_calls {
+        return Err(syntax_error(
+            call.as_span(),
+            "constructor calls are supported only if enabled in the parse options",
+        ));
+    }
+    let (name,) = parse_pairs_as!(call.into_inner(), (Rule::empty_call_name,));
+    Ok(match name.as_str() {
+        "set" => Value::Set(Vec::new()),
+        "dict" => Value::Dict(Vec::new()),
+        "list" => Value::List(Vec::new()),
+        "tuple" => Value::Tuple(Vec::new()),
+        _ => unreachable!(),
+    })
+}
+
+/// Parses comma-separated values as a tuple, unless there is only a single
+/// value without a trailing comma, in which case the value is returned
+/// as-is.
+pub(crate) fn parse_comma_separated<'i>(
+    pairs: impl Iterator<Item = Pair<'i, Rule>>,
+    opts: &ParseOptions,
+) -> Result<Value, ParseError> {
+    let mut values = Vec::new();
+    let mut trailing_comma = false;
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::value => {
+                opts.check_cancelled()?;
+                opts.check_elements(values.len(), pair.as_span())?;
+                let end = pair.as_span().end();
+                values.push(parse_value(pair, opts)?);
+                opts.report_progress(end);
+            }
+            Rule::tuple_comma => trailing_comma = true,
+            Rule::EOI => (),
+            _ => unreachable!(),
+        }
+    }
+    if values.len() == 1 && !trailing_comma {
+        Ok(values.pop().unwrap())
+    } else {
+        Ok(Value::Tuple(values))
+    }
+}
+
+/// Adds an item to a dict, checking the key and handling duplicate keys
+/// according to the parse options. `index` must contain the keys of `dict`,
+/// unless keeping all duplicate keys.
+pub(crate) fn insert_dict_item<K: Element, V>(
+    dict: &mut Vec<(K, V)>,
+    index: &mut ElementIndex,
+    key: K,
+    value: V,
+    key_span: pest::Span<'_>,
+    opts: &ParseOptions,
+) -> Result<(), ParseError> {
+    if opts.strict && !key.is_hashable() {
+        return Err(syntax_error(key_span, "unhashable dict key"));
+    }
+    if opts.duplicate_keys != DuplicateKeys::KeepAll {
+        let bucket = index.bucket(&key);
+        if let Some(&i) = bucket.iter().find(|&&i| dict[i].0.python_eq(&key)) {
+            if opts.duplicate_keys == DuplicateKeys::Error {
+                return Err(ParseError::DuplicateKey(
+                    key.describe(),
+                    key_span.start()..key_span.end(),
+                ));
+            }
+            dict[i].1 = value;
+            return Ok(());
+        }
+        bucket.push(dict.len());
+    }
+    dict.push((key, value));
+    Ok(())
+}
+
+#[cfg(feature = "bigdecimal")]
+fn parse_decimal(call: Pair<'_, Rule>, opts: &ParseOptions) -> Result<Value, ParseError> {
+    debug_assert_eq!(call.as_rule(), Rule::decimal);
+    let span = call.as_span();
+    if !opts.decimals {
+        return Err(syntax_error(
+            span,
+            "`Decimal()` calls are supported only if enabled in the parse options",
+        ));
+    }
+    let (arg,) = parse_pairs_as!(call.into_inner(), (Rule::string,));
+    let arg = parse_string(arg, opts)?;
+    let digits: String = arg.trim().chars().filter(|&c| c != '_').collect();
+    digits
+        .parse::<bigdecimal::BigDecimal>()
+        .map(Value::Decimal)
+        .map_err(|_| syntax_error(span, "invalid or unsupported `Decimal()` argument"))
+}
+
+#[cfg(not(feature = "bigdecimal"))]
+fn parse_decimal(call: Pair<'_, Rule>, _opts: &ParseOptions) -> Result<Value, ParseError> {
+    debug_assert_eq!(call.as_rule(), Rule::decimal);
+    Err(syntax_error(
+        call.as_span(),
+        "`Decimal()` calls require the `bigdecimal` feature",
+    ))
+}
+
+#[cfg(feature = "num-rational")]
+fn parse_rational(call: Pair<'_, Rule>, opts: &ParseOptions) -> Result<Value, ParseError> {
+    debug_assert_eq!(call.as_rule(), Rule::rational);
+    let span = call.as_span();
+    if !opts.fractions {
+        return Err(syntax_error(
+            span,
+            "`Fraction()` calls are supported only if enabled in the parse options",
+        ));
+    }
+    let mut parts = Vec::with_capacity(2);
+    for arg in call.into_inner() {
+        let arg_span = arg.as_span();
+        match parse_number_expr(arg, opts)? {
+            Value::Integer(int) => parts.push(int),
+            _ => {
+                return Err(syntax_error(
+                    arg_span,
+                    "`Fraction()` arguments must be integers",
+                ))
+            }
+        }
+    }
+    let denom = if parts.len() == 2 {
+        parts.pop().unwrap()
+    } else {
+        numb::BigInt::from(1)
+    };
+    let numer = parts.pop().unwrap();
+    if denom == numb::BigInt::from(0) {
+        return Err(syntax_error(span, "`Fraction()` denominator is zero"));
+    }
+    Ok(Value::Rational(num_rational::BigRational::new(
+        numer, denom,
+    )))
+}
+
+#[cfg(not(feature = "num-rational"))]
+fn parse_rational(call: Pair<'_, Rule>, _opts: &ParseOptions) -> Result<Value, ParseError> {
+    debug_assert_eq!(call.as_rule(), Rule::rational);
+    Err(syntax_error(
+        call.as_span(),
+        "`Fraction()` calls require the `num-rational` feature",
+    ))
+}
+
+fn parse_datetime(call: Pair<'_, Rule>, opts: &ParseOptions) -> Result<Value, ParseError> {
+    debug_assert_eq!(call.as_rule(), Rule::datetime);
+    let span = call.as_span();
+    if !opts.datetimes {
+        return Err(syntax_error(
+            span,
+            "`datetime` calls are supported only if enabled in the parse options",
+        ));
+    }
+    let mut pairs = call.into_inner();
+    let is_date = !pairs.next().unwrap().as_str().ends_with("datetime");
+    let mut args = Vec::with_capacity(7);
+    for arg in pairs {
+        let arg_span = arg.as_span();
+        match parse_number_expr(arg, opts)?
+            .as_integer()
+            .and_then(|int| int.to_i32())
+        {
+            Some(int) => args.push(int),
+            None => return Err(syntax_error(arg_span, "expected an integer")),
+        }
+    }
+    let (min_args, max_args) = if is_date { (3, 3) } else { (3, 7) };
+    if args.len() < min_args || args.len() > max_args {
+        return Err(syntax_error(span, "wrong number of arguments"));
+    }
+    args.resize(7, 0);
+    let small = |n: i32| u8::try_from(n).unwrap_or(std::u8::MAX);
+    let out_of_range = || syntax_error(span, "date or time out of range");
+    let date = Date::new(args[0], small(args[1]), small(args[2])).ok_or_else(out_of_range)?;
+    if is_date {
+        return Ok(Value::Date(date));
+    }
+    let microsecond = u32::try_from(args[6]).unwrap_or(std::u32::MAX);
+    DateTime::new(
+        date,
+        small(args[3]),
+        small(args[4]),
+        small(args[5]),
+        microsecond,
+    )
+    .map(Value::DateTime)
+    .ok_or_else(out_of_range)
 }
 
 fn parse_boolean(b: Pair<'_, Rule>) -> bool {
@@ -334,22 +2656,54 @@ fn parse_boolean(b: Pair<'_, Rule>) -> bool {
 
 /// NumPy uses [`ast.literal_eval()`] to parse the header dictionary.
 /// `literal_eval()` supports only the following Python literals: strings,
-/// bytes, numbers, tuples, lists, dicts, sets, booleans, and `None`.
+/// bytes, numbers, tuples, lists, dicts, sets, booleans, `None`, and
+/// `Ellipsis` (`...`).
 ///
 /// [`ast.literal_eval()`]: https://docs.python.org/3/library/ast.html#ast.literal_eval
-fn parse_value(value: Pair<'_, Rule>) -> Result<Value, ParseError> {
+pub(crate) fn parse_value(value: Pair<'_, Rule>, opts: &ParseOptions) -> Result<Value, ParseError> {
     debug_assert_eq!(value.as_rule(), Rule::value);
     let (inner,) = parse_pairs_as!(value.into_inner(), (_,));
+    if is_collection(inner.as_rule()) {
+        parse_collection(inner, opts)
+    } else {
+        parse_non_collection(inner, opts)
+    }
+}
+
+/// Parses the contents of a `value` pair which isn't a collection parsed by
+/// `parse_collection`.
+fn parse_non_collection(inner: Pair<'_, Rule>, opts: &ParseOptions) -> Result<Value, ParseError> {
     match inner.as_rule() {
-        Rule::string => Ok(Value::String(parse_string(inner)?)),
-        Rule::bytes => Ok(Value::Bytes(parse_bytes(inner)?)),
-        Rule::number_expr => parse_number_expr(inner),
-        Rule::tuple => Ok(Value::Tuple(parse_seq(inner)?)),
-        Rule::list => Ok(Value::List(parse_seq(inner)?)),
-        Rule::dict => Ok(Value::Dict(parse_dict(inner)?)),
-        Rule::set => Ok(Value::Set(parse_seq(inner)?)),
-        Rule::boolean => Ok(Value::Boolean(parse_boolean(inner))),
+        Rule::string | Rule::string_concat => {
+            Ok(Value::String(parse_string_value(inner, opts)?.into_owned()))
+        }
+        Rule::bytes | Rule::bytes_concat => {
+            Ok(Value::Bytes(parse_bytes_value(inner, opts)?.into_owned()))
+        }
+        Rule::number_expr => {
+            if opts.strict && !NumberShape::of(&inner).is_literal_eval_compatible() {
+                return Err(syntax_error(
+                    inner.as_span(),
+                    "unsupported numeric expression in strict mode",
+                ));
+            }
+            parse_number_expr(inner, opts)
+        }
+        Rule::bytearray => Ok(Value::ByteArray(parse_bytearray(inner, opts)?)),
+        Rule::empty_call => parse_empty_call(inner, opts),
+        Rule::decimal => parse_decimal(inner, opts),
+        Rule::rational => parse_rational(inner, opts),
+        Rule::datetime => parse_datetime(inner, opts),
         Rule::none => Ok(Value::None),
+        Rule::ellipsis => {
+            if opts.dialect == Dialect::Py27 {
+                return Err(syntax_error(
+                    inner.as_span(),
+                    "`...` outside of a slice requires Python 3",
+                ));
+            }
+            Ok(Value::Ellipsis)
+        }
         _ => unreachable!(),
     }
 }
@@ -359,12 +2713,34 @@ fn int_to_f64(int: numb::BigInt) -> Result<f64, ParseError> {
         .ok_or_else(|| ParseError::NumericCast(format!("{}", int), "f64".into()))
 }
 
-/// Adds two numbers.
+/// Converts a boolean to an integer, like Python's `int()`. Other values are
+/// returned unchanged.
+fn bool_to_int(value: Value) -> Value {
+    match value {
+        Value::Boolean(b) => Value::Integer(u8::from(b).into()),
+        value => value,
+    }
+}
+
+/// Negates a number. Booleans are converted to integers.
+///
+/// **Panics** if the argument is not a number.
+fn neg_number(value: Value) -> Value {
+    use self::Value::*;
+    match bool_to_int(value) {
+        Integer(int) => Integer(-int),
+        Float(float) => Float(-float),
+        Complex(comp) => Complex(-comp),
+        _ => unimplemented!(),
+    }
+}
+
+/// Adds two numbers. Booleans are converted to integers.
 ///
 /// **Panics** if either of the arguments is not a number.
 fn add_numbers(lhs: Value, rhs: Value) -> Result<Value, ParseError> {
     use self::Value::*;
-    match (lhs, rhs) {
+    match (bool_to_int(lhs), bool_to_int(rhs)) {
         (Integer(int1), Integer(int2)) => Ok(Integer(int1 + int2)),
         (Float(float1), Float(float2)) => Ok(Float(float1 + float2)),
         (Complex(comp1), Complex(comp2)) => Ok(Complex(comp1 + comp2)),
@@ -379,12 +2755,12 @@ fn add_numbers(lhs: Value, rhs: Value) -> Result<Value, ParseError> {
     }
 }
 
-/// Subtracts two numbers.
+/// Subtracts two numbers. Booleans are converted to integers.
 ///
 /// **Panics** if either of the arguments is not a number.
 fn sub_numbers(lhs: Value, rhs: Value) -> Result<Value, ParseError> {
     use self::Value::*;
-    match (lhs, rhs) {
+    match (bool_to_int(lhs), bool_to_int(rhs)) {
         (Integer(int1), Integer(int2)) => Ok(Integer(int1 - int2)),
         (Integer(int), Float(float)) => Ok(Float(int_to_f64(int)? - float)),
         (Integer(int), Complex(comp)) => Ok(Complex(int_to_f64(int)? - comp)),
@@ -403,19 +2779,386 @@ mod test {
     use super::*;
 
     #[test]
-    fn parse_string_example() {
+    fn parse_string_example() {
+        for &(input, correct) in &[
+            ("''", ""),
+            (
+                r#"'he\qllo\th\03o\x1bw\
+a\n\rre\a\'\"y\u1234o\U00031234u'"#,
+                "he\\qllo\th\x03o\x1bwa\n\rre\x07'\"y\u{1234}o\u{31234}u",
+            ),
+        ] {
+            let mut parsed = Parser::parse(Rule::string, input)
+                .unwrap_or_else(|err| panic!("failed to parse: {}", err));
+            let s = parse_string(
+                parse_pairs_as!(parsed, (Rule::string,)).0,
+                &ParseOptions::default(),
+            )
+            .unwrap();
+            assert_eq!(s, correct);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "unicode_names2")]
+    fn parse_string_name_escape_example() {
+        let value: Value = r"'\N{GREEK SMALL LETTER ALPHA}\N{snowman}'"
+            .parse()
+            .unwrap();
+        assert_eq!(value, Value::String("\u{3b1}\u{2603}".into()));
+        match r"'\N{NOT A CHARACTER NAME}'".parse::<Value>() {
+            Err(ParseError::IllegalEscapeSequence(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_surrogate_escapes_example() {
+        use self::SurrogateEscapes::*;
+        for &(input, policy, correct) in &[
+            (r"'\ud83d\ude00'", Error, None),
+            (r"'\ud83d\ude00'", JoinPairs, Some("\u{1F600}")),
+            (r"'a\U0000D83D\uDE00b'", JoinPairs, Some("a\u{1F600}b")),
+            (r"'\ud83d'", JoinPairs, None),
+            (r"'\ude00\ud83d'", JoinPairs, None),
+            (r"'\ud83d\ude00'", Replace, Some("\u{1F600}")),
+            (r"'\ude00\ud83d x'", Replace, Some("\u{FFFD}\u{FFFD} x")),
+            (r"'\ud83d\ud83d\ude00'", Replace, Some("\u{FFFD}\u{1F600}")),
+            (r"'\ud83d\\ude00'", Replace, Some("\u{FFFD}\\ude00")),
+        ] {
+            let opts = ParseOptions::new().surrogate_escapes(policy);
+            match Value::parse_with_options(input, &opts) {
+                Ok(Value::String(s)) => assert_eq!(Some(&s[..]), correct, "{:?}", input),
+                Err(ParseError::IllegalEscapeSequence(_)) => {
+                    assert!(correct.is_none(), "{:?}", input)
+                }
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_line_breaks_example() {
+        use self::Value::*;
+        for &(input, ref correct) in &[
+            ("'a\\\r\nb'", String("ab".into())),
+            ("'''a\r\nb\rc'''", String("a\nb\nc".into())),
+            ("'''a\\r\r\n'''", String("a\r\n".into())),
+            ("r'a\\\r\nb'", String("a\\\nb".into())),
+            ("b'''a\r\n'''", Bytes(b"a\n".to_vec())),
+            ("rb'''a\r\n'''", Bytes(b"a\n".to_vec())),
+            (
+                "[1,\x0c2]",
+                List(vec![Integer(1.into()), Integer(2.into())]),
+            ),
+        ] {
+            for &comments in &[false, true] {
+                let opts = ParseOptions::new().comments(comments);
+                let value = Value::parse_with_options(input, &opts).unwrap();
+                assert_eq!(&value, correct, "{:?}", input);
+            }
+        }
+        let opts = ParseOptions::new().comments(true);
+        let value = Value::parse_with_options("(1,\r\n 'a\\\r\n')", &opts).unwrap();
+        assert_eq!(value.to_string(), "(1, 'a')");
+    }
+
+    #[test]
+    fn parse_string_hook_example() {
+        use std::collections::HashSet;
+        use std::sync::Mutex;
+        let seen = Arc::new(Mutex::new(HashSet::new()));
+        let opts = ParseOptions::new().float_calls(true).string_hook({
+            let seen = seen.clone();
+            move |s| {
+                seen.lock().unwrap().insert(s.to_string());
+                None
+            }
+        });
+        let input = "[{'a': 1, 'b': 2}, {'a': float('inf')}, (u'c\\x41',)]";
+        Value::parse_with_options(input, &opts).unwrap();
+        let mut seen: Vec<_> = seen.lock().unwrap().iter().cloned().collect();
+        seen.sort();
+        assert_eq!(seen, ["a", "b", "cA"]);
+        let opts =
+            ParseOptions::new().string_hook(|s| if s == "x" { Some("y".into()) } else { None });
+        let value = Value::parse_with_options("{'x': 'z'}", &opts).unwrap();
+        assert_eq!(value.to_string(), "{'y': 'z'}");
+    }
+
+    #[test]
+    fn parse_string_prefix_example() {
+        for &(input, correct) in &[
+            ("'''it's'''", "it's"),
+            (r"u'a\tb'", "a\tb"),
+            (r"r'\d+\''", r"\d+\'"),
+            (r#"R"""a\"""b""""#, r#"a\"""b"#),
+        ] {
+            let value: Value = input.parse().unwrap();
+            assert_eq!(value, Value::String(correct.into()));
+        }
+    }
+
+    #[test]
+    fn parse_python2_example() {
+        use self::Value::*;
+        let py2 = ParseOptions::new().dialect(Dialect::Py27);
+        for &(input, ref correct) in &[
+            ("123L", Integer(123.into())),
+            ("0xffl", Integer(255.into())),
+            ("-0755", Integer((-0o755).into())),
+            ("0L", Integer(0.into())),
+            (r"ur'\d'", String(r"\d".into())),
+        ] {
+            assert_eq!(Value::parse_with_options(input, &py2).unwrap(), *correct);
+            assert!(input.parse::<Value>().is_err());
+        }
+        match Value::parse_with_options("089", &py2) {
+            Err(ParseError::LegacyOctalInteger(_, _)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_dialect_example() {
+        use self::Dialect::*;
+        for &(input, accepted_by) in &[
+            ("0755", &[Py27][..]),
+            ("br'a'", &[Py27, Py35, Py312, Latest][..]),
+            ("rb'a'", &[Py35, Py312, Latest][..]),
+            ("(1, ...)", &[Py35, Py312, Latest][..]),
+            ("1_000", &[Py312, Latest][..]),
+            ("0x_ff + 1.5_0j", &[Py312, Latest][..]),
+        ] {
+            for &dialect in &[Py27, Py35, Py312, Latest] {
+                let opts = ParseOptions::new().dialect(dialect);
+                assert_eq!(
+                    Value::parse_with_options(input, &opts).is_ok(),
+                    accepted_by.contains(&dialect),
+                    "{} in {:?}",
+                    input,
+                    dialect,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parse_inf_nan_example() {
+        use self::Value::*;
+        let opts = ParseOptions::new().inf_nan(true);
+        for &(input, ref correct) in &[
+            ("inf", Float(std::f64::INFINITY)),
+            ("-inf", Float(std::f64::NEG_INFINITY)),
+            (
+                "[1., inf, -inf]",
+                List(vec![
+                    Float(1.),
+                    Float(std::f64::INFINITY),
+                    Float(std::f64::NEG_INFINITY),
+                ]),
+            ),
+        ] {
+            assert_eq!(Value::parse_with_options(input, &opts).unwrap(), *correct);
+            assert!(input.parse::<Value>().is_err());
+        }
+        match Value::parse_with_options("(nan, -nan)", &opts).unwrap() {
+            Tuple(tup) => assert!(tup.iter().all(|v| v.as_float().unwrap().is_nan())),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(Value::parse_with_options("info", &opts).is_err());
+        assert!(Value::parse_with_options("Inf", &opts).is_err());
+    }
+
+    #[test]
+    fn parse_float_call_example() {
+        use self::Value::*;
+        let opts = ParseOptions::new().float_calls(true);
+        for &(input, ref correct) in &[
+            ("float('inf')", Float(std::f64::INFINITY)),
+            ("float(\"-inf\")", Float(std::f64::NEG_INFINITY)),
+            ("-float( ' +Infinity\\n' )", Float(std::f64::NEG_INFINITY)),
+            (
+                "{'a': float('inf'), 'b': 1}",
+                Dict(vec![
+                    (String("a".into()), Float(std::f64::INFINITY)),
+                    (String("b".into()), Integer(1.into())),
+                ]),
+            ),
+        ] {
+            assert_eq!(Value::parse_with_options(input, &opts).unwrap(), *correct);
+            assert!(input.parse::<Value>().is_err());
+        }
+        for input in &["float('nan')", "float('-NaN')"] {
+            let parsed = Value::parse_with_options(input, &opts).unwrap();
+            assert!(parsed.as_float().unwrap().is_nan());
+        }
+        assert!(Value::parse_with_options("float('1.5')", &opts).is_err());
+        assert!(Value::parse_with_options("float(1)", &opts).is_err());
+    }
+
+    #[test]
+    fn parse_hex_float_call() {
+        let opts = ParseOptions::new().hex_floats(true);
         for &(input, correct) in &[
-            ("''", ""),
+            ("float.fromhex('0x1.8p+1')", 3.),
+            ("float.fromhex('0X1P-2')", 0.25),
+            ("-float.fromhex(' -1.8 ')", 1.5),
+            ("float.fromhex('0x.1')", 0.0625),
+            ("float.fromhex('a.')", 10.),
+            ("float.fromhex('0x1.999999999999ap-4')", 0.1),
+            ("float.fromhex('0x0.0000000000001p-1022')", 5e-324),
+            ("float.fromhex('0x1p-1075')", 0.),
+            ("float.fromhex('0x1.0000000000001p-1075')", 5e-324),
+            ("float.fromhex('0x3p-1075')", 1e-323),
+            ("float.fromhex('0x3p-1076')", 5e-324),
+            ("float.fromhex('0x1.fffffffffffffp+1023')", std::f64::MAX),
+            ("float.fromhex('0x1.00000000000008p0')", 1.),
             (
-                r#"'he\qllo\th\03o\x1bw\
-a\n\rre\a\'\"y\u1234o\U00031234u'"#,
-                "he\\qllo\th\x03o\x1bwa\n\rre\x07'\"y\u{1234}o\u{31234}u",
+                "float.fromhex('0x1.000000000000080000000001p0')",
+                1. + std::f64::EPSILON,
+            ),
+            (
+                "float.fromhex('0x1.00000000000018p0')",
+                1. + 2. * std::f64::EPSILON,
             ),
+            ("float.fromhex('0x10000000000000000000p-76')", 1.),
+            ("float.fromhex('0x0.000000000000000000001p84')", 1.),
+            ("float.fromhex('-inf')", std::f64::NEG_INFINITY),
         ] {
-            let mut parsed = Parser::parse(Rule::string, input)
-                .unwrap_or_else(|err| panic!("failed to parse: {}", err));
-            let s = parse_string(parse_pairs_as!(parsed, (Rule::string,)).0).unwrap();
-            assert_eq!(s, correct);
+            let parsed = Value::parse_with_options(input, &opts).unwrap();
+            assert_eq!(parsed.as_float().unwrap().to_bits(), correct.to_bits());
+        }
+        for input in &[
+            "float.fromhex('0x1p+1024')",
+            "float.fromhex('0x1.fffffffffffff8p+1023')",
+            "float.fromhex('0x')",
+            "float.fromhex('1p')",
+            "float.fromhex('1_0')",
+            "float.fromhex('0x1.8p+1')",
+        ] {
+            let opts = if input.contains("8p+1") {
+                ParseOptions::new().float_calls(true)
+            } else {
+                opts.clone()
+            };
+            assert!(Value::parse_with_options(input, &opts).is_err());
+        }
+        assert!(Value::parse_with_options("float('inf')", &opts).is_err());
+    }
+
+    #[test]
+    fn parse_complex_call_example() {
+        use self::Value::*;
+        let opts = ParseOptions::new().complex_calls(true);
+        for &(input, correct) in &[
+            ("complex()", (0., 0.)),
+            ("complex(1)", (1., 0.)),
+            ("complex(1, 2)", (1., 2.)),
+            ("complex(-1.5, -2,)", (-1.5, -2.)),
+            ("complex(1+1j, 2j)", (-1., 1.)),
+            ("-complex(1, 2) + 3", (2., -2.)),
+        ] {
+            let correct = Complex(numc::Complex::new(correct.0, correct.1));
+            assert_eq!(Value::parse_with_options(input, &opts).unwrap(), correct);
+            assert!(input.parse::<Value>().is_err());
+        }
+        assert!(Value::parse_with_options("complex('1+2j')", &opts).is_err());
+        assert!(Value::parse_with_options("complex(1, 2, 3)", &opts).is_err());
+    }
+
+    #[test]
+    fn parse_comments_example() {
+        use self::Value::*;
+        let opts = ParseOptions::new().comments(true);
+        let input = "# Header\n{\n    'a': 1,  # the '#' is kept below\n    'b#': '#',\n}\n";
+        assert_eq!(
+            Value::parse_with_options(input, &opts).unwrap(),
+            Dict(vec![
+                (String("a".into()), Integer(1.into())),
+                (String("b#".into()), String("#".into())),
+            ]),
+        );
+        assert!(input.parse::<Value>().is_err());
+        assert!("1 # one".parse::<Value>().is_err());
+        assert!(Value::parse_with_options("'a # b", &opts).is_err());
+    }
+
+    #[test]
+    fn parse_strict_example() {
+        let strict = ParseOptions::new().strict(true);
+        for input in &[
+            "1",
+            "-1",
+            "+1.5",
+            "-(1)",
+            "(-1)",
+            "1 + 2j",
+            "-1.5 - 2j",
+            "(1) + (2j)",
+            "((1 + 2j))",
+            "True",
+            "(True,)",
+            "{(1, 'a'): {1, (2, b'')}}",
+            "...",
+        ] {
+            assert!(
+                Value::parse_with_options(input, &strict).is_ok(),
+                "{}",
+                input
+            );
+        }
+        for input in &[
+            "1 + 2",
+            "--1",
+            "-(-1)",
+            "1j + 2",
+            "1 + -2j",
+            "1 + 2j + 3j",
+            "-(1 + 2j)",
+            "True + 1",
+            "-True",
+            "1 + 2j - 3",
+            "{[1]}",
+            "{(1, [2])}",
+            "{{}: 1}",
+        ] {
+            assert!(input.parse::<Value>().is_ok(), "{}", input);
+            assert!(
+                Value::parse_with_options(input, &strict).is_err(),
+                "{}",
+                input
+            );
+        }
+        let lenient = ParseOptions::new().inf_nan(true).comments(true);
+        assert!(Value::parse_with_options("inf # c", &lenient).is_ok());
+        assert!(Value::parse_with_options("inf # c", &lenient.strict(true)).is_err());
+        let trimmed = ParseOptions::new().trim(true).strict(true);
+        assert!(Value::parse_with_options("\u{feff} 1\n", &trimmed).is_ok());
+        assert!(Value::parse_with_options("\u{feff} 1\n", &strict).is_err());
+        let py27 = ParseOptions::new().dialect(Dialect::Py27).strict(true);
+        assert_eq!(
+            Value::parse_with_options("0755", &py27).unwrap(),
+            Value::Integer(493.into()),
+        );
+        assert!(Value::parse_with_options("0755", &strict).is_err());
+        for (limited, input) in vec![
+            (strict.clone().max_depth(1), "[[1]]"),
+            (strict.clone().max_length(2), "'abc'"),
+            (strict.clone().max_elements(2), "[1, 2, 3]"),
+            (strict.clone().max_nodes(2), "[1, 2]"),
+            (strict.clone().max_int_digits(2), "123"),
+        ] {
+            assert!(
+                Value::parse_with_options(input, &strict).is_ok(),
+                "{}",
+                input
+            );
+            assert!(
+                Value::parse_with_options(input, &limited).is_err(),
+                "{}",
+                input
+            );
         }
     }
 
@@ -428,44 +3171,251 @@ a\n\rre\a\'\"y\u1234o\U00031234u'"#,
 a\n\rre\a\'\"y\u1234o\U00031234u'"#,
                 &b"he\\qllo\th\x03o\x1bwa\n\rre\x07'\"y\\u1234o\\U00031234u"[..],
             ),
+            ("B'''it's'''", &b"it's"[..]),
+            (r"rb'\x00\d+'", &br"\x00\d+"[..]),
+            (r#"bR"\"\n""#, &br#"\"\n"#[..]),
+            ("Rb'''a\\\nb'c'''", &b"a\\\nb'c"[..]),
         ] {
             let mut parsed = Parser::parse(Rule::bytes, input)
                 .unwrap_or_else(|err| panic!("failed to parse: {}", err));
-            let bytes = parse_bytes(parse_pairs_as!(parsed, (Rule::bytes,)).0).unwrap();
+            let bytes = parse_bytes(
+                parse_pairs_as!(parsed, (Rule::bytes,)).0,
+                &ParseOptions::default(),
+            )
+            .unwrap();
             assert_eq!(bytes, correct);
         }
     }
 
+    #[test]
+    fn parse_concatenated_example() {
+        for &(input, ref correct) in &[
+            ("'a' 'b'", Value::String("ab".into())),
+            ("'a''b' r'\\c'", Value::String("ab\\c".into())),
+            ("b'a' b'b'", Value::Bytes(b"ab".to_vec())),
+            (
+                "['a'\n 'b', 'c']",
+                Value::List(vec![Value::String("ab".into()), Value::String("c".into())]),
+            ),
+            ("('a'\n 'b')", Value::String("ab".into())),
+        ] {
+            let opts = ParseOptions::new().comments(true);
+            let value = Value::parse_with_options(input, &opts).unwrap();
+            assert_eq!(&value, correct, "{:?}", input);
+        }
+        assert!("'a' b'b'".parse::<Value>().is_err());
+        assert!("'a'\n'b'".parse::<Value>().is_err());
+        let opts = ParseOptions::new().max_length(3);
+        assert!(Value::parse_with_options("'ab' 'c'", &opts).is_ok());
+        match Value::parse_with_options("'ab' 'cd'", &opts) {
+            Err(ParseError::LengthLimitExceeded(span)) => assert_eq!(span, 0..9),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
     #[test]
     fn parse_number_expr_example() {
         let input = "+-23 + 4.5 -+- -5j - 3e2 + 1.2 - 9";
         let mut parsed = Parser::parse(Rule::number_expr, input)
             .unwrap_or_else(|err| panic!("failed to parse: {}", err));
-        let expr = parse_number_expr(parse_pairs_as!(parsed, (Rule::number_expr,)).0).unwrap();
+        let expr = parse_number_expr(
+            parse_pairs_as!(parsed, (Rule::number_expr,)).0,
+            &ParseOptions::default(),
+        )
+        .unwrap();
         assert_eq!(
             expr,
             Value::Complex(-23. + 4.5 - numc::Complex::new(0., 5.) - 3e2 + 1.2 - 9.)
         );
     }
 
+    #[test]
+    fn parse_boolean_arithmetic_example() {
+        use self::Value::*;
+        for &(input, ref correct) in &[
+            ("True", Boolean(true)),
+            ("(False)", Boolean(false)),
+            (
+                "[True, (False,)]",
+                List(vec![Boolean(true), Tuple(vec![Boolean(false)])]),
+            ),
+            ("True + 1", Integer(2.into())),
+            ("1 - True - True", Integer((-1).into())),
+            ("-True", Integer((-1).into())),
+            ("+False", Integer(0.into())),
+            ("True + False", Integer(1.into())),
+            ("(True) + 0.5", Float(1.5)),
+            ("False - 1j", Complex(numc::Complex::new(0., -1.))),
+        ] {
+            let parsed: Value = input
+                .parse()
+                .unwrap_or_else(|err| panic!("failed to parse: {}", err));
+            assert_eq!(parsed, *correct);
+        }
+        let neg_zero = "-0.0".parse::<Value>().unwrap().as_float().unwrap();
+        assert!(neg_zero == 0. && neg_zero.is_sign_negative());
+    }
+
     #[test]
     fn parse_integer_example() {
         let inputs = ["0b_1001_0010_1010", "0o44_52", "0x9_2a", "2_346"];
         for input in &inputs {
             let mut parsed = Parser::parse(Rule::integer, input)
                 .unwrap_or_else(|err| panic!("failed to parse: {}", err));
-            let int = parse_integer(parse_pairs_as!(parsed, (Rule::integer,)).0);
+            let int = parse_integer(
+                parse_pairs_as!(parsed, (Rule::integer,)).0,
+                &ParseOptions::default(),
+            )
+            .unwrap();
             assert_eq!(int, numb::BigInt::from(2346));
         }
     }
 
+    #[test]
+    fn parse_zero_integer_example() {
+        for input in &["0", "00", "0_0"] {
+            let value: Value = input.parse().unwrap();
+            assert_eq!(value, Value::Integer(0.into()));
+        }
+    }
+
+    #[test]
+    fn parse_legacy_octal_integer_error() {
+        match "[1, 0_755]".parse::<Value>() {
+            Err(ParseError::LegacyOctalInteger(literal, span)) => {
+                assert_eq!(literal, "0_755");
+                assert_eq!(span, 4..9);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let err = "0755".parse::<Value>().unwrap_err();
+        assert!(format!("{}", err).ends_with("(use an 0o prefix for octal integers: 0o755)"));
+        let err = "089".parse::<Value>().unwrap_err();
+        assert!(!format!("{}", err).contains("0o"));
+    }
+
     #[test]
     fn parse_float_example() {
         let input = "3_51.4_6e-2_7";
         let mut parsed = Parser::parse(Rule::float, input)
             .unwrap_or_else(|err| panic!("failed to parse: {}", err));
-        let float = parse_float(parse_pairs_as!(parsed, (Rule::float,)).0).unwrap();
+        let float = parse_float(
+            parse_pairs_as!(parsed, (Rule::float,)).0,
+            &ParseOptions::default(),
+        )
+        .unwrap();
         assert_eq!(float, 351.46e-27);
+        for &(input, correct) in &[
+            ("1.", 1.),
+            (".5", 0.5),
+            ("1_0.e1", 100.),
+            ("2E+3", 2e3),
+            ("0_1.2_5", 1.25),
+        ] {
+            let value: Value = input.parse().unwrap();
+            assert_eq!(value, Value::Float(correct), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn validate_example() {
+        for input in &[
+            "[1, 2.5, -3j, True + 1, (1,), {'a': {b'b'}}]",
+            r"'\x41\u00b0\U0001F600' b'\377\n'",
+            "[1, 2",
+            "1 2",
+            "0755",
+            "12L",
+            "ur'a'",
+            r"'\U00110000'",
+            r"b'\400'",
+            "inf",
+            "float('nan')",
+            "complex(1, 2)",
+            "frozenset()",
+            "set()",
+            "OrderedDict()",
+            "Decimal('1')",
+            "Fraction(1, 2)",
+            "date(2020, 1, 1)",
+            "{[1]: 2}",
+        ] {
+            assert_eq!(
+                validate(input).is_ok(),
+                input.parse::<Value>().is_ok(),
+                "{:?}",
+                input,
+            );
+        }
+    }
+
+    #[test]
+    fn parse_trim_example() {
+        let opts = ParseOptions::new().trim(true);
+        for &input in &[
+            "\u{feff}[1]",
+            "[1]\r\n",
+            "\n \u{feff}[1]",
+            "\u{feff}\n[1] \n\n",
+        ] {
+            assert!(input.parse::<Value>().is_err(), "{:?}", input);
+            let value = Value::parse_with_options(input, &opts);
+            if input.starts_with('\n') {
+                assert!(value.is_err());
+            } else {
+                assert_eq!(value.unwrap().to_string(), "[1]", "{:?}", input);
+            }
+        }
+        let opts = opts.comments(true);
+        let value = Value::parse_with_options("\u{feff}# c\n[1, # one\n 2]\n", &opts);
+        assert_eq!(value.unwrap().to_string(), "[1, 2]");
+        let spanned = Value::parse_spanned_with_options("\u{feff}\n('a',)", &opts).unwrap();
+        assert_eq!(spanned.span, 4..10);
+    }
+
+    #[test]
+    fn parse_float_overflow_example() {
+        let opts = ParseOptions::new().reject_float_overflow(true);
+        for input in &["1e308", "-1.7e308", "1e-999", "1_000e-3j"] {
+            let lenient = input.parse::<Value>();
+            let strict = Value::parse_with_options(input, &opts);
+            assert_eq!(lenient.is_ok(), strict.is_ok(), "{:?}", input);
+        }
+        assert_eq!(
+            "1e999".parse::<Value>().unwrap(),
+            Value::Float(std::f64::INFINITY)
+        );
+        for &(input, ref span) in &[("1e999", 0..5), ("[-1e999]", 2..7), ("1+1e400j", 2..7)] {
+            match Value::parse_with_options(input, &opts) {
+                Err(ParseError::FloatOverflow(actual)) => assert_eq!(actual, *span),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+        let digits = format!("{}j", "9".repeat(400));
+        assert!(Value::parse_with_options(&digits, &opts).is_err());
+        assert!(Value::parse_with_options("inf", &opts.inf_nan(true)).is_ok());
+    }
+
+    #[test]
+    fn parse_progress_example() {
+        use std::sync::Mutex;
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let opts = ParseOptions::new().progress({
+            let reports = reports.clone();
+            move |done, total| reports.lock().unwrap().push((done, total))
+        });
+        let input = format!("[{}]", vec!["'abcdefgh'"; 20_000].join(", "));
+        Value::parse_with_options(&input, &opts).unwrap();
+        {
+            let reports = reports.lock().unwrap();
+            assert!(reports.len() > 2);
+            assert_eq!(reports[0], (0, input.len()));
+            assert_eq!(*reports.last().unwrap(), (input.len(), input.len()));
+            assert!(reports.windows(2).all(|w| w[0].0 <= w[1].0));
+        }
+        reports.lock().unwrap().clear();
+        assert!(Value::parse_with_options("[1, x]", &opts).is_err());
+        assert_eq!(*reports.lock().unwrap(), [(0, 6)]);
     }
 
     #[test]
@@ -479,11 +3429,122 @@ a\n\rre\a\'\"y\u1234o\U00031234u'"#,
         ] {
             let mut parsed = Parser::parse(Rule::value, input)
                 .unwrap_or_else(|err| panic!("failed to parse: {}", err));
-            let tuple = parse_value(parse_pairs_as!(parsed, (Rule::value,)).0).unwrap();
+            let tuple = parse_value(
+                parse_pairs_as!(parsed, (Rule::value,)).0,
+                &ParseOptions::default(),
+            )
+            .unwrap();
             assert_eq!(tuple, *correct);
         }
     }
 
+    #[test]
+    fn parse_deeply_nested() {
+        // Nesting this deep would overflow the call stack if values were
+        // parsed recursively. The thread's stack just needs to be large
+        // enough for the grammar, which is matched recursively.
+        let test = || {
+            let depth = 10_000;
+            let nest = |open: &str, inner: &str, close: &str| {
+                format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth))
+            };
+            let opts = ParseOptions::new()
+                .frozensets(true)
+                .ordered_dicts(true)
+                .complex_calls(true);
+            let parse = |input: &str| Value::parse_with_options(input, &opts).unwrap();
+
+            let parens = nest("(", "1", ")");
+            assert_eq!(parse(&parens), Value::Integer(1.into()));
+            assert!(validate(&parens).is_ok());
+            let strict = ParseOptions::new().strict(true);
+            assert!(Value::parse_with_options(&parens, &strict).is_ok());
+            let negated = nest("-(", "1", ")");
+            assert_eq!(parse(&negated), Value::Integer(1.into()));
+            assert!(Value::parse_with_options(&negated, &strict).is_err());
+            assert_eq!(parse(&nest("(", "0", "+1)")), Value::Integer(depth.into()));
+            assert_eq!(
+                parse(&nest("complex(", "1", ", 0)")),
+                Value::Complex(numc::Complex::new(1., 0.)),
+            );
+
+            let frozensets = parse(&nest("frozenset({", "None", "})"));
+            let mut value = &frozensets;
+            for _ in 0..depth {
+                value = &value.as_frozenset().unwrap()[0];
+            }
+            assert_eq!(value, &Value::None);
+            let dicts = parse(&nest("OrderedDict({0: ", "None", "})"));
+            let mut value = &dicts;
+            for _ in 0..depth {
+                value = &value.as_dict().unwrap()[0].1;
+            }
+            assert_eq!(value, &Value::None);
+        };
+        std::thread::Builder::new()
+            .stack_size(256 << 20)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn parse_parenthesized_example() {
+        use self::Value::*;
+        for &(input, ref correct) in &[
+            ("(5)", Integer(5.into())),
+            ("( 'a' )", String("a".into())),
+            ("-(5)", Integer((-5).into())),
+            ("(-(1 - 2))", Integer(1.into())),
+            ("(1) + (2j)", Complex(numc::Complex::new(1., 2.))),
+            (
+                "((1, 2))",
+                Tuple(vec![Integer(1.into()), Integer(2.into())]),
+            ),
+            (
+                "[(1), ((2,)), (None)]",
+                List(vec![
+                    Integer(1.into()),
+                    Tuple(vec![Integer(2.into())]),
+                    None,
+                ]),
+            ),
+        ] {
+            let mut parsed = Parser::parse(Rule::value, input)
+                .unwrap_or_else(|err| panic!("failed to parse: {}", err));
+            let value = parse_value(
+                parse_pairs_as!(parsed, (Rule::value,)).0,
+                &ParseOptions::default(),
+            )
+            .unwrap();
+            assert_eq!(value, *correct);
+        }
+    }
+
+    #[test]
+    fn parse_bare_tuple_example() {
+        use self::Value::*;
+        for &(input, ref correct) in &[
+            ("1,", Tuple(vec![Integer(1.into())])),
+            ("1, 2", Tuple(vec![Integer(1.into()), Integer(2.into())])),
+            (
+                " 'a' , (2,), [3] , ",
+                Tuple(vec![
+                    String("a".into()),
+                    Tuple(vec![Integer(2.into())]),
+                    List(vec![Integer(3.into())]),
+                ]),
+            ),
+            ("(1, 2)", Tuple(vec![Integer(1.into()), Integer(2.into())])),
+            ("1", Integer(1.into())),
+        ] {
+            assert_eq!(input.parse::<Value>().unwrap(), *correct);
+        }
+        assert!(",".parse::<Value>().is_err());
+        assert!("1,,".parse::<Value>().is_err());
+    }
+
     #[test]
     fn parse_list_example() {
         use self::Value::*;
@@ -504,7 +3565,11 @@ a\n\rre\a\'\"y\u1234o\U00031234u'"#,
         ] {
             let mut parsed = Parser::parse(Rule::value, input)
                 .unwrap_or_else(|err| panic!("failed to parse: {}", err));
-            let list = parse_value(parse_pairs_as!(parsed, (Rule::value,)).0).unwrap();
+            let list = parse_value(
+                parse_pairs_as!(parsed, (Rule::value,)).0,
+                &ParseOptions::default(),
+            )
+            .unwrap();
             assert_eq!(list, *correct);
         }
     }
@@ -526,11 +3591,253 @@ a\n\rre\a\'\"y\u1234o\U00031234u'"#,
         ] {
             let mut parsed = Parser::parse(Rule::value, input)
                 .unwrap_or_else(|err| panic!("failed to parse: {}", err));
-            let dict = parse_value(parse_pairs_as!(parsed, (Rule::value,)).0).unwrap();
+            let dict = parse_value(
+                parse_pairs_as!(parsed, (Rule::value,)).0,
+                &ParseOptions::default(),
+            )
+            .unwrap();
             assert_eq!(dict, *correct);
         }
     }
 
+    #[test]
+    fn parse_duplicate_keys_example() {
+        use self::Value::*;
+        let input = "{'a': 1, 'b': 2, 'a': 3}";
+        let keep_all = Dict(vec![
+            (String("a".into()), Integer(1.into())),
+            (String("b".into()), Integer(2.into())),
+            (String("a".into()), Integer(3.into())),
+        ]);
+        let keep_last = Dict(vec![
+            (String("a".into()), Integer(3.into())),
+            (String("b".into()), Integer(2.into())),
+        ]);
+        assert_eq!(input.parse::<Value>().unwrap(), keep_all);
+        let opts = ParseOptions::new().duplicate_keys(DuplicateKeys::KeepLast);
+        assert_eq!(Value::parse_with_options(input, &opts).unwrap(), keep_last);
+        let opts = opts.ordered_dicts(true);
+        assert_eq!(
+            Value::parse_with_options("OrderedDict([('a', 1), ('b', 2), ('a', 3)])", &opts)
+                .unwrap(),
+            keep_last,
+        );
+        let opts = ParseOptions::new().duplicate_keys(DuplicateKeys::Error);
+        match Value::parse_with_options(input, &opts) {
+            Err(ParseError::DuplicateKey(key, span)) => {
+                assert_eq!(key, "'a'");
+                assert_eq!(span, 17..20);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(Value::parse_with_options("{1: 1, 1.0: 1, True: 1}", &opts).is_err());
+        assert!(Value::parse_with_options("{1: 1, 1.5: 1, 'True': 1}", &opts).is_ok());
+    }
+
+    #[test]
+    fn from_reader_example() {
+        let mut buf = String::from("leftover");
+        let opts = ParseOptions::new().comments(true);
+        let value = Value::from_reader_buffered(&b"[1, # one\n 2]"[..], &mut buf, &opts).unwrap();
+        assert_eq!(
+            value,
+            Value::List(vec![Value::Integer(1.into()), Value::Integer(2.into())])
+        );
+        assert_eq!(buf, "[1, # one\n 2]");
+        assert!(Value::from_reader(&b"[1, # one\n 2]"[..]).is_err());
+        match Value::from_reader(&b"'\xff'"[..]) {
+            Err(ParseError::InvalidUtf8(err)) => assert_eq!(err.valid_up_to(), 1),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let opts = ParseOptions::new().encoding(SourceEncoding::Latin1);
+        let value = Value::from_reader_buffered(&b"'\xff'"[..], &mut buf, &opts).unwrap();
+        assert_eq!(value, Value::String("\u{ff}".into()));
+        assert_eq!(buf, "'\u{ff}'");
+    }
+
+    #[test]
+    fn from_bytes_example() {
+        use self::SourceEncoding::*;
+        assert_eq!(
+            Value::from_bytes(b"('a', 1)").unwrap(),
+            Value::Tuple(vec![Value::String("a".into()), Value::Integer(1.into())]),
+        );
+        for &(input, encoding, correct) in &[
+            (&b"'\xc3\xa9'"[..], Utf8, Some("\u{e9}")),
+            (&b"'\xc3\xa9'"[..], Utf8Lossy, Some("\u{e9}")),
+            (&b"'\xc3\xa9'"[..], Latin1, Some("\u{c3}\u{a9}")),
+            (&b"'\xe9'"[..], Utf8, None),
+            (&b"'\xe9'"[..], Utf8Lossy, Some("\u{fffd}")),
+            (&b"'\xe9'"[..], Latin1, Some("\u{e9}")),
+        ] {
+            let opts = ParseOptions::new().encoding(encoding);
+            let result = Value::from_bytes_with_options(input, &opts);
+            match correct {
+                Some(s) => assert_eq!(result.unwrap(), Value::String(s.into())),
+                None => assert!(matches!(result, Err(ParseError::InvalidUtf8(_)))),
+            }
+        }
+    }
+
+    #[cfg(feature = "memmap2")]
+    #[test]
+    fn from_path_example() {
+        use std::io::Write;
+        let path = std::env::temp_dir().join(format!("py_literal-{}.py", std::process::id()));
+        std::fs::File::create(&path)
+            .and_then(|mut f| f.write_all(b"{'shape': (3, 4)}  # header\n"))
+            .unwrap();
+        let opts = ParseOptions::new().comments(true);
+        let result = Value::from_path(&path, &opts);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap().to_string(), "{'shape': (3, 4)}");
+        match Value::from_path(&path, &opts) {
+            Err(ParseError::Io(err)) => assert_eq!(err.kind(), std::io::ErrorKind::NotFound),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_many_duplicate_keys() {
+        // Large dicts would take too long if each key were compared with all
+        // of the previous ones.
+        let n = 10_000;
+        let items: Vec<_> = (0..n).map(|i| format!("{}: 1, {}.0: 2", i, i)).collect();
+        let input = format!("{{{}}}", items.join(", "));
+        let opts = ParseOptions::new().duplicate_keys(DuplicateKeys::KeepLast);
+        let dict = Value::parse_with_options(&input, &opts).unwrap();
+        let dict = dict.as_dict().unwrap();
+        assert_eq!(dict.len(), n);
+        assert!(dict
+            .iter()
+            .all(|(k, v)| k.is_integer() && *v == Value::from(2)));
+        let opts = ParseOptions::new().duplicate_keys(DuplicateKeys::Error);
+        let err = Value::parse_with_options(&input, &opts).unwrap_err();
+        assert_eq!(err.to_string(), "duplicate dict key: 0e0 at 7..10");
+        let items: Vec<_> = (0..n).map(|i| format!("'{}': {}", i, i)).collect();
+        let input = format!("{{{}}}", items.join(", "));
+        assert!(Value::parse_with_options(&input, &opts).is_ok());
+    }
+
+    #[test]
+    fn parse_deduplicate_sets_example() {
+        use self::Value::*;
+        let input = "{1, 'a', 1.0, True, 2, 'a'}";
+        assert_eq!(input.parse::<Value>().unwrap().as_set().unwrap().len(), 6);
+        let opts = ParseOptions::new().deduplicate_sets(true).frozensets(true);
+        assert_eq!(
+            Value::parse_with_options(input, &opts).unwrap(),
+            Set(vec![
+                Integer(1.into()),
+                String("a".into()),
+                Integer(2.into())
+            ]),
+        );
+        assert_eq!(
+            Value::parse_with_options("frozenset({(1, 2), (1.0, 2), (2, 1)})", &opts).unwrap(),
+            FrozenSet(vec![
+                Tuple(vec![Integer(1.into()), Integer(2.into())]),
+                Tuple(vec![Integer(2.into()), Integer(1.into())]),
+            ]),
+        );
+        let nan =
+            Value::parse_with_options("{float('nan'), float('nan')}", &opts.float_calls(true));
+        assert_eq!(nan.unwrap().as_set().unwrap().len(), 2);
+        // Large sets would take too long if each element were compared with
+        // all of the previous ones.
+        let n = 10_000;
+        let elems: Vec<_> = (0..n)
+            .map(|i| format!("{}, {}.0, ({}, '{}')", i, i, i, i))
+            .collect();
+        let input = format!("{{{}}}", elems.join(", "));
+        let opts = ParseOptions::new().deduplicate_sets(true);
+        let set = Value::parse_with_options(&input, &opts).unwrap();
+        assert_eq!(set.as_set().unwrap().len(), 2 * n);
+    }
+
+    #[test]
+    fn parse_max_depth_example() {
+        let opts = ParseOptions::new().max_depth(2).comments(true);
+        for &(input, correct) in &[
+            ("1", None),
+            ("[(1,), {'[[': 2}]", None),
+            ("[1, # [[[\n 2]", None),
+            ("[[[1]]]", Some(2)),
+            ("{'a': [1, {2}]}", Some(10)),
+        ] {
+            match Value::parse_with_options(input, &opts) {
+                Err(ParseError::DepthLimitExceeded(pos)) => assert_eq!(Some(pos), correct),
+                Ok(_) => assert_eq!(correct, None),
+                Err(err) => panic!("unexpected error: {}", err),
+            }
+        }
+        let strict = ParseOptions::new().max_depth(0).strict(true);
+        assert!(Value::parse_with_options("()", &strict).is_err());
+        assert!(Value::parse_with_options(&"[".repeat(100_000), &strict).is_err());
+    }
+
+    #[test]
+    fn parse_max_int_digits_example() {
+        let opts = ParseOptions::new().max_int_digits(4);
+        for input in &[
+            "1234",
+            "1_234",
+            "-0000",
+            "0x12345",
+            "0b1_0000_0000",
+            "12345.0",
+        ] {
+            assert!(
+                Value::parse_with_options(input, &opts).is_ok(),
+                "{:?}",
+                input
+            );
+        }
+        match Value::parse_with_options("[1, 2 + 1_0000j, -10_000]", &opts) {
+            Err(ParseError::DigitLimitExceeded(span)) => assert_eq!(span, 18..24),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let opts = ParseOptions::new().max_int_digits(4300);
+        assert!(Value::parse_with_options(&"9".repeat(4300), &opts).is_ok());
+        assert!(Value::parse_with_options(&"9".repeat(10_000), &opts).is_err());
+    }
+
+    #[test]
+    fn parse_size_limits_example() {
+        let opts = ParseOptions::new().max_length(3);
+        assert!(Value::parse_with_options(r"['abc', b'\x00\x01\x02', 'é€\u00b0']", &opts).is_ok());
+        match Value::parse_with_options("['abc', r'abcd']", &opts) {
+            Err(ParseError::LengthLimitExceeded(span)) => assert_eq!(span, 8..15),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(Value::parse_with_options(r"b'\x00\x00\x00\x00'", &opts).is_err());
+
+        let opts = ParseOptions::new().max_elements(2).ordered_dicts(true);
+        for &(input, ref correct) in &[
+            ("[1, 2]", None),
+            ("{1: [3, 4], 2: ()}", None),
+            ("[1, 2, 3]", Some(7..8)),
+            ("1, (2, 3, 4)", Some(10..11)),
+            ("{1: 1, 2: 2, 3: 3}", Some(13..14)),
+            ("OrderedDict([(1, 1), (2, 2), (3, 3)])", Some(29..35)),
+        ] {
+            match Value::parse_with_options(input, &opts) {
+                Err(ParseError::ElementLimitExceeded(span)) => assert_eq!(Some(span), *correct),
+                Ok(_) => assert_eq!(*correct, None, "{:?}", input),
+                Err(err) => panic!("unexpected error: {}", err),
+            }
+        }
+
+        let opts = ParseOptions::new().max_nodes(5);
+        assert!(Value::parse_with_options("[1, (2, 3)]", &opts).is_ok());
+        match Value::parse_with_options("[1, (2, 3)], 4", &opts) {
+            Err(ParseError::NodeLimitExceeded(span)) => assert_eq!(span, 13..14),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let strict = opts.max_nodes(0).strict(true);
+        assert!(Value::parse_with_options("1", &strict).is_err());
+    }
+
     #[test]
     fn parse_set_example() {
         use self::Value::*;
@@ -541,11 +3848,192 @@ a\n\rre\a\'\"y\u1234o\U00031234u'"#,
         ] {
             let mut parsed = Parser::parse(Rule::value, input)
                 .unwrap_or_else(|err| panic!("failed to parse: {}", err));
-            let set = parse_value(parse_pairs_as!(parsed, (Rule::value,)).0).unwrap();
+            let set = parse_value(
+                parse_pairs_as!(parsed, (Rule::value,)).0,
+                &ParseOptions::default(),
+            )
+            .unwrap();
             assert_eq!(set, *correct);
         }
     }
 
+    #[test]
+    fn parse_ellipsis_example() {
+        use self::Value::*;
+        for &(input, ref correct) in &[
+            ("...", Ellipsis),
+            ("(...)", Ellipsis),
+            ("(..., 0)", Tuple(vec![Ellipsis, Integer(0.into())])),
+            ("[1, ...,]", List(vec![Integer(1.into()), Ellipsis])),
+        ] {
+            let parsed: Value = input
+                .parse()
+                .unwrap_or_else(|err| panic!("failed to parse: {}", err));
+            assert_eq!(parsed, *correct);
+        }
+        assert!("....".parse::<Value>().is_err());
+        assert!("..".parse::<Value>().is_err());
+    }
+
+    #[test]
+    fn parse_frozenset_example() {
+        use self::Value::*;
+        let opts = ParseOptions::new().frozensets(true);
+        for &(input, ref correct) in &[
+            ("frozenset()", FrozenSet(vec![])),
+            ("frozenset({1})", FrozenSet(vec![Integer(1.into())])),
+            (
+                "[frozenset( {'a', (2,),} )]",
+                List(vec![FrozenSet(vec![
+                    String("a".into()),
+                    Tuple(vec![Integer(2.into())]),
+                ])]),
+            ),
+        ] {
+            assert_eq!(Value::parse_with_options(input, &opts).unwrap(), *correct);
+            assert!(input.parse::<Value>().is_err());
+        }
+        assert!(Value::parse_with_options("frozenset([1])", &opts).is_err());
+    }
+
+    #[test]
+    fn parse_bytearray_example() {
+        use self::Value::*;
+        let opts = ParseOptions::new().bytearrays(true);
+        for &(input, ref correct) in &[
+            ("bytearray()", ByteArray(vec![])),
+            ("bytearray(b'')", ByteArray(vec![])),
+            (
+                r"(bytearray( b'a\x00' ), b'a')",
+                Tuple(vec![ByteArray(b"a\x00".to_vec()), Bytes(b"a".to_vec())]),
+            ),
+        ] {
+            assert_eq!(Value::parse_with_options(input, &opts).unwrap(), *correct);
+            assert!(input.parse::<Value>().is_err());
+        }
+        assert!(Value::parse_with_options("bytearray('a')", &opts).is_err());
+    }
+
+    #[test]
+    fn parse_empty_call_example() {
+        use self::Value::*;
+        let opts = ParseOptions::new().empty_calls(true);
+        for &(input, ref correct) in &[
+            ("set()", Set(vec![])),
+            ("dict( )", Dict(vec![])),
+            ("list()", List(vec![])),
+            ("tuple()", Tuple(vec![])),
+            ("{1: set()}", Dict(vec![(Integer(1.into()), Set(vec![]))])),
+        ] {
+            assert_eq!(Value::parse_with_options(input, &opts).unwrap(), *correct);
+            assert!(input.parse::<Value>().is_err());
+        }
+        assert!(Value::parse_with_options("set([1])", &opts).is_err());
+    }
+
+    #[test]
+    fn parse_ordered_dict_example() {
+        use self::Value::*;
+        let opts = ParseOptions::new().ordered_dicts(true);
+        let b_a = Dict(vec![
+            (String("b".into()), Integer(2.into())),
+            (String("a".into()), Integer(1.into())),
+        ]);
+        for &(input, ref correct) in &[
+            ("OrderedDict()", Dict(vec![])),
+            ("OrderedDict([])", Dict(vec![])),
+            ("OrderedDict([('b', 2), ['a', 1]])", b_a.clone()),
+            ("OrderedDict({'b': 2, 'a': 1})", b_a.clone()),
+        ] {
+            assert_eq!(Value::parse_with_options(input, &opts).unwrap(), *correct);
+            assert!(input.parse::<Value>().is_err());
+        }
+        assert!(Value::parse_with_options("OrderedDict([('a', 1, 2)])", &opts).is_err());
+        assert!(Value::parse_with_options("OrderedDict([1])", &opts).is_err());
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    #[test]
+    fn parse_decimal_example() {
+        use self::Value::*;
+        use bigdecimal::BigDecimal;
+        let opts = ParseOptions::new().decimals(true);
+        for &(input, correct) in &[
+            ("Decimal('1.23')", "1.23"),
+            ("Decimal(\"-0.000\")", "0.000"),
+            ("Decimal(' 1_000.5E+3 ')", "1000500"),
+            ("Decimal('1e-30')", "0.000000000000000000000000000001"),
+        ] {
+            let correct = Decimal(correct.parse::<BigDecimal>().unwrap());
+            assert_eq!(Value::parse_with_options(input, &opts).unwrap(), correct);
+            assert!(input.parse::<Value>().is_err());
+        }
+        assert!(Value::parse_with_options("Decimal('NaN')", &opts).is_err());
+        assert!(Value::parse_with_options("Decimal(1)", &opts).is_err());
+    }
+
+    #[cfg(feature = "num-rational")]
+    #[test]
+    fn parse_rational_example() {
+        use self::Value::*;
+        use num_rational::BigRational;
+        let ratio = |n: i32, d: i32| Rational(BigRational::new(n.into(), d.into()));
+        let opts = ParseOptions::new().fractions(true);
+        for &(input, ref correct) in &[
+            ("Fraction(1, 3)", ratio(1, 3)),
+            ("Fraction(2, -4)", ratio(-1, 2)),
+            ("Fraction(-5)", ratio(-5, 1)),
+            ("[Fraction(0, 1)]", List(vec![ratio(0, 1)])),
+        ] {
+            assert_eq!(Value::parse_with_options(input, &opts).unwrap(), *correct);
+            assert!(input.parse::<Value>().is_err());
+        }
+        assert!(Value::parse_with_options("Fraction(1, 0)", &opts).is_err());
+        assert!(Value::parse_with_options("Fraction(1.5, 2)", &opts).is_err());
+        assert!(Value::parse_with_options("Fraction(1, 2, 3)", &opts).is_err());
+    }
+
+    #[test]
+    fn parse_datetime_example() {
+        use self::Value::*;
+        let date = crate::Date::new(2023, 5, 1).unwrap();
+        let datetime = |h, m, s, us| DateTime(crate::DateTime::new(date, h, m, s, us).unwrap());
+        let opts = ParseOptions::new().datetimes(true);
+        for &(input, ref correct) in &[
+            ("datetime.date(2023, 5, 1)", Date(date)),
+            ("date(2023, 5, 1)", Date(date)),
+            ("datetime.datetime(2023, 5, 1)", datetime(0, 0, 0, 0)),
+            (
+                "datetime.datetime(2023, 5, 1, 12, 0)",
+                datetime(12, 0, 0, 0),
+            ),
+            ("datetime(2023, 5, 1, 12, 0, 7, 5,)", datetime(12, 0, 7, 5)),
+            (
+                "{'t': datetime.datetime(2023, 5, 1, 23, 59, 59, 999999)}",
+                Dict(vec![(String("t".into()), datetime(23, 59, 59, 999_999))]),
+            ),
+        ] {
+            assert_eq!(Value::parse_with_options(input, &opts).unwrap(), *correct);
+            assert!(input.parse::<Value>().is_err());
+        }
+        for input in &[
+            "datetime.date(2023, 2, 29)",
+            "datetime.date(2023, 5)",
+            "datetime.date(2023, 5, 1, 0)",
+            "datetime.datetime(2023, 5, 1, 24, 0)",
+            "datetime.datetime(2023, 5, 1, 0, -1)",
+            "datetime.datetime(2023, 5, 1, 0, 0, 0, 0, 0)",
+            "datetime.date(2023, 5, 1.0)",
+            "datetime.datetime(2023, 5, 1, 12, 0, tzinfo=datetime.timezone.utc)",
+        ] {
+            assert!(
+                Value::parse_with_options(input, &opts).is_err(),
+                "{}",
+                input
+            );
+        }
+    }
+
     #[test]
     fn parse_list_of_tuples_example() {
         use self::Value::*;
@@ -571,7 +4059,11 @@ a\n\rre\a\'\"y\u1234o\U00031234u'"#,
         ] {
             let mut parsed = Parser::parse(Rule::value, input)
                 .unwrap_or_else(|err| panic!("failed to parse: {}", err));
-            let list = parse_value(parse_pairs_as!(parsed, (Rule::value,)).0).unwrap();
+            let list = parse_value(
+                parse_pairs_as!(parsed, (Rule::value,)).0,
+                &ParseOptions::default(),
+            )
+            .unwrap();
             assert_eq!(list, *correct);
         }
     }
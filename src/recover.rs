@@ -0,0 +1,593 @@
+//! Parsing which recovers from errors, for tools such as editors which need
+//! a result even for invalid input.
+
+use crate::parse::{insert_dict_item, push_set_element, Element, ElementIndex, ParseOptions};
+use crate::scan;
+use crate::{ParseError, Value};
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::mem;
+use std::ops::Range;
+use std::vec;
+
+/// Result of parsing with error recovery, created by
+/// [`Value::parse_recovering`].
+///
+/// [`Value::parse_recovering`]: enum.Value.html#method.parse_recovering
+#[derive(Debug)]
+pub struct Recovered {
+    /// The literal without the parts which couldn't be parsed, or `None` if
+    /// nothing could be recovered.
+    pub value: Option<Value>,
+    /// The errors, which are empty if the input is valid.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Error found while parsing with error recovery.
+#[derive(Debug)]
+pub struct Diagnostic {
+    /// Byte range of the text containing the error, which was skipped.
+    pub span: Range<usize>,
+    /// The error. It describes the text in `span` on its own, so positions in
+    /// the error are relative to the start of `span`.
+    pub error: ParseError,
+}
+
+impl Value {
+    /// Parses a Python literal, recovering from errors, using the default
+    /// options.
+    pub fn parse_recovering(s: &str) -> Recovered {
+        Value::parse_recovering_with_options(s, &ParseOptions::default())
+    }
+
+    /// Parses a Python literal, recovering from errors, using the given
+    /// options.
+    ///
+    /// If part of the input is invalid, the parser skips to the next
+    /// delimiter (a comma, a colon in a dict, or a closing bracket), and
+    /// continues with the next element. An unclosed bracket is treated as if
+    /// it was closed at the end of the enclosing collection. The result
+    /// contains the parts of the literal which are valid, together with a
+    /// diagnostic for each part that was skipped.
+    ///
+    /// The elements of collections which contain errors aren't checked
+    /// against each other, so the `duplicate_keys` and `deduplicate_sets`
+    /// options don't apply to them.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let recovered = Value::parse_recovering("{'a': [1, 2 3], 'b': x, 'c': (4,)");
+    /// assert_eq!(
+    ///     recovered.value.unwrap().to_string(),
+    ///     "{'a': [1], 'c': (4,)}",
+    /// );
+    /// let spans: Vec<_> = recovered.diagnostics.iter().map(|d| d.span.clone()).collect();
+    /// assert_eq!(spans, [0..33, 10..13, 21..22]);
+    /// ```
+    pub fn parse_recovering_with_options(s: &str, options: &ParseOptions) -> Recovered {
+        let error = match Value::parse_with_options(s, options) {
+            Ok(value) => {
+                return Recovered {
+                    value: Some(value),
+                    diagnostics: Vec::new(),
+                }
+            }
+            Err(err) => err,
+        };
+        let options = options.resolve();
+        let text = options.preprocess(s);
+        let mut recovery = Recovery {
+            text: &text,
+            brackets: scan::match_brackets(&text),
+            options: &options,
+            diagnostics: Vec::new(),
+        };
+        let all = recovery.trim(0..s.len());
+        let value = match error {
+            // Elements of input which is nested too deeply aren't examined.
+            ParseError::DepthLimitExceeded(_) => {
+                recovery.diagnose(all, error);
+                None
+            }
+            _ if all.start == all.end => {
+                recovery.diagnose(all, error);
+                None
+            }
+            _ => {
+                let (kind, parts) = recovery.comma_separated(all.clone());
+                recovery.value(Frame {
+                    kind,
+                    range: all,
+                    parts: parts.into_iter(),
+                    values: Vec::new(),
+                    before: 0,
+                    error: Some(error),
+                })
+            }
+        };
+        Recovered {
+            value,
+            diagnostics: recovery.diagnostics,
+        }
+    }
+}
+
+/// State of parsing with error recovery.
+struct Recovery<'a> {
+    /// The input, with comments blanked out if they're enabled.
+    text: &'a str,
+    /// Closing bracket of each opening bracket in `text` which is closed.
+    brackets: HashMap<usize, usize>,
+    options: &'a ParseOptions,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Kind of a collection whose elements are being recovered.
+enum Kind {
+    /// A single value in parentheses, or at the top level.
+    Single,
+    Tuple,
+    List,
+    Set,
+    Dict,
+}
+
+/// Part of the contents of a collection.
+enum Part {
+    /// An element, dict key, or dict value.
+    Value(Range<usize>),
+    /// An item of a dict without a colon.
+    MissingColon(Range<usize>),
+}
+
+/// Collection whose elements are being recovered.
+struct Frame {
+    kind: Kind,
+    /// Range of the collection, including the brackets.
+    range: Range<usize>,
+    parts: vec::IntoIter<Part>,
+    /// The recovered elements, or the keys and values of a dict, with their
+    /// ranges.
+    values: Vec<(Option<Value>, Range<usize>)>,
+    /// Number of diagnostics before the collection was examined.
+    before: usize,
+    /// Error in the collection itself, reported if none of the elements
+    /// contain errors.
+    error: Option<ParseError>,
+}
+
+/// Element of a collection, identified by its position, for checking the
+/// elements without taking ownership of them.
+struct Indexed<'v>(usize, &'v Value);
+
+impl Element for Indexed<'_> {
+    fn is_hashable(&self) -> bool {
+        self.1.is_hashable()
+    }
+
+    fn python_eq(&self, other: &Self) -> bool {
+        self.1.python_eq(other.1)
+    }
+
+    fn python_hash(&self, depth: usize, state: &mut dyn Hasher) {
+        self.1.python_hash(depth, state)
+    }
+
+    fn describe(&self) -> String {
+        self.1.describe()
+    }
+}
+
+impl<'a> Recovery<'a> {
+    fn diagnose(&mut self, span: Range<usize>, error: ParseError) {
+        self.diagnostics.push(Diagnostic { span, error });
+    }
+
+    /// Returns the range without leading and trailing whitespace.
+    fn trim(&self, range: Range<usize>) -> Range<usize> {
+        let whitespace = &[' ', '\t', '\x0C'][..];
+        let part = &self.text[range.clone()];
+        let start = range.start + (part.len() - part.trim_start_matches(whitespace).len());
+        let end = range.start + part.trim_end_matches(whitespace).len();
+        start..end.max(start)
+    }
+
+    /// Splits the range at top-level occurrences of `delim`, and returns the
+    /// trimmed parts.
+    fn split(&self, range: Range<usize>, delim: u8) -> Vec<Range<usize>> {
+        scan::split_top_level(
+            &self.text[range.clone()],
+            range.start,
+            &self.brackets,
+            delim,
+        )
+        .into_iter()
+        .map(|part| self.trim(range.start + part.start..range.start + part.end))
+        .collect()
+    }
+
+    /// Returns the span of `part` within `range`, which is the text that an
+    /// error in `part` is reported for.
+    fn span(&self, range: &Range<usize>, part: &Range<usize>) -> pest::Span<'a> {
+        pest::Span::new(
+            &self.text[range.clone()],
+            part.start - range.start,
+            part.end - range.start,
+        )
+        .unwrap()
+    }
+
+    /// Parses a single value, recovering from errors inside brackets.
+    ///
+    /// Only values which aren't collections are parsed. Collections are
+    /// examined with an explicit stack instead, so each part of the input is
+    /// parsed at most once, regardless of how deeply it's nested.
+    fn value(&mut self, mut frame: Frame) -> Option<Value> {
+        let mut stack = Vec::new();
+        loop {
+            match frame.parts.next() {
+                Some(Part::Value(range)) => match self.element(range) {
+                    Ok(inner) => stack.push(mem::replace(&mut frame, inner)),
+                    Err(value) => frame.values.push(value),
+                },
+                Some(Part::MissingColon(range)) => {
+                    let error = ParseError::Syntax("expected ':' after dict key".into());
+                    self.diagnose(range, error);
+                }
+                None => {
+                    let range = frame.range.clone();
+                    let value = self.finish(frame);
+                    match stack.pop() {
+                        Some(outer) => {
+                            frame = outer;
+                            frame.values.push((value, range));
+                        }
+                        None => return value,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the frame for the collection in the range, or parses the
+    /// range if it isn't a collection.
+    fn element(&mut self, range: Range<usize>) -> Result<Frame, (Option<Value>, Range<usize>)> {
+        let range = self.trim(range);
+        let bytes = self.text.as_bytes();
+        let open = match bytes[range.clone()].first() {
+            Some(&open) if matches!(open, b'(' | b'[' | b'{') => open,
+            _ => return Err((self.leaf(range.clone()), range)),
+        };
+        let before = self.diagnostics.len();
+        let mut error = None;
+        let end = match self.brackets.get(&range.start) {
+            Some(&close) if close + 1 == range.end => {
+                let expected = match open {
+                    b'(' => b')',
+                    b'[' => b']',
+                    _ => b'}',
+                };
+                if bytes[close] != expected {
+                    error = Some(ParseError::Syntax("mismatched closing bracket".into()));
+                }
+                close
+            }
+            Some(&close) if close < range.end => {
+                let rest = self.text[close + 1..range.end].trim_start();
+                if rest.starts_with('+') || rest.starts_with('-') {
+                    // A number expression, e.g. `(1) + 2j`.
+                    return Err((self.leaf(range.clone()), range));
+                }
+                let error = ParseError::Syntax("unexpected text after closing bracket".into());
+                self.diagnose(range.clone(), error);
+                close
+            }
+            _ => {
+                let error = ParseError::Syntax("unclosed bracket".into());
+                self.diagnose(range.clone(), error);
+                range.end
+            }
+        };
+        let inner = range.start + 1..end;
+        let (kind, parts) = match open {
+            b'(' => self.comma_separated(inner),
+            b'[' => (Kind::List, self.elements(inner)),
+            _ => self.braces(inner),
+        };
+        Ok(Frame {
+            kind,
+            range,
+            parts: parts.into_iter(),
+            values: Vec::new(),
+            before,
+            error,
+        })
+    }
+
+    /// Parses a value which isn't a collection.
+    fn leaf(&mut self, range: Range<usize>) -> Option<Value> {
+        match Value::parse_with_options(&self.text[range.clone()], self.options) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.diagnose(range, err);
+                None
+            }
+        }
+    }
+
+    /// Splits comma-separated values, which form a tuple unless there is
+    /// only a single value without a trailing comma.
+    fn comma_separated(&self, range: Range<usize>) -> (Kind, Vec<Part>) {
+        let parts = self.split(range.clone(), b',');
+        if parts.len() == 1 && parts[0].start != parts[0].end {
+            (Kind::Single, vec![Part::Value(range)])
+        } else {
+            (Kind::Tuple, self.elements(range))
+        }
+    }
+
+    /// Splits comma-separated values, which may have a trailing comma.
+    fn elements(&self, range: Range<usize>) -> Vec<Part> {
+        let mut parts = self.split(range, b',');
+        if parts.last().map_or(false, |part| part.start == part.end) {
+            parts.pop();
+        }
+        parts.into_iter().map(Part::Value).collect()
+    }
+
+    /// Splits the contents of a dict or set.
+    fn braces(&self, range: Range<usize>) -> (Kind, Vec<Part>) {
+        let mut parts = self.split(range, b',');
+        let colons: Vec<_> = parts
+            .iter()
+            .map(|part| {
+                let text = &self.text[part.clone()];
+                let key = &scan::split_top_level(text, part.start, &self.brackets, b':')[0];
+                Some(part.start + key.end).filter(|&colon| colon != part.end)
+            })
+            .collect();
+        let is_set =
+            parts.iter().any(|part| part.start != part.end) && colons.iter().all(Option::is_none);
+        if parts.last().map_or(false, |part| part.start == part.end) {
+            parts.pop();
+        }
+        if is_set {
+            return (Kind::Set, parts.into_iter().map(Part::Value).collect());
+        }
+        let mut items = Vec::new();
+        for (part, colon) in parts.into_iter().zip(colons) {
+            match colon {
+                Some(colon) => {
+                    items.push(Part::Value(part.start..colon));
+                    items.push(Part::Value(colon + 1..part.end));
+                }
+                None => items.push(Part::MissingColon(part)),
+            }
+        }
+        (Kind::Dict, items)
+    }
+
+    /// Creates the collection from the recovered elements. The elements are
+    /// checked against each other only if none of them contain errors.
+    fn finish(&mut self, frame: Frame) -> Option<Value> {
+        let Frame {
+            kind,
+            range,
+            values,
+            before,
+            error,
+            ..
+        } = frame;
+        let mut clean = self.diagnostics.len() == before;
+        if let (true, Some(error)) = (clean, error) {
+            // The error wasn't in any of the elements, e.g. a mismatched
+            // closing bracket.
+            self.diagnose(range.clone(), error);
+            clean = false;
+        }
+        match kind {
+            Kind::Single => values.into_iter().next().and_then(|(value, _)| value),
+            Kind::Dict => {
+                let mut items = Vec::new();
+                let mut values = values.into_iter();
+                while let (Some((key, span)), Some((value, _))) = (values.next(), values.next()) {
+                    if let (Some(key), Some(value)) = (key, value) {
+                        items.push((key, value, span));
+                    }
+                }
+                if clean {
+                    match self.check_dict(&range, &items) {
+                        Ok(kept) => {
+                            let dict = kept
+                                .into_iter()
+                                .map(|(k, v)| (items[k].0.take(), items[v].1.take()))
+                                .collect();
+                            return Some(Value::Dict(dict));
+                        }
+                        Err(error) => self.diagnose(range, error),
+                    }
+                }
+                Some(Value::Dict(
+                    items
+                        .into_iter()
+                        .map(|(key, value, _)| (key, value))
+                        .collect(),
+                ))
+            }
+            Kind::Tuple | Kind::List | Kind::Set => {
+                let mut elems: Vec<_> = values
+                    .into_iter()
+                    .filter_map(|(value, span)| value.map(|value| (value, span)))
+                    .collect();
+                if clean {
+                    match self.check_sequence(&kind, &range, &elems) {
+                        Ok(kept) => {
+                            elems = kept
+                                .into_iter()
+                                .map(|i| (elems[i].0.take(), elems[i].1.clone()))
+                                .collect();
+                        }
+                        Err(error) => self.diagnose(range, error),
+                    }
+                }
+                let elems = elems.into_iter().map(|(elem, _)| elem).collect();
+                Some(match kind {
+                    Kind::Tuple => Value::Tuple(elems),
+                    Kind::List => Value::List(elems),
+                    _ => Value::Set(elems),
+                })
+            }
+        }
+    }
+
+    /// Checks the elements of a tuple, list, or set in the range like the
+    /// parser would, and returns the positions of the elements to keep.
+    fn check_sequence(
+        &self,
+        kind: &Kind,
+        range: &Range<usize>,
+        elems: &[(Value, Range<usize>)],
+    ) -> Result<Vec<usize>, ParseError> {
+        let mut kept = Vec::new();
+        let mut index = ElementIndex::default();
+        for (i, (elem, part)) in elems.iter().enumerate() {
+            let span = self.span(range, part);
+            self.options.check_elements(kept.len(), span)?;
+            match kind {
+                Kind::Set => {
+                    push_set_element(&mut kept, &mut index, Indexed(i, elem), span, self.options)?
+                }
+                _ => kept.push(Indexed(i, elem)),
+            }
+        }
+        Ok(kept.into_iter().map(|elem| elem.0).collect())
+    }
+
+    /// Checks the items of a dict in the range like the parser would, and
+    /// returns the positions of the keys and values to keep.
+    fn check_dict(
+        &self,
+        range: &Range<usize>,
+        items: &[(Value, Value, Range<usize>)],
+    ) -> Result<Vec<(usize, usize)>, ParseError> {
+        let mut kept = Vec::new();
+        let mut index = ElementIndex::default();
+        for (i, (key, _, part)) in items.iter().enumerate() {
+            let span = self.span(range, part);
+            self.options.check_elements(kept.len(), span)?;
+            insert_dict_item(
+                &mut kept,
+                &mut index,
+                Indexed(i, key),
+                i,
+                span,
+                self.options,
+            )?;
+        }
+        Ok(kept
+            .into_iter()
+            .map(|(key, value)| (key.0, value))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_recovering_example() {
+        for &(input, correct, spans) in &[
+            ("[1, 2]", Some("[1, 2]"), &[][..]),
+            ("", None, &[(0, 0)][..]),
+            ("1, x, 3,", Some("(1, 3)"), &[(3, 4)][..]),
+            ("[1,, 2", Some("[1, 2]"), &[(0, 6), (3, 3)][..]),
+            ("[(1, 2]", Some("[(1, 2)]"), &[(0, 7), (1, 7)][..]),
+            ("{1, 2: 3}", Some("{2: 3}"), &[(1, 2)][..]),
+            ("{1: 2, 3: }", Some("{1: 2}"), &[(9, 9)][..]),
+            ("{x, 1}", Some("{1}"), &[(1, 2)][..]),
+            ("[1] 2", Some("[1]"), &[(0, 5)][..]),
+            ("[1)", Some("[1]"), &[(0, 3)][..]),
+            ("[(1) + 2, x]", Some("[3]"), &[(10, 11)][..]),
+            ("[(1) x, 2]", Some("[1, 2]"), &[(1, 6)][..]),
+        ] {
+            let recovered = Value::parse_recovering(input);
+            assert_eq!(
+                recovered.value.map(|v| v.to_string()).as_deref(),
+                correct,
+                "{:?}",
+                input,
+            );
+            let actual: Vec<_> = recovered
+                .diagnostics
+                .into_iter()
+                .map(|d| (d.span.start, d.span.end))
+                .collect();
+            assert_eq!(actual, spans, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn parse_recovering_options() {
+        let opts = ParseOptions::new().comments(true);
+        let recovered = Value::parse_recovering_with_options("[1, # one\n x, 3]", &opts);
+        assert_eq!(recovered.value.unwrap().to_string(), "[1, 3]");
+        assert_eq!(recovered.diagnostics[0].span, 11..12);
+        let opts = ParseOptions::new().max_depth(1);
+        let recovered = Value::parse_recovering_with_options("[1, x, [2]]", &opts);
+        assert!(recovered.value.is_none());
+        assert_eq!(recovered.diagnostics.len(), 1);
+        let strict = ParseOptions::new().strict(true);
+        let recovered = Value::parse_recovering_with_options("[{[1]}]", &strict);
+        assert_eq!(recovered.value.unwrap().to_string(), "[{[1]}]");
+        assert_eq!(recovered.diagnostics[0].span, 1..6);
+        let opts = ParseOptions::new().max_elements(2);
+        let recovered = Value::parse_recovering_with_options("[[1, 2, 3], x]", &opts);
+        assert_eq!(recovered.value.unwrap().to_string(), "[[1, 2, 3]]");
+        let spans: Vec<_> = recovered
+            .diagnostics
+            .iter()
+            .map(|d| d.span.clone())
+            .collect();
+        assert_eq!(spans, [1..10, 12..13]);
+    }
+
+    #[test]
+    fn parse_recovering_deeply_nested() {
+        // Nested parts of the input are examined without recursion and
+        // without being parsed again. The thread's stack just needs to be
+        // large enough for the grammar, which is matched recursively.
+        let test = || {
+            let depth = 10_000;
+            let input = format!("{}x{}", "[".repeat(depth), "]".repeat(depth));
+            let recovered = Value::parse_recovering(&input);
+            assert_eq!(recovered.diagnostics.len(), 1);
+            assert_eq!(recovered.diagnostics[0].span, depth..depth + 1);
+            let mut value = &recovered.value.unwrap();
+            for _ in 1..depth {
+                value = &value.as_list().unwrap()[0];
+            }
+            assert_eq!(value, &Value::List(vec![]));
+
+            let input = "[1, ".repeat(depth);
+            let recovered = Value::parse_recovering(&input);
+            assert_eq!(recovered.diagnostics.len(), depth);
+            assert_eq!(
+                recovered.diagnostics[depth - 1].span,
+                input.len() - 4..input.len() - 1
+            );
+            let mut value = &recovered.value.unwrap();
+            for _ in 1..depth {
+                value = &value.as_list().unwrap()[1];
+            }
+            assert_eq!(value.to_string(), "[1]");
+        };
+        std::thread::Builder::new()
+            .stack_size(256 << 20)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
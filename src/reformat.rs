@@ -0,0 +1,112 @@
+//! Parsing and re-emitting Python literals in a chosen style.
+
+use crate::{FormatError, FormatOptions, ParseError, ParseOptions, Value};
+use std::error::Error;
+use std::fmt;
+
+/// Error reformatting a Python literal.
+#[derive(Debug)]
+pub enum ReformatError {
+    /// The input couldn't be parsed.
+    Parse(ParseError),
+    /// The parsed value couldn't be formatted.
+    Format(FormatError),
+}
+
+impl Error for ReformatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use ReformatError::*;
+        match self {
+            Parse(err) => Some(err),
+            Format(err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for ReformatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ReformatError::*;
+        match self {
+            Parse(err) => write!(f, "parse error: {}", err),
+            Format(err) => write!(f, "format error: {}", err),
+        }
+    }
+}
+
+impl From<ParseError> for ReformatError {
+    fn from(err: ParseError) -> ReformatError {
+        ReformatError::Parse(err)
+    }
+}
+
+impl From<FormatError> for ReformatError {
+    fn from(err: FormatError) -> ReformatError {
+        ReformatError::Format(err)
+    }
+}
+
+/// Parses a Python literal using the default parse options, and formats it
+/// using the given options, e.g. for a formatting tool.
+///
+/// See [`reformat_with_options`] for details.
+///
+/// ```
+/// use py_literal::{reformat, FloatStyle, FormatOptions};
+///
+/// # fn main() -> Result<(), py_literal::ReformatError> {
+/// let opts = FormatOptions::new()
+///     .pretty(true)
+///     .width(24)
+///     .float_style(FloatStyle::Repr);
+/// assert_eq!(
+///     reformat("{'a':[1e3,2],'b' :\"text\"}", &opts)?,
+///     "{\n    'a': [1000.0, 2],\n    'b': 'text'\n}",
+/// );
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`reformat_with_options`]: fn.reformat_with_options.html
+pub fn reformat(input: &str, options: &FormatOptions) -> Result<String, ReformatError> {
+    reformat_with_options(input, &ParseOptions::default(), options)
+}
+
+/// Parses a Python literal using the given parse options, and formats it
+/// using the given format options.
+///
+/// The output is determined by the parsed value, so comments, redundant
+/// parentheses, and the spelling of numbers and strings in the input aren't
+/// preserved.
+pub fn reformat_with_options(
+    input: &str,
+    parse_options: &ParseOptions,
+    format_options: &FormatOptions,
+) -> Result<String, ReformatError> {
+    let value = Value::parse_with_options(input, parse_options)?;
+    Ok(value.format_with(format_options)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reformat_example() {
+        let opts = FormatOptions::new();
+        assert_eq!(reformat(" ( 0x10 ,'a' ) ", &opts).unwrap(), "(16, 'a')");
+        assert_eq!(reformat("1,", &opts).unwrap(), "(1,)");
+        match reformat("[1,", &opts) {
+            Err(ReformatError::Parse(ParseError::Syntax(_))) => (),
+            other => panic!("{:?}", other),
+        }
+        let parse = ParseOptions::new().empty_calls(true).comments(true);
+        let input = "[  # numbers\n  set(),\n]";
+        match reformat_with_options(input, &parse, &opts) {
+            Err(ReformatError::Format(FormatError::EmptySet)) => (),
+            other => panic!("{:?}", other),
+        }
+        let empty_sets = FormatOptions::new().empty_sets(true);
+        let reformatted = reformat_with_options(input, &parse, &empty_sets).unwrap();
+        assert_eq!(reformatted, "[set()]");
+    }
+}
@@ -0,0 +1,456 @@
+//! Lightweight scanning of Python literal source text, independent of the
+//! grammar. This is used for preprocessing the input before parsing, for
+//! finding the end of a literal in incomplete input, and for splitting
+//! invalid input into parts when recovering from errors.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Replaces `#` comments and line breaks outside of string and bytes
+/// literals with spaces.
+///
+/// Every replaced byte becomes a single space, so byte offsets (and hence
+/// error positions) are the same in the output as in the input.
+pub(crate) fn blank_comments(s: &str) -> Cow<'_, str> {
+    if !s.bytes().any(|b| matches!(b, b'#' | b'\n' | b'\r')) {
+        return Cow::Borrowed(s);
+    }
+    let mut out = s.as_bytes().to_vec();
+    let mut i = 0;
+    while i < out.len() {
+        match out[i] {
+            quote @ b'\'' | quote @ b'"' => i = skip_string(&out, i, quote),
+            b'#' => {
+                while i < out.len() && !matches!(out[i], b'\n' | b'\r') {
+                    out[i] = b' ';
+                    i += 1;
+                }
+            }
+            b'\n' | b'\r' => {
+                out[i] = b' ';
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    // Only ASCII bytes were replaced, and only with ASCII bytes, so the
+    // result is still valid UTF-8.
+    Cow::Owned(String::from_utf8(out).unwrap())
+}
+
+/// Replaces `#` comments outside of string and bytes literals, and line
+/// breaks inside brackets, with spaces, so that each statement of an
+/// assignment file is on a single line.
+///
+/// Like `blank_comments`, this doesn't change byte offsets.
+pub(crate) fn blank_statement_comments(s: &str) -> Cow<'_, str> {
+    if !s.bytes().any(|b| b == b'#' || b == b'\n' || b == b'\r') {
+        return Cow::Borrowed(s);
+    }
+    let mut out = s.as_bytes().to_vec();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < out.len() {
+        match out[i] {
+            quote @ b'\'' | quote @ b'"' => {
+                i = skip_string(&out, i, quote);
+                continue;
+            }
+            b'#' => {
+                while i < out.len() && !matches!(out[i], b'\n' | b'\r') {
+                    out[i] = b' ';
+                    i += 1;
+                }
+                continue;
+            }
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            b'\n' | b'\r' if depth > 0 => out[i] = b' ',
+            _ => (),
+        }
+        i += 1;
+    }
+    // Only ASCII bytes were replaced, and only with ASCII bytes, so the
+    // result is still valid UTF-8.
+    Cow::Owned(String::from_utf8(out).unwrap())
+}
+
+/// Replaces a UTF-8 byte order mark at the start of the input, and line
+/// breaks before and after the text, with spaces.
+///
+/// Like `blank_comments`, this doesn't change byte offsets.
+pub(crate) fn blank_surroundings(s: Cow<'_, str>) -> Cow<'_, str> {
+    let is_space = |c: char| matches!(c, ' ' | '\t' | '\x0C' | '\n' | '\r');
+    let bom = if s.starts_with('\u{feff}') { 3 } else { 0 };
+    let start = s.len() - s[bom..].trim_start_matches(is_space).len();
+    let end = s[start..].trim_end_matches(is_space).len() + start;
+    let is_blank = |part: &str| !part.bytes().any(|b| matches!(b, b'\n' | b'\r'));
+    if bom == 0 && is_blank(&s[..start]) && is_blank(&s[end..]) {
+        return s;
+    }
+    let mut out = String::with_capacity(s.len());
+    out.extend(std::iter::repeat(' ').take(start));
+    out.push_str(&s[start..end]);
+    out.extend(std::iter::repeat(' ').take(s.len() - end));
+    Cow::Owned(out)
+}
+
+/// Returns the index just past the end of the string literal whose opening
+/// quote is at `start`, or the length of the input if it's unterminated.
+fn skip_string(s: &[u8], start: usize, quote: u8) -> usize {
+    let triple = s[start..].starts_with(&[quote; 3]);
+    let mut i = start + if triple { 3 } else { 1 };
+    while i < s.len() {
+        match s[i] {
+            // In both normal and raw strings, a backslash prevents the next
+            // character (or `\r\n` line break) from terminating the string.
+            b'\\' if s[i + 1..].starts_with(b"\r\n") => i += 3,
+            b'\\' => i += 2,
+            c if c == quote && (!triple || s[i..].starts_with(&[quote; 3])) => {
+                return i + if triple { 3 } else { 1 };
+            }
+            // Short strings can't contain line breaks. Stop here so that the
+            // rest of the input is still processed.
+            b'\n' | b'\r' if !triple => return i,
+            _ => i += 1,
+        }
+    }
+    s.len()
+}
+
+/// Returns the index of the matching closing bracket of each opening bracket
+/// (outside of strings) which is closed, by the index of the opening
+/// bracket. Brackets of different kinds aren't distinguished.
+pub(crate) fn match_brackets(s: &str) -> HashMap<usize, usize> {
+    let s = s.as_bytes();
+    let mut matches = HashMap::new();
+    let mut open = Vec::new();
+    let mut i = 0;
+    while i < s.len() {
+        match s[i] {
+            quote @ b'\'' | quote @ b'"' => {
+                i = skip_string(s, i, quote);
+                continue;
+            }
+            b'(' | b'[' | b'{' => open.push(i),
+            b')' | b']' | b'}' => {
+                if let Some(start) = open.pop() {
+                    matches.insert(start, i);
+                }
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    matches
+}
+
+/// Returns the index of the first opening bracket (outside of strings and,
+/// if `comments` is true, comments) which is nested more than `max_depth`
+/// levels deep, if any.
+pub(crate) fn find_too_deep(s: &str, max_depth: usize, comments: bool) -> Option<usize> {
+    let s = s.as_bytes();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < s.len() {
+        match s[i] {
+            quote @ b'\'' | quote @ b'"' => {
+                i = skip_string(s, i, quote);
+                continue;
+            }
+            b'#' if comments => {
+                while i < s.len() && !matches!(s[i], b'\n' | b'\r') {
+                    i += 1;
+                }
+                continue;
+            }
+            b'(' | b'[' | b'{' => {
+                depth += 1;
+                if depth > max_depth {
+                    return Some(i);
+                }
+            }
+            b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Splits `s` at each occurrence of `delim` outside of brackets and strings,
+/// and returns the ranges of the parts (excluding the delimiters).
+///
+/// `brackets` is the result of `match_brackets` for input in which `s` starts
+/// at `offset`. Brackets are skipped using it instead of scanning their
+/// contents, so splitting takes time proportional to the length of the top
+/// level of `s`. An unclosed bracket extends to the end of `s`.
+pub(crate) fn split_top_level(
+    s: &str,
+    offset: usize,
+    brackets: &HashMap<usize, usize>,
+    delim: u8,
+) -> Vec<Range<usize>> {
+    let s = s.as_bytes();
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < s.len() {
+        match s[i] {
+            quote @ b'\'' | quote @ b'"' => {
+                i = skip_string(s, i, quote);
+                continue;
+            }
+            b'(' | b'[' | b'{' => match brackets.get(&(offset + i)) {
+                Some(&close) if close - offset < s.len() => {
+                    i = close - offset + 1;
+                    continue;
+                }
+                // The rest of `s` is inside the unclosed bracket.
+                _ => break,
+            },
+            c if c == delim => {
+                parts.push(start..i);
+                start = i + 1;
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    parts.push(start..s.len());
+    parts
+}
+
+/// Incremental scanner which finds the end of a literal in input which
+/// arrives in pieces.
+///
+/// A literal which starts with an opening bracket ends at the matching
+/// closing bracket. Any other literal ends at the first line break outside of
+/// brackets and strings (which is included in the literal).
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Framer {
+    /// Index of the next byte to examine.
+    pos: usize,
+    /// Index of the first byte of the literal, once found.
+    start: Option<usize>,
+    /// Whether the literal starts with an opening bracket.
+    bracketed: bool,
+    /// Bracket nesting depth.
+    depth: usize,
+    /// Quote character and whether the string is triple-quoted, if inside a
+    /// string.
+    string: Option<(u8, bool)>,
+    /// Whether the scanner is inside a comment.
+    comment: bool,
+}
+
+impl Framer {
+    /// Scans `buf`, which must start with the bytes that were previously
+    /// scanned, and returns the range of the literal if its end was found.
+    /// The range excludes leading whitespace.
+    pub(crate) fn advance(&mut self, buf: &[u8], comments: bool) -> Option<Range<usize>> {
+        while self.pos < buf.len() {
+            let b = buf[self.pos];
+            if self.comment {
+                if matches!(b, b'\n' | b'\r') {
+                    self.comment = false;
+                } else {
+                    self.pos += 1;
+                }
+                continue;
+            }
+            if let Some((quote, triple)) = self.string {
+                match b {
+                    b'\\' if self.pos + 1 >= buf.len() => return None,
+                    b'\\' => self.pos += 2,
+                    c if c == quote && triple => {
+                        let rest = &buf[self.pos..buf.len().min(self.pos + 3)];
+                        if rest == [quote; 3] {
+                            self.string = None;
+                            self.pos += 3;
+                        } else if rest.len() < 3 && rest.iter().all(|&c| c == quote) {
+                            return None;
+                        } else {
+                            self.pos += 1;
+                        }
+                    }
+                    c if c == quote => {
+                        self.string = None;
+                        self.pos += 1;
+                    }
+                    // Short strings can't contain line breaks, so this is a
+                    // syntax error, which the parser will report.
+                    b'\n' | b'\r' if !triple => self.string = None,
+                    _ => self.pos += 1,
+                }
+                continue;
+            }
+            if self.start.is_none() {
+                if matches!(b, b' ' | b'\t' | b'\x0C' | b'\n' | b'\r') {
+                    self.pos += 1;
+                    continue;
+                }
+                self.start = Some(self.pos);
+                self.bracketed = matches!(b, b'(' | b'[' | b'{');
+            }
+            match b {
+                b'\'' | b'"' => {
+                    let rest = &buf[self.pos..buf.len().min(self.pos + 3)];
+                    if rest.len() < 3 && rest.iter().all(|&c| c == b) {
+                        return None;
+                    }
+                    let triple = rest == [b; 3];
+                    self.string = Some((b, triple));
+                    self.pos += if triple { 3 } else { 1 };
+                }
+                b'#' if comments => {
+                    self.comment = true;
+                    self.pos += 1;
+                }
+                b'(' | b'[' | b'{' => {
+                    self.depth += 1;
+                    self.pos += 1;
+                }
+                b')' | b']' | b'}' => {
+                    self.depth = self.depth.saturating_sub(1);
+                    self.pos += 1;
+                    if self.depth == 0 && self.bracketed {
+                        return self.start.map(|start| start..self.pos);
+                    }
+                }
+                b'\n' | b'\r' if self.depth == 0 => {
+                    self.pos += 1;
+                    return self.start.map(|start| start..self.pos);
+                }
+                _ => self.pos += 1,
+            }
+        }
+        None
+    }
+
+    /// Returns the index of the first byte of the literal, if it has been
+    /// found.
+    pub(crate) fn start(&self) -> Option<usize> {
+        self.start
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn blank_comments_example() {
+        for &(input, correct) in &[
+            ("[1, 2]", "[1, 2]"),
+            ("[1, # one\n 2]  # end", "[1,        2]       "),
+            ("{'#': \"#\"}#", "{'#': \"#\"} "),
+            ("'''a\n#b''' # c\r\n", "'''a\n#b'''      "),
+            (r"'\'#' # é", r"'\'#'     "),
+            ("'abc\n# d", "'abc    "),
+            ("['a\\\r\nb', # c\r\n 1]", "['a\\\r\nb',       1]"),
+        ] {
+            assert_eq!(blank_comments(input), correct);
+        }
+    }
+
+    #[test]
+    fn blank_statement_comments_example() {
+        for &(input, correct) in &[
+            ("A = 1", "A = 1"),
+            (
+                "A = [1, # one\n 2]  # end\nB = 2",
+                "A = [1,        2]       \nB = 2",
+            ),
+            ("# c\r\nA = '#'\n", "   \r\nA = '#'\n"),
+            ("A = ('''\n#''',\n)", "A = ('''\n#''', )"),
+        ] {
+            assert_eq!(blank_statement_comments(input), correct);
+        }
+    }
+
+    #[test]
+    fn blank_surroundings_example() {
+        for &(input, correct) in &[
+            ("[1, 2]", "[1, 2]"),
+            (" 1\t", " 1\t"),
+            ("\u{feff}1", "   1"),
+            ("\r\n 1\n\n", "   1  "),
+            ("\u{feff}\n'a\nb' \n", "    'a\nb'  "),
+            ("\n", " "),
+        ] {
+            assert_eq!(blank_surroundings(Cow::Borrowed(input)), correct);
+        }
+    }
+
+    #[test]
+    fn split_example() {
+        let split = |s: &str, delim| split_top_level(s, 0, &match_brackets(s), delim);
+        let parts = split("", b',');
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0], 0..0);
+        assert_eq!(
+            split("1, (2, 3), ',', ", b','),
+            [0..1, 2..9, 10..14, 15..16],
+        );
+        assert_eq!(split("{1: 2}: ':'", b':'), [0..6, 7..11]);
+        let input = "[1, (2, ']'), [3, (4], 5";
+        let brackets = match_brackets(input);
+        assert_eq!(brackets.len(), 2);
+        assert_eq!((brackets[&4], brackets[&18]), (11, 20));
+        let parts = split_top_level(input, 0, &brackets, b',');
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0], 0..input.len());
+        assert_eq!(
+            split_top_level(&input[1..12], 1, &brackets, b','),
+            [0..1, 2..11],
+        );
+        assert_eq!(
+            split_top_level(&input[15..], 15, &brackets, b','),
+            [0..1, 2..6, 7..9],
+        );
+    }
+
+    #[test]
+    fn find_too_deep_example() {
+        assert_eq!(find_too_deep("[(1,), {'[': 2}]", 2, false), None);
+        assert_eq!(find_too_deep("[(1,), {'[': [2]}]", 2, false), Some(13));
+        assert_eq!(find_too_deep("1 # [", 0, true), None);
+        assert_eq!(find_too_deep("1 # [", 0, false), Some(4));
+    }
+
+    #[test]
+    fn framer_example() {
+        for &(input, comments, ref correct) in &[
+            ("  [1, (2)] 3", false, Some(2..10)),
+            ("1 + 2j\n[", false, Some(0..7)),
+            ("frozenset({1,\n2})\n", false, Some(0..18)),
+            ("{'}': ''']\n'''}", false, Some(0..15)),
+            ("'a\\'\n", false, Some(0..5)),
+            ("[1, # ]\n2]", true, Some(0..10)),
+            ("[1, # ]\n2]", false, Some(0..7)),
+            ("  ", false, None),
+            ("[1, 2", false, None),
+            ("'''a''", false, None),
+            ("12", false, None),
+        ] {
+            assert_eq!(
+                Framer::default().advance(input.as_bytes(), comments),
+                *correct,
+                "{:?}",
+                input,
+            );
+        }
+    }
+
+    #[test]
+    fn framer_pieces() {
+        let input = b"\n{'a': '''x'''}";
+        let mut framer = Framer::default();
+        for end in 0..input.len() {
+            assert_eq!(framer.advance(&input[..end], false), None);
+        }
+        assert_eq!(framer.advance(input, false), Some(1..input.len()));
+    }
+}
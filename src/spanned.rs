@@ -0,0 +1,593 @@
+//! Parsing with the location of each part of a literal in the input.
+
+use crate::compare::{self, HashKind};
+use crate::parse::{
+    insert_dict_item, parse_start, parse_value, push_set_element, Element, ElementIndex,
+    ParseOptions, Rule,
+};
+use crate::{ParseError, Value};
+use pest::iterators::{Pair, Pairs};
+use std::hash::Hasher;
+use std::ops::Range;
+use std::vec;
+
+/// Python literal with the location of each of its parts in the input,
+/// created by [`Value::parse_spanned`].
+///
+/// [`Value::parse_spanned`]: enum.Value.html#method.parse_spanned
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpannedValue {
+    /// Byte range of the literal in the input.
+    pub span: Range<usize>,
+    /// The literal.
+    pub node: SpannedNode,
+}
+
+/// Python literal whose elements have spans.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpannedNode {
+    /// Tuple.
+    Tuple(Vec<SpannedValue>),
+    /// List.
+    List(Vec<SpannedValue>),
+    /// Dict.
+    Dict(Vec<(SpannedValue, SpannedValue)>),
+    /// Set.
+    Set(Vec<SpannedValue>),
+    /// Any other value. This includes collections which aren't written with
+    /// brackets, such as `frozenset({1})` and `OrderedDict(...)`; their
+    /// elements don't have spans.
+    Value(Value),
+}
+
+impl SpannedValue {
+    /// Converts the literal into a `Value`, discarding the spans.
+    pub fn into_value(self) -> Value {
+        // Nested collections are converted with an explicit stack instead of
+        // recursively, so deeply nested values can't overflow the call stack.
+        let mut stack = match Converting::new(self) {
+            Ok(converting) => vec![converting],
+            Err(value) => return value,
+        };
+        let mut done = None;
+        while let Some(converting) = stack.last_mut() {
+            if let Some(value) = done.take() {
+                converting.push(value);
+            }
+            match converting.next_value() {
+                Some(spanned) => match Converting::new(spanned) {
+                    Ok(converting) => stack.push(converting),
+                    Err(value) => done = Some(value),
+                },
+                None => done = Some(stack.pop().unwrap().finish()),
+            }
+        }
+        done.unwrap()
+    }
+}
+
+/// Collection whose elements are being converted by
+/// `SpannedValue::into_value`.
+enum Converting {
+    /// Tuple, list, or set, with its remaining elements and the converted
+    /// ones.
+    Elements {
+        kind: fn(Vec<Value>) -> Value,
+        elems: vec::IntoIter<SpannedValue>,
+        converted: Vec<Value>,
+    },
+    /// Dict, with its remaining items, the value of the current item if its
+    /// key is being converted, and the converted items and key.
+    Dict {
+        items: vec::IntoIter<(SpannedValue, SpannedValue)>,
+        value: Option<SpannedValue>,
+        converted: Vec<(Value, Value)>,
+        key: Option<Value>,
+    },
+}
+
+impl Converting {
+    /// Returns a collection to convert, or the value if `spanned` isn't a
+    /// collection with spans.
+    fn new(spanned: SpannedValue) -> Result<Converting, Value> {
+        let elements = |kind, elems: Vec<SpannedValue>| Converting::Elements {
+            kind,
+            converted: Vec::with_capacity(elems.len()),
+            elems: elems.into_iter(),
+        };
+        match spanned.node {
+            SpannedNode::Tuple(tup) => Ok(elements(Value::Tuple, tup)),
+            SpannedNode::List(list) => Ok(elements(Value::List, list)),
+            SpannedNode::Set(set) => Ok(elements(Value::Set, set)),
+            SpannedNode::Dict(dict) => Ok(Converting::Dict {
+                converted: Vec::with_capacity(dict.len()),
+                items: dict.into_iter(),
+                value: None,
+                key: None,
+            }),
+            SpannedNode::Value(value) => Err(value),
+        }
+    }
+
+    /// Returns the next element (or dict key or value) to convert.
+    fn next_value(&mut self) -> Option<SpannedValue> {
+        match self {
+            Converting::Elements { elems, .. } => elems.next(),
+            Converting::Dict { items, value, .. } => value.take().or_else(|| {
+                let (k, v) = items.next()?;
+                *value = Some(v);
+                Some(k)
+            }),
+        }
+    }
+
+    /// Adds a converted element (or dict key or value).
+    fn push(&mut self, value: Value) {
+        match self {
+            Converting::Elements { converted, .. } => converted.push(value),
+            Converting::Dict { converted, key, .. } => match key.take() {
+                None => *key = Some(value),
+                Some(key) => converted.push((key, value)),
+            },
+        }
+    }
+
+    fn finish(self) -> Value {
+        match self {
+            Converting::Elements {
+                kind, converted, ..
+            } => kind(converted),
+            Converting::Dict { converted, .. } => Value::Dict(converted),
+        }
+    }
+}
+
+/// Returns whether the spanned literal would compare equal with the value in
+/// Python.
+fn python_eq_value(spanned: &SpannedValue, value: &Value) -> bool {
+    match (&spanned.node, value) {
+        (SpannedNode::Value(a), b) => a.python_eq(b),
+        (SpannedNode::Tuple(a), Value::Tuple(b)) | (SpannedNode::List(a), Value::List(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(x, y)| python_eq_value(x, y))
+        }
+        (SpannedNode::Dict(a), Value::Dict(b)) => compare::dict_eq(
+            a,
+            b,
+            Element::python_eq,
+            compare::python_eq,
+            python_eq_value,
+        ),
+        (SpannedNode::Set(a), Value::Set(b)) | (SpannedNode::Set(a), Value::FrozenSet(b)) => {
+            compare::is_subset(a, b, python_eq_value)
+                && compare::is_subset(b, a, |y, x| python_eq_value(x, y))
+        }
+        _ => false,
+    }
+}
+
+impl Element for SpannedValue {
+    fn is_hashable(&self) -> bool {
+        match &self.node {
+            SpannedNode::Tuple(tup) => tup.iter().all(Element::is_hashable),
+            SpannedNode::List(_) | SpannedNode::Dict(_) | SpannedNode::Set(_) => false,
+            SpannedNode::Value(value) => value.is_hashable(),
+        }
+    }
+
+    fn python_eq(&self, other: &SpannedValue) -> bool {
+        match (&self.node, &other.node) {
+            (_, SpannedNode::Value(b)) => python_eq_value(self, b),
+            (SpannedNode::Value(a), _) => python_eq_value(other, a),
+            (SpannedNode::Tuple(a), SpannedNode::Tuple(b))
+            | (SpannedNode::List(a), SpannedNode::List(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.python_eq(y))
+            }
+            (SpannedNode::Dict(a), SpannedNode::Dict(b)) => compare::dict_eq(
+                a,
+                b,
+                Element::python_eq,
+                Element::python_eq,
+                Element::python_eq,
+            ),
+            (SpannedNode::Set(a), SpannedNode::Set(b)) => {
+                compare::is_subset(a, b, Element::python_eq)
+                    && compare::is_subset(b, a, Element::python_eq)
+            }
+            _ => false,
+        }
+    }
+
+    fn python_hash(&self, depth: usize, state: &mut dyn Hasher) {
+        match &self.node {
+            SpannedNode::Tuple(tup) => {
+                compare::hash_sequence(HashKind::Tuple, tup, depth, state, Element::python_hash)
+            }
+            SpannedNode::List(list) => {
+                compare::hash_sequence(HashKind::List, list, depth, state, Element::python_hash)
+            }
+            SpannedNode::Dict(_) => HashKind::Dict.hash(state),
+            SpannedNode::Set(_) => HashKind::Set.hash(state),
+            SpannedNode::Value(value) => value.python_hash(depth, state),
+        }
+    }
+
+    fn describe(&self) -> String {
+        self.clone().into_value().describe()
+    }
+}
+
+impl Value {
+    /// Parses a Python literal, recording the location of each of its parts,
+    /// using the default options.
+    ///
+    /// ```
+    /// use py_literal::{SpannedNode, Value};
+    ///
+    /// # fn main() -> Result<(), py_literal::ParseError> {
+    /// let spanned = Value::parse_spanned("{'a': [1, 2]}")?;
+    /// assert_eq!(spanned.span, 0..13);
+    /// if let SpannedNode::Dict(dict) = &spanned.node {
+    ///     assert_eq!(dict[0].0.span, 1..4);
+    ///     assert_eq!(dict[0].1.span, 6..12);
+    /// }
+    /// assert_eq!(spanned.into_value(), "{'a': [1, 2]}".parse()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_spanned(s: &str) -> Result<SpannedValue, ParseError> {
+        Value::parse_spanned_with_options(s, &ParseOptions::default())
+    }
+
+    /// Parses a Python literal, recording the location of each of its parts,
+    /// using the given options.
+    pub fn parse_spanned_with_options(
+        s: &str,
+        options: &ParseOptions,
+    ) -> Result<SpannedValue, ParseError> {
+        parse_start(s, options, |pairs, opts| {
+            let start = span_of(&pairs.clone().next().unwrap()).start;
+            let last = pairs
+                .clone()
+                .filter(|pair| pair.as_rule() != Rule::EOI)
+                .last();
+            let end = span_of(&last.unwrap()).end;
+            parse_comma_separated(start..end, pairs, opts)
+        })
+    }
+}
+
+/// Parses comma-separated values as a tuple with the given span, unless there
+/// is only a single value without a trailing comma, in which case the value
+/// is returned as-is.
+fn parse_comma_separated(
+    span: Range<usize>,
+    pairs: Pairs<'_, Rule>,
+    opts: &ParseOptions,
+) -> Result<SpannedValue, ParseError> {
+    let mut values = Vec::new();
+    let mut trailing_comma = false;
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::value => values.push(parse_spanned_value(pair, opts)?),
+            Rule::tuple_comma => trailing_comma = true,
+            Rule::EOI => (),
+            _ => unreachable!(),
+        }
+    }
+    if values.len() == 1 && !trailing_comma {
+        Ok(values.pop().unwrap())
+    } else {
+        Ok(SpannedValue {
+            span,
+            node: SpannedNode::Tuple(values),
+        })
+    }
+}
+
+/// Returns the span of the pair, excluding trailing whitespace.
+pub(crate) fn span_of(pair: &Pair<'_, Rule>) -> Range<usize> {
+    let start = pair.as_span().start();
+    start
+        ..start
+            + pair
+                .as_str()
+                .trim_end_matches(&[' ', '\t', '\x0C'][..])
+                .len()
+}
+
+/// Parses a value.
+///
+/// Nested tuples, lists, sets, and dicts are parsed with an explicit stack
+/// instead of recursively, so deeply nested input can't overflow the call
+/// stack.
+fn parse_spanned_value(
+    value: Pair<'_, Rule>,
+    opts: &ParseOptions,
+) -> Result<SpannedValue, ParseError> {
+    debug_assert_eq!(value.as_rule(), Rule::value);
+    let mut stack = match Frame::new(value.clone()) {
+        Some(frame) => vec![frame],
+        None => return parse_non_collection(value, opts),
+    };
+    let mut done = None;
+    while let Some(frame) = stack.last_mut() {
+        opts.check_cancelled()?;
+        if let Some(value) = done.take() {
+            frame
+                .collection
+                .push(value, frame.elem_span, &mut frame.index, opts)?;
+        }
+        match frame.next_value() {
+            Some(value) => match Frame::new(value.clone()) {
+                Some(frame) => stack.push(frame),
+                None => done = Some(parse_non_collection(value, opts)?),
+            },
+            None => done = Some(stack.pop().unwrap().finish()),
+        }
+    }
+    Ok(done.unwrap())
+}
+
+/// Parses a value which isn't a tuple, list, set, or dict.
+fn parse_non_collection(
+    value: Pair<'_, Rule>,
+    opts: &ParseOptions,
+) -> Result<SpannedValue, ParseError> {
+    Ok(SpannedValue {
+        span: span_of(&value),
+        node: SpannedNode::Value(parse_value(value, opts)?),
+    })
+}
+
+/// Collection whose elements are being parsed by `parse_spanned_value`.
+enum Collection<'i> {
+    /// Comma-separated values in parentheses, and whether there is a trailing
+    /// comma.
+    Parenthesized(Vec<SpannedValue>, bool),
+    List(Vec<SpannedValue>),
+    Set(Vec<SpannedValue>),
+    /// Dict, and the key (and its span) of the item whose value is being
+    /// parsed.
+    Dict(
+        Vec<(SpannedValue, SpannedValue)>,
+        Option<(SpannedValue, pest::Span<'i>)>,
+    ),
+}
+
+impl<'i> Collection<'i> {
+    /// Adds an element (or dict key or value) with the given span.
+    fn push(
+        &mut self,
+        value: SpannedValue,
+        span: pest::Span<'i>,
+        index: &mut ElementIndex,
+        opts: &ParseOptions,
+    ) -> Result<(), ParseError> {
+        match self {
+            Collection::Parenthesized(values, _) | Collection::List(values) => values.push(value),
+            Collection::Set(set) => push_set_element(set, index, value, span, opts)?,
+            Collection::Dict(dict, key) => match key.take() {
+                None => *key = Some((value, span)),
+                Some((key, key_span)) => insert_dict_item(dict, index, key, value, key_span, opts)?,
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Collection on the stack of `parse_spanned_value`.
+struct Frame<'i> {
+    collection: Collection<'i>,
+    /// Span of the collection.
+    span: Range<usize>,
+    /// Remaining items of the collection.
+    items: Pairs<'i, Rule>,
+    /// Remaining key and value of the current dict item.
+    dict_elem: Option<Pairs<'i, Rule>>,
+    /// Index of the elements of a set or keys of a dict.
+    index: ElementIndex,
+    /// Span of the element being parsed.
+    elem_span: pest::Span<'i>,
+}
+
+impl<'i> Frame<'i> {
+    /// Returns a frame for the value if it's a tuple (or a value in redundant
+    /// parentheses), list, set, or dict.
+    fn new(value: Pair<'i, Rule>) -> Option<Frame<'i>> {
+        let span = span_of(&value);
+        let (inner,) = parse_pairs_as!(value.into_inner(), (_,));
+        let collection = match inner.as_rule() {
+            Rule::parenthesized => Collection::Parenthesized(Vec::new(), false),
+            Rule::list => Collection::List(Vec::new()),
+            Rule::set => Collection::Set(Vec::new()),
+            Rule::dict => Collection::Dict(Vec::new(), None),
+            _ => return None,
+        };
+        Some(Frame {
+            collection,
+            span,
+            elem_span: inner.as_span(),
+            items: inner.into_inner(),
+            dict_elem: None,
+            index: ElementIndex::default(),
+        })
+    }
+
+    /// Returns the next element (or dict key or value) to parse.
+    fn next_value(&mut self) -> Option<Pair<'i, Rule>> {
+        loop {
+            let item = match self.dict_elem.as_mut().and_then(Iterator::next) {
+                Some(pair) => pair,
+                None => self.items.next()?,
+            };
+            match item.as_rule() {
+                Rule::value => {
+                    self.elem_span = item.as_span();
+                    return Some(item);
+                }
+                Rule::dict_elem => self.dict_elem = Some(item.into_inner()),
+                Rule::tuple_comma => {
+                    if let Collection::Parenthesized(_, trailing_comma) = &mut self.collection {
+                        *trailing_comma = true;
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    fn finish(self) -> SpannedValue {
+        let node = match self.collection {
+            Collection::Parenthesized(mut values, trailing_comma) => {
+                if values.len() == 1 && !trailing_comma {
+                    return values.pop().unwrap();
+                }
+                SpannedNode::Tuple(values)
+            }
+            Collection::List(list) => SpannedNode::List(list),
+            Collection::Set(set) => SpannedNode::Set(set),
+            Collection::Dict(dict, _) => SpannedNode::Dict(dict),
+        };
+        SpannedValue {
+            span: self.span,
+            node,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DuplicateKeys;
+
+    fn spans(value: &SpannedValue, out: &mut Vec<Range<usize>>) {
+        out.push(value.span.clone());
+        match &value.node {
+            SpannedNode::Tuple(seq) | SpannedNode::List(seq) | SpannedNode::Set(seq) => {
+                seq.iter().for_each(|v| spans(v, out))
+            }
+            SpannedNode::Dict(dict) => dict.iter().for_each(|(k, v)| {
+                spans(k, out);
+                spans(v, out);
+            }),
+            SpannedNode::Value(_) => (),
+        }
+    }
+
+    #[test]
+    fn parse_spanned_example() {
+        for &(input, ref correct) in &[
+            ("(1 ,) ", vec![0..5, 1..2]),
+            (" 1, 'a',", vec![1..8, 1..2, 4..7]),
+            ("((1), -2)", vec![0..9, 1..4, 6..8]),
+            (
+                "[{1: b''}, {2}]",
+                vec![0..15, 1..9, 2..3, 5..8, 11..14, 12..13],
+            ),
+            ("[1, # one\n frozenset({2})]", vec![0..26, 1..2, 11..25]),
+        ] {
+            let opts = ParseOptions::new().comments(true).frozensets(true);
+            let value = Value::parse_spanned_with_options(input, &opts).unwrap();
+            let mut out = Vec::new();
+            spans(&value, &mut out);
+            assert_eq!(&out, correct, "{:?}", input);
+            assert_eq!(
+                value.into_value(),
+                Value::parse_with_options(input, &opts).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn parse_spanned_options() {
+        let opts = ParseOptions::new()
+            .duplicate_keys(DuplicateKeys::KeepLast)
+            .deduplicate_sets(true);
+        let value = Value::parse_spanned_with_options("{(1, 2): 3, (1.0, 2): 4}", &opts).unwrap();
+        match value.node {
+            SpannedNode::Dict(dict) => {
+                assert_eq!(dict.len(), 1);
+                assert_eq!(dict[0].0.span, 1..7);
+                assert_eq!(dict[0].1.span, 22..23);
+            }
+            _ => panic!(),
+        }
+        let value = Value::parse_spanned_with_options("{1, True, 2}", &opts).unwrap();
+        assert_eq!(value.into_value().as_set().unwrap().len(), 2);
+        let strict = ParseOptions::new().strict(true);
+        assert!(Value::parse_spanned_with_options("{[1]: 2}", &strict).is_err());
+    }
+
+    #[test]
+    fn spanned_python_eq() {
+        let opts = ParseOptions::new()
+            .deduplicate_sets(true)
+            .duplicate_keys(DuplicateKeys::KeepLast)
+            .empty_calls(true)
+            .ordered_dicts(true);
+        for &(input, len) in &[
+            ("{(1, (2.0,)), (True, (2,)), (1, [2])}", 2),
+            ("{[], list(), [1]}", 2),
+            ("{{1: 2}, {1.0: 2.0}, OrderedDict([(1, 2)])}", 1),
+            ("{(), tuple(), (None,)}", 2),
+        ] {
+            let value = Value::parse_spanned_with_options(input, &opts).unwrap();
+            assert_eq!(value.into_value().len(), Some(len), "{:?}", input);
+        }
+        let value =
+            Value::parse_spanned_with_options("{{1: {2}}: 1, {True: {2.0}}: 2}", &opts).unwrap();
+        assert_eq!(value.into_value().to_string(), "{{1: {2}}: 2}");
+        let opts = ParseOptions::new()
+            .deduplicate_sets(true)
+            .ordered_dicts(true);
+        for &(input, len) in &[
+            ("{{1: 'a', 1: 'a'}, {1: 'a', 2: 'b'}}", 2),
+            ("{{1: 'a', 2: 'b'}, {1: 'a', 1: 'a'}}", 2),
+            ("{{1: 'a', 1.0: 'b'}, {True: 'b'}}", 1),
+            ("{{1: 'a', 1: 'a'}, OrderedDict([(1, 'a'), (2, 'b')])}", 2),
+            ("{{1: 'a', 1: 'b'}, OrderedDict([(1, 'b')])}", 1),
+        ] {
+            let value = Value::parse_spanned_with_options(input, &opts).unwrap();
+            assert_eq!(value.into_value().len(), Some(len), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn parse_spanned_deeply_nested() {
+        // The thread's stack just needs to be large enough for the grammar,
+        // which is matched recursively.
+        let test = || {
+            let depth = 10_000;
+            let input = format!("{}1{}", "[{1: (".repeat(depth), ",)}]".repeat(depth));
+            let spanned = Value::parse_spanned(&input).unwrap();
+            let mut elem = &spanned;
+            for i in 0..depth {
+                assert_eq!(elem.span, 6 * i..input.len() - 4 * i);
+                elem = match &elem.node {
+                    SpannedNode::List(list) => match &list[0].node {
+                        SpannedNode::Dict(dict) => match &dict[0].1.node {
+                            SpannedNode::Tuple(tuple) => &tuple[0],
+                            _ => panic!(),
+                        },
+                        _ => panic!(),
+                    },
+                    _ => panic!(),
+                };
+            }
+            assert_eq!(elem.node, SpannedNode::Value(Value::Integer(1.into())));
+            let value = spanned.into_value();
+            let mut elem = &value;
+            for _ in 0..depth {
+                let dict = &elem.as_list().unwrap()[0];
+                elem = &dict.as_dict().unwrap()[0].1.as_tuple().unwrap()[0];
+            }
+            assert_eq!(elem, &Value::Integer(1.into()));
+        };
+        std::thread::Builder::new()
+            .stack_size(256 << 20)
+            .spawn(test)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
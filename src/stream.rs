@@ -0,0 +1,310 @@
+//! Parsing of multiple literals from the same input, and of input which
+//! arrives in pieces.
+
+use crate::parse::{parse_value, syntax_error, ParseOptions, Parser, Rule};
+use crate::scan::Framer;
+use crate::{ParseError, Value};
+use pest::iterators::{Pair, Pairs};
+use pest::Parser as ParserTrait;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Iterator over the literals in a string, created by [`Value::parse_many`].
+///
+/// [`Value::parse_many`]: enum.Value.html#method.parse_many
+#[derive(Debug)]
+pub struct ValueStream<'a> {
+    input: &'a str,
+    /// Remaining items, or `None` if the input was rejected up front.
+    items: Option<Pairs<'a, Rule>>,
+    error: Option<ParseError>,
+    options: Cow<'a, ParseOptions>,
+}
+
+impl<'a> ValueStream<'a> {
+    fn new(input: &'a str, options: Cow<'a, ParseOptions>) -> ValueStream<'a> {
+        if let Err(err) = options.check_depth(input) {
+            return ValueStream {
+                input,
+                items: None,
+                error: Some(err),
+                options,
+            };
+        }
+        let mut parsed =
+            Parser::parse(Rule::stream, input).expect("the stream rule accepts every input");
+        let (stream,) = parse_pairs_as!(parsed, (Rule::stream,));
+        ValueStream {
+            input,
+            items: Some(stream.into_inner()),
+            error: None,
+            options,
+        }
+    }
+
+    /// Returns the error for an item which isn't a valid literal.
+    fn item_error(&self, item: Pair<'a, Rule>) -> ParseError {
+        let start = item.as_span().start();
+        let err = match Parser::parse(Rule::single_value, item.as_str()) {
+            Ok(_) => return syntax_error(item.as_span(), "invalid literal"),
+            Err(err) => err,
+        };
+        // Report the position relative to the whole input, not the item.
+        let pos = match err.location {
+            pest::error::InputLocation::Pos(pos) => pos,
+            pest::error::InputLocation::Span((pos, _)) => pos,
+        };
+        let pos = pest::Position::new(self.input, start + pos).unwrap();
+        let err = pest::error::Error::new_from_pos(err.variant, pos);
+        ParseError::Syntax(format!("{}", err))
+    }
+}
+
+impl<'a> Iterator for ValueStream<'a> {
+    type Item = Result<Value, ParseError>;
+
+    fn next(&mut self) -> Option<Result<Value, ParseError>> {
+        if let Some(err) = self.error.take() {
+            return Some(Err(err));
+        }
+        loop {
+            let item = self.items.as_mut()?.next()?;
+            if let Err(err) = self.options.check_cancelled() {
+                self.items = None;
+                return Some(Err(err));
+            }
+            match item.as_rule() {
+                Rule::value => {
+                    return Some(
+                        self.options
+                            .check_nodes(std::iter::once(item.clone()))
+                            .and_then(|()| parse_value(item, &self.options)),
+                    )
+                }
+                Rule::stream_comment if self.options.comments_enabled() => (),
+                Rule::stream_comment => {
+                    return Some(Err(syntax_error(
+                        item.as_span(),
+                        "comments are supported only if enabled in the parse options",
+                    )))
+                }
+                Rule::stream_error => return Some(Err(self.item_error(item))),
+                Rule::newline | Rule::EOI => (),
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+/// Incremental parser for input which arrives in pieces, such as from a
+/// network connection.
+///
+/// Feed the input to the parser with [`feed`] until it returns
+/// [`PushStatus::Complete`]. A literal which starts with an opening bracket
+/// ends at the matching closing bracket; any other literal ends at the first
+/// line break outside of brackets and strings. The input is decoded according
+/// to the [encoding] in the parse options.
+///
+/// ```
+/// use py_literal::{PushParser, PushStatus, Value};
+///
+/// # fn main() -> Result<(), py_literal::ParseError> {
+/// let mut parser = PushParser::new();
+/// assert_eq!(parser.feed(b"{'shape': (2")?, PushStatus::NeedMoreData);
+/// match parser.feed(b", 3)} {'shape': ()}")? {
+///     PushStatus::Complete(value, consumed) => {
+///         assert_eq!(value.to_string(), "{'shape': (2, 3)}");
+///         assert_eq!(consumed, 5);
+///     }
+///     PushStatus::NeedMoreData => unreachable!(),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`feed`]: #method.feed
+/// [`PushStatus::Complete`]: enum.PushStatus.html#variant.Complete
+/// [encoding]: struct.ParseOptions.html#method.encoding
+#[derive(Clone, Debug, Default)]
+pub struct PushParser {
+    buf: Vec<u8>,
+    framer: Framer,
+    options: ParseOptions,
+}
+
+/// Result of feeding input to a [`PushParser`].
+///
+/// [`PushParser`]: struct.PushParser.html
+#[derive(Clone, Debug, PartialEq)]
+pub enum PushStatus {
+    /// The literal isn't complete yet. All of the input was consumed.
+    NeedMoreData,
+    /// The literal is complete. Contains the literal and the number of bytes
+    /// of the last input which were consumed. The rest of the input hasn't
+    /// been consumed, and should be fed to the parser again to parse the next
+    /// literal.
+    Complete(Value, usize),
+}
+
+impl PushParser {
+    /// Creates a parser with the default options.
+    pub fn new() -> PushParser {
+        PushParser::default()
+    }
+
+    /// Creates a parser with the given options.
+    pub fn with_options(options: ParseOptions) -> PushParser {
+        PushParser {
+            options,
+            ..PushParser::default()
+        }
+    }
+
+    /// Feeds more input to the parser.
+    ///
+    /// Once a literal is complete, the parser is ready for the next one. If
+    /// parsing fails, the input of the failed literal is discarded, and the
+    /// number of bytes consumed is unknown, so it's usually best to close the
+    /// connection.
+    pub fn feed(&mut self, input: &[u8]) -> Result<PushStatus, ParseError> {
+        let prev_len = self.buf.len();
+        self.buf.extend_from_slice(input);
+        let comments = self.options.resolve().comments_enabled();
+        match self.framer.advance(&self.buf, comments) {
+            None => Ok(PushStatus::NeedMoreData),
+            Some(range) => {
+                let consumed = range.end - prev_len;
+                let result = self.parse(range);
+                self.buf.clear();
+                self.framer = Framer::default();
+                Ok(PushStatus::Complete(result?, consumed))
+            }
+        }
+    }
+
+    /// Parses the rest of the input after the end of the input has been
+    /// reached, such as a literal without a line break after it. Returns
+    /// `None` if the rest of the input is blank.
+    pub fn finish(self) -> Result<Option<Value>, ParseError> {
+        match self.framer.start() {
+            None => Ok(None),
+            Some(start) => self.parse(start..self.buf.len()).map(Some),
+        }
+    }
+
+    fn parse(&self, range: Range<usize>) -> Result<Value, ParseError> {
+        let mut literal = &self.buf[range];
+        while let Some((b'\n', rest)) | Some((b'\r', rest)) = literal.split_last() {
+            literal = rest;
+        }
+        Value::from_bytes_with_options(literal, &self.options)
+    }
+}
+
+impl Value {
+    /// Returns an iterator over the literals in `s`, using the default
+    /// options.
+    ///
+    /// The literals must be separated by whitespace or line breaks, and a
+    /// literal can span multiple lines only within a triple-quoted string.
+    /// Tuples must be parenthesized, and adjacent strings on the same line,
+    /// like `'a' 'b'`, are a single literal, as in Python. Text which isn't a
+    /// valid literal, from where the literal would begin to the end of the
+    /// line, produces a single error, and iteration continues on the next
+    /// line. Positions in errors are relative to the start of `s`.
+    ///
+    /// ```
+    /// use py_literal::Value;
+    ///
+    /// let log = "(1, 'a')\n[2.5] {3}\n{4: 'b'\nNone";
+    /// let values: Vec<_> = Value::parse_many(log).collect();
+    /// assert_eq!(values.len(), 5);
+    /// assert_eq!(values[0].as_ref().unwrap().to_string(), "(1, 'a')");
+    /// assert_eq!(values[2].as_ref().unwrap().to_string(), "{3}");
+    /// assert!(values[3].is_err());
+    /// assert_eq!(values[4].as_ref().unwrap(), &Value::None);
+    /// ```
+    pub fn parse_many(s: &str) -> ValueStream<'_> {
+        ValueStream::new(s, Cow::Owned(ParseOptions::default()))
+    }
+
+    /// Returns an iterator over the literals in `s`, using the given options.
+    ///
+    /// See [`parse_many`](#method.parse_many) for details. If `comments` is
+    /// enabled in the options, comments can follow the literals on each line.
+    /// If the input exceeds the maximum nesting depth in the options, the
+    /// iterator produces only that error.
+    pub fn parse_many_with_options<'a>(s: &'a str, options: &'a ParseOptions) -> ValueStream<'a> {
+        ValueStream::new(s, options.resolve())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_many_example() {
+        let input = "1 'a'\n\n  [2, 3] # three\n(4,\n5)\n{6: '''x\ny'''}  ";
+        let results: Vec<_> = Value::parse_many(input)
+            .map(|r| r.map_err(|e| e.to_string()))
+            .collect();
+        assert_eq!(results.len(), 8);
+        assert_eq!(results[0], Ok(Value::Integer(1.into())));
+        assert_eq!(results[1], Ok(Value::String("a".into())));
+        assert!(results[3].as_ref().unwrap_err().contains("3:10"));
+        assert!(results[3].as_ref().unwrap_err().contains("comments"));
+        assert!(results[4].as_ref().unwrap_err().contains("4:4"));
+        assert_eq!(results[5], Ok(Value::Integer(5.into())));
+        assert!(results[6].as_ref().unwrap_err().contains("5:2"));
+        assert_eq!(results[7].as_ref().unwrap().to_string(), "{6: 'x\\ny'}");
+        let opts = ParseOptions::new().comments(true);
+        let results: Vec<_> = Value::parse_many_with_options(input, &opts).collect();
+        assert_eq!(results.len(), 7);
+        assert_eq!(results[2].as_ref().unwrap().to_string(), "[2, 3]");
+        assert!(results[3].is_err());
+        assert_eq!(Value::parse_many(" \n ").count(), 0);
+        let opts = ParseOptions::new().max_depth(1);
+        let results: Vec<_> = Value::parse_many_with_options("[1]\n[[2]]", &opts).collect();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Err(ParseError::DepthLimitExceeded(5))));
+        let token = crate::CancellationToken::new();
+        let opts = ParseOptions::new().cancellation_token(token.clone());
+        let mut stream = Value::parse_many_with_options("1 2 3", &opts);
+        assert!(stream.next().unwrap().is_ok());
+        token.cancel();
+        assert!(matches!(stream.next(), Some(Err(ParseError::Cancelled))));
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn push_parser_example() {
+        let input = b"  (1, 2)\n3 + 4j\r\n{'a': '''\n'''}'x'\n[";
+        for chunk_len in 1..input.len() {
+            let mut parser = PushParser::new();
+            let mut values = Vec::new();
+            for chunk in input.chunks(chunk_len) {
+                let mut chunk = chunk;
+                while let PushStatus::Complete(value, consumed) = parser.feed(chunk).unwrap() {
+                    values.push(value.to_string());
+                    chunk = &chunk[consumed..];
+                }
+            }
+            assert_eq!(values, ["(1, 2)", "3+4j", "{'a': '\\n'}", "'x'"]);
+            assert!(parser.finish().is_err());
+        }
+        let mut parser = PushParser::with_options(ParseOptions::new().comments(true));
+        assert_eq!(
+            parser.feed(b"[1, # one\n").unwrap(),
+            PushStatus::NeedMoreData
+        );
+        assert_eq!(
+            parser.feed(b"2]").unwrap(),
+            PushStatus::Complete("[1, 2]".parse().unwrap(), 2),
+        );
+        assert_eq!(parser.feed(b"5 # five").unwrap(), PushStatus::NeedMoreData);
+        assert_eq!(parser.finish().unwrap(), Some(Value::Integer(5.into())));
+        assert_eq!(PushParser::new().finish().unwrap(), None);
+        assert!(PushParser::new().feed(b"[1, 2}").is_err());
+    }
+}